@@ -1,2 +1,3 @@
 pub mod input;
 pub mod serialize;
+pub mod output;
@@ -0,0 +1,372 @@
+//! Reading of the band structure written by bands.x to `filband` and `filband.gnu`.
+//!
+//! Band energies are in eV. k-points are in Cartesian coordinates in units of 2pi/alat,
+//! and distances along the path are in the same units.
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use pw::input::KPoints;
+use pw::kpath::KPath;
+use pw::output::split_numbers;
+
+/// The largest number of band energies bands.x writes on one line of `filband`.
+const ENERGIES_PER_LINE: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BandStructure {
+    /// The k-points along the path. These are not given in the `.gnu` format.
+    pub k_points: Option<Vec<[f64; 3]>>,
+    /// Cumulative distance along the path at each k-point. The distance is held constant
+    /// across the jumps between segments of the path.
+    pub distances: Vec<f64>,
+    /// `bands[i][k]` is the energy of band `i` at the k-point with index `k`.
+    pub bands: Vec<Vec<f64>>,
+    /// Distances at which the path passes through the panel bounds of the `CrystalBands`
+    /// k-points given to pw.x. Empty if the k-points were not of this type. The two bounds
+    /// of a jump between segments of the path share one tick.
+    pub ticks: Vec<f64>,
+}
+
+pub fn read_filband<P: AsRef<Path>>(
+    file_path: P,
+    k_points: &KPoints,
+    path: Option<&KPath>,
+) -> Result<BandStructure, Error> {
+    parse_filband(&read_text(file_path)?, k_points, path)
+}
+
+pub fn read_filband_gnu<P: AsRef<Path>>(
+    file_path: P,
+    k_points: &KPoints,
+    path: Option<&KPath>,
+) -> Result<BandStructure, Error> {
+    parse_filband_gnu(&read_text(file_path)?, k_points, path)
+}
+
+/// Parse the `filband` file, which starts with a header ` &plot nbnd=   8, nks=  61 /`
+/// followed by each k-point and the band energies at that k-point.
+///
+/// `k_points` are the k-points given to the pw.x `bands` calculation. If these are those of
+/// the `KPath` `path`, the k-points pw.x generates inside the panels which jump between its
+/// segments are dropped, and the distance is held constant across the jumps.
+pub fn parse_filband(
+    text: &str,
+    k_points: &KPoints,
+    path: Option<&KPath>,
+) -> Result<BandStructure, Error> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|&(_, l)| !l.trim().is_empty());
+
+    let (header_line, (nbnd, nks)) = match lines.next() {
+        Some((i, header)) => (i + 1, parse_header(header, i + 1)?),
+        None => return Err(Error::Empty),
+    };
+
+    // Each k-point is given on one line, followed by its energies with up to 10 per line.
+    // The counts in the header are checked against the file before anything is allocated
+    // from them.
+    let expected_lines = nks.checked_mul(1 + nbnd.div_ceil(ENERGIES_PER_LINE));
+    if nbnd == 0 || nks == 0 || expected_lines != Some(lines.clone().count()) {
+        return Err(value_error(
+            header_line,
+            "`nbnd` and `nks` do not match the number of lines in the file",
+        ));
+    }
+
+    let mut ks = Vec::new();
+    let mut bands = vec![Vec::new(); nbnd];
+
+    for _ in 0..nks {
+        let (i, k_line) = lines.next().ok_or(Error::Empty)?;
+        let k = match split_numbers(k_line) {
+            Some(ref k) if k.len() == 3 => [k[0], k[1], k[2]],
+            _ => return Err(value_error(i + 1, "expected a k-point")),
+        };
+        ks.push(k);
+
+        let mut energies = Vec::new();
+        while energies.len() < nbnd {
+            let (i, line) = lines.next().ok_or(Error::Empty)?;
+            let values =
+                split_numbers(line).ok_or_else(|| value_error(i + 1, "expected band energies"))?;
+            energies.extend(values);
+        }
+        if energies.len() != nbnd {
+            return Err(value_error(i + 1, "wrong number of band energies"));
+        }
+
+        for (band, e) in bands.iter_mut().zip(energies) {
+            band.push(e);
+        }
+    }
+
+    if let Some((i, _)) = lines.next() {
+        return Err(value_error(i + 1, "unexpected data after the last k-point"));
+    }
+
+    let distances = path_distances(&ks, &jump_breaks(k_points, path));
+
+    band_structure(Some(ks), distances, bands, k_points, path)
+}
+
+/// Parse the `filband.gnu` file, which gives each band as a block of `distance energy` lines,
+/// with blocks separated by blank lines.
+///
+/// bands.x accumulates the distance across the jumps between the segments of `path`; it is
+/// held constant across these here, and the k-points inside the jumps are dropped as in
+/// `parse_filband`.
+pub fn parse_filband_gnu(
+    text: &str,
+    k_points: &KPoints,
+    path: Option<&KPath>,
+) -> Result<BandStructure, Error> {
+    let mut distances: Vec<f64> = Vec::new();
+    let mut bands: Vec<Vec<f64>> = Vec::new();
+    let mut band: Vec<f64> = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            if !band.is_empty() {
+                finish_gnu_band(&mut bands, &mut band, &distances, i)?;
+            }
+            continue;
+        }
+
+        let values = match split_numbers(line) {
+            Some(ref values) if values.len() == 2 => [values[0], values[1]],
+            _ => return Err(value_error(i + 1, "expected a distance and an energy")),
+        };
+
+        if bands.is_empty() {
+            distances.push(values[0]);
+        } else if distances
+            .get(band.len())
+            .is_none_or(|&d| (d - values[0]).abs() > 1e-3)
+        {
+            return Err(value_error(
+                i + 1,
+                "distance does not match that of the first band",
+            ));
+        }
+        band.push(values[1]);
+    }
+
+    if !band.is_empty() {
+        let n = text.lines().count();
+        finish_gnu_band(&mut bands, &mut band, &distances, n)?;
+    }
+
+    if bands.is_empty() {
+        return Err(Error::Empty);
+    }
+
+    let breaks = jump_breaks(k_points, path);
+    let mut offset = 0.0;
+    let distances: Vec<f64> = (0..distances.len())
+        .map(|i| {
+            if breaks.contains(&i) {
+                offset += distances[i] - distances[i - 1];
+            }
+            distances[i] - offset
+        })
+        .collect();
+
+    band_structure(None, distances, bands, k_points, path)
+}
+
+fn finish_gnu_band(
+    bands: &mut Vec<Vec<f64>>,
+    band: &mut Vec<f64>,
+    distances: &[f64],
+    line: usize,
+) -> Result<(), Error> {
+    if band.len() != distances.len() {
+        return Err(value_error(line, "band has a different number of k-points"));
+    }
+    bands.push(band.split_off(0));
+
+    Ok(())
+}
+
+/// The cumulative distance along the path through `ks`. The distance is held at each of the
+/// indices `breaks`, where the path restarts after a jump from the previous k-point.
+pub fn path_distances(ks: &[[f64; 3]], breaks: &[usize]) -> Vec<f64> {
+    let mut distances = Vec::with_capacity(ks.len());
+    let mut total = 0.0;
+
+    for (i, k) in ks.iter().enumerate() {
+        if i > 0 && !breaks.contains(&i) {
+            let prev = ks[i - 1];
+            let dk = [k[0] - prev[0], k[1] - prev[1], k[2] - prev[2]];
+            total += (dk[0] * dk[0] + dk[1] * dk[1] + dk[2] * dk[2]).sqrt();
+        }
+        distances.push(total);
+    }
+
+    distances
+}
+
+/// The indices of the panel bounds within the list of k-points that pw.x generates for
+/// `KPoints::CrystalBands` or `KPoints::TwoPiByACartesianBands`: `nk_per_panel` points are
+/// generated for each panel, followed by the final panel bound. Returns `None` for other types
+/// of k-points.
+pub fn panel_bound_indices(k_points: &KPoints) -> Option<Vec<usize>> {
+    match *k_points {
+        KPoints::TwoPiByACartesianBands {
+            nk_per_panel,
+            ref panel_bounds,
+        }
+        | KPoints::CrystalBands {
+            nk_per_panel,
+            ref panel_bounds,
+        } => Some(
+            (0..panel_bounds.len())
+                .map(|i| i * nk_per_panel as usize)
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// The indices of the first and last bounds of the panels of `k_points` which jump between
+/// segments of `path`, within the list of k-points generated by pw.x.
+fn jump_panels(k_points: &KPoints, path: Option<&KPath>) -> Vec<(usize, usize)> {
+    let (indices, path) = match (panel_bound_indices(k_points), path) {
+        (Some(indices), Some(path)) => (indices, path),
+        _ => return Vec::new(),
+    };
+
+    path.jumps
+        .iter()
+        .filter(|&&panel| panel + 1 < indices.len())
+        .map(|&panel| (indices[panel], indices[panel + 1]))
+        .collect()
+}
+
+/// The indices of the k-points generated by pw.x along the panels of `k_points` which jump
+/// between segments of `path`, excluding the first bound of each panel.
+fn jump_breaks(k_points: &KPoints, path: Option<&KPath>) -> Vec<usize> {
+    jump_panels(k_points, path)
+        .into_iter()
+        .flat_map(|(start, end)| start + 1..=end)
+        .collect()
+}
+
+/// Collect the band structure along the full list of k-points generated by pw.x for
+/// `k_points`, dropping the k-points inside the jumps between segments of `path`.
+fn band_structure(
+    ks: Option<Vec<[f64; 3]>>,
+    distances: Vec<f64>,
+    bands: Vec<Vec<f64>>,
+    k_points: &KPoints,
+    path: Option<&KPath>,
+) -> Result<BandStructure, Error> {
+    let indices = match panel_bound_indices(k_points) {
+        Some(indices) => indices,
+        None => {
+            return Ok(BandStructure {
+                k_points: ks,
+                distances,
+                bands,
+                ticks: Vec::new(),
+            })
+        }
+    };
+
+    let expected = indices.last().map_or(0, |&i| i + 1);
+    if expected != distances.len() {
+        return Err(Error::KPoints {
+            expected,
+            found: distances.len(),
+        });
+    }
+
+    let jumps = jump_panels(k_points, path);
+    let ticks = indices
+        .into_iter()
+        .filter(|i| !jumps.iter().any(|&(_, end)| end == *i))
+        .map(|i| distances[i])
+        .collect();
+
+    let keep = |i: &usize| !jumps.iter().any(|&(start, end)| start < *i && *i < end);
+    let retain = |values: Vec<f64>| -> Vec<f64> {
+        values
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| keep(i))
+            .map(|(_, x)| x)
+            .collect()
+    };
+
+    Ok(BandStructure {
+        k_points: ks.map(|ks| {
+            ks.into_iter()
+                .enumerate()
+                .filter(|(i, _)| keep(i))
+                .map(|(_, k)| k)
+                .collect()
+        }),
+        distances: retain(distances),
+        bands: bands.into_iter().map(retain).collect(),
+        ticks,
+    })
+}
+
+fn parse_header(header: &str, line: usize) -> Result<(usize, usize), Error> {
+    let header = header.trim();
+    if !header.starts_with("&plot") {
+        return Err(value_error(line, "expected `&plot` header"));
+    }
+
+    let field = |name: &str| -> Result<usize, Error> {
+        let start = header
+            .find(name)
+            .ok_or_else(|| value_error(line, &format!("missing `{}` in header", name)))?;
+        header[start + name.len()..]
+            .trim_start_matches([' ', '='])
+            .split([',', ' ', '/'])
+            .next()
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(|| value_error(line, &format!("invalid `{}` in header", name)))
+    };
+
+    Ok((field("nbnd")?, field("nks")?))
+}
+
+fn read_text<P: AsRef<Path>>(file_path: P) -> Result<String, Error> {
+    let mut text = String::new();
+    File::open(file_path)?.read_to_string(&mut text)?;
+
+    Ok(text)
+}
+
+fn value_error(line: usize, message: &str) -> Error {
+    Error::Value {
+        line,
+        message: String::from(message),
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "line {}: {}", line, message)]
+    Value { line: usize, message: String },
+    #[fail(display = "band structure file ends unexpectedly")]
+    Empty,
+    #[fail(
+        display = "the k-points given to pw.x give {} points along the path, but the band structure has {}",
+        expected, found
+    )]
+    KPoints { expected: usize, found: usize },
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
@@ -2,31 +2,35 @@ use std::path::Path;
 use std::io;
 use std::io::Write;
 use std::fs::File;
-use serialize_util::push_bool_field;
+use namelist;
 use bands::input::Input;
 
-pub fn make_input_file(input: &Input) -> Result<String, Error> {
-    let mut lines = Vec::new();
-    lines.push(String::from(" &bands"));
-
-    if let Some(ref prefix) = input.prefix {
-        lines.push(format!("   prefix='{}',", prefix));
-    }
-
-    if let Some(ref out_dir) = input.out_dir {
-        let path = out_dir.to_str().ok_or(Error::OutDir)?;
-        lines.push(format!("   out_dir='{}',", path));
-    }
-
-    if let Some(ref filband) = input.filband {
-        let path = filband.to_str().ok_or(Error::Filband)?;
-        lines.push(format!("   filband='{}',", path));
-    }
-
-    push_bool_field(&mut lines, "lsym", Some(input.lsym));
+/// The `bands` namelist, with fields as they appear in the file.
+#[derive(Serialize)]
+struct Bands<'a> {
+    prefix: Option<&'a str>,
+    outdir: Option<&'a str>,
+    filband: Option<&'a str>,
+    lsym: bool,
+}
 
-    lines.push(String::from(" /"));
-    Ok(lines.join("\n"))
+pub fn make_input_file(input: &Input) -> Result<String, Error> {
+    let bands = Bands {
+        prefix: input.prefix.as_deref(),
+        outdir: input
+            .out_dir
+            .as_ref()
+            .map(|path| path.to_str().ok_or(Error::OutDir))
+            .transpose()?,
+        filband: input
+            .filband
+            .as_ref()
+            .map(|path| path.to_str().ok_or(Error::Filband))
+            .transpose()?,
+        lsym: input.lsym,
+    };
+
+    Ok(namelist::to_string("bands", &bands)?)
 }
 
 pub fn write_input_file<P: AsRef<Path>>(input: &Input, file_path: P) -> Result<(), Error> {
@@ -43,6 +47,7 @@ pub enum Error {
     #[fail(display = "{}", _0)] Io(#[cause] io::Error),
     #[fail(display = "`out_dir` is not valid UTF-8")] OutDir,
     #[fail(display = "`filband` is not valid UTF-8")] Filband,
+    #[fail(display = "{}", _0)] Namelist(#[cause] namelist::Error),
 }
 
 impl From<io::Error> for Error {
@@ -50,3 +55,9 @@ impl From<io::Error> for Error {
         Error::Io(e)
     }
 }
+
+impl From<namelist::Error> for Error {
+    fn from(e: namelist::Error) -> Error {
+        Error::Namelist(e)
+    }
+}
@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+/// The `dos` namelist of dos.x, run after a pw.x `nscf` calculation.
+///
+/// Energies `Emin`, `Emax` and `DeltaE` are in eV; `degauss` is in Ry. If neither
+/// `bz_sum` nor `degauss` is given, the broadening of the pw.x calculation is used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Input {
+    pub prefix: Option<String>,
+    pub out_dir: Option<PathBuf>,
+    /// The file the DOS is written to; dos.x uses `<prefix>.dos` by default.
+    pub fildos: Option<PathBuf>,
+    pub bz_sum: Option<BzSum>,
+    /// The type of smearing used with `degauss`: 0 for Gaussian, 1 for Methfessel-Paxton,
+    /// -1 for Marzari-Vanderbilt and -99 for Fermi-Dirac.
+    pub ngauss: Option<i64>,
+    pub degauss: Option<f64>,
+    pub emin: Option<f64>,
+    pub emax: Option<f64>,
+    pub delta_e: Option<f64>,
+}
+
+/// Values of `bz_sum`, the method of integration over the Brillouin zone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BzSum {
+    Smearing,
+    Tetrahedra,
+    TetrahedraLin,
+    TetrahedraOpt,
+}
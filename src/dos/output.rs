@@ -0,0 +1,167 @@
+//! Reading of the density of states written by dos.x to `fildos`.
+//!
+//! Energies are in eV and densities of states in states/eV.
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use pw::output::split_numbers;
+
+/// A density of states on an energy grid, for each spin component.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Dos {
+    /// Given for unpolarized and noncollinear calculations.
+    Unpolarized(Vec<f64>),
+    Polarized {
+        up: Vec<f64>,
+        down: Vec<f64>,
+    },
+}
+
+impl Dos {
+    /// The density of states summed over the spin components.
+    pub fn total(&self) -> Vec<f64> {
+        match *self {
+            Dos::Unpolarized(ref dos) => dos.clone(),
+            Dos::Polarized { ref up, ref down } => {
+                up.iter().zip(down).map(|(u, d)| u + d).collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DosFile {
+    /// The Fermi energy given in the header of the file.
+    pub fermi_energy: Option<f64>,
+    pub energies: Vec<f64>,
+    pub dos: Dos,
+    /// The number of states below each energy, summed over the spin components.
+    pub integrated_dos: Vec<f64>,
+}
+
+/// A table of numbers with a one-line header starting with `#`, as written by dos.x and
+/// projwfc.x.
+pub(crate) struct Table {
+    pub header: String,
+    /// `columns[i][j]` is the value in column `i` of row `j`.
+    pub columns: Vec<Vec<f64>>,
+}
+
+pub fn read_dos_file<P: AsRef<Path>>(file_path: P) -> Result<DosFile, Error> {
+    parse_dos_file(&read_text(file_path)?)
+}
+
+/// Parse the `fildos` file, which has a header
+/// `#  E (eV)   dos(E)     Int dos(E) EFermi =    6.123 eV`
+/// followed by a line for each energy. For spin-polarized calculations, `dos(E)` is replaced
+/// by `dosup(E)` and `dosdw(E)`.
+pub fn parse_dos_file(text: &str) -> Result<DosFile, Error> {
+    let table = parse_table(text)?;
+    let polarized = table.header.contains("dosup(E)");
+    let n_columns = if polarized { 4 } else { 3 };
+    if table.columns.len() != n_columns {
+        return Err(value_error(
+            1,
+            &format!(
+                "expected {} columns; got {}",
+                n_columns,
+                table.columns.len()
+            ),
+        ));
+    }
+
+    let fermi_energy = match table.header.find("EFermi") {
+        Some(start) => {
+            let value = table.header[start + "EFermi".len()..]
+                .trim_start_matches([' ', '='])
+                .split_whitespace()
+                .next()
+                .and_then(|x| x.parse().ok());
+            Some(value.ok_or_else(|| value_error(1, "invalid `EFermi` in header"))?)
+        }
+        None => None,
+    };
+
+    let mut columns = table.columns.into_iter();
+    let energies = columns.next().unwrap_or_default();
+    let dos = if polarized {
+        Dos::Polarized {
+            up: columns.next().unwrap_or_default(),
+            down: columns.next().unwrap_or_default(),
+        }
+    } else {
+        Dos::Unpolarized(columns.next().unwrap_or_default())
+    };
+
+    Ok(DosFile {
+        fermi_energy,
+        energies,
+        dos,
+        integrated_dos: columns.next().unwrap_or_default(),
+    })
+}
+
+/// Parse a header line starting with `#` followed by rows with the same number of values.
+pub(crate) fn parse_table(text: &str) -> Result<Table, Error> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|&(_, l)| !l.trim().is_empty());
+
+    let header = match lines.next() {
+        Some((_, header)) if header.trim_start().starts_with('#') => String::from(header),
+        Some((i, _)) => return Err(value_error(i + 1, "expected a header starting with `#`")),
+        None => return Err(Error::Empty),
+    };
+
+    let mut columns: Vec<Vec<f64>> = Vec::new();
+    for (i, line) in lines {
+        let values = split_numbers(line).ok_or_else(|| value_error(i + 1, "expected numbers"))?;
+        if columns.is_empty() {
+            columns = vec![Vec::new(); values.len()];
+        } else if values.len() != columns.len() {
+            return Err(value_error(i + 1, "wrong number of values"));
+        }
+
+        for (column, x) in columns.iter_mut().zip(values) {
+            column.push(x);
+        }
+    }
+
+    if columns.is_empty() {
+        return Err(Error::Empty);
+    }
+
+    Ok(Table { header, columns })
+}
+
+pub(crate) fn read_text<P: AsRef<Path>>(file_path: P) -> Result<String, Error> {
+    let mut text = String::new();
+    File::open(file_path)?.read_to_string(&mut text)?;
+
+    Ok(text)
+}
+
+pub(crate) fn value_error(line: usize, message: &str) -> Error {
+    Error::Value {
+        line,
+        message: String::from(message),
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "line {}: {}", line, message)]
+    Value { line: usize, message: String },
+    #[fail(display = "density of states file contains no data")]
+    Empty,
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,68 @@
+use std::path::Path;
+use std::io;
+use std::io::Write;
+use std::fs::File;
+use namelist;
+use dos::input::{BzSum, Input};
+
+/// The `dos` namelist, with fields as they appear in the file.
+#[derive(Serialize)]
+struct Dos<'a> {
+    prefix: Option<&'a str>,
+    outdir: Option<&'a Path>,
+    fildos: Option<&'a Path>,
+    bz_sum: Option<&'a BzSum>,
+    ngauss: Option<i64>,
+    degauss: Option<f64>,
+    #[serde(rename = "Emin")]
+    emin: Option<f64>,
+    #[serde(rename = "Emax")]
+    emax: Option<f64>,
+    #[serde(rename = "DeltaE")]
+    delta_e: Option<f64>,
+}
+
+pub fn make_input_file(input: &Input) -> Result<String, Error> {
+    let dos = Dos {
+        prefix: input.prefix.as_deref(),
+        outdir: input.out_dir.as_deref(),
+        fildos: input.fildos.as_deref(),
+        bz_sum: input.bz_sum.as_ref(),
+        ngauss: input.ngauss,
+        degauss: input.degauss,
+        emin: input.emin,
+        emax: input.emax,
+        delta_e: input.delta_e,
+    };
+
+    Ok(namelist::to_string("dos", &dos)?)
+}
+
+pub fn write_input_file<P: AsRef<Path>>(input: &Input, file_path: P) -> Result<(), Error> {
+    let input_text = make_input_file(input)?;
+
+    let mut file = File::create(file_path)?;
+    file.write_all(input_text.as_bytes())?;
+
+    Ok(())
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "{}", _0)]
+    Namelist(#[cause] namelist::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<namelist::Error> for Error {
+    fn from(e: namelist::Error) -> Error {
+        Error::Namelist(e)
+    }
+}
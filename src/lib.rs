@@ -1,10 +1,23 @@
+// `failure_derive` generates its trait impls inside an anonymous `const` block.
+#![allow(non_local_definitions)]
+
 #[macro_use]
 extern crate failure;
 #[macro_use]
+extern crate serde;
+#[macro_use]
 extern crate serde_derive;
+extern crate roxmltree;
 
 pub mod error;
-mod serialize_util;
+pub mod namelist;
+pub mod units;
 pub mod pw;
 pub mod bands;
+pub mod dos;
+pub mod projwfc;
 pub mod pw2wannier90;
+pub mod wannier90;
+pub mod run;
+pub mod upf;
+pub mod structure;
@@ -0,0 +1,420 @@
+use serde::de;
+use serde::de::{DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use namelist::read::format_key;
+use namelist::{read_file, Entry, Error, Namelist, Value};
+
+/// The largest index accepted for a field read into a sequence of unknown length. pw.x and
+/// the post-processing codes dimension their namelist arrays by small limits, such as the
+/// maximum number of species `ntypx = 10`.
+const MAX_INDEX: usize = 1000;
+
+/// Read the namelist `nl` into `T`, which must deserialize as a struct or map.
+///
+/// Field names are matched ignoring case. Indexed fields such as `celldm(1)` are collected
+/// into a sequence, with the element at position `i - 1` given by `celldm(i)`; elements not
+/// given in the namelist are `None`, so the sequence element type must be an `Option` if
+/// any index may be skipped. Errors for a field give the line on which it appears.
+pub fn from_namelist<T: DeserializeOwned>(nl: &Namelist) -> Result<T, Error> {
+    T::deserialize(NamelistDeserializer { nl })
+}
+
+/// Read `text`, which must contain exactly one namelist and no cards, into `T`.
+pub fn from_str<T: DeserializeOwned>(text: &str) -> Result<T, Error> {
+    let file = read_file(text, &[])?;
+
+    match file.namelists.len() {
+        1 => from_namelist(&file.namelists[0]),
+        n => Err(Error::Message(format!(
+            "expected exactly one namelist; got {}",
+            n
+        ))),
+    }
+}
+
+/// Attach the location of the field being read to an error, unless it already has one.
+fn in_field(e: Error, name: &str, entry: &Entry) -> Error {
+    match e {
+        Error::Message(message) => Error::Value {
+            line: entry.line,
+            field: String::from(name),
+            message,
+        },
+        e => e,
+    }
+}
+
+struct NamelistDeserializer<'a> {
+    nl: &'a Namelist,
+}
+
+impl<'de, 'a> de::Deserializer<'de> for NamelistDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(FieldsAccess::new(self.nl, &[]))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_map(FieldsAccess::new(self.nl, fields))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// Access to the fields of a namelist, grouping together all entries with the same name.
+struct FieldsAccess<'a> {
+    groups: Vec<(String, Vec<&'a Entry>)>,
+    pos: usize,
+}
+
+impl<'a> FieldsAccess<'a> {
+    /// Each field name in the namelist is mapped to the matching element of `fields` (ignoring
+    /// case), so that struct fields with uppercase letters such as `Hubbard_U` are recognized.
+    fn new(nl: &'a Namelist, fields: &'static [&'static str]) -> FieldsAccess<'a> {
+        let mut groups: Vec<(String, Vec<&Entry>)> = Vec::new();
+
+        for entry in &nl.entries {
+            let name = fields
+                .iter()
+                .find(|f| f.to_lowercase() == entry.name)
+                .map_or_else(|| entry.name.clone(), |f| String::from(*f));
+
+            match groups.iter().position(|g| g.0 == name) {
+                Some(i) => groups[i].1.push(entry),
+                None => groups.push((name, vec![entry])),
+            }
+        }
+
+        FieldsAccess { groups, pos: 0 }
+    }
+}
+
+impl<'de, 'a> de::MapAccess<'de> for FieldsAccess<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.groups.get(self.pos) {
+            Some((name, entries)) => seed
+                .deserialize(name.clone().into_deserializer())
+                .map(Some)
+                .map_err(|e| in_field(e, &entries[0].name, entries[0])),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (_, ref entries) = self.groups[self.pos];
+        self.pos += 1;
+
+        let first = entries[0];
+        seed.deserialize(EntriesDeserializer {
+            name: &first.name,
+            index: Vec::new(),
+            entries: entries.clone(),
+        })
+        .map_err(|e| in_field(e, &first.name, first))
+    }
+}
+
+/// Deserializer for the entries of one field which share the leading indices `index`.
+/// If no further indices remain, this is a scalar value.
+struct EntriesDeserializer<'a> {
+    name: &'a str,
+    index: Vec<usize>,
+    entries: Vec<&'a Entry>,
+}
+
+impl<'a> EntriesDeserializer<'a> {
+    fn is_indexed(&self) -> bool {
+        let depth = self.index.len();
+        self.entries.iter().any(|e| e.index.len() > depth)
+    }
+
+    fn scalar(&self) -> Result<&'a Value, Error> {
+        let key = format_key(self.name, &self.index);
+
+        if self.is_indexed() {
+            return Err(Error::Value {
+                line: self.entries[0].line,
+                field: key,
+                message: String::from("expected a single value, not an array"),
+            });
+        }
+
+        if self.entries.len() > 1 {
+            return Err(Error::Value {
+                line: self.entries[1].line,
+                field: key,
+                message: String::from("given more than once"),
+            });
+        }
+
+        Ok(&self.entries[0].value)
+    }
+
+    /// Read the entries as a sequence of at most `max_len` elements, checking the indices
+    /// before any elements are allocated.
+    fn deserialize_indexed<'de, V: Visitor<'de>>(
+        self,
+        max_len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let depth = self.index.len();
+        let position = |e: &Entry| e.index.get(depth).cloned().unwrap_or(1);
+
+        if let Some(entry) = self.entries.iter().find(|e| position(e) > max_len) {
+            return Err(Error::Value {
+                line: entry.line,
+                field: entry.key(),
+                message: format!("index exceeds the maximum of {}", max_len),
+            });
+        }
+
+        let len = self.entries.iter().map(|e| position(e)).max().unwrap_or(0);
+        let mut elements = vec![Vec::new(); len];
+        for entry in &self.entries {
+            elements[position(entry) - 1].push(*entry);
+        }
+
+        visitor.visit_seq(IndexedAccess {
+            name: self.name,
+            index: self.index,
+            elements: elements.into_iter(),
+            next: 1,
+        })
+    }
+}
+
+macro_rules! forward_to_scalar {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                ValueDeserializer { value: self.scalar()? }.$method(visitor)
+            }
+        )*
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for EntriesDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_indexed() {
+            self.deserialize_seq(visitor)
+        } else {
+            ValueDeserializer {
+                value: self.scalar()?,
+            }
+            .deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// An entry without further indices, as in `celldm = 10.2`, is taken as the first element.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_indexed(MAX_INDEX, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_indexed(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_indexed(len, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        ValueDeserializer {
+            value: self.scalar()?,
+        }
+        .deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        ValueDeserializer {
+            value: self.scalar()?,
+        }
+        .deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(Error::Message(String::from(
+            "nested structs cannot be represented in a namelist",
+        )))
+    }
+
+    forward_to_scalar! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_unit
+        deserialize_map deserialize_identifier deserialize_ignored_any
+    }
+}
+
+/// Access to the elements of an indexed field. `elements` holds the entries at each
+/// successive index; an empty element is an index which was not given.
+struct IndexedAccess<'a> {
+    name: &'a str,
+    index: Vec<usize>,
+    elements: ::std::vec::IntoIter<Vec<&'a Entry>>,
+    next: usize,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for IndexedAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let entries = match self.elements.next() {
+            Some(entries) => entries,
+            None => return Ok(None),
+        };
+
+        let mut index = self.index.clone();
+        index.push(self.next);
+        self.next += 1;
+
+        if entries.is_empty() {
+            seed.deserialize(MissingDeserializer {
+                key: format_key(self.name, &index),
+            })
+            .map(Some)
+        } else {
+            seed.deserialize(EntriesDeserializer {
+                name: self.name,
+                index,
+                entries,
+            })
+            .map(Some)
+        }
+    }
+}
+
+/// Deserializer for an index of an array field which was not given.
+/// This is `None` if an `Option` is expected, and an error otherwise.
+struct MissingDeserializer {
+    key: String,
+}
+
+impl<'de> de::Deserializer<'de> for MissingDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(Error::Message(format!("missing value for `{}`", self.key)))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_none()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Deserializer for a single value. Strings may be read as unit enum variants, and integers
+/// may be read where a real value is expected.
+struct ValueDeserializer<'a> {
+    value: &'a Value,
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ValueDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match *self.value {
+            Value::Str(ref s) => visitor.visit_string(s.clone()),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Int(x) => visitor.visit_i64(x),
+            Value::Real(x) => visitor.visit_f64(x),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.as_f64() {
+            Some(x) => visitor.visit_f64(x),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match *self.value {
+            Value::Str(ref s) => visitor.visit_enum(s.clone().into_deserializer()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
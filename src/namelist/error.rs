@@ -0,0 +1,60 @@
+use std::error;
+use std::fmt;
+use std::fmt::Display;
+use serde::{de, ser};
+
+/// serde requires its error types to implement `std::error::Error`, so this implements
+/// `Display` and `std::error::Error` directly and obtains `Fail` from `failure`'s blanket impl.
+#[derive(Debug)]
+pub enum Error {
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    Value {
+        line: usize,
+        field: String,
+        message: String,
+    },
+    Field {
+        field: String,
+        message: String,
+    },
+    Message(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Syntax { line, ref message } => write!(f, "line {}: {}", line, message),
+            Error::Value {
+                line,
+                ref field,
+                ref message,
+            } => write!(
+                f,
+                "line {}: invalid value for `{}`: {}",
+                line, field, message
+            ),
+            Error::Field {
+                ref field,
+                ref message,
+            } => write!(f, "cannot write field `{}`: {}", field, message),
+            Error::Message(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Error {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Error {
+        Error::Message(msg.to_string())
+    }
+}
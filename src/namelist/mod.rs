@@ -0,0 +1,27 @@
+//! Reading of the Fortran namelist + card input format used by the Quantum Espresso programs.
+//!
+//! An input file consists of a sequence of namelists such as
+//!
+//! ```text
+//!  &system
+//!     ibrav=0, celldm(1)=10.2,  ! comment
+//!     ecutwfc=3.0d1,
+//!  /
+//! ```
+//!
+//! followed by a sequence of cards (`ATOMIC_SPECIES`, `K_POINTS automatic`, ...) whose
+//! contents are given as whitespace-separated fields. The reader here only splits the file
+//! into these pieces; mapping them onto the input representation of a particular program
+//! is left to that program's module.
+//!
+//! Individual namelists can also be read into and written from any struct implementing
+//! `Deserialize`/`Serialize`, with `from_namelist` and `to_string`.
+pub mod de;
+mod error;
+pub mod read;
+pub mod ser;
+
+pub use self::de::{from_namelist, from_str};
+pub use self::error::Error;
+pub use self::read::{read_file, Card, CardLine, Entry, File, Namelist, Value};
+pub use self::ser::to_string;
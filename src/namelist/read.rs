@@ -0,0 +1,529 @@
+use std::fmt;
+use namelist::Error;
+
+/// The contents of a namelist + card input file, in the order they appear in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct File {
+    pub namelists: Vec<Namelist>,
+    pub cards: Vec<Card>,
+}
+
+impl File {
+    /// Find the namelist with the given (lowercase) name, if present.
+    pub fn namelist(&self, name: &str) -> Option<&Namelist> {
+        self.namelists.iter().find(|nl| nl.name == name)
+    }
+
+    /// Find the card with the given (uppercase) name, if present.
+    pub fn card(&self, name: &str) -> Option<&Card> {
+        self.cards.iter().find(|card| card.name == name)
+    }
+}
+
+/// A namelist such as `&system ... /`. The name is converted to lowercase, since Fortran
+/// names are case-insensitive. `line` is the line on which the namelist begins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Namelist {
+    pub name: String,
+    pub line: usize,
+    pub entries: Vec<Entry>,
+}
+
+/// A single `name(index)=value` assignment within a namelist.
+///
+/// `name` is converted to lowercase. `index` is empty for scalar fields; for indexed fields
+/// such as `celldm(1)` or `starting_ns_eigenvalue(1,2,1)` it holds the (1-based) indices.
+/// An assignment of several values to an array, e.g. `celldm = 10.0, 1.0`, is split into one
+/// entry per value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub index: Vec<usize>,
+    pub value: Value,
+    pub line: usize,
+}
+
+impl Entry {
+    /// The field name as it would appear in the input file, including the index.
+    pub fn key(&self) -> String {
+        format_key(&self.name, &self.index)
+    }
+}
+
+/// Render a field name with its indices, e.g. `celldm(1)`.
+pub fn format_key(name: &str, index: &[usize]) -> String {
+    if index.is_empty() {
+        String::from(name)
+    } else {
+        let index = index
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        format!("{}({})", name, index)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Bool(bool),
+    Int(i64),
+    Real(f64),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::Str(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Int(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Integer values are accepted where a real value is expected, as in Fortran.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Int(x) => Some(x as f64),
+            Value::Real(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Str(ref s) => write!(f, "'{}'", s.replace('\'', "''")),
+            Value::Bool(true) => write!(f, ".true."),
+            Value::Bool(false) => write!(f, ".false."),
+            Value::Int(x) => write!(f, "{}", x),
+            Value::Real(x) => write!(f, "{:?}", x),
+        }
+    }
+}
+
+/// A card such as `ATOMIC_POSITIONS {crystal}`. The name is converted to uppercase and the
+/// option (with any enclosing braces or parentheses removed) to lowercase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Card {
+    pub name: String,
+    pub option: Option<String>,
+    pub line: usize,
+    pub lines: Vec<CardLine>,
+}
+
+/// A non-empty line within a card, split into whitespace-separated fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardLine {
+    pub line: usize,
+    pub fields: Vec<String>,
+}
+
+/// Split `text` into its namelists and cards.
+///
+/// Since card contents are free-form, a line is only recognized as beginning a card if its
+/// first word (ignoring case) is one of `card_names`.
+pub fn read_file(text: &str, card_names: &[&str]) -> Result<File, Error> {
+    let mut namelists = Vec::new();
+    let mut cards = Vec::new();
+
+    let mut open_namelist: Option<(String, usize, Vec<Token>)> = None;
+    let mut open_card: Option<Card> = None;
+
+    for (i, raw_line) in text.lines().enumerate() {
+        let line = i + 1;
+
+        if let Some((name, start, mut tokens)) = open_namelist.take() {
+            if lex_line(line, raw_line, &mut tokens)? {
+                namelists.push(make_namelist(name, start, &tokens)?);
+            } else {
+                open_namelist = Some((name, start, tokens));
+            }
+            continue;
+        }
+
+        let content = strip_card_comment(raw_line).trim();
+        if content.is_empty() {
+            continue;
+        }
+
+        if content.starts_with('&') {
+            if open_card.is_some() {
+                return Err(Error::Syntax {
+                    line,
+                    message: String::from("namelists must be given before all cards"),
+                });
+            }
+
+            let name_end = content
+                .find(|c: char| c.is_whitespace() || c == '!')
+                .unwrap_or(content.len());
+            let name = content[1..name_end].to_lowercase();
+            if name.is_empty() {
+                return Err(Error::Syntax {
+                    line,
+                    message: String::from("missing namelist name after `&`"),
+                });
+            }
+
+            let mut tokens = Vec::new();
+            if lex_line(line, &content[name_end..], &mut tokens)? {
+                namelists.push(make_namelist(name, line, &tokens)?);
+            } else {
+                open_namelist = Some((name, line, tokens));
+            }
+            continue;
+        }
+
+        let fields: Vec<String> = content.split_whitespace().map(String::from).collect();
+        let first = fields[0].to_uppercase();
+
+        if card_names.iter().any(|name| *name == first) {
+            if let Some(card) = open_card.take() {
+                cards.push(card);
+            }
+
+            let option = content[fields[0].len()..]
+                .trim()
+                .trim_matches(|c| c == '{' || c == '}' || c == '(' || c == ')')
+                .trim()
+                .to_lowercase();
+
+            open_card = Some(Card {
+                name: first,
+                option: if option.is_empty() {
+                    None
+                } else {
+                    Some(option)
+                },
+                line,
+                lines: Vec::new(),
+            });
+        } else if let Some(ref mut card) = open_card {
+            card.lines.push(CardLine { line, fields });
+        } else {
+            return Err(Error::Syntax {
+                line,
+                message: format!(
+                    "unexpected text outside of a namelist or card: `{}`",
+                    content
+                ),
+            });
+        }
+    }
+
+    if let Some((name, start, _)) = open_namelist {
+        return Err(Error::Syntax {
+            line: start,
+            message: format!("namelist `&{}` is not terminated by `/`", name),
+        });
+    }
+
+    if let Some(card) = open_card {
+        cards.push(card);
+    }
+
+    Ok(File { namelists, cards })
+}
+
+fn strip_card_comment(line: &str) -> &str {
+    match line.find(['!', '#']) {
+        Some(i) => &line[..i],
+        None => line,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Str(String),
+    Equals,
+    Comma,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+}
+
+/// Split the namelist text on one line into tokens, appending them to `tokens`.
+/// Returns `true` if the end of the namelist was reached on this line.
+fn lex_line(line: usize, text: &str, tokens: &mut Vec<Token>) -> Result<bool, Error> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '!' {
+            break;
+        } else if c == '/' {
+            return Ok(true);
+        } else if c == ',' {
+            tokens.push(Token {
+                kind: TokenKind::Comma,
+                line,
+            });
+            i += 1;
+        } else if c == '=' {
+            tokens.push(Token {
+                kind: TokenKind::Equals,
+                line,
+            });
+            i += 1;
+        } else if c == '\'' || c == '"' {
+            // Quotes inside a string are escaped by doubling them.
+            let mut s = String::new();
+            let mut j = i + 1;
+            loop {
+                if j >= chars.len() {
+                    return Err(Error::Syntax {
+                        line,
+                        message: String::from("unterminated string"),
+                    });
+                }
+                if chars[j] == c {
+                    if j + 1 < chars.len() && chars[j + 1] == c {
+                        s.push(c);
+                        j += 2;
+                        continue;
+                    }
+                    break;
+                }
+                s.push(chars[j]);
+                j += 1;
+            }
+            tokens.push(Token {
+                kind: TokenKind::Str(s),
+                line,
+            });
+            i = j + 1;
+        } else {
+            let mut word = String::new();
+            let mut in_parens = false;
+            while i < chars.len() {
+                let c = chars[i];
+                if in_parens {
+                    if c == ')' {
+                        in_parens = false;
+                    }
+                    if !c.is_whitespace() {
+                        word.push(c);
+                    }
+                } else if c.is_whitespace() || "=,/!'\"".contains(c) {
+                    break;
+                } else {
+                    if c == '(' {
+                        in_parens = true;
+                    }
+                    word.push(c);
+                }
+                i += 1;
+            }
+
+            if word.to_lowercase() == "&end" {
+                return Ok(true);
+            }
+            if word.starts_with('&') {
+                return Err(Error::Syntax {
+                    line,
+                    message: format!("unexpected `{}` before the end of the namelist", word),
+                });
+            }
+
+            tokens.push(Token {
+                kind: TokenKind::Word(word),
+                line,
+            });
+        }
+    }
+
+    Ok(false)
+}
+
+fn make_namelist(name: String, line: usize, tokens: &[Token]) -> Result<Namelist, Error> {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+
+    while pos < tokens.len() {
+        let token = &tokens[pos];
+
+        let key = match token.kind {
+            TokenKind::Comma => {
+                pos += 1;
+                continue;
+            }
+            TokenKind::Word(ref key) => key,
+            _ => {
+                return Err(Error::Syntax {
+                    line: token.line,
+                    message: String::from("expected a field name"),
+                })
+            }
+        };
+        let (field_name, index) = parse_key(token.line, key)?;
+
+        match tokens.get(pos + 1) {
+            Some(&Token {
+                kind: TokenKind::Equals,
+                ..
+            }) => (),
+            _ => {
+                return Err(Error::Syntax {
+                    line: token.line,
+                    message: format!("expected `=` after `{}`", key),
+                })
+            }
+        }
+        pos += 2;
+
+        // Collect the values assigned: a single value, or a list of values for an array.
+        let mut values = Vec::new();
+        while let Some(value_token) = tokens.get(pos) {
+            let is_key = match tokens.get(pos + 1) {
+                Some(next) => next.kind == TokenKind::Equals,
+                None => false,
+            };
+
+            match value_token.kind {
+                TokenKind::Word(ref word) if !is_key => {
+                    values.push((value_token.line, parse_value(value_token.line, word)?));
+                }
+                TokenKind::Str(ref s) => {
+                    values.push((value_token.line, Value::Str(s.clone())));
+                }
+                TokenKind::Comma if !values.is_empty() => (),
+                _ => break,
+            }
+            pos += 1;
+        }
+
+        if values.is_empty() {
+            return Err(Error::Syntax {
+                line: token.line,
+                message: format!("missing value for `{}`", key),
+            });
+        }
+
+        if values.len() == 1 {
+            let (value_line, value) = values.remove(0);
+            entries.push(Entry {
+                name: field_name,
+                index,
+                value,
+                line: value_line,
+            });
+        } else {
+            let start = match index.len() {
+                0 => 1,
+                1 => index[0],
+                _ => {
+                    return Err(Error::Syntax {
+                        line: token.line,
+                        message: format!(
+                            "cannot assign a list of values to multi-dimensional `{}`",
+                            key
+                        ),
+                    })
+                }
+            };
+
+            for (offset, (value_line, value)) in values.into_iter().enumerate() {
+                entries.push(Entry {
+                    name: field_name.clone(),
+                    index: vec![start + offset],
+                    value,
+                    line: value_line,
+                });
+            }
+        }
+    }
+
+    Ok(Namelist {
+        name,
+        line,
+        entries,
+    })
+}
+
+/// Split a field name such as `celldm(1)` into its lowercase name and its indices.
+fn parse_key(line: usize, key: &str) -> Result<(String, Vec<usize>), Error> {
+    let invalid = || Error::Syntax {
+        line,
+        message: format!("invalid field name `{}`", key),
+    };
+
+    let (name, index) = match key.find('(') {
+        Some(open) => {
+            if !key.ends_with(')') {
+                return Err(invalid());
+            }
+            let index = key[open + 1..key.len() - 1]
+                .split(',')
+                .map(|i| i.trim().parse::<usize>())
+                .collect::<Result<Vec<usize>, _>>()
+                .map_err(|_| invalid())?;
+            if index.contains(&0) {
+                return Err(invalid());
+            }
+            (&key[..open], index)
+        }
+        None => (key, Vec::new()),
+    };
+
+    let valid_name = name.chars().next().is_some_and(|c| c.is_alphabetic())
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if !valid_name {
+        return Err(invalid());
+    }
+
+    Ok((name.to_lowercase(), index))
+}
+
+/// Interpret an unquoted value: a logical, an integer, or a real number, which may use
+/// the Fortran `d` exponent marker.
+fn parse_value(line: usize, word: &str) -> Result<Value, Error> {
+    let lower = word.to_lowercase();
+
+    match lower.as_str() {
+        ".true." | ".t." | ".t" | "t" | "true" => return Ok(Value::Bool(true)),
+        ".false." | ".f." | ".f" | "f" | "false" => return Ok(Value::Bool(false)),
+        _ => (),
+    }
+
+    if let Ok(x) = lower.parse::<i64>() {
+        return Ok(Value::Int(x));
+    }
+
+    let starts_numeric = lower
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_digit() || "+-.".contains(c));
+    if starts_numeric {
+        if let Ok(x) = lower.replace('d', "e").parse::<f64>() {
+            return Ok(Value::Real(x));
+        }
+    }
+
+    Err(Error::Syntax {
+        line,
+        message: format!("invalid value `{}`", word),
+    })
+}
@@ -0,0 +1,460 @@
+use serde::ser;
+use serde::ser::{Impossible, Serialize};
+use namelist::read::format_key;
+use namelist::{Error, Value};
+
+/// Write `value`, which must serialize as a struct, as the namelist `&name`.
+///
+/// Each field of the struct gives one `field=value,` line, in the order the fields are
+/// declared. Fields with a value of `None` are omitted. Sequences are written element by
+/// element as indexed fields, e.g. a field `celldm: vec![Some(10.2), None, Some(1.6)]`
+/// gives `celldm(1)=10.2,` and `celldm(3)=1.6,`; nested sequences give multiple indices.
+pub fn to_string<T: Serialize>(name: &str, value: &T) -> Result<String, Error> {
+    let mut lines = vec![format!(" &{}", name)];
+    value.serialize(NamelistSerializer { lines: &mut lines })?;
+    lines.push(String::from(" /"));
+
+    Ok(lines.join("\n"))
+}
+
+/// Render a real value such that it can be read by Fortran: use exponential notation for
+/// very small or large magnitudes, and the shortest exact decimal representation otherwise.
+pub fn format_real(x: f64) -> String {
+    if x != 0.0 && (x.abs() < 1e-3 || x.abs() >= 1e7) {
+        format!("{:e}", x)
+    } else {
+        format!("{}", x)
+    }
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::Message(format!("{} cannot be represented in a namelist", what))
+}
+
+struct NamelistSerializer<'a> {
+    lines: &'a mut Vec<String>,
+}
+
+impl<'a> ser::Serializer for NamelistSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<(), Error> {
+        Err(unsupported("a top-level logical value"))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<(), Error> {
+        Err(unsupported("a top-level integer"))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<(), Error> {
+        Err(unsupported("a top-level integer"))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<(), Error> {
+        Err(unsupported("a top-level real value"))
+    }
+
+    fn serialize_char(self, _v: char) -> Result<(), Error> {
+        Err(unsupported("a top-level string"))
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<(), Error> {
+        Err(unsupported("a top-level string"))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), Error> {
+        Err(unsupported("a byte array"))
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Err(unsupported("a top-level optional value"))
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<(), Error> {
+        Err(unsupported("a top-level optional value"))
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Err(unsupported("a unit value"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Err(unsupported("a unit struct"))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Error> {
+        Err(unsupported("a top-level enum"))
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        Err(unsupported("a top-level enum"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(unsupported("a top-level sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(unsupported("a top-level sequence"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(unsupported("a top-level sequence"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(unsupported("a top-level enum"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(unsupported("a map"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(unsupported("a top-level enum"))
+    }
+}
+
+impl<'a> ser::SerializeStruct for NamelistSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value
+            .serialize(FieldSerializer {
+                name: key,
+                index: Vec::new(),
+                lines: self.lines,
+            })
+            .map_err(|e| match e {
+                Error::Message(message) => Error::Field {
+                    field: String::from(key),
+                    message,
+                },
+                e => e,
+            })
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Serializer for the value of a single (possibly indexed) namelist field.
+struct FieldSerializer<'a> {
+    name: &'static str,
+    index: Vec<usize>,
+    lines: &'a mut Vec<String>,
+}
+
+impl<'a> FieldSerializer<'a> {
+    fn push(self, value: String) -> Result<(), Error> {
+        let key = format_key(self.name, &self.index);
+        self.lines.push(format!("    {}={},", key, value));
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for FieldSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = IndexedSerializer<'a>;
+    type SerializeTuple = IndexedSerializer<'a>;
+    type SerializeTupleStruct = IndexedSerializer<'a>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.push(Value::Bool(v).to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.push(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.push(v.to_string())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        if !v.is_finite() {
+            return Err(Error::Message(format!("non-finite value {}", v)));
+        }
+        self.push(format_real(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.push(Value::Str(v.to_string()).to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.push(Value::Str(String::from(v)).to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), Error> {
+        Err(unsupported("a byte array"))
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Err(unsupported("a unit value"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Err(unsupported("a unit struct"))
+    }
+
+    /// Unit variants are written as strings, e.g. `occupations='smearing'`.
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        Err(unsupported("an enum variant with data"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<IndexedSerializer<'a>, Error> {
+        Ok(IndexedSerializer {
+            field: self,
+            next: 1,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<IndexedSerializer<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<IndexedSerializer<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(unsupported("an enum variant with data"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(unsupported("a map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(unsupported("a nested struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(unsupported("an enum variant with data"))
+    }
+}
+
+/// Serializer for the elements of an array field, each of which is written with its own index.
+struct IndexedSerializer<'a> {
+    field: FieldSerializer<'a>,
+    next: usize,
+}
+
+impl<'a> IndexedSerializer<'a> {
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let mut index = self.field.index.clone();
+        index.push(self.next);
+        self.next += 1;
+
+        value.serialize(FieldSerializer {
+            name: self.field.name,
+            index,
+            lines: self.field.lines,
+        })
+    }
+}
+
+impl<'a> ser::SerializeSeq for IndexedSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTuple for IndexedSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for IndexedSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+use std::path::PathBuf;
+
+/// The `projwfc` namelist of projwfc.x, run after a pw.x `nscf` calculation.
+///
+/// Energies `Emin`, `Emax` and `DeltaE` are in eV; `degauss` is in Ry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Input {
+    pub prefix: Option<String>,
+    pub out_dir: Option<PathBuf>,
+    /// The prefix of the projected DOS files, which are written to `<filpdos>.pdos_tot` and
+    /// `<filpdos>.pdos_atm#N(El)_wfc#M(l)`. projwfc.x uses `prefix` by default.
+    pub filpdos: Option<String>,
+    /// The type of smearing used with `degauss`: 0 for Gaussian, 1 for Methfessel-Paxton,
+    /// -1 for Marzari-Vanderbilt and -99 for Fermi-Dirac.
+    pub ngauss: Option<i64>,
+    pub degauss: Option<f64>,
+    pub emin: Option<f64>,
+    pub emax: Option<f64>,
+    pub delta_e: Option<f64>,
+    /// If false, the projections are not symmetrized, as is appropriate for `nscf`
+    /// calculations with `nosym = .true.`.
+    pub lsym: Option<bool>,
+}
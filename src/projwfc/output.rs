@@ -0,0 +1,279 @@
+//! Reading of the projected densities of states written by projwfc.x.
+//!
+//! projwfc.x writes the total DOS to `<filpdos>.pdos_tot` and the DOS projected on each
+//! atomic wavefunction to `<filpdos>.pdos_atm#N(El)_wfc#M(l)`, where `N` is the index of the
+//! atom, `El` its species, `M` the index of the wavefunction on the atom and `l` its angular
+//! momentum, e.g. `p` or `p_j1.5` with spin-orbit coupling. Energies are in eV and densities
+//! of states in states/eV.
+use std::fs;
+use std::io;
+use std::path::Path;
+use dos::output::{parse_table, read_text, value_error, Dos};
+use dos;
+use projwfc::input::Input;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pdos {
+    pub energies: Vec<f64>,
+    /// The total DOS for `pdos_tot` files, or the DOS projected on the wavefunction, summed
+    /// over its magnetic quantum numbers, for `pdos_atm` files.
+    pub ldos: Dos,
+    /// The sum of the projections on all wavefunctions for `pdos_tot` files, or the DOS
+    /// projected on each magnetic quantum number `m` (or `m_j` with spin-orbit coupling) for
+    /// `pdos_atm` files.
+    pub pdos: Vec<Dos>,
+}
+
+/// The atomic wavefunction a `pdos_atm` file is projected on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtomicWfc {
+    /// The index of the atom, starting at 1 as in the file name.
+    pub atom: usize,
+    pub species: String,
+    /// The index of the wavefunction on the atom, starting at 1 as in the file name.
+    pub wfc: usize,
+    pub l: u32,
+    /// The total angular momentum, given with spin-orbit coupling only.
+    pub j: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectedDos {
+    pub wfc: AtomicWfc,
+    pub pdos: Pdos,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Projections {
+    pub total: Pdos,
+    /// The projections ordered by atom and wavefunction index.
+    pub wfcs: Vec<ProjectedDos>,
+}
+
+/// The prefix of the files written by projwfc.x for `input`, relative to the directory it
+/// is run in.
+pub fn pdos_file_prefix(input: &Input) -> String {
+    match (input.filpdos.as_ref(), input.prefix.as_ref()) {
+        (Some(filpdos), _) => filpdos.clone(),
+        (None, Some(prefix)) => prefix.clone(),
+        (None, None) => String::from("pwscf"),
+    }
+}
+
+/// Read `pdos_tot` and all of the `pdos_atm` files written by projwfc.x for `input`, run
+/// in `run_dir`.
+pub fn read_projections<P: AsRef<Path>>(input: &Input, run_dir: P) -> Result<Projections, Error> {
+    let prefix_path = run_dir.as_ref().join(pdos_file_prefix(input));
+    let dir = match prefix_path.parent() {
+        Some(dir) => dir.to_path_buf(),
+        None => run_dir.as_ref().to_path_buf(),
+    };
+    let file_prefix = match prefix_path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => format!("{}.pdos_", name),
+        None => return Err(Error::FileName(pdos_file_prefix(input))),
+    };
+
+    let total = read_pdos(dir.join(format!("{}tot", file_prefix)))?;
+
+    let mut wfcs = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.starts_with(&file_prefix) => name[file_prefix.len()..].to_string(),
+            _ => continue,
+        };
+        if !name.starts_with("atm#") {
+            continue;
+        }
+
+        let wfc = parse_wfc_name(&name)?;
+        let pdos = read_pdos(&path)?;
+        if pdos.energies != total.energies {
+            return Err(Error::Mismatch(name));
+        }
+
+        wfcs.push(ProjectedDos { wfc, pdos });
+    }
+    wfcs.sort_by_key(|p| (p.wfc.atom, p.wfc.wfc));
+
+    Ok(Projections { total, wfcs })
+}
+
+pub fn read_pdos<P: AsRef<Path>>(file_path: P) -> Result<Pdos, Error> {
+    parse_pdos(&read_text(file_path)?)
+}
+
+/// Parse a `pdos_tot` or `pdos_atm` file.
+///
+/// Each column after the energy is named in the header, e.g. `ldos(E)` or `pdos(E)`. The
+/// spin components of spin-polarized calculations are given in pairs of columns named
+/// `...up(E)` and `...dw(E)`.
+pub fn parse_pdos(text: &str) -> Result<Pdos, Error> {
+    let table = parse_table(text)?;
+
+    let names: Vec<&str> = table
+        .header
+        .split_whitespace()
+        .filter(|name| name.ends_with("(E)"))
+        .collect();
+    if names.len() + 1 != table.columns.len() {
+        return Err(value_error(1, "header does not match the number of columns").into());
+    }
+
+    let mut columns = table.columns.into_iter();
+    let energies = columns.next().unwrap_or_default();
+
+    let mut doses = Vec::new();
+    let mut names = names.into_iter();
+    while let Some(name) = names.next() {
+        let column = columns.next().unwrap_or_default();
+        if !name.ends_with("up(E)") {
+            doses.push(Dos::Unpolarized(column));
+            continue;
+        }
+
+        match names.next() {
+            Some(down) if down.ends_with("dw(E)") => doses.push(Dos::Polarized {
+                up: column,
+                down: columns.next().unwrap_or_default(),
+            }),
+            _ => return Err(value_error(1, "spin up column without spin down column").into()),
+        }
+    }
+
+    if doses.len() < 2 {
+        return Err(value_error(1, "expected a DOS and a projected DOS").into());
+    }
+    let ldos = doses.remove(0);
+
+    Ok(Pdos {
+        energies,
+        ldos,
+        pdos: doses,
+    })
+}
+
+/// Parse the part of a `pdos_atm` file name following `.pdos_`, e.g. `atm#1(Si)_wfc#2(p)`.
+pub fn parse_wfc_name(name: &str) -> Result<AtomicWfc, Error> {
+    let error = || Error::FileName(String::from(name));
+
+    let rest = name.strip_prefix("atm#").ok_or_else(error)?;
+    let (atom, rest) = rest.split_at(rest.find('(').ok_or_else(error)?);
+    let rest = &rest[1..];
+    let (species, rest) = rest.split_at(rest.find(")_wfc#").ok_or_else(error)?);
+    let rest = &rest[")_wfc#".len()..];
+    let (wfc, rest) = rest.split_at(rest.find('(').ok_or_else(error)?);
+    let label = rest[1..].strip_suffix(')').ok_or_else(error)?;
+
+    let (l, j) = match label.find("_j") {
+        Some(i) => (
+            &label[..i],
+            Some(label[i + 2..].parse().map_err(|_| error())?),
+        ),
+        None => (label, None),
+    };
+    let l = match l {
+        "s" => 0,
+        "p" => 1,
+        "d" => 2,
+        "f" => 3,
+        _ => return Err(error()),
+    };
+
+    Ok(AtomicWfc {
+        atom: atom.parse().map_err(|_| error())?,
+        species: String::from(species),
+        wfc: wfc.parse().map_err(|_| error())?,
+        l,
+        j,
+    })
+}
+
+/// The sum of `ldos` over the wavefunctions with the same `key`, in order of first
+/// appearance of each key.
+pub fn group_by<K, F>(projections: &Projections, key: F) -> Result<Vec<(K, Dos)>, Error>
+where
+    K: PartialEq,
+    F: Fn(&AtomicWfc) -> K,
+{
+    let mut groups: Vec<(K, Dos)> = Vec::new();
+    for projection in projections.wfcs.iter() {
+        let k = key(&projection.wfc);
+        let ldos = &projection.pdos.ldos;
+        match groups.iter_mut().find(|(group, _)| *group == k) {
+            Some((_, sum)) => {
+                if !add_dos(sum, ldos) {
+                    return Err(Error::Spin);
+                }
+            }
+            None => groups.push((k, ldos.clone())),
+        }
+    }
+
+    Ok(groups)
+}
+
+/// The projected DOS of each atom, by the index of the atom starting at 1.
+pub fn dos_by_atom(projections: &Projections) -> Result<Vec<(usize, Dos)>, Error> {
+    group_by(projections, |wfc| wfc.atom)
+}
+
+pub fn dos_by_species(projections: &Projections) -> Result<Vec<(String, Dos)>, Error> {
+    group_by(projections, |wfc| wfc.species.clone())
+}
+
+pub fn dos_by_l(projections: &Projections) -> Result<Vec<(u32, Dos)>, Error> {
+    group_by(projections, |wfc| wfc.l)
+}
+
+/// Add `other` to `sum`, returning false if they have different spin components.
+fn add_dos(sum: &mut Dos, other: &Dos) -> bool {
+    let add = |a: &mut Vec<f64>, b: &Vec<f64>| {
+        for (x, y) in a.iter_mut().zip(b) {
+            *x += y;
+        }
+    };
+
+    match (sum, other) {
+        (&mut Dos::Unpolarized(ref mut a), Dos::Unpolarized(b)) => add(a, b),
+        (
+            &mut Dos::Polarized {
+                up: ref mut a_up,
+                down: ref mut a_down,
+            },
+            Dos::Polarized { up, down },
+        ) => {
+            add(a_up, up);
+            add(a_down, down);
+        }
+        _ => return false,
+    }
+
+    true
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "{}", _0)]
+    Dos(#[cause] dos::output::Error),
+    #[fail(display = "invalid projected DOS file name `{}`", _0)]
+    FileName(String),
+    #[fail(display = "energies of `{}` differ from those of `pdos_tot`", _0)]
+    Mismatch(String),
+    #[fail(display = "projections have different spin components")]
+    Spin,
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<dos::output::Error> for Error {
+    fn from(e: dos::output::Error) -> Error {
+        Error::Dos(e)
+    }
+}
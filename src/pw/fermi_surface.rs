@@ -0,0 +1,294 @@
+//! Fermi surfaces from a pw.x `nscf` calculation on a uniform k-point grid, written as
+//! XCrySDen band grid files (BXSF).
+//!
+//! The BXSF grid includes the periodic endpoints of each direction: a grid of `n` points
+//! along a reciprocal lattice vector runs from 0 to 1 in steps of `1 / (n - 1)`. pw.x is run
+//! on the `n - 1` distinct points only, listed by `generate_uniform_kpoints`, and the
+//! endpoints are filled in from their periodic images.
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use pw::input::{Calculation, Cell, Control, Input, KPoints, LatticeUnits, Verbosity};
+use pw::lattice::lattice_vectors;
+use pw::output;
+use pw::xml;
+use units::{BOHR_ANGSTROM, HARTREE_EV};
+
+/// The largest error in each Cartesian component of the k-points read from the data file,
+/// in units of 2pi/alat.
+const DATA_FILE_PRECISION: f64 = 1e-6;
+
+/// The largest error in each Cartesian component of the k-points read from standard output,
+/// in units of 2pi/alat. pw.x writes these with 4 decimals.
+const OUTPUT_PRECISION: f64 = 5e-5;
+
+/// The parameters of the `nscf` calculation which are not determined by the `scf` input.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Parameters {
+    /// The number of points of the BXSF grid along each reciprocal lattice vector, including
+    /// the periodic endpoints. Each must be at least 2.
+    pub grid: [u64; 3],
+    pub diago_thr_init: f64,
+    pub nbnd: Option<u64>,
+}
+
+/// Band energies on a grid spanning the reciprocal cell, as written to a BXSF file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FermiSurface {
+    /// The number of grid points along each reciprocal lattice vector, including the
+    /// periodic endpoints.
+    pub grid: [u64; 3],
+    /// The reciprocal lattice vectors in 1/Angstrom, as rows.
+    pub reciprocal_vectors: [[f64; 3]; 3],
+    /// Fermi energy in eV.
+    pub fermi_energy: f64,
+    /// The energies of each band in eV at each grid point, with the index along the third
+    /// reciprocal lattice vector varying fastest. For spin-polarized calculations, the spin
+    /// up bands are followed by the spin down bands.
+    pub bands: Vec<Vec<f64>>,
+}
+
+/// Derive the `nscf` input from the `scf` input `base`.
+///
+/// The input uses `KPoints::CrystalUniform` with `nosym = .true.`, so that pw.x computes the
+/// bands at every point of the grid rather than at the irreducible points only. It sets
+/// `verbosity = 'high'`, without which pw.x does not write the band energies to standard
+/// output for 100 or more k-points.
+pub fn from_scf_input(base: &Input, parameters: &Parameters) -> Result<Input, Error> {
+    if !matches!(base.calculation, Calculation::Scf { .. }) {
+        return Err(Error::ScfCalculation);
+    }
+    if parameters.grid.iter().any(|&n| n < 2) {
+        return Err(Error::Grid(parameters.grid));
+    }
+
+    Ok(Input {
+        calculation: Calculation::Nscf {
+            diago_thr_init: Some(parameters.diago_thr_init),
+            nbnd: parameters.nbnd,
+            nosym: Some(true),
+        },
+        control: Control {
+            verbosity: Some(Verbosity::High),
+            ..base.control.clone()
+        },
+        k_points: KPoints::CrystalUniform(nscf_grid(parameters.grid)),
+        ..base.clone()
+    })
+}
+
+/// The Fermi surface from the data file written by pw.x for the `nscf` input `input`.
+pub fn from_data_file(input: &Input, data_file: &xml::DataFile) -> Result<FermiSurface, Error> {
+    let band_structure = &data_file.band_structure;
+
+    let fermi_energy = match band_structure.fermi_energy {
+        Some(xml::FermiEnergy::Fermi(e)) | Some(xml::FermiEnergy::HighestOccupied(e, _)) => e,
+        _ => return Err(Error::FermiEnergy),
+    };
+
+    let k_points: Vec<([f64; 3], Vec<f64>)> = band_structure
+        .k_points
+        .iter()
+        .map(|k| {
+            let energies = k.eigenvalues.iter().map(|e| e * HARTREE_EV).collect();
+            (k.k, energies)
+        })
+        .collect();
+
+    fermi_surface(
+        input,
+        fermi_energy * HARTREE_EV,
+        &k_points,
+        DATA_FILE_PRECISION,
+    )
+}
+
+/// The Fermi surface from the standard output of pw.x for the `nscf` input `input`.
+pub fn from_output(input: &Input, output: &output::Output) -> Result<FermiSurface, Error> {
+    let bands = match output.result {
+        output::CalculationOutput::Nscf(ref bands) => bands,
+        _ => return Err(Error::NscfCalculation),
+    };
+
+    let fermi_energy = match bands.fermi_energy {
+        Some(output::FermiEnergy::Fermi(e)) | Some(output::FermiEnergy::HighestOccupied(e, _)) => e,
+        _ => return Err(Error::FermiEnergy),
+    };
+
+    let k_points: Vec<([f64; 3], Vec<f64>)> = match bands.band_energies {
+        output::BandEnergies::Unpolarized(ref ks) => {
+            ks.iter().map(|k| (k.k, k.energies.clone())).collect()
+        }
+        output::BandEnergies::Polarized { ref up, ref down } => {
+            if up.len() != down.len() {
+                return Err(Error::KPoints);
+            }
+            up.iter()
+                .zip(down)
+                .map(|(k_up, k_down)| {
+                    let mut energies = k_up.energies.clone();
+                    energies.extend(&k_down.energies);
+                    (k_up.k, energies)
+                })
+                .collect()
+        }
+    };
+
+    fermi_surface(input, fermi_energy, &k_points, OUTPUT_PRECISION)
+}
+
+/// Place the energies at `k_points`, given in Cartesian coordinates in units of 2pi/alat
+/// with an error of up to `precision` in each component, on the grid of the `nscf` input
+/// `input`.
+fn fermi_surface(
+    input: &Input,
+    fermi_energy: f64,
+    k_points: &[([f64; 3], Vec<f64>)],
+    precision: f64,
+) -> Result<FermiSurface, Error> {
+    let nk = match input.k_points {
+        KPoints::CrystalUniform(nk) => nk,
+        _ => return Err(Error::KPoints),
+    };
+    // The number of points is checked against `k_points` before the grid is used, so that
+    // neither it nor `grid` overflows.
+    let n_points = nk[0].checked_mul(nk[1]).and_then(|n| n.checked_mul(nk[2]));
+    if nk.contains(&0) || n_points != Some(k_points.len() as u64) {
+        return Err(Error::KPoints);
+    }
+    let grid = [nk[0] + 1, nk[1] + 1, nk[2] + 1];
+
+    let alat = input.system.alat;
+    let cell = Cell {
+        units: LatticeUnits::Bohr,
+        cell: lattice_vectors(&input.system.ibrav, alat),
+    };
+
+    let n_points = k_points.len();
+    let nbnd = k_points.first().map_or(0, |(_, energies)| energies.len());
+    if k_points.iter().any(|(_, e)| e.len() != nbnd) {
+        return Err(Error::KPoints);
+    }
+
+    // The energies at each point of the pw.x grid, in the order of `generate_uniform_kpoints`.
+    let mut energies: Vec<Option<&Vec<f64>>> = vec![None; n_points];
+    for &(k, ref e) in k_points {
+        let mut index = 0;
+        for (a, &n_a) in cell.cell.iter().zip(nk.iter()) {
+            // With k in units of 2pi/alat and the lattice vectors in bohr, the crystal
+            // coordinates of k are `k . a_i / alat`.
+            let x = (0..3).map(|d| k[d] * a[d]).sum::<f64>() / alat;
+            let tolerance = precision * a.iter().map(|a_d| a_d.abs()).sum::<f64>() / alat;
+            let n = x * (n_a as f64);
+            if (n - n.round()).abs() > tolerance * (n_a as f64) {
+                return Err(Error::KPoints);
+            }
+            index = index * (n_a as usize) + (n.round() as i64).rem_euclid(n_a as i64) as usize;
+        }
+
+        if energies[index].is_some() {
+            return Err(Error::KPoints);
+        }
+        energies[index] = Some(e);
+    }
+
+    let mut bands = vec![Vec::new(); nbnd];
+    for i0 in 0..grid[0] {
+        for i1 in 0..grid[1] {
+            for i2 in 0..grid[2] {
+                let index = (((i0 % nk[0]) * nk[1] + (i1 % nk[1])) * nk[2] + (i2 % nk[2])) as usize;
+                // Every point is present, since the points are distinct and as many as the grid.
+                let e = energies[index].ok_or(Error::KPoints)?;
+                for (band, &energy) in bands.iter_mut().zip(e.iter()) {
+                    band.push(energy);
+                }
+            }
+        }
+    }
+
+    let b = cell.reciprocal_vectors(alat);
+    let in_angstrom = |v: [f64; 3]| {
+        [
+            v[0] / BOHR_ANGSTROM,
+            v[1] / BOHR_ANGSTROM,
+            v[2] / BOHR_ANGSTROM,
+        ]
+    };
+
+    Ok(FermiSurface {
+        grid,
+        reciprocal_vectors: [in_angstrom(b[0]), in_angstrom(b[1]), in_angstrom(b[2])],
+        fermi_energy,
+        bands,
+    })
+}
+
+/// The number of distinct k-points along each direction of `grid`.
+fn nscf_grid(grid: [u64; 3]) -> [u64; 3] {
+    [grid[0] - 1, grid[1] - 1, grid[2] - 1]
+}
+
+/// The BXSF file for `fermi_surface`, which XCrySDen reads by the extension `.bxsf`.
+pub fn make_bxsf(fermi_surface: &FermiSurface) -> String {
+    let grid = fermi_surface.grid;
+    let mut lines = Vec::new();
+
+    lines.push(String::from("BEGIN_INFO"));
+    lines.push(format!("  Fermi Energy: {}", fermi_surface.fermi_energy));
+    lines.push(String::from("END_INFO"));
+    lines.push(String::from("BEGIN_BLOCK_BANDGRID_3D"));
+    lines.push(String::from("band_energies"));
+    lines.push(String::from("BEGIN_BANDGRID_3D"));
+    lines.push(format!(" {}", fermi_surface.bands.len()));
+    lines.push(format!(" {} {} {}", grid[0], grid[1], grid[2]));
+    lines.push(String::from(" 0 0 0"));
+    for b in fermi_surface.reciprocal_vectors.iter() {
+        lines.push(format!(" {} {} {}", b[0], b[1], b[2]));
+    }
+
+    for (i, band) in fermi_surface.bands.iter().enumerate() {
+        lines.push(format!("BAND: {}", i + 1));
+        for chunk in band.chunks(6) {
+            let values: Vec<String> = chunk.iter().map(|e| format!("{}", e)).collect();
+            lines.push(format!(" {}", values.join(" ")));
+        }
+    }
+
+    lines.push(String::from("END_BANDGRID_3D"));
+    lines.push(String::from("END_BLOCK_BANDGRID_3D"));
+
+    lines.join("\n") + "\n"
+}
+
+pub fn write_bxsf<P: AsRef<Path>>(fermi_surface: &FermiSurface, file_path: P) -> Result<(), Error> {
+    let mut file = File::create(file_path)?;
+    file.write_all(make_bxsf(fermi_surface).as_bytes())?;
+
+    Ok(())
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "The Fermi surface must be derived from an `scf` calculation.")]
+    ScfCalculation,
+    #[fail(display = "The output must be from an `nscf` calculation.")]
+    NscfCalculation,
+    #[fail(
+        display = "The grid must have at least 2 points in each direction; got {:?}.",
+        _0
+    )]
+    Grid([u64; 3]),
+    #[fail(display = "The k-points of the run must be the `CrystalUniform` grid of the input.")]
+    KPoints,
+    #[fail(display = "A single Fermi energy or highest occupied level must be given.")]
+    FermiEnergy,
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
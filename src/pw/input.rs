@@ -1,5 +1,18 @@
 use std::path::PathBuf;
 use error;
+use pw::lattice::{determinant, lattice_vectors};
+
+/// Cell volumes smaller than this fraction of the product of the lattice vector lengths are
+/// taken to vanish.
+const VOLUME_TOLERANCE: f64 = 1e-8;
+
+/// `alat` may differ from the length of the first lattice vector of a cell in bohr or angstrom
+/// by this fraction of the length.
+const ALAT_TOLERANCE: f64 = 1e-6;
+
+/// K-points in Berry phase strings may differ from their expected coordinates by this much,
+/// allowing for the precision with which they are written.
+const STRING_TOLERANCE: f64 = 1e-6;
 
 /// Representation of the input file for Quantum Espresso 6.2.
 ///
@@ -26,10 +39,20 @@ pub struct Input {
     pub control: Control,
     pub system: System,
 
-    /// `efield` specifies the sawtooth electric field parameters, which are in both the
-    /// `control` and `system` namelists.
+    /// `efield` specifies the electric field parameters: either a sawtooth potential, with
+    /// parameters in both the `control` and `system` namelists, or a homogeneous field
+    /// described through the Berry phase, with parameters in the `control` and `electrons`
+    /// namelists.
     pub efield: Option<Efield>,
 
+    /// `berry_phase` specifies the strings of k-points used to compute the polarization through
+    /// the Berry phase, corresponding to `lberry = .true.`.
+    pub berry_phase: Option<KPointStrings>,
+
+    /// `hubbard` specifies the DFT+U parameters, which are given in the `system` namelist or
+    /// in the `HUBBARD` card.
+    pub hubbard: Option<Hubbard>,
+
     pub electrons: Electrons,
     pub species: Vec<Species>,
 
@@ -61,26 +84,224 @@ pub fn generate_uniform_kpoints(nk: [u64; 3]) -> Vec<[f64; 3]> {
     ks
 }
 
+/// Generate a list of k-points on a grid of `nk` points, ordered in strings of `nk[gdir]`
+/// points along the reciprocal lattice vector `gdir`, as required by Berry phase calculations.
+///
+/// Strings used for the polarization (`lberry = .true.`) must end at the periodic image of
+/// their first point, which is included if `include_endpoint` is true; strings used with a
+/// finite electric field (`lelfield = .true.`) do not include it. In the first case
+/// `nk[gdir]` must be at least 2, and `None` is returned otherwise.
+pub fn generate_kpoint_strings(
+    nk: [u64; 3],
+    gdir: &LatticeDirection,
+    include_endpoint: bool,
+) -> Option<Vec<[f64; 3]>> {
+    let d = direction_axis(gdir);
+    if include_endpoint && nk[d] < 2 {
+        return None;
+    }
+
+    // The remaining directions, in increasing order.
+    let (d1, d2) = match d {
+        0 => (1, 2),
+        1 => (0, 2),
+        _ => (0, 1),
+    };
+    let step = string_step(nk[d], include_endpoint);
+
+    let mut ks = Vec::new();
+
+    for k1 in 0..nk[d1] {
+        for k2 in 0..nk[d2] {
+            for k in 0..nk[d] {
+                let mut point = [0.0; 3];
+                point[d] = (k as f64) * step;
+                point[d1] = (k1 as f64) / (nk[d1] as f64);
+                point[d2] = (k2 as f64) / (nk[d2] as f64);
+                ks.push(point);
+            }
+        }
+    }
+
+    Some(ks)
+}
+
+fn direction_axis(direction: &LatticeDirection) -> usize {
+    match *direction {
+        LatticeDirection::D1 => 0,
+        LatticeDirection::D2 => 1,
+        LatticeDirection::D3 => 2,
+    }
+}
+
+/// The spacing along `gdir` of the points in a string of `nppstr` k-points.
+fn string_step(nppstr: u64, include_endpoint: bool) -> f64 {
+    if include_endpoint {
+        1.0 / ((nppstr as f64) - 1.0)
+    } else {
+        1.0 / (nppstr as f64)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Calculation {
     Scf {
         conv_thr: f64,
     },
+    /// If `diago_thr_init` is not given, pw.x derives it from `conv_thr` and the number of
+    /// electrons.
     Nscf {
-        diago_thr_init: f64,
+        diago_thr_init: Option<f64>,
         nbnd: Option<u64>,
         nosym: Option<bool>,
     },
     Bands {
-        diago_thr_init: f64,
+        diago_thr_init: Option<f64>,
         nbnd: Option<u64>,
         // TODO - should we always set `nosym = .true.` for bands? Expect so.
         nosym: Option<bool>,
     },
+    /// Relaxation of the atomic positions in a fixed cell.
+    Relax {
+        conv_thr: f64,
+        relax: Relax,
+        ion_dynamics: RelaxIonDynamics,
+    },
+    /// Relaxation of both the atomic positions and the cell.
+    VcRelax {
+        conv_thr: f64,
+        relax: Relax,
+        dynamics: VcRelaxDynamics,
+        cell: VariableCell,
+        press_conv_thr: Option<f64>,
+    },
+    /// Molecular dynamics in a fixed cell.
+    Md {
+        conv_thr: f64,
+        md: MolecularDynamics,
+        ion_dynamics: MdIonDynamics,
+    },
+    /// Molecular dynamics with a variable cell. The ions are always moved with the Beeman
+    /// algorithm (`ion_dynamics = 'beeman'`), which is the only choice QE allows here.
+    VcMd {
+        conv_thr: f64,
+        md: MolecularDynamics,
+        cell_dynamics: VcMdCellDynamics,
+        cell: VariableCell,
+    },
+}
+
+/// Parameters shared by the `relax` and `vc-relax` calculations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relax {
+    pub nstep: Option<u64>,
+    pub etot_conv_thr: Option<f64>,
+    pub forc_conv_thr: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelaxIonDynamics {
+    Bfgs,
+    Damp,
+}
+
+/// The combinations of `ion_dynamics` and `cell_dynamics` accepted by QE for `vc-relax`:
+/// BFGS must be used for both or for neither, and damped ionic dynamics is paired with
+/// damped Parrinello-Rahman or Wentzcovitch cell dynamics.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VcRelaxDynamics {
+    Bfgs,
+    DampPr,
+    DampW,
+}
+
+/// Parameters shared by the `md` and `vc-md` calculations.
+///
+/// `dt` is required here, since its default value is given in Rydberg atomic units and is
+/// rarely appropriate for a particular system.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MolecularDynamics {
+    pub dt: f64,
+    pub nstep: Option<u64>,
+    /// `tempw`: the starting temperature, which is also the target temperature of the
+    /// thermostats in `ion_temperature` and of Langevin dynamics.
+    pub tempw: Option<f64>,
+    pub ion_temperature: Option<IonTemperature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MdIonDynamics {
+    Verlet,
+    Langevin,
+    LangevinSmc,
+}
+
+/// Temperature control for molecular dynamics, with the parameters used by each method.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum IonTemperature {
+    Rescaling {
+        tolp: Option<f64>,
+    },
+    RescaleV {
+        nraise: Option<u64>,
+    },
+    RescaleT {
+        delta_t: Option<f64>,
+    },
+    ReduceT {
+        delta_t: Option<f64>,
+        nraise: Option<u64>,
+    },
+    Berendsen {
+        nraise: Option<u64>,
+    },
+    Andersen {
+        nraise: Option<u64>,
+    },
+    Initial,
+    NotControlled,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VcMdCellDynamics {
+    /// Parrinello-Rahman dynamics.
+    Pr,
+    /// Wentzcovitch dynamics.
+    W,
+}
+
+/// Parameters of the `cell` namelist shared by the `vc-relax` and `vc-md` calculations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VariableCell {
+    /// Target pressure in kbar.
+    pub press: Option<f64>,
+    pub wmass: Option<f64>,
+    pub cell_factor: Option<f64>,
+    pub cell_dofree: Option<CellDofree>,
+}
+
+/// Degrees of freedom of the cell which are allowed to change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CellDofree {
+    All,
+    /// Keep the Bravais lattice type given by `ibrav`; requires `ibrav != 0`.
+    Ibrav,
+    X,
+    Y,
+    Z,
+    Xy,
+    Xz,
+    Yz,
+    Xyz,
+    Shape,
+    Volume,
+    TwoDxy,
+    TwoDShape,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Control {
+    pub verbosity: Option<Verbosity>,
     pub restart_mode: Option<RestartMode>,
     pub disk_io: Option<DiskIO>,
     pub wf_collect: Option<bool>,
@@ -89,6 +310,15 @@ pub struct Control {
     pub prefix: Option<String>,
 }
 
+/// pw.x also accepts `debug` and `medium` for `High`, and `default` and `minimal` for `Low`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Verbosity {
+    Low,
+    /// Required for the band energies to be written to standard output with 100 or more
+    /// k-points.
+    High,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RestartMode {
     FromScratch,
@@ -111,6 +341,10 @@ pub struct System {
     pub ecutrho: f64,
     pub occupations: Occupations,
     pub spin_type: Option<SpinType>,
+    /// `input_dft`, which overrides the exchange-correlation functional given by the
+    /// pseudopotentials.
+    pub input_dft: Option<Functional>,
+    pub vdw_corr: Option<VdwCorr>,
 }
 
 /// Bravais lattice settings, given in the order they appear in the QE input description.
@@ -127,24 +361,39 @@ pub struct System {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Ibrav {
     Free(Cell),
-    //SimpleCubic,
-    //Fcc,
-    //Bcc,
-    //BccSymmetric,
-    //Hexagonal(f64),
-    //TrigonalRAxisC(f64),
-    //TrigonalRAxis111(f64),
-    //TetragonalP(f64),
-    //TetragonalI(f64),
-    //OrthorhombicP(f64, f64),
-    //OrthorhombicBco(f64, f64),
-    //OrthorhombicBcoAlternate(f64, f64),
-    //OrthorhombicFaceCentered(f64, f64),
-    //OrthorhombicBodyCentered(f64, f64),
-    //MonoclinicPUniqueAxisC(f64, f64, f64),
-    //MonoclinicPUniqueAxisB(f64, f64, f64),
-    //MonoclinicBaseCentered(f64, f64, f64),
-    //Triclinic(f64, f64, f64, f64, f64),
+    SimpleCubic,
+    Fcc,
+    Bcc,
+    BccSymmetric,
+    /// `celldm(3)` = c/a.
+    Hexagonal(f64),
+    /// `celldm(4)` = cos(gamma), where gamma is the angle between any pair of primitive vectors.
+    TrigonalRAxisC(f64),
+    /// `celldm(4)` = cos(gamma), where gamma is the angle between any pair of primitive vectors.
+    TrigonalRAxis111(f64),
+    /// `celldm(3)` = c/a.
+    TetragonalP(f64),
+    /// `celldm(3)` = c/a.
+    TetragonalI(f64),
+    /// `celldm(2)` = b/a, `celldm(3)` = c/a.
+    OrthorhombicP(f64, f64),
+    /// `celldm(2)` = b/a, `celldm(3)` = c/a.
+    OrthorhombicBco(f64, f64),
+    /// `celldm(2)` = b/a, `celldm(3)` = c/a.
+    OrthorhombicBcoAlternate(f64, f64),
+    /// `celldm(2)` = b/a, `celldm(3)` = c/a.
+    OrthorhombicFaceCentered(f64, f64),
+    /// `celldm(2)` = b/a, `celldm(3)` = c/a.
+    OrthorhombicBodyCentered(f64, f64),
+    /// `celldm(2)` = b/a, `celldm(3)` = c/a, `celldm(4)` = cos(ab).
+    MonoclinicPUniqueAxisC(f64, f64, f64),
+    /// `celldm(2)` = b/a, `celldm(3)` = c/a, `celldm(5)` = cos(ac).
+    MonoclinicPUniqueAxisB(f64, f64, f64),
+    /// `celldm(2)` = b/a, `celldm(3)` = c/a, `celldm(4)` = cos(ab).
+    MonoclinicBaseCentered(f64, f64, f64),
+    /// `celldm(2)` = b/a, `celldm(3)` = c/a, `celldm(4)` = cos(bc), `celldm(5)` = cos(ac),
+    /// `celldm(6)` = cos(ab).
+    Triclinic(f64, f64, f64, f64, f64),
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -170,13 +419,138 @@ pub enum Smearing {
 /// Type of the spin representation.
 ///
 /// `NonPolarized` and `CollinearPolarized` are equivalent to `nspin = 1` and `nspin = 2`
-/// respectively. `NonCollinear(false)` is equivalent to `noncolin = .true.`, `lspinorb = .false.`.
-/// `Noncollinear(true)` is equivalent to `noncolin = .true.`, `lspinorb = .true.`.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// respectively. `Noncollinear` with `spin_orbit` false is equivalent to `noncolin = .true.`,
+/// `lspinorb = .false.`, and with `spin_orbit` true to `noncolin = .true.`, `lspinorb = .true.`.
+///
+/// The magnetic parameters are only given for the spin-polarized cases.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SpinType {
     NonPolarized,
-    CollinearPolarized,
-    Noncollinear { spin_orbit: bool },
+    CollinearPolarized(Magnetization),
+    Noncollinear {
+        spin_orbit: bool,
+        magnetization: Magnetization,
+    },
+}
+
+/// Magnetic parameters of a spin-polarized calculation.
+///
+/// Per-species values are indexed by position in `Input::species`, so that the value for
+/// `species[i]` corresponds to the `(i + 1)` index in the input file. Species without a value,
+/// including those past the end of the list, are given `None`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Magnetization {
+    /// `starting_magnetization(i)`, between -1 (all spins down) and 1 (all spins up).
+    pub starting_magnetization: Vec<Option<f64>>,
+    /// `angle1(i)` and `angle2(i)`, in degrees: the angle of the starting magnetization to the
+    /// z axis, and the angle of its projection on the xy plane to the x axis. Only used for
+    /// `Noncollinear` calculations.
+    pub angles: Vec<Option<[f64; 2]>>,
+    /// `tot_magnetization`, the difference of the numbers of spin-up and spin-down electrons,
+    /// which is kept fixed. Only used for `CollinearPolarized` calculations.
+    pub tot_magnetization: Option<f64>,
+    pub constrained_magnetization: Option<ConstrainedMagnetization>,
+}
+
+/// Constraints on the magnetization, with the penalty parameters of `constrained_magnetization`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConstrainedMagnetization {
+    /// `total`: the total magnetization is fixed to `fixed_magnetization`. Only used for
+    /// `Noncollinear` calculations; use `tot_magnetization` for `CollinearPolarized` ones.
+    Total { fixed_magnetization: [f64; 3] },
+    /// `atomic`: the atomic magnetic moments are constrained to the starting magnetization,
+    /// with penalty parameter `lambda`.
+    Atomic { lambda: f64 },
+    /// `total direction`: the angle of the total magnetization to the z axis, in degrees, is
+    /// constrained to `fixed_magnetization(3)`. Only used for `Noncollinear` calculations.
+    TotalDirection { angle: f64 },
+    /// `atomic direction`: the angle of the atomic magnetic moments to the z axis is constrained
+    /// to `angle1`, with penalty parameter `lambda`. Only used for `Noncollinear` calculations.
+    AtomicDirection { lambda: f64 },
+}
+
+/// Exchange-correlation functionals, given by their `input_dft` names.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Functional {
+    /// `pz`: the LDA of Perdew and Zunger.
+    Pz,
+    Pbe,
+    PbeSol,
+    RevPbe,
+    Blyp,
+    Scan,
+    /// `vdw-df`, `vdw-df2`, `vdw-df-cx` and `rvv10`: nonlocal van der Waals functionals.
+    VdwDf,
+    VdwDf2,
+    VdwDfCx,
+    Rvv10,
+    /// Functionals including a fraction of exact exchange.
+    Hybrid(HybridFunctional, Hybrid),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HybridFunctional {
+    Pbe0,
+    Hse,
+    B3lyp,
+}
+
+/// Parameters of the exact exchange calculation for hybrid functionals. Values of `None` take
+/// the QE defaults.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Hybrid {
+    /// `nqx1`, `nqx2` and `nqx3`: the mesh of q-points used for the exact exchange, which
+    /// must divide the k-point mesh.
+    pub nqx: Option<[u64; 3]>,
+    pub exxdiv_treatment: Option<ExxdivTreatment>,
+    /// `ecutfock`, the cutoff energy of the exact exchange operator in Ry. This may not be
+    /// larger than `ecutrho`.
+    pub ecutfock: Option<f64>,
+    /// `exx_fraction`, the fraction of exact exchange, overriding that of the functional.
+    pub exx_fraction: Option<f64>,
+}
+
+/// Values of `exxdiv_treatment`, the treatment of the Coulomb divergence in exact exchange.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExxdivTreatment {
+    GygiBaldereschi,
+    VcutSpherical,
+    VcutWs,
+    None,
+}
+
+/// Van der Waals corrections given by `vdw_corr`, with their parameters. Values of `None`
+/// take the QE defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum VdwCorr {
+    /// `grimme-d3`, with `dftd3_version` and `dftd3_threebody`.
+    GrimmeD3 {
+        version: Option<Dftd3Version>,
+        threebody: Option<bool>,
+    },
+    /// `ts-vdw`, the Tkatchenko-Scheffler correction, with `ts_vdw_econv_thr` and
+    /// `ts_vdw_isolated`.
+    TkatchenkoScheffler {
+        econv_thr: Option<f64>,
+        isolated: Option<bool>,
+    },
+    /// `xdm`, the exchange-hole dipole moment model, with `xdm_a1` and `xdm_a2`.
+    Xdm { a1: Option<f64>, a2: Option<f64> },
+}
+
+/// Values of `dftd3_version`, the damping function of the Grimme-D3 correction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dftd3Version {
+    /// `dftd3_version = 2`: Grimme-D2.
+    D2,
+    /// `dftd3_version = 3`: zero damping.
+    ZeroDamping,
+    /// `dftd3_version = 4`: Becke-Johnson damping.
+    BeckeJohnson,
+    /// `dftd3_version = 5`: modified zero damping.
+    ZeroDampingModified,
+    /// `dftd3_version = 6`: modified Becke-Johnson damping.
+    BeckeJohnsonModified,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -188,7 +562,27 @@ pub enum Efield {
         eopreg: f64,
         eamp: f64,
     },
-    //LelField,
+    /// `lelfield = .true.`: a homogeneous finite electric field, described through the
+    /// Berry phase along the strings of k-points given by `strings`.
+    LelField {
+        /// `efield_cart`, the field in Ry atomic units along the Cartesian axes.
+        efield_cart: [f64; 3],
+        /// `nberrycyc`, the number of iterations converging the wavefunctions in the field
+        /// at each SCF step.
+        nberrycyc: Option<u64>,
+        strings: KPointStrings,
+    },
+}
+
+/// The strings of k-points used in Berry phase calculations. pw.x requires these to be given
+/// as an explicit list of k-points in crystal coordinates, in the order produced by
+/// `generate_kpoint_strings`, and the calculation to be done without symmetry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KPointStrings {
+    /// `gdir`, the reciprocal lattice vector along which the strings lie.
+    pub gdir: LatticeDirection,
+    /// `nppstr`, the number of k-points in each string.
+    pub nppstr: u64,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -198,6 +592,61 @@ pub enum LatticeDirection {
     D3,
 }
 
+/// DFT+U settings, corresponding to `lda_plus_u = .true.`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hubbard {
+    /// `lda_plus_u_kind`.
+    pub kind: HubbardKind,
+    /// `U_projection_type`, the projectors onto the Hubbard manifolds.
+    pub projection: HubbardProjection,
+    pub parameters: Vec<HubbardParameters>,
+    /// If true, the parameters are given in the `HUBBARD` card, as required by QE 7.1 and
+    /// later; otherwise they are given by `Hubbard_U(i)` and related fields of the `system`
+    /// namelist, as required by earlier versions. `Hubbard_alpha(i)` is given in the namelist
+    /// in both cases.
+    pub card: bool,
+}
+
+/// Values of `lda_plus_u_kind`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HubbardKind {
+    /// `lda_plus_u_kind = 0`: the simplified rotationally-invariant scheme of Dudarev, with
+    /// parameters `U` and `J0`.
+    Simplified,
+    /// `lda_plus_u_kind = 1`: the full rotationally-invariant scheme of Liechtenstein, with
+    /// parameters `U` and `J`.
+    Full,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HubbardProjection {
+    Atomic,
+    OrthoAtomic,
+    NormAtomic,
+    /// Projection onto the Wannier functions read from a file. Not supported by the `HUBBARD`
+    /// card.
+    File,
+    Pseudo,
+}
+
+/// The DFT+U parameters of one species, in eV.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HubbardParameters {
+    /// The label of the species in `Input::species`.
+    pub species: String,
+    /// The Hubbard manifold, such as `3d`. This is required for the `HUBBARD` card, and
+    /// unused otherwise, since QE then chooses the manifold from the element.
+    pub manifold: Option<String>,
+    /// `Hubbard_U(i)`.
+    pub u: f64,
+    /// `Hubbard_J0(i)`, only used with `HubbardKind::Simplified`.
+    pub j0: Option<f64>,
+    /// `Hubbard_J(1,i)`, only used with `HubbardKind::Full`.
+    pub j: Option<f64>,
+    /// `Hubbard_alpha(i)`, the perturbation used to compute U by linear response.
+    pub alpha: Option<f64>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Electrons {
     pub startingwfc: Option<StartingWfc>,
@@ -262,7 +711,8 @@ pub struct AtomCoordinate {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum KPoints {
-    //TwoPiByACartesian(Vec<[f64; 4]>),
+    /// `tpiba`: a list of k-points and weights, in Cartesian coordinates in units of 2 pi / alat.
+    TwoPiByACartesian(Vec<[f64; 4]>),
     Crystal(Vec<[f64; 4]>),
     CrystalUniform([u64; 3]),
     Automatic {
@@ -271,14 +721,32 @@ pub enum KPoints {
         /// A value of `None` for `sk` is equivalent to `[false, false, false]`.
         sk: Option<[bool; 3]>,
     },
-    //Gamma,
-    //TwoPiByACartesianBands { nk_per_panel: u64, panel_bounds: Vec<f64; 3]> },
+    /// `gamma`: only the Gamma point, using the real wavefunctions of pw.x's Gamma tricks.
+    Gamma,
+    /// `tpiba_b`: a band path through `panel_bounds`, in Cartesian coordinates in units of
+    /// 2 pi / alat.
+    TwoPiByACartesianBands {
+        nk_per_panel: u64,
+        panel_bounds: Vec<[f64; 3]>,
+    },
     CrystalBands {
         nk_per_panel: u64,
         panel_bounds: Vec<[f64; 3]>,
     },
-    //TwoPiByACartesianContour([[f64; 3]; 3]),
-    //CrystalContour([[f64; 3]; 3]),
+    /// `tpiba_c`: a rectangular grid for contour plots of the bands, in Cartesian coordinates
+    /// in units of 2 pi / alat.
+    TwoPiByACartesianContour {
+        /// The corners `k0`, `k1` and `k2`: the sides of the rectangle are `k1 - k0` and
+        /// `k2 - k0`.
+        corners: [[f64; 3]; 3],
+        /// The number of intervals along each side, given as the weights of `k1` and `k2`.
+        nk: [u64; 2],
+    },
+    /// `crystal_c`: as `TwoPiByACartesianContour`, in crystal coordinates.
+    CrystalContour {
+        corners: [[f64; 3]; 3],
+        nk: [u64; 2],
+    },
 }
 
 /// Some required properties of the `Input` cannot be conveniently encoded in the type system
@@ -293,20 +761,120 @@ pub fn validate(input: &Input) -> Result<(), ErrorList> {
         errs.push(Error::LatticeConstant(system.alat));
     }
 
+    // Check that the lattice parameter ratios b/a and c/a are positive, and that the cosines
+    // of the angles between lattice vectors are strictly between -1 and 1.
+    let (ratios, cosines) = match system.ibrav {
+        Ibrav::Free(_) | Ibrav::SimpleCubic | Ibrav::Fcc | Ibrav::Bcc | Ibrav::BccSymmetric => {
+            (vec![], vec![])
+        }
+        Ibrav::Hexagonal(c_a) | Ibrav::TetragonalP(c_a) | Ibrav::TetragonalI(c_a) => {
+            (vec![c_a], vec![])
+        }
+        Ibrav::TrigonalRAxisC(cos_gamma) | Ibrav::TrigonalRAxis111(cos_gamma) => {
+            (vec![], vec![cos_gamma])
+        }
+        Ibrav::OrthorhombicP(b_a, c_a)
+        | Ibrav::OrthorhombicBco(b_a, c_a)
+        | Ibrav::OrthorhombicBcoAlternate(b_a, c_a)
+        | Ibrav::OrthorhombicFaceCentered(b_a, c_a)
+        | Ibrav::OrthorhombicBodyCentered(b_a, c_a) => (vec![b_a, c_a], vec![]),
+        Ibrav::MonoclinicPUniqueAxisC(b_a, c_a, cos)
+        | Ibrav::MonoclinicPUniqueAxisB(b_a, c_a, cos)
+        | Ibrav::MonoclinicBaseCentered(b_a, c_a, cos) => (vec![b_a, c_a], vec![cos]),
+        Ibrav::Triclinic(b_a, c_a, cos_bc, cos_ac, cos_ab) => {
+            (vec![b_a, c_a], vec![cos_bc, cos_ac, cos_ab])
+        }
+    };
+
+    for ratio in ratios {
+        if ratio <= 0.0 {
+            errs.push(Error::LatticeRatio(ratio));
+        }
+    }
+
+    for cosine in cosines {
+        if cosine <= -1.0 || cosine >= 1.0 {
+            errs.push(Error::LatticeCosine(cosine));
+        }
+    }
+
     // Check that `conv_thr` or `diago_thr_init` are positive.
     match input.calculation {
-        Calculation::Scf { conv_thr } => {
+        Calculation::Scf { conv_thr }
+        | Calculation::Relax { conv_thr, .. }
+        | Calculation::VcRelax { conv_thr, .. }
+        | Calculation::Md { conv_thr, .. }
+        | Calculation::VcMd { conv_thr, .. } => {
             if conv_thr <= 0.0 {
                 errs.push(Error::ConvThr(conv_thr));
             }
         }
         Calculation::Nscf { diago_thr_init, .. } | Calculation::Bands { diago_thr_init, .. } => {
-            if diago_thr_init <= 0.0 {
-                errs.push(Error::DiagoThrInit(diago_thr_init));
+            if let Some(diago_thr_init) = diago_thr_init {
+                if diago_thr_init <= 0.0 {
+                    errs.push(Error::DiagoThrInit(diago_thr_init));
+                }
             }
         }
     }
 
+    // Check that the ionic and cell convergence thresholds and time step are positive.
+    match input.calculation {
+        Calculation::Relax { ref relax, .. } | Calculation::VcRelax { ref relax, .. } => {
+            if let Some(etot_conv_thr) = relax.etot_conv_thr {
+                if etot_conv_thr <= 0.0 {
+                    errs.push(Error::EtotConvThr(etot_conv_thr));
+                }
+            }
+            if let Some(forc_conv_thr) = relax.forc_conv_thr {
+                if forc_conv_thr <= 0.0 {
+                    errs.push(Error::ForcConvThr(forc_conv_thr));
+                }
+            }
+        }
+        Calculation::Md { ref md, .. } | Calculation::VcMd { ref md, .. } => {
+            if md.dt <= 0.0 {
+                errs.push(Error::Dt(md.dt));
+            }
+            if let Some(tempw) = md.tempw {
+                if tempw <= 0.0 {
+                    errs.push(Error::Temperature(tempw));
+                }
+            }
+        }
+        _ => (),
+    }
+
+    if let Calculation::VcRelax {
+        press_conv_thr: Some(press_conv_thr),
+        ..
+    } = input.calculation
+    {
+        if press_conv_thr <= 0.0 {
+            errs.push(Error::PressConvThr(press_conv_thr));
+        }
+    }
+
+    // Check that the variable cell parameters are consistent with the lattice.
+    match input.calculation {
+        Calculation::VcRelax { ref cell, .. } | Calculation::VcMd { ref cell, .. } => {
+            if let Some(wmass) = cell.wmass {
+                if wmass <= 0.0 {
+                    errs.push(Error::Wmass(wmass));
+                }
+            }
+            if let Some(cell_factor) = cell.cell_factor {
+                if cell_factor <= 0.0 {
+                    errs.push(Error::CellFactor(cell_factor));
+                }
+            }
+            if let (Some(CellDofree::Ibrav), &Ibrav::Free(_)) = (&cell.cell_dofree, &system.ibrav) {
+                errs.push(Error::CellDofreeIbrav);
+            }
+        }
+        _ => (),
+    }
+
     // Check that ecutwfc and ecutrho are positive.
     if system.ecutwfc <= 0.0 {
         errs.push(Error::Ecutwfc(system.ecutwfc));
@@ -315,12 +883,8 @@ pub fn validate(input: &Input) -> Result<(), ErrorList> {
         errs.push(Error::Ecutrho(system.ecutrho));
     }
 
-    // TODO (would be very nice to have, but not simple to fit in since we don't
-    // have an explicit statement of the type of pseudopotential):
-    // Check that ecutrho is consistent with ecutwfc, according to the pseudopotential type.
-    // For NC PP, should always have ecutrho = 4 * ecutwfc.
-    // For US PP and PAW, should have ecutrho \approx (8 to 12) * ecutwfc.
-    // Could implement by extracting the pseudopotential header (UPF format).
+    // Whether ecutrho is consistent with ecutwfc depends on the type of the pseudopotentials,
+    // which is given in their files rather than here; see `upf::check_cutoffs`.
 
     // Check that smearing, if present, is positive.
     if let Occupations::Smearing(_, degauss) = system.occupations {
@@ -336,33 +900,340 @@ pub fn validate(input: &Input) -> Result<(), ErrorList> {
         }
     }
 
-    // TODO: Check that cell volume `|(a1 x a2) . a3|` does not vanish.
+    // Check that the lattice vectors span a nonzero volume. pw.x accepts left-handed free
+    // cells, so the sign of the volume is only checked by `warnings`.
+    let cell = lattice_vectors(&system.ibrav, system.alat);
+    let volume = determinant(&cell);
+    let norms = cell
+        .iter()
+        .map(|a| (a[0] * a[0] + a[1] * a[1] + a[2] * a[2]).sqrt())
+        .product::<f64>();
+    if volume.is_nan() || volume.abs() <= VOLUME_TOLERANCE * norms {
+        errs.push(Error::CellVolume(volume));
+    }
+
+    // `celldm(1)` is not written for cells in bohr or angstrom, and pw.x then takes `alat` to
+    // be the length of the first lattice vector.
+    if let Ibrav::Free(ref free) = system.ibrav {
+        let a1 = cell[0];
+        let length = (a1[0] * a1[0] + a1[1] * a1[1] + a1[2] * a1[2]).sqrt();
+        let difference = (system.alat - length).abs();
+        if free.units != LatticeUnits::Alat
+            && (difference.is_nan() || difference > ALAT_TOLERANCE * length)
+        {
+            errs.push(Error::CellAlat(length, system.alat));
+        }
+    }
 
-    // TODO: Does QE complain if `(a1 x a2) . a3` is negative? If so, check that this is positive.
+    // Check that the sawtooth potential parameters are given as fractions of the lattice
+    // vector along `edir`.
+    if let Some(Efield::TeField {
+        emaxpos, eopreg, ..
+    }) = input.efield
+    {
+        if emaxpos <= 0.0 || emaxpos >= 1.0 {
+            errs.push(Error::Emaxpos(emaxpos));
+        }
+        if eopreg <= 0.0 || eopreg >= 1.0 {
+            errs.push(Error::Eopreg(eopreg));
+        }
+    }
 
-    // TODO: Check that `emaxpos` and `eopreg`, if present, are between 0 and 1.
+    // Gamma-only runs use real wavefunctions, which cannot describe the band structure at
+    // other k-points.
+    let bands = matches!(input.calculation, Calculation::Bands { .. });
+    if bands && input.k_points == KPoints::Gamma {
+        errs.push(Error::GammaBands);
+    }
 
-    // TODO: Check that if a tetrahedron method is used for occupations, the k-point list type
-    // is `automatic`.
+    // Check that contours span a rectangle with points along each side.
+    match input.k_points {
+        KPoints::TwoPiByACartesianContour { corners, nk }
+        | KPoints::CrystalContour { corners, nk } => {
+            let side = |i: usize| (0..3).any(|d| corners[i][d] != corners[0][d]);
+            if !side(1) || !side(2) || nk.contains(&0) {
+                errs.push(Error::Contour);
+            }
+        }
+        _ => (),
+    }
 
-    // TODO: All species identified in the atom coordinate list should correspond
-    // to species given in the species list.
+    // Check that Berry phase calculations are done without symmetry, on k-points given in
+    // strings along `gdir`.
+    let strings = match (&input.efield, input.berry_phase.as_ref()) {
+        (&Some(Efield::LelField { .. }), Some(_)) => {
+            errs.push(Error::BerryPhaseLelfield);
+            None
+        }
+        (&Some(Efield::LelField { ref strings, .. }), None) => Some((strings, false)),
+        (_, Some(strings)) => Some((strings, true)),
+        _ => None,
+    };
+    if let Some((strings, include_endpoint)) = strings {
+        if let Calculation::Nscf { nosym, .. } | Calculation::Bands { nosym, .. } =
+            input.calculation
+        {
+            if nosym != Some(true) {
+                errs.push(Error::BerryPhaseSymmetry);
+            }
+        }
+        if !kpoint_strings_valid(&input.k_points, strings, include_endpoint) {
+            errs.push(Error::BerryPhaseKPoints(strings.nppstr));
+        }
+    }
 
-    if errs.len() == 0 {
+    // The tetrahedron methods require a uniform k-point mesh generated by pw.x.
+    let tetrahedra = matches!(
+        system.occupations,
+        Occupations::Tetrahedra | Occupations::TetrahedraLin | Occupations::TetrahedraOpt
+    );
+    if tetrahedra && !matches!(input.k_points, KPoints::Automatic { .. }) {
+        errs.push(Error::TetrahedraKPoints);
+    }
+
+    // Check that the magnetic parameters are given for existing species, and are used with
+    // the spin type they apply to.
+    let (magnetization, noncollinear) = match system.spin_type {
+        Some(SpinType::CollinearPolarized(ref magnetization)) => (Some(magnetization), false),
+        Some(SpinType::Noncollinear {
+            ref magnetization, ..
+        }) => (Some(magnetization), true),
+        _ => (None, false),
+    };
+    if let Some(magnetization) = magnetization {
+        let ntyp = input.species.len();
+        let last_given = magnetization
+            .starting_magnetization
+            .iter()
+            .rposition(Option::is_some)
+            .max(magnetization.angles.iter().rposition(Option::is_some));
+        if let Some(i) = last_given {
+            if i >= ntyp {
+                errs.push(Error::MagnetizationSpecies(i + 1, ntyp));
+            }
+        }
+
+        for &m in magnetization.starting_magnetization.iter().flatten() {
+            if m.is_nan() || !(-1.0..=1.0).contains(&m) {
+                errs.push(Error::StartingMagnetization(m));
+            }
+        }
+
+        if !noncollinear {
+            if magnetization.angles.iter().any(Option::is_some) {
+                errs.push(Error::MagnetizationSpinType(String::from("angle1/angle2")));
+            }
+            match magnetization.constrained_magnetization {
+                Some(ConstrainedMagnetization::Total { .. }) => {
+                    errs.push(Error::MagnetizationSpinType(String::from(
+                        "constrained_magnetization = 'total'",
+                    )))
+                }
+                Some(ConstrainedMagnetization::TotalDirection { .. }) => {
+                    errs.push(Error::MagnetizationSpinType(String::from(
+                        "constrained_magnetization = 'total direction'",
+                    )))
+                }
+                Some(ConstrainedMagnetization::AtomicDirection { .. }) => {
+                    errs.push(Error::MagnetizationSpinType(String::from(
+                        "constrained_magnetization = 'atomic direction'",
+                    )))
+                }
+                _ => (),
+            }
+        } else if magnetization.tot_magnetization.is_some() {
+            errs.push(Error::MagnetizationSpinType(String::from("tot_magnetization")));
+        }
+
+        if let Some(ConstrainedMagnetization::Atomic { lambda })
+        | Some(ConstrainedMagnetization::AtomicDirection { lambda }) =
+            magnetization.constrained_magnetization
+        {
+            if lambda.is_nan() || lambda < 0.0 {
+                errs.push(Error::Lambda(lambda));
+            }
+        }
+    }
+
+    // Check that the exact exchange parameters of hybrid functionals are consistent with the
+    // cutoffs and k-point mesh.
+    if let Some(Functional::Hybrid(_, ref hybrid)) = system.input_dft {
+        if let Some(nqx) = hybrid.nqx {
+            let nk = match input.k_points {
+                KPoints::Automatic { nk, .. } | KPoints::CrystalUniform(nk) => Some(nk),
+                KPoints::Gamma => Some([1, 1, 1]),
+                _ => None,
+            };
+            if nqx.contains(&0) {
+                errs.push(Error::Nqx(nqx));
+            } else if let Some(nk) = nk {
+                if (0..3).any(|i| nk[i] % nqx[i] != 0) {
+                    errs.push(Error::NqxKPoints(nqx, nk));
+                }
+            }
+        }
+        if let Some(ecutfock) = hybrid.ecutfock {
+            if ecutfock.is_nan() || ecutfock <= 0.0 || ecutfock > system.ecutrho {
+                errs.push(Error::Ecutfock(ecutfock));
+            }
+        }
+        if let Some(exx_fraction) = hybrid.exx_fraction {
+            if exx_fraction.is_nan() || !(0.0..=1.0).contains(&exx_fraction) {
+                errs.push(Error::ExxFraction(exx_fraction));
+            }
+        }
+    }
+
+    if let Some(VdwCorr::TkatchenkoScheffler {
+        econv_thr: Some(econv_thr),
+        ..
+    }) = system.vdw_corr
+    {
+        if econv_thr.is_nan() || econv_thr <= 0.0 {
+            errs.push(Error::TsVdwEconvThr(econv_thr));
+        }
+    }
+
+    // Check that DFT+U parameters are given once for each of a set of existing species, and
+    // can be written in the chosen format.
+    if let Some(ref hubbard) = input.hubbard {
+        for (i, parameters) in hubbard.parameters.iter().enumerate() {
+            let label = &parameters.species;
+            if !input.species.iter().any(|s| &s.label == label) {
+                errs.push(Error::HubbardSpecies(label.clone()));
+            }
+            if hubbard.parameters[..i].iter().any(|p| &p.species == label) {
+                errs.push(Error::DuplicateHubbardSpecies(label.clone()));
+            }
+            if hubbard.card && parameters.manifold.is_none() {
+                errs.push(Error::HubbardManifold(label.clone()));
+            }
+            match hubbard.kind {
+                HubbardKind::Simplified if parameters.j.is_some() => {
+                    errs.push(Error::HubbardKind(String::from("Hubbard_J")))
+                }
+                HubbardKind::Full if parameters.j0.is_some() => {
+                    errs.push(Error::HubbardKind(String::from("Hubbard_J0")))
+                }
+                _ => (),
+            }
+        }
+
+        if hubbard.card && hubbard.projection == HubbardProjection::File {
+            errs.push(Error::HubbardProjection);
+        }
+    }
+
+    // Species labels must be unique, and each atom must belong to one of the species.
+    for (i, species) in input.species.iter().enumerate() {
+        if input.species[..i].iter().any(|s| s.label == species.label) {
+            errs.push(Error::DuplicateSpecies(species.label.clone()));
+        }
+    }
+
+    let mut missing_species: Vec<&str> = Vec::new();
+    for coord in &input.atomic_positions.coordinates {
+        let species = coord.species.as_str();
+        if !input.species.iter().any(|s| s.label == species) && !missing_species.contains(&species)
+        {
+            missing_species.push(species);
+        }
+    }
+    for species in missing_species {
+        errs.push(Error::Species(String::from(species)));
+    }
+
+    if errs.is_empty() {
         Ok(())
     } else {
         Err(ErrorList { errs })
     }
 }
 
+/// Properties of the `Input` which pw.x accepts, but which are likely to be mistakes. Unlike
+/// the errors given by `validate`, these do not prevent the input file from being written.
+pub fn warnings(input: &Input) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    // Lattices given by `ibrav` follow QE's conventions, which are right-handed; a free cell
+    // given by swapping two of its vectors is most likely a mistake.
+    if let Ibrav::Free(_) = input.system.ibrav {
+        let volume = determinant(&lattice_vectors(&input.system.ibrav, input.system.alat));
+        if volume < 0.0 {
+            warnings.push(Warning::LeftHandedCell(volume));
+        }
+    }
+
+    warnings
+}
+
+/// Check that `k_points` is a list of k-points in crystal coordinates made up of strings of
+/// `strings.nppstr` points along `strings.gdir`, ordered as by `generate_kpoint_strings`.
+fn kpoint_strings_valid(
+    k_points: &KPoints,
+    strings: &KPointStrings,
+    include_endpoint: bool,
+) -> bool {
+    let ks = match *k_points {
+        KPoints::Crystal(ref ks) => ks,
+        _ => return false,
+    };
+    let nppstr = strings.nppstr as usize;
+    let min_nppstr = if include_endpoint { 2 } else { 1 };
+    if nppstr < min_nppstr || ks.is_empty() || ks.len() % nppstr != 0 {
+        return false;
+    }
+
+    let d = direction_axis(&strings.gdir);
+    let step = string_step(strings.nppstr, include_endpoint);
+    ks.chunks(nppstr).all(|string| {
+        string.iter().enumerate().all(|(j, k)| {
+            (0..3).all(|i| {
+                let expected = if i == d {
+                    string[0][i] + (j as f64) * step
+                } else {
+                    string[0][i]
+                };
+                (k[i] - expected).abs() < STRING_TOLERANCE
+            })
+        })
+    })
+}
+
+#[derive(Fail, Debug)]
+pub enum Warning {
+    #[fail(display = "Lattice vectors are left-handed; got `(a1 x a2) . a3` = {}.", _0)]
+    LeftHandedCell(f64),
+}
+
 #[derive(Fail, Debug)]
 pub enum Error {
     #[fail(display = "Lattice constant `alat` must be positive; got {} instead.", _0)]
     LatticeConstant(f64),
+    #[fail(display = "Lattice parameter ratio (b/a or c/a) must be positive; got {} instead.", _0)]
+    LatticeRatio(f64),
+    #[fail(display = "Cosine of angle between lattice vectors must be between -1 and 1; got {} instead.", _0)]
+    LatticeCosine(f64),
     #[fail(display = "SCF convergence threshold `conv_thr` must be positive; got {} instead.", _0)]
     ConvThr(f64),
     #[fail(display = "Diagonalization convergence threshold `diago_thr_init` must be positive; got {} insead.", _0)]
     DiagoThrInit(f64),
+    #[fail(display = "Total energy convergence threshold `etot_conv_thr` must be positive; got {} instead.", _0)]
+    EtotConvThr(f64),
+    #[fail(display = "Force convergence threshold `forc_conv_thr` must be positive; got {} instead.", _0)]
+    ForcConvThr(f64),
+    #[fail(display = "Pressure convergence threshold `press_conv_thr` must be positive; got {} instead.", _0)]
+    PressConvThr(f64),
+    #[fail(display = "Molecular dynamics time step `dt` must be positive; got {} instead.", _0)]
+    Dt(f64),
+    #[fail(display = "Ionic temperature `tempw` must be positive; got {} instead.", _0)]
+    Temperature(f64),
+    #[fail(display = "Fictitious cell mass `wmass` must be positive; got {} instead.", _0)]
+    Wmass(f64),
+    #[fail(display = "`cell_factor` must be positive; got {} instead.", _0)]
+    CellFactor(f64),
+    #[fail(display = "`cell_dofree = 'ibrav'` requires a Bravais lattice type other than `ibrav = 0`.")]
+    CellDofreeIbrav,
     #[fail(display = "Wavefunction cutoff energy `ecutwfc` must be positive; got {} instead.", _0)]
     Ecutwfc(f64),
     #[fail(display = "Charge density cutoff energy `ecutwfc` must be positive; got {} instead.", _0)]
@@ -373,6 +1244,56 @@ pub enum Error {
     Mass(String, f64),
     #[fail(display = "Species {} in coordinate list is not given in species list.", _0)]
     Species(String),
+    #[fail(display = "Species {} is given more than once in species list.", _0)]
+    DuplicateSpecies(String),
+    #[fail(display = "Lattice vectors must span a nonzero volume; got volume {} instead.", _0)]
+    CellVolume(f64),
+    #[fail(display = "`alat` must be the length {} of the first lattice vector for a cell in bohr or angstrom; got {} instead.", _0, _1)]
+    CellAlat(f64, f64),
+    #[fail(display = "`emaxpos` must be between 0 and 1; got {} instead.", _0)]
+    Emaxpos(f64),
+    #[fail(display = "`eopreg` must be between 0 and 1; got {} instead.", _0)]
+    Eopreg(f64),
+    #[fail(display = "Gamma-only k-points cannot be used for a `bands` calculation.")]
+    GammaBands,
+    #[fail(display = "K-point contours require two sides of nonzero length, each with a positive number of points.")]
+    Contour,
+    #[fail(display = "`lberry` and `lelfield` cannot be used together.")]
+    BerryPhaseLelfield,
+    #[fail(display = "Berry phase calculations require `nosym = .true.`.")]
+    BerryPhaseSymmetry,
+    #[fail(display = "Berry phase calculations require a list of k-points in crystal coordinates, in strings of {} points along `gdir`.", _0)]
+    BerryPhaseKPoints(u64),
+    #[fail(display = "Tetrahedron occupations require `automatic` k-points.")]
+    TetrahedraKPoints,
+    #[fail(display = "Magnetic parameters are given for species {} but there are only {} species.", _0, _1)]
+    MagnetizationSpecies(usize, usize),
+    #[fail(display = "`starting_magnetization` must be between -1 and 1; got {} instead.", _0)]
+    StartingMagnetization(f64),
+    #[fail(display = "`{}` cannot be used with this spin type.", _0)]
+    MagnetizationSpinType(String),
+    #[fail(display = "Magnetization constraint `lambda` must not be negative; got {} instead.", _0)]
+    Lambda(f64),
+    #[fail(display = "`nqx1`, `nqx2` and `nqx3` must be positive; got {:?} instead.", _0)]
+    Nqx([u64; 3]),
+    #[fail(display = "The q-point mesh {:?} must divide the k-point mesh {:?}.", _0, _1)]
+    NqxKPoints([u64; 3], [u64; 3]),
+    #[fail(display = "`ecutfock` must be positive and no larger than `ecutrho`; got {} instead.", _0)]
+    Ecutfock(f64),
+    #[fail(display = "`exx_fraction` must be between 0 and 1; got {} instead.", _0)]
+    ExxFraction(f64),
+    #[fail(display = "`ts_vdw_econv_thr` must be positive; got {} instead.", _0)]
+    TsVdwEconvThr(f64),
+    #[fail(display = "Species {} with Hubbard parameters is not given in species list.", _0)]
+    HubbardSpecies(String),
+    #[fail(display = "Hubbard parameters are given more than once for species {}.", _0)]
+    DuplicateHubbardSpecies(String),
+    #[fail(display = "The `HUBBARD` card requires a Hubbard manifold for species {}.", _0)]
+    HubbardManifold(String),
+    #[fail(display = "`{}` cannot be used with this `lda_plus_u_kind`.", _0)]
+    HubbardKind(String),
+    #[fail(display = "`U_projection_type = 'file'` cannot be used with the `HUBBARD` card.")]
+    HubbardProjection,
 }
 
 pub type ErrorList = error::ErrorList<Error>;
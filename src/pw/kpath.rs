@@ -0,0 +1,1060 @@
+//! Paths through the high-symmetry points of the Brillouin zone for band structure
+//! calculations, following W. Setyawan and S. Curtarolo, Comput. Mater. Sci. 49, 299 (2010).
+//!
+//! The Bravais lattice is identified from the lattice vectors alone, so any cell describing
+//! the lattice may be used, whether given by `Ibrav::Free` or by another `Ibrav` variant. The
+//! high-symmetry points are given in crystal coordinates with respect to the reciprocal
+//! lattice vectors of that cell.
+use pw::input::{Ibrav, KPoints};
+use pw::lattice::{
+    cartesian_rotation, cross, determinant, dot, integer_determinant, inverse, lattice_rotations,
+    lattice_vectors, matrix_product, norm, reciprocal_vectors, reduce_basis, scale_vector,
+};
+
+/// Relative tolerance used in comparing lengths and angles of the lattice.
+const TOLERANCE: f64 = 1e-5;
+
+/// The lattice types of Setyawan and Curtarolo. Where the high-symmetry points of a Bravais
+/// lattice depend on the lattice parameters, the variants are distinguished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LatticeType {
+    Cub,
+    Fcc,
+    Bcc,
+    Tet,
+    Bct1,
+    Bct2,
+    Orc,
+    Orcf1,
+    Orcf2,
+    Orcf3,
+    Orci,
+    Orcc,
+    Hex,
+    Rhl1,
+    Rhl2,
+    Mcl,
+    Mclc1,
+    Mclc2,
+    Mclc3,
+    Mclc4,
+    Mclc5,
+    Tri1a,
+    Tri1b,
+    Tri2a,
+    Tri2b,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KPath {
+    pub lattice_type: LatticeType,
+    /// The path as a list of continuous segments, each given by the labels of its points.
+    pub segments: Vec<Vec<String>>,
+    /// The whole path as band structure k-points. Consecutive segments are joined by a panel
+    /// from the end of one segment to the start of the next.
+    ///
+    /// `KPoints::CrystalBands` has a single `nk_per_panel`, so pw.x generates `nk_per_panel - 1`
+    /// points inside each of these joining panels as well. These points are not on the path
+    /// and their calculation is wasted; `bands::output` drops them from the band structure.
+    pub k_points: KPoints,
+    /// The label of each panel bound of `k_points`.
+    pub labels: Vec<String>,
+    /// The indices of the panels of `k_points` which join one segment to the next. These are
+    /// not part of the path.
+    pub jumps: Vec<usize>,
+}
+
+/// The Setyawan-Curtarolo path for the lattice given by `ibrav` and `alat`, with
+/// `nk_per_panel` k-points generated by pw.x along each panel.
+pub fn setyawan_curtarolo(ibrav: &Ibrav, alat: f64, nk_per_panel: u64) -> Result<KPath, Error> {
+    let cell = lattice_vectors(ibrav, alat);
+    let standard = standard_cell(&cell)?;
+    let (points, segments) = high_symmetry_points(&standard);
+
+    // The standard primitive vectors are given by `M` times the vectors of `cell`, so that
+    // crystal coordinates of k-points transform as `k_cell = M^-1 k_standard`.
+    let m = round_matrix(&matrix_product(&standard.primitive, &inverse(&cell)))?;
+    let m_inv = round_matrix(&inverse(&m))?;
+
+    let mut labels = Vec::new();
+    let mut panel_bounds = Vec::new();
+    let mut jumps = Vec::new();
+    for segment in segments {
+        if !panel_bounds.is_empty() {
+            jumps.push(panel_bounds.len() - 1);
+        }
+        for &label in segment.iter() {
+            let k = points
+                .iter()
+                .find(|&&(point_label, _)| point_label == label)
+                .map(|&(_, k)| k)
+                .expect("path point is among the high-symmetry points");
+            panel_bounds.push(apply_columns(&m_inv, k));
+            labels.push(String::from(label));
+        }
+    }
+
+    Ok(KPath {
+        lattice_type: standard.lattice_type,
+        segments: segments
+            .iter()
+            .map(|segment| segment.iter().map(|&l| String::from(l)).collect())
+            .collect(),
+        k_points: KPoints::CrystalBands {
+            nk_per_panel,
+            panel_bounds,
+        },
+        labels,
+        jumps,
+    })
+}
+
+/// The Setyawan-Curtarolo lattice type of the lattice given by `ibrav` and `alat`.
+pub fn lattice_type(ibrav: &Ibrav, alat: f64) -> Result<LatticeType, Error> {
+    Ok(standard_cell(&lattice_vectors(ibrav, alat))?.lattice_type)
+}
+
+/// The lattice in the standard setting of Setyawan and Curtarolo.
+struct StandardCell {
+    lattice_type: LatticeType,
+    /// The standard primitive vectors, as rows.
+    primitive: [[f64; 3]; 3],
+    /// The standard conventional vectors, whose lengths and angles give the lattice
+    /// parameters `a`, `b`, `c` and `alpha`. For rhombohedral lattices, these are the
+    /// primitive vectors.
+    conventional: [[f64; 3]; 3],
+}
+
+/// Matrices giving the standard primitive vectors in terms of the conventional vectors.
+const IDENTITY: [[f64; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+const FACE_CENTERED: [[f64; 3]; 3] = [[0.0, 0.5, 0.5], [0.5, 0.0, 0.5], [0.5, 0.5, 0.0]];
+const BODY_CENTERED: [[f64; 3]; 3] = [[-0.5, 0.5, 0.5], [0.5, -0.5, 0.5], [0.5, 0.5, -0.5]];
+const ORCC_CENTERED: [[f64; 3]; 3] = [[0.5, -0.5, 0.0], [0.5, 0.5, 0.0], [0.0, 0.0, 1.0]];
+const MCLC_CENTERED: [[f64; 3]; 3] = [[0.5, 0.5, 0.0], [-0.5, 0.5, 0.0], [0.0, 0.0, 1.0]];
+
+/// Identify the Bravais lattice of `cell` from the order of its lattice point group, and
+/// construct the standard conventional cell from the rotation axes.
+fn standard_cell(cell: &[[f64; 3]; 3]) -> Result<StandardCell, Error> {
+    let volume = determinant(cell).abs();
+    let scale = cell.iter().map(|a| norm(*a)).fold(0.0, f64::max);
+    if volume.is_nan() || volume <= TOLERANCE * scale * scale * scale {
+        return Err(Error::CellVolume);
+    }
+
+    let lattice = Lattice::new(cell);
+    let rotations = lattice_rotations(&lattice.basis, TOLERANCE);
+
+    let standard = |lattice_type, conventional: [[f64; 3]; 3], centering: &[[f64; 3]; 3]| {
+        Ok(StandardCell {
+            lattice_type,
+            primitive: matrix_product(centering, &conventional),
+            conventional,
+        })
+    };
+
+    match rotations.len() {
+        48 => {
+            let axes = lattice.distinct_axes(&rotations, 1);
+            if axes.len() != 3 {
+                return Err(Error::Lattice);
+            }
+            let conventional = right_handed([axes[0], axes[1], axes[2]]);
+            match lattice.centering(&conventional) {
+                1 => standard(LatticeType::Cub, conventional, &IDENTITY),
+                2 => standard(LatticeType::Bcc, conventional, &BODY_CENTERED),
+                4 => standard(LatticeType::Fcc, conventional, &FACE_CENTERED),
+                _ => Err(Error::Lattice),
+            }
+        }
+        24 => {
+            let six_fold = lattice.rotation(&rotations, 2)?;
+            let c = lattice.axis(&six_fold)?;
+            let a = lattice.shortest(|v| perpendicular(v, c))?;
+            let three_fold = matrix_product(&six_fold, &six_fold);
+            let mut b = apply_rows(a, &three_fold);
+            if determinant(&[a, b, c]) < 0.0 {
+                b = apply_rows(b, &three_fold);
+            }
+            standard(LatticeType::Hex, [a, b, c], &IDENTITY)
+        }
+        16 => {
+            let four_fold = lattice.rotation(&rotations, 1)?;
+            let c = lattice.axis(&four_fold)?;
+            let a = lattice
+                .distinct_axes(&rotations, -1)
+                .into_iter()
+                .find(|&v| perpendicular(v, c))
+                .ok_or(Error::Lattice)?;
+            let b = apply_rows(a, &four_fold);
+            let conventional = right_handed([a, b, c]);
+            match lattice.centering(&conventional) {
+                1 => standard(LatticeType::Tet, conventional, &IDENTITY),
+                2 if norm(c) < norm(a) => standard(LatticeType::Bct1, conventional, &BODY_CENTERED),
+                2 => standard(LatticeType::Bct2, conventional, &BODY_CENTERED),
+                _ => Err(Error::Lattice),
+            }
+        }
+        12 => {
+            let three_fold = lattice.rotation(&rotations, 0)?;
+            let axis = lattice.axis(&three_fold)?;
+            // The rhombohedral primitive vectors are related by the three-fold rotation.
+            let rhombohedral = lattice
+                .vectors
+                .iter()
+                .filter(|&&v| !perpendicular(v, axis))
+                .map(|&v| {
+                    let v1 = apply_rows(v, &three_fold);
+                    [v, v1, apply_rows(v1, &three_fold)]
+                })
+                .find(|vs| close(determinant(vs).abs(), volume, volume))
+                .ok_or(Error::Lattice)?;
+            let rhombohedral = if determinant(&rhombohedral) < 0.0 {
+                [rhombohedral[0], rhombohedral[2], rhombohedral[1]]
+            } else {
+                rhombohedral
+            };
+            let lattice_type = if dot(rhombohedral[0], rhombohedral[1]) > 0.0 {
+                LatticeType::Rhl1
+            } else {
+                LatticeType::Rhl2
+            };
+            standard(lattice_type, rhombohedral, &IDENTITY)
+        }
+        8 => {
+            let mut axes = lattice.distinct_axes(&rotations, -1);
+            if axes.len() != 3 {
+                return Err(Error::Lattice);
+            }
+            axes.sort_by(|u, v| norm(*u).total_cmp(&norm(*v)));
+            let sorted = right_handed([axes[0], axes[1], axes[2]]);
+            match lattice.centering(&sorted) {
+                1 => standard(LatticeType::Orc, sorted, &IDENTITY),
+                4 => {
+                    let (a, b, c) = (norm(sorted[0]), norm(sorted[1]), norm(sorted[2]));
+                    let lhs = 1.0 / (a * a);
+                    let rhs = 1.0 / (b * b) + 1.0 / (c * c);
+                    let lattice_type = if close(lhs, rhs, lhs) {
+                        LatticeType::Orcf3
+                    } else if lhs > rhs {
+                        LatticeType::Orcf1
+                    } else {
+                        LatticeType::Orcf2
+                    };
+                    standard(lattice_type, sorted, &FACE_CENTERED)
+                }
+                2 => {
+                    let body = scale_vector(0.5, add(add(sorted[0], sorted[1]), sorted[2]));
+                    if lattice.contains(body) {
+                        return standard(LatticeType::Orci, sorted, &BODY_CENTERED);
+                    }
+                    // Base-centered: `c` is the axis normal to the centered face, and `a < b`.
+                    let k = (0..3)
+                        .find(|&k| {
+                            let (i, j) = ((k + 1) % 3, (k + 2) % 3);
+                            lattice.contains(scale_vector(0.5, add(sorted[i], sorted[j])))
+                        })
+                        .ok_or(Error::Lattice)?;
+                    let mut face: Vec<[f64; 3]> =
+                        (0..3).filter(|&i| i != k).map(|i| sorted[i]).collect();
+                    face.sort_by(|u, v| norm(*u).total_cmp(&norm(*v)));
+                    let conventional = right_handed([face[0], face[1], sorted[k]]);
+                    standard(LatticeType::Orcc, conventional, &ORCC_CENTERED)
+                }
+                _ => Err(Error::Lattice),
+            }
+        }
+        4 => {
+            let two_fold = lattice.rotation(&rotations, -1)?;
+            let unique = lattice.axis(&two_fold)?;
+            let plane: Vec<[f64; 3]> = lattice
+                .vectors
+                .iter()
+                .cloned()
+                .filter(|&v| perpendicular(v, unique))
+                .collect();
+            let v1 = *plane.first().ok_or(Error::Lattice)?;
+            let v2 = *plane
+                .iter()
+                .find(|&&v| !parallel(v, v1))
+                .ok_or(Error::Lattice)?;
+
+            match lattice.centering(&[unique, v1, v2]) {
+                1 => standard(LatticeType::Mcl, monoclinic(unique, v1, v2), &IDENTITY),
+                2 => {
+                    // The centering vector is `(a + b) / 2`, with `c` completing the basis of
+                    // the lattice plane normal to the unique axis `a`.
+                    let b = *plane
+                        .iter()
+                        .find(|&&v| lattice.contains(scale_vector(0.5, add(unique, v))))
+                        .ok_or(Error::Lattice)?;
+                    let c = *plane
+                        .iter()
+                        .find(|&&v| close(determinant(&[unique, b, v]).abs(), 2.0 * volume, volume))
+                        .ok_or(Error::Lattice)?;
+                    let conventional = monoclinic(unique, b, c);
+                    let primitive = matrix_product(&MCLC_CENTERED, &conventional);
+                    let reciprocal = reciprocal_vectors(&primitive);
+                    let cos_kgamma = cosine(reciprocal[0], reciprocal[1]);
+                    let (a, b, c) = (
+                        norm(conventional[0]),
+                        norm(conventional[1]),
+                        norm(conventional[2]),
+                    );
+                    let cos_alpha = cosine(conventional[1], conventional[2]);
+                    let sin2_alpha = 1.0 - cos_alpha * cos_alpha;
+                    let criterion = b * cos_alpha / c + b * b * sin2_alpha / (a * a);
+
+                    let lattice_type = if cos_kgamma.abs() < TOLERANCE {
+                        LatticeType::Mclc2
+                    } else if cos_kgamma < 0.0 {
+                        LatticeType::Mclc1
+                    } else if close(criterion, 1.0, 1.0) {
+                        LatticeType::Mclc4
+                    } else if criterion < 1.0 {
+                        LatticeType::Mclc3
+                    } else {
+                        LatticeType::Mclc5
+                    };
+                    standard(lattice_type, conventional, &MCLC_CENTERED)
+                }
+                _ => Err(Error::Lattice),
+            }
+        }
+        2 => triclinic(&lattice.basis),
+        _ => Err(Error::Lattice),
+    }
+}
+
+/// The monoclinic conventional cell with unique axis `a`, and angle `alpha < 90` degrees
+/// between `b` and `c`.
+fn monoclinic(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> [[f64; 3]; 3] {
+    let c = if dot(b, c) < 0.0 {
+        scale_vector(-1.0, c)
+    } else {
+        c
+    };
+    let a = if determinant(&[a, b, c]) < 0.0 {
+        scale_vector(-1.0, a)
+    } else {
+        a
+    };
+
+    [a, b, c]
+}
+
+/// The triclinic lattice types are defined by the angles `k_alpha`, `k_beta` and `k_gamma`
+/// between the reciprocal lattice vectors, which are either all obtuse or all acute, with
+/// `k_gamma` the smallest or largest respectively.
+fn triclinic(basis: &[[f64; 3]; 3]) -> Result<StandardCell, Error> {
+    let reciprocal = reduce_basis(&reciprocal_vectors(basis));
+    let permutations = [
+        [0, 1, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [1, 0, 2],
+        [0, 2, 1],
+        [2, 1, 0],
+    ];
+
+    for permutation in &permutations {
+        for signs in 0..8 {
+            let mut k = [[0.0; 3]; 3];
+            for (i, &p) in permutation.iter().enumerate() {
+                let sign = if signs & (1 << i) == 0 { 1.0 } else { -1.0 };
+                k[i] = scale_vector(sign, reciprocal[p]);
+            }
+            if determinant(&k) < 0.0 {
+                continue;
+            }
+
+            let cos_alpha = cosine(k[1], k[2]);
+            let cos_beta = cosine(k[0], k[2]);
+            let cos_gamma = cosine(k[0], k[1]);
+            let obtuse = cos_alpha < -TOLERANCE && cos_beta < -TOLERANCE;
+            let acute = cos_alpha > TOLERANCE && cos_beta > TOLERANCE;
+
+            let lattice_type = if cos_gamma.abs() < TOLERANCE && obtuse {
+                LatticeType::Tri2a
+            } else if cos_gamma.abs() < TOLERANCE && acute {
+                LatticeType::Tri2b
+            } else if obtuse
+                && cos_gamma < -TOLERANCE
+                && cos_gamma >= cos_alpha
+                && cos_gamma >= cos_beta
+            {
+                LatticeType::Tri1a
+            } else if acute
+                && cos_gamma > TOLERANCE
+                && cos_gamma <= cos_alpha
+                && cos_gamma <= cos_beta
+            {
+                LatticeType::Tri1b
+            } else {
+                continue;
+            };
+
+            let primitive = reciprocal_vectors(&k);
+            return Ok(StandardCell {
+                lattice_type,
+                primitive,
+                conventional: primitive,
+            });
+        }
+    }
+
+    Err(Error::Lattice)
+}
+
+type Points = Vec<(&'static str, [f64; 3])>;
+type Path = &'static [&'static [&'static str]];
+
+/// The high-symmetry points in crystal coordinates of the standard primitive reciprocal
+/// lattice vectors, and the path through them.
+fn high_symmetry_points(standard: &StandardCell) -> (Points, Path) {
+    let conv = &standard.conventional;
+    let (a, b, c) = (norm(conv[0]), norm(conv[1]), norm(conv[2]));
+    let (a2, b2, c2) = (a * a, b * b, c * c);
+
+    match standard.lattice_type {
+        LatticeType::Cub => (
+            vec![
+                ("Γ", [0.0, 0.0, 0.0]),
+                ("M", [0.5, 0.5, 0.0]),
+                ("R", [0.5, 0.5, 0.5]),
+                ("X", [0.0, 0.5, 0.0]),
+            ],
+            &[&["Γ", "X", "M", "Γ", "R", "X"], &["M", "R"]],
+        ),
+        LatticeType::Fcc => (
+            vec![
+                ("Γ", [0.0, 0.0, 0.0]),
+                ("K", [3.0 / 8.0, 3.0 / 8.0, 3.0 / 4.0]),
+                ("L", [0.5, 0.5, 0.5]),
+                ("U", [5.0 / 8.0, 1.0 / 4.0, 5.0 / 8.0]),
+                ("W", [0.5, 1.0 / 4.0, 3.0 / 4.0]),
+                ("X", [0.5, 0.0, 0.5]),
+            ],
+            &[
+                &["Γ", "X", "W", "K", "Γ", "L", "U", "W", "L", "K"],
+                &["U", "X"],
+            ],
+        ),
+        LatticeType::Bcc => (
+            vec![
+                ("Γ", [0.0, 0.0, 0.0]),
+                ("H", [0.5, -0.5, 0.5]),
+                ("P", [0.25, 0.25, 0.25]),
+                ("N", [0.0, 0.0, 0.5]),
+            ],
+            &[&["Γ", "H", "N", "Γ", "P", "H"], &["P", "N"]],
+        ),
+        LatticeType::Tet => (
+            vec![
+                ("Γ", [0.0, 0.0, 0.0]),
+                ("A", [0.5, 0.5, 0.5]),
+                ("M", [0.5, 0.5, 0.0]),
+                ("R", [0.0, 0.5, 0.5]),
+                ("X", [0.0, 0.5, 0.0]),
+                ("Z", [0.0, 0.0, 0.5]),
+            ],
+            &[
+                &["Γ", "X", "M", "Γ", "Z", "R", "A", "Z"],
+                &["X", "R"],
+                &["M", "A"],
+            ],
+        ),
+        LatticeType::Bct1 => {
+            let eta = (1.0 + c2 / a2) / 4.0;
+            (
+                vec![
+                    ("Γ", [0.0, 0.0, 0.0]),
+                    ("M", [-0.5, 0.5, 0.5]),
+                    ("N", [0.0, 0.5, 0.0]),
+                    ("P", [0.25, 0.25, 0.25]),
+                    ("X", [0.0, 0.0, 0.5]),
+                    ("Z", [eta, eta, -eta]),
+                    ("Z1", [-eta, 1.0 - eta, eta]),
+                ],
+                &[&["Γ", "X", "M", "Γ", "Z", "P", "N", "Z1", "M"], &["X", "P"]],
+            )
+        }
+        LatticeType::Bct2 => {
+            let eta = (1.0 + a2 / c2) / 4.0;
+            let zeta = a2 / (2.0 * c2);
+            (
+                vec![
+                    ("Γ", [0.0, 0.0, 0.0]),
+                    ("N", [0.0, 0.5, 0.0]),
+                    ("P", [0.25, 0.25, 0.25]),
+                    ("Σ", [-eta, eta, eta]),
+                    ("Σ1", [eta, 1.0 - eta, -eta]),
+                    ("X", [0.0, 0.0, 0.5]),
+                    ("Y", [-zeta, zeta, 0.5]),
+                    ("Y1", [0.5, 0.5, -zeta]),
+                    ("Z", [0.5, 0.5, -0.5]),
+                ],
+                &[
+                    &["Γ", "X", "Y", "Σ", "Γ", "Z", "Σ1", "N", "P", "Y1", "Z"],
+                    &["X", "P"],
+                ],
+            )
+        }
+        LatticeType::Orc => (
+            vec![
+                ("Γ", [0.0, 0.0, 0.0]),
+                ("R", [0.5, 0.5, 0.5]),
+                ("S", [0.5, 0.5, 0.0]),
+                ("T", [0.0, 0.5, 0.5]),
+                ("U", [0.5, 0.0, 0.5]),
+                ("X", [0.5, 0.0, 0.0]),
+                ("Y", [0.0, 0.5, 0.0]),
+                ("Z", [0.0, 0.0, 0.5]),
+            ],
+            &[
+                &["Γ", "X", "S", "Y", "Γ", "Z", "U", "R", "T", "Z"],
+                &["Y", "T"],
+                &["U", "X"],
+                &["S", "R"],
+            ],
+        ),
+        LatticeType::Orcf1 | LatticeType::Orcf3 => {
+            let zeta = (1.0 + a2 / b2 - a2 / c2) / 4.0;
+            let eta = (1.0 + a2 / b2 + a2 / c2) / 4.0;
+            let points = vec![
+                ("Γ", [0.0, 0.0, 0.0]),
+                ("A", [0.5, 0.5 + zeta, zeta]),
+                ("A1", [0.5, 0.5 - zeta, 1.0 - zeta]),
+                ("L", [0.5, 0.5, 0.5]),
+                ("T", [1.0, 0.5, 0.5]),
+                ("X", [0.0, eta, eta]),
+                ("X1", [1.0, 1.0 - eta, 1.0 - eta]),
+                ("Y", [0.5, 0.0, 0.5]),
+                ("Z", [0.5, 0.5, 0.0]),
+            ];
+            if standard.lattice_type == LatticeType::Orcf1 {
+                (
+                    points,
+                    &[
+                        &["Γ", "Y", "T", "Z", "Γ", "X", "A1", "Y"],
+                        &["T", "X1"],
+                        &["X", "A", "Z"],
+                        &["L", "Γ"],
+                    ],
+                )
+            } else {
+                (
+                    points,
+                    &[
+                        &["Γ", "Y", "T", "Z", "Γ", "X", "A1", "Y"],
+                        &["X", "A", "Z"],
+                        &["L", "Γ"],
+                    ],
+                )
+            }
+        }
+        LatticeType::Orcf2 => {
+            let eta = (1.0 + a2 / b2 - a2 / c2) / 4.0;
+            let phi = (1.0 + c2 / b2 - c2 / a2) / 4.0;
+            let delta = (1.0 + b2 / a2 - b2 / c2) / 4.0;
+            (
+                vec![
+                    ("Γ", [0.0, 0.0, 0.0]),
+                    ("C", [0.5, 0.5 - eta, 1.0 - eta]),
+                    ("C1", [0.5, 0.5 + eta, eta]),
+                    ("D", [0.5 - delta, 0.5, 1.0 - delta]),
+                    ("D1", [0.5 + delta, 0.5, delta]),
+                    ("L", [0.5, 0.5, 0.5]),
+                    ("H", [1.0 - phi, 0.5 - phi, 0.5]),
+                    ("H1", [phi, 0.5 + phi, 0.5]),
+                    ("X", [0.0, 0.5, 0.5]),
+                    ("Y", [0.5, 0.0, 0.5]),
+                    ("Z", [0.5, 0.5, 0.0]),
+                ],
+                &[
+                    &["Γ", "Y", "C", "D", "X", "Γ", "Z", "D1", "H", "C"],
+                    &["C1", "Z"],
+                    &["X", "H1"],
+                    &["H", "Y"],
+                    &["L", "Γ"],
+                ],
+            )
+        }
+        LatticeType::Orci => {
+            let zeta = (1.0 + a2 / c2) / 4.0;
+            let eta = (1.0 + b2 / c2) / 4.0;
+            let delta = (b2 - a2) / (4.0 * c2);
+            let mu = (a2 + b2) / (4.0 * c2);
+            (
+                vec![
+                    ("Γ", [0.0, 0.0, 0.0]),
+                    ("L", [-mu, mu, 0.5 - delta]),
+                    ("L1", [mu, -mu, 0.5 + delta]),
+                    ("L2", [0.5 - delta, 0.5 + delta, -mu]),
+                    ("R", [0.0, 0.5, 0.0]),
+                    ("S", [0.5, 0.0, 0.0]),
+                    ("T", [0.0, 0.0, 0.5]),
+                    ("W", [0.25, 0.25, 0.25]),
+                    ("X", [-zeta, zeta, zeta]),
+                    ("X1", [zeta, 1.0 - zeta, -zeta]),
+                    ("Y", [eta, -eta, eta]),
+                    ("Y1", [1.0 - eta, eta, -eta]),
+                    ("Z", [0.5, 0.5, -0.5]),
+                ],
+                &[
+                    &["Γ", "X", "L", "T", "W", "R", "X1", "Z", "Γ", "Y", "S", "W"],
+                    &["L1", "Y"],
+                    &["Y1", "Z"],
+                ],
+            )
+        }
+        LatticeType::Orcc => {
+            let zeta = (1.0 + a2 / b2) / 4.0;
+            (
+                vec![
+                    ("Γ", [0.0, 0.0, 0.0]),
+                    ("A", [zeta, zeta, 0.5]),
+                    ("A1", [-zeta, 1.0 - zeta, 0.5]),
+                    ("R", [0.0, 0.5, 0.5]),
+                    ("S", [0.0, 0.5, 0.0]),
+                    ("T", [-0.5, 0.5, 0.5]),
+                    ("X", [zeta, zeta, 0.0]),
+                    ("X1", [-zeta, 1.0 - zeta, 0.0]),
+                    ("Y", [-0.5, 0.5, 0.0]),
+                    ("Z", [0.0, 0.0, 0.5]),
+                ],
+                &[
+                    &["Γ", "X", "S", "R", "A", "Z", "Γ", "Y", "X1", "A1", "T", "Y"],
+                    &["Z", "T"],
+                ],
+            )
+        }
+        LatticeType::Hex => (
+            vec![
+                ("Γ", [0.0, 0.0, 0.0]),
+                ("A", [0.0, 0.0, 0.5]),
+                ("H", [1.0 / 3.0, 1.0 / 3.0, 0.5]),
+                ("K", [1.0 / 3.0, 1.0 / 3.0, 0.0]),
+                ("L", [0.5, 0.0, 0.5]),
+                ("M", [0.5, 0.0, 0.0]),
+            ],
+            &[
+                &["Γ", "M", "K", "Γ", "A", "L", "H", "A"],
+                &["L", "M"],
+                &["K", "H"],
+            ],
+        ),
+        LatticeType::Rhl1 => {
+            let cos_alpha = cosine(conv[0], conv[1]);
+            let eta = (1.0 + 4.0 * cos_alpha) / (2.0 + 4.0 * cos_alpha);
+            let nu = 0.75 - eta / 2.0;
+            (
+                vec![
+                    ("Γ", [0.0, 0.0, 0.0]),
+                    ("B", [eta, 0.5, 1.0 - eta]),
+                    ("B1", [0.5, 1.0 - eta, eta - 1.0]),
+                    ("F", [0.5, 0.5, 0.0]),
+                    ("L", [0.5, 0.0, 0.0]),
+                    ("L1", [0.0, 0.0, -0.5]),
+                    ("P", [eta, nu, nu]),
+                    ("P1", [1.0 - nu, 1.0 - nu, 1.0 - eta]),
+                    ("P2", [nu, nu, eta - 1.0]),
+                    ("Q", [1.0 - nu, nu, 0.0]),
+                    ("X", [nu, 0.0, -nu]),
+                    ("Z", [0.5, 0.5, 0.5]),
+                ],
+                &[
+                    &["Γ", "L", "B1"],
+                    &["B", "Z", "Γ", "X"],
+                    &["Q", "F", "P1", "Z"],
+                    &["L", "P"],
+                ],
+            )
+        }
+        LatticeType::Rhl2 => {
+            let cos_alpha = cosine(conv[0], conv[1]);
+            // tan^2(alpha / 2) = (1 - cos(alpha)) / (1 + cos(alpha))
+            let eta = (1.0 + cos_alpha) / (2.0 * (1.0 - cos_alpha));
+            let nu = 0.75 - eta / 2.0;
+            (
+                vec![
+                    ("Γ", [0.0, 0.0, 0.0]),
+                    ("F", [0.5, -0.5, 0.0]),
+                    ("L", [0.5, 0.0, 0.0]),
+                    ("P", [1.0 - nu, -nu, 1.0 - nu]),
+                    ("P1", [nu, nu - 1.0, nu - 1.0]),
+                    ("Q", [eta, eta, eta]),
+                    ("Q1", [1.0 - eta, -eta, -eta]),
+                    ("Z", [0.5, -0.5, 0.5]),
+                ],
+                &[&["Γ", "P", "Z", "Q", "Γ", "F", "P1", "Q1", "L", "Z"]],
+            )
+        }
+        LatticeType::Mcl => {
+            let cos_alpha = cosine(conv[1], conv[2]);
+            let sin2_alpha = 1.0 - cos_alpha * cos_alpha;
+            let eta = (1.0 - b * cos_alpha / c) / (2.0 * sin2_alpha);
+            let nu = 0.5 - eta * c * cos_alpha / b;
+            (
+                vec![
+                    ("Γ", [0.0, 0.0, 0.0]),
+                    ("A", [0.5, 0.5, 0.0]),
+                    ("C", [0.0, 0.5, 0.5]),
+                    ("D", [0.5, 0.0, 0.5]),
+                    ("D1", [0.5, 0.0, -0.5]),
+                    ("E", [0.5, 0.5, 0.5]),
+                    ("H", [0.0, eta, 1.0 - nu]),
+                    ("H1", [0.0, 1.0 - eta, nu]),
+                    ("H2", [0.0, eta, -nu]),
+                    ("M", [0.5, eta, 1.0 - nu]),
+                    ("M1", [0.5, 1.0 - eta, nu]),
+                    ("M2", [0.5, eta, -nu]),
+                    ("X", [0.0, 0.5, 0.0]),
+                    ("Y", [0.0, 0.0, 0.5]),
+                    ("Y1", [0.0, 0.0, -0.5]),
+                    ("Z", [0.5, 0.0, 0.0]),
+                ],
+                &[
+                    &["Γ", "Y", "H", "C", "E", "M1", "A", "X", "H1"],
+                    &["M", "D", "Z"],
+                    &["Y", "D"],
+                ],
+            )
+        }
+        LatticeType::Mclc1 | LatticeType::Mclc2 => {
+            let cos_alpha = cosine(conv[1], conv[2]);
+            let sin2_alpha = 1.0 - cos_alpha * cos_alpha;
+            let zeta = (2.0 - b * cos_alpha / c) / (4.0 * sin2_alpha);
+            let eta = 0.5 + 2.0 * zeta * c * cos_alpha / b;
+            let psi = 0.75 - a2 / (4.0 * b2 * sin2_alpha);
+            let phi = psi + (0.75 - psi) * b * cos_alpha / c;
+            let points = vec![
+                ("Γ", [0.0, 0.0, 0.0]),
+                ("N", [0.5, 0.0, 0.0]),
+                ("N1", [0.0, -0.5, 0.0]),
+                ("F", [1.0 - zeta, 1.0 - zeta, 1.0 - eta]),
+                ("F1", [zeta, zeta, eta]),
+                ("F2", [-zeta, -zeta, 1.0 - eta]),
+                ("I", [phi, 1.0 - phi, 0.5]),
+                ("I1", [1.0 - phi, phi - 1.0, 0.5]),
+                ("L", [0.5, 0.5, 0.5]),
+                ("M", [0.5, 0.0, 0.5]),
+                ("X", [1.0 - psi, psi - 1.0, 0.0]),
+                ("X1", [psi, 1.0 - psi, 0.0]),
+                ("X2", [psi - 1.0, -psi, 0.0]),
+                ("Y", [0.5, 0.5, 0.0]),
+                ("Y1", [-0.5, -0.5, 0.0]),
+                ("Z", [0.0, 0.0, 0.5]),
+            ];
+            if standard.lattice_type == LatticeType::Mclc1 {
+                (
+                    points,
+                    &[
+                        &["Γ", "Y", "F", "L", "I"],
+                        &["I1", "Z", "F1"],
+                        &["Y", "X1"],
+                        &["X", "Γ", "N"],
+                        &["M", "Γ"],
+                    ],
+                )
+            } else {
+                (
+                    points,
+                    &[
+                        &["Γ", "Y", "F", "L", "I"],
+                        &["I1", "Z", "F1"],
+                        &["N", "Γ", "M"],
+                    ],
+                )
+            }
+        }
+        LatticeType::Mclc3 | LatticeType::Mclc4 => {
+            let cos_alpha = cosine(conv[1], conv[2]);
+            let sin2_alpha = 1.0 - cos_alpha * cos_alpha;
+            let mu = (1.0 + b2 / a2) / 4.0;
+            let delta = b * c * cos_alpha / (2.0 * a2);
+            let zeta = mu - 0.25 + (1.0 - b * cos_alpha / c) / (4.0 * sin2_alpha);
+            let eta = 0.5 + 2.0 * zeta * c * cos_alpha / b;
+            let phi = 1.0 + zeta - 2.0 * mu;
+            let psi = eta - 2.0 * delta;
+            let points = vec![
+                ("Γ", [0.0, 0.0, 0.0]),
+                ("F", [1.0 - phi, 1.0 - phi, 1.0 - psi]),
+                ("F1", [phi, phi - 1.0, psi]),
+                ("F2", [1.0 - phi, -phi, 1.0 - psi]),
+                ("H", [zeta, zeta, eta]),
+                ("H1", [1.0 - zeta, -zeta, 1.0 - eta]),
+                ("H2", [-zeta, -zeta, 1.0 - eta]),
+                ("I", [0.5, -0.5, 0.5]),
+                ("M", [0.5, 0.0, 0.5]),
+                ("N", [0.5, 0.0, 0.0]),
+                ("N1", [0.0, -0.5, 0.0]),
+                ("X", [0.5, -0.5, 0.0]),
+                ("Y", [mu, mu, delta]),
+                ("Y1", [1.0 - mu, -mu, -delta]),
+                ("Y2", [-mu, -mu, -delta]),
+                ("Y3", [mu, mu - 1.0, delta]),
+                ("Z", [0.0, 0.0, 0.5]),
+            ];
+            if standard.lattice_type == LatticeType::Mclc3 {
+                (
+                    points,
+                    &[
+                        &["Γ", "Y", "F", "H", "Z", "I", "F1"],
+                        &["H1", "Y1", "X", "Γ", "N"],
+                        &["M", "Γ"],
+                    ],
+                )
+            } else {
+                (
+                    points,
+                    &[
+                        &["Γ", "Y", "F", "H", "Z", "I"],
+                        &["H1", "Y1", "X", "Γ", "N"],
+                        &["M", "Γ"],
+                    ],
+                )
+            }
+        }
+        LatticeType::Mclc5 => {
+            let cos_alpha = cosine(conv[1], conv[2]);
+            let sin2_alpha = 1.0 - cos_alpha * cos_alpha;
+            let zeta = (b2 / a2 + (1.0 - b * cos_alpha / c) / sin2_alpha) / 4.0;
+            let eta = 0.5 + 2.0 * zeta * c * cos_alpha / b;
+            let mu = eta / 2.0 + b2 / (4.0 * a2) - b * c * cos_alpha / (2.0 * a2);
+            let nu = 2.0 * mu - zeta;
+            let omega = (4.0 * nu - 1.0 - b2 * sin2_alpha / a2) * c / (2.0 * b * cos_alpha);
+            let delta = zeta * c * cos_alpha / b + omega / 2.0 - 0.25;
+            let rho = 1.0 - zeta * a2 / b2;
+            (
+                vec![
+                    ("Γ", [0.0, 0.0, 0.0]),
+                    ("F", [nu, nu, omega]),
+                    ("F1", [1.0 - nu, 1.0 - nu, 1.0 - omega]),
+                    ("F2", [nu, nu - 1.0, omega]),
+                    ("H", [zeta, zeta, eta]),
+                    ("H1", [1.0 - zeta, -zeta, 1.0 - eta]),
+                    ("H2", [-zeta, -zeta, 1.0 - eta]),
+                    ("I", [rho, 1.0 - rho, 0.5]),
+                    ("I1", [1.0 - rho, rho - 1.0, 0.5]),
+                    ("L", [0.5, 0.5, 0.5]),
+                    ("M", [0.5, 0.0, 0.5]),
+                    ("N", [0.5, 0.0, 0.0]),
+                    ("N1", [0.0, -0.5, 0.0]),
+                    ("X", [0.5, -0.5, 0.0]),
+                    ("Y", [mu, mu, delta]),
+                    ("Y1", [1.0 - mu, -mu, -delta]),
+                    ("Y2", [-mu, -mu, -delta]),
+                    ("Y3", [mu, mu - 1.0, delta]),
+                    ("Z", [0.0, 0.0, 0.5]),
+                ],
+                &[
+                    &["Γ", "Y", "F", "L", "I"],
+                    &["I1", "Z", "H", "F1"],
+                    &["H1", "Y1", "X", "Γ", "N"],
+                    &["M", "Γ"],
+                ],
+            )
+        }
+        LatticeType::Tri1a | LatticeType::Tri2a => (
+            vec![
+                ("Γ", [0.0, 0.0, 0.0]),
+                ("L", [0.5, 0.5, 0.0]),
+                ("M", [0.0, 0.5, 0.5]),
+                ("N", [0.5, 0.0, 0.5]),
+                ("R", [0.5, 0.5, 0.5]),
+                ("X", [0.5, 0.0, 0.0]),
+                ("Y", [0.0, 0.5, 0.0]),
+                ("Z", [0.0, 0.0, 0.5]),
+            ],
+            TRICLINIC_PATH,
+        ),
+        LatticeType::Tri1b | LatticeType::Tri2b => (
+            vec![
+                ("Γ", [0.0, 0.0, 0.0]),
+                ("L", [0.5, -0.5, 0.0]),
+                ("M", [0.0, 0.0, 0.5]),
+                ("N", [-0.5, -0.5, 0.5]),
+                ("R", [0.0, -0.5, 0.5]),
+                ("X", [0.0, -0.5, 0.0]),
+                ("Y", [0.5, 0.0, 0.0]),
+                ("Z", [-0.5, 0.0, 0.5]),
+            ],
+            TRICLINIC_PATH,
+        ),
+    }
+}
+
+const TRICLINIC_PATH: Path = &[
+    &["X", "Γ", "Y"],
+    &["L", "Γ", "Z"],
+    &["N", "Γ", "M"],
+    &["R", "Γ"],
+];
+
+/// A reduced basis of a lattice, together with its short lattice vectors.
+struct Lattice {
+    basis: [[f64; 3]; 3],
+    basis_inverse: [[f64; 3]; 3],
+    volume: f64,
+    /// Lattice vectors with small coefficients in the reduced basis, sorted by length.
+    vectors: Vec<[f64; 3]>,
+}
+
+impl Lattice {
+    fn new(cell: &[[f64; 3]; 3]) -> Lattice {
+        let basis = reduce_basis(cell);
+        let mut vectors = Vec::new();
+        for n0 in -3..=3 {
+            for n1 in -3..=3 {
+                for n2 in -3..=3 {
+                    if (n0, n1, n2) != (0, 0, 0) {
+                        vectors.push(apply_rows(
+                            [f64::from(n0), f64::from(n1), f64::from(n2)],
+                            &basis,
+                        ));
+                    }
+                }
+            }
+        }
+        vectors.sort_by(|u, v| norm(*u).total_cmp(&norm(*v)));
+
+        Lattice {
+            basis,
+            basis_inverse: inverse(&basis),
+            volume: determinant(&basis).abs(),
+            vectors,
+        }
+    }
+
+    /// The proper rotation among `rotations` with the given trace, as a Cartesian matrix
+    /// acting on row vectors. The trace identifies the order of the rotation: -1, 0, 1 and 2
+    /// for two-, three-, four- and six-fold rotations.
+    fn rotation(&self, rotations: &[[[i32; 3]; 3]], trace: i32) -> Result<[[f64; 3]; 3], Error> {
+        self.rotations(rotations, trace)
+            .into_iter()
+            .next()
+            .ok_or(Error::Lattice)
+    }
+
+    fn rotations(&self, rotations: &[[[i32; 3]; 3]], trace: i32) -> Vec<[[f64; 3]; 3]> {
+        rotations
+            .iter()
+            .filter(|w| integer_determinant(w) == 1 && w[0][0] + w[1][1] + w[2][2] == trace)
+            .map(|w| cartesian_rotation(&self.basis, w))
+            .collect()
+    }
+
+    /// The shortest lattice vector along the axis of the rotation `q`.
+    fn axis(&self, q: &[[f64; 3]; 3]) -> Result<[f64; 3], Error> {
+        self.shortest(|v| close_vectors(apply_rows(v, q), v))
+    }
+
+    /// The shortest lattice vectors along the distinct axes of the proper rotations with the
+    /// given trace.
+    fn distinct_axes(&self, rotations: &[[[i32; 3]; 3]], trace: i32) -> Vec<[f64; 3]> {
+        let mut axes: Vec<[f64; 3]> = Vec::new();
+        for q in self.rotations(rotations, trace) {
+            if let Ok(axis) = self.axis(&q) {
+                if !axes.iter().any(|&u| parallel(u, axis)) {
+                    axes.push(axis);
+                }
+            }
+        }
+        axes.sort_by(|u, v| norm(*u).total_cmp(&norm(*v)));
+
+        axes
+    }
+
+    fn shortest<F: Fn([f64; 3]) -> bool>(&self, predicate: F) -> Result<[f64; 3], Error> {
+        self.vectors
+            .iter()
+            .cloned()
+            .find(|&v| predicate(v))
+            .ok_or(Error::Lattice)
+    }
+
+    fn contains(&self, v: [f64; 3]) -> bool {
+        apply_rows(v, &self.basis_inverse)
+            .iter()
+            .all(|x| (x - x.round()).abs() < TOLERANCE.sqrt())
+    }
+
+    /// The number of lattice points in the cell `vectors`.
+    fn centering(&self, vectors: &[[f64; 3]; 3]) -> usize {
+        (determinant(vectors).abs() / self.volume).round() as usize
+    }
+}
+
+fn right_handed(vectors: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    if determinant(&vectors) < 0.0 {
+        [vectors[0], vectors[1], scale_vector(-1.0, vectors[2])]
+    } else {
+        vectors
+    }
+}
+
+fn perpendicular(u: [f64; 3], v: [f64; 3]) -> bool {
+    dot(u, v).abs() < TOLERANCE * norm(u) * norm(v)
+}
+
+fn parallel(u: [f64; 3], v: [f64; 3]) -> bool {
+    norm(cross(u, v)) < TOLERANCE * norm(u) * norm(v)
+}
+
+fn close(x: f64, y: f64, scale: f64) -> bool {
+    (x - y).abs() < TOLERANCE * scale.abs()
+}
+
+fn close_vectors(u: [f64; 3], v: [f64; 3]) -> bool {
+    norm(sub(u, v)) < TOLERANCE * norm(v)
+}
+
+fn cosine(u: [f64; 3], v: [f64; 3]) -> f64 {
+    dot(u, v) / (norm(u) * norm(v))
+}
+
+fn add(u: [f64; 3], v: [f64; 3]) -> [f64; 3] {
+    [u[0] + v[0], u[1] + v[1], u[2] + v[2]]
+}
+
+fn sub(u: [f64; 3], v: [f64; 3]) -> [f64; 3] {
+    [u[0] - v[0], u[1] - v[1], u[2] - v[2]]
+}
+
+/// The row vector `v` times the matrix `m`.
+fn apply_rows(v: [f64; 3], m: &[[f64; 3]; 3]) -> [f64; 3] {
+    let mut result = [0.0; 3];
+    for (i, row) in m.iter().enumerate() {
+        for j in 0..3 {
+            result[j] += v[i] * row[j];
+        }
+    }
+
+    result
+}
+
+/// The matrix `m` times the column vector `v`.
+fn apply_columns(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    [dot(m[0], v), dot(m[1], v), dot(m[2], v)]
+}
+
+/// Round the elements of `m`, which must be integers to within the tolerance.
+fn round_matrix(m: &[[f64; 3]; 3]) -> Result<[[f64; 3]; 3], Error> {
+    let mut result = *m;
+    for x in result.iter_mut().flat_map(|row| row.iter_mut()) {
+        if (*x - x.round()).abs() > TOLERANCE.sqrt() {
+            return Err(Error::Lattice);
+        }
+        *x = x.round();
+    }
+
+    Ok(result)
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "Lattice vectors must span a nonzero volume.")]
+    CellVolume,
+    #[fail(display = "Could not identify the Bravais lattice of the lattice vectors.")]
+    Lattice,
+}
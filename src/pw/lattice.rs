@@ -0,0 +1,461 @@
+//! Lattice vectors and coordinate conversions for the lattices described by `Ibrav`.
+use std::f64::consts::PI;
+use pw::input::{AtomCoordinate, Cell, Ibrav, LatticeUnits, PositionCoordinateType, Positions};
+use units::Length;
+
+pub use units::BOHR_ANGSTROM;
+
+/// The lattice vectors in bohr, as rows. For lattices other than `Ibrav::Free`, these
+/// follow the conventions of QE's `latgen`.
+pub fn lattice_vectors(ibrav: &Ibrav, alat: f64) -> [[f64; 3]; 3] {
+    let a = alat;
+
+    match *ibrav {
+        Ibrav::Free(ref cell) => cell_vectors(cell, alat),
+        Ibrav::SimpleCubic => [[a, 0.0, 0.0], [0.0, a, 0.0], [0.0, 0.0, a]],
+        Ibrav::Fcc => scale(
+            a / 2.0,
+            [[-1.0, 0.0, 1.0], [0.0, 1.0, 1.0], [-1.0, 1.0, 0.0]],
+        ),
+        Ibrav::Bcc => scale(
+            a / 2.0,
+            [[1.0, 1.0, 1.0], [-1.0, 1.0, 1.0], [-1.0, -1.0, 1.0]],
+        ),
+        Ibrav::BccSymmetric => scale(
+            a / 2.0,
+            [[-1.0, 1.0, 1.0], [1.0, -1.0, 1.0], [1.0, 1.0, -1.0]],
+        ),
+        Ibrav::Hexagonal(c_a) => scale(
+            a,
+            [
+                [1.0, 0.0, 0.0],
+                [-0.5, 3f64.sqrt() / 2.0, 0.0],
+                [0.0, 0.0, c_a],
+            ],
+        ),
+        Ibrav::TrigonalRAxisC(c) => {
+            let (tx, ty, tz) = trigonal(c);
+            scale(a, [[tx, -ty, tz], [0.0, 2.0 * ty, tz], [-tx, -ty, tz]])
+        }
+        Ibrav::TrigonalRAxis111(c) => {
+            let (_, ty, tz) = trigonal(c);
+            let u = tz - 2.0 * 2f64.sqrt() * ty;
+            let v = tz + 2f64.sqrt() * ty;
+            scale(a / 3f64.sqrt(), [[u, v, v], [v, u, v], [v, v, u]])
+        }
+        Ibrav::TetragonalP(c_a) => scale(a, [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, c_a]]),
+        Ibrav::TetragonalI(c_a) => scale(
+            a / 2.0,
+            [[1.0, -1.0, c_a], [1.0, 1.0, c_a], [-1.0, -1.0, c_a]],
+        ),
+        Ibrav::OrthorhombicP(b_a, c_a) => {
+            scale(a, [[1.0, 0.0, 0.0], [0.0, b_a, 0.0], [0.0, 0.0, c_a]])
+        }
+        Ibrav::OrthorhombicBco(b_a, c_a) => scale(
+            a,
+            [
+                [0.5, b_a / 2.0, 0.0],
+                [-0.5, b_a / 2.0, 0.0],
+                [0.0, 0.0, c_a],
+            ],
+        ),
+        Ibrav::OrthorhombicBcoAlternate(b_a, c_a) => scale(
+            a,
+            [
+                [0.5, -b_a / 2.0, 0.0],
+                [0.5, b_a / 2.0, 0.0],
+                [0.0, 0.0, c_a],
+            ],
+        ),
+        Ibrav::OrthorhombicFaceCentered(b_a, c_a) => {
+            scale(a / 2.0, [[1.0, 0.0, c_a], [1.0, b_a, 0.0], [0.0, b_a, c_a]])
+        }
+        Ibrav::OrthorhombicBodyCentered(b_a, c_a) => scale(
+            a / 2.0,
+            [[1.0, b_a, c_a], [-1.0, b_a, c_a], [-1.0, -b_a, c_a]],
+        ),
+        Ibrav::MonoclinicPUniqueAxisC(b_a, c_a, cos_ab) => {
+            let sin_ab = (1.0 - cos_ab * cos_ab).sqrt();
+            scale(
+                a,
+                [
+                    [1.0, 0.0, 0.0],
+                    [b_a * cos_ab, b_a * sin_ab, 0.0],
+                    [0.0, 0.0, c_a],
+                ],
+            )
+        }
+        Ibrav::MonoclinicPUniqueAxisB(b_a, c_a, cos_ac) => {
+            let sin_ac = (1.0 - cos_ac * cos_ac).sqrt();
+            scale(
+                a,
+                [
+                    [1.0, 0.0, 0.0],
+                    [0.0, b_a, 0.0],
+                    [c_a * cos_ac, 0.0, c_a * sin_ac],
+                ],
+            )
+        }
+        Ibrav::MonoclinicBaseCentered(b_a, c_a, cos_ab) => {
+            let sin_ab = (1.0 - cos_ab * cos_ab).sqrt();
+            scale(
+                a,
+                [
+                    [0.5, 0.0, -c_a / 2.0],
+                    [b_a * cos_ab, b_a * sin_ab, 0.0],
+                    [0.5, 0.0, c_a / 2.0],
+                ],
+            )
+        }
+        Ibrav::Triclinic(b_a, c_a, cos_bc, cos_ac, cos_ab) => {
+            let sin_ab = (1.0 - cos_ab * cos_ab).sqrt();
+            let volume_term = 1.0 + 2.0 * cos_bc * cos_ac * cos_ab
+                - cos_bc * cos_bc
+                - cos_ac * cos_ac
+                - cos_ab * cos_ab;
+            scale(
+                a,
+                [
+                    [1.0, 0.0, 0.0],
+                    [b_a * cos_ab, b_a * sin_ab, 0.0],
+                    [
+                        c_a * cos_ac,
+                        c_a * (cos_bc - cos_ac * cos_ab) / sin_ab,
+                        c_a * volume_term.sqrt() / sin_ab,
+                    ],
+                ],
+            )
+        }
+    }
+}
+
+/// The vectors of `cell` in bohr.
+pub fn cell_vectors(cell: &Cell, alat: f64) -> [[f64; 3]; 3] {
+    scale(cell.units.in_bohr(alat), cell.cell)
+}
+
+impl LatticeUnits {
+    /// The size of the unit in bohr, for the lattice constant `alat` in bohr.
+    pub fn in_bohr(&self, alat: f64) -> f64 {
+        match *self {
+            LatticeUnits::Bohr => Length::Bohr.in_bohr(),
+            LatticeUnits::Angstrom => Length::Angstrom.in_bohr(),
+            LatticeUnits::Alat => alat,
+        }
+    }
+}
+
+impl Cell {
+    /// The same cell with its vectors given in `units`, for the lattice constant `alat` in
+    /// bohr.
+    pub fn to_units(&self, units: LatticeUnits, alat: f64) -> Cell {
+        let factor = self.units.in_bohr(alat) / units.in_bohr(alat);
+
+        Cell {
+            cell: scale(factor, self.cell),
+            units,
+        }
+    }
+
+    /// The reciprocal lattice vectors of the cell in 1/bohr, as rows, for the lattice
+    /// constant `alat` in bohr.
+    pub fn reciprocal_vectors(&self, alat: f64) -> [[f64; 3]; 3] {
+        reciprocal_vectors(&cell_vectors(self, alat))
+    }
+}
+
+impl PositionCoordinateType {
+    /// The size of the unit of Cartesian coordinates in bohr, for the lattice constant `alat`
+    /// in bohr, or `None` for crystal coordinates.
+    pub fn in_bohr(&self, alat: f64) -> Option<f64> {
+        match *self {
+            PositionCoordinateType::AlatCartesian => Some(alat),
+            PositionCoordinateType::BohrCartesian => Some(Length::Bohr.in_bohr()),
+            PositionCoordinateType::AngstromCartesian => Some(Length::Angstrom.in_bohr()),
+            PositionCoordinateType::Crystal | PositionCoordinateType::CrystalSG => None,
+        }
+    }
+}
+
+impl Positions {
+    /// The same positions given in `coordinate_type`, for the lattice vectors `cell` in bohr
+    /// (as given by `lattice_vectors`) and the lattice constant `alat` in bohr.
+    ///
+    /// Positions given as `CrystalSG` depend on the space group, and are not converted to or
+    /// from other coordinate types.
+    pub fn to_coordinate_type(
+        &self,
+        coordinate_type: PositionCoordinateType,
+        cell: &[[f64; 3]; 3],
+        alat: f64,
+    ) -> Result<Positions, Error> {
+        if self.coordinate_type == coordinate_type {
+            return Ok(self.clone());
+        }
+        if self.coordinate_type == PositionCoordinateType::CrystalSG
+            || coordinate_type == PositionCoordinateType::CrystalSG
+        {
+            return Err(Error::CrystalSG);
+        }
+
+        let from = self.coordinate_type.in_bohr(alat);
+        let to = coordinate_type.in_bohr(alat);
+        let coordinates = self
+            .coordinates
+            .iter()
+            .map(|atom| {
+                let r_bohr = match from {
+                    Some(factor) => scale_vector(factor, atom.r),
+                    None => crystal_to_cartesian(cell, atom.r),
+                };
+                let r = match to {
+                    Some(factor) => scale_vector(1.0 / factor, r_bohr),
+                    None => cartesian_to_crystal(cell, r_bohr),
+                };
+                AtomCoordinate { r, ..atom.clone() }
+            })
+            .collect();
+
+        Ok(Positions {
+            coordinate_type,
+            coordinates,
+        })
+    }
+}
+
+/// Convert the Cartesian position `r` to crystal coordinates with respect to the lattice
+/// vectors `cell` (given as rows, in the same units as `r`).
+pub fn cartesian_to_crystal(cell: &[[f64; 3]; 3], r: [f64; 3]) -> [f64; 3] {
+    // Solve r = sum_i x_i a_i by Cramer's rule.
+    let det = determinant(cell);
+    let mut x = [0.0; 3];
+
+    for (i, xi) in x.iter_mut().enumerate() {
+        let mut m = *cell;
+        m[i] = r;
+        *xi = determinant(&m) / det;
+    }
+
+    x
+}
+
+/// Convert the crystal coordinates `x` to a Cartesian position, in the units of `cell`.
+pub fn crystal_to_cartesian(cell: &[[f64; 3]; 3], x: [f64; 3]) -> [f64; 3] {
+    let mut r = [0.0; 3];
+
+    for (i, a) in cell.iter().enumerate() {
+        for j in 0..3 {
+            r[j] += x[i] * a[j];
+        }
+    }
+
+    r
+}
+
+/// The determinant of the matrix with rows `m`; for lattice vectors, this is the signed
+/// cell volume `(a1 x a2) . a3`.
+pub fn determinant(m: &[[f64; 3]; 3]) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+fn trigonal(cos_gamma: f64) -> (f64, f64, f64) {
+    let tx = ((1.0 - cos_gamma) / 2.0).sqrt();
+    let ty = ((1.0 - cos_gamma) / 6.0).sqrt();
+    let tz = ((1.0 + 2.0 * cos_gamma) / 3.0).sqrt();
+
+    (tx, ty, tz)
+}
+
+fn scale(factor: f64, m: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut result = m;
+    for row in result.iter_mut() {
+        for x in row.iter_mut() {
+            *x *= factor;
+        }
+    }
+
+    result
+}
+
+/// The reciprocal lattice vectors `b_i` of `cell`, as rows, with `a_i . b_j = 2 pi delta_ij`.
+pub fn reciprocal_vectors(cell: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let factor = 2.0 * PI / determinant(cell);
+
+    [
+        scale_vector(factor, cross(cell[1], cell[2])),
+        scale_vector(factor, cross(cell[2], cell[0])),
+        scale_vector(factor, cross(cell[0], cell[1])),
+    ]
+}
+
+/// The inverse of the matrix `m`.
+pub fn inverse(m: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let det = determinant(m);
+    let cofactors = [cross(m[1], m[2]), cross(m[2], m[0]), cross(m[0], m[1])];
+
+    let mut result = [[0.0; 3]; 3];
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, x) in row.iter_mut().enumerate() {
+            *x = cofactors[j][i] / det;
+        }
+    }
+
+    result
+}
+
+/// A basis of the lattice spanned by `cell`, sorted by length, in which no vector can be
+/// shortened by adding or subtracting the other vectors.
+pub fn reduce_basis(cell: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut basis = *cell;
+    let scale = basis.iter().map(|a| dot(*a, *a)).fold(0.0, f64::max);
+
+    loop {
+        basis.sort_by(|a, b| dot(*a, *a).total_cmp(&dot(*b, *b)));
+
+        let mut changed = false;
+        for i in 0..3 {
+            let (j, k) = ((i + 1) % 3, (i + 2) % 3);
+            for &sj in &[-1.0, 0.0, 1.0] {
+                for &sk in &[-1.0, 0.0, 1.0] {
+                    let mut v = basis[i];
+                    for x in 0..3 {
+                        v[x] += sj * basis[j][x] + sk * basis[k][x];
+                    }
+                    if dot(v, v) < dot(basis[i], basis[i]) - 1e-12 * scale {
+                        basis[i] = v;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        if !changed {
+            return basis;
+        }
+    }
+}
+
+/// The rotations (proper and improper) which map the lattice spanned by `cell` onto itself.
+/// Each rotation is given as the integer matrix `W` such that the image of `a_i` is
+/// `sum_j W_ij a_j`.
+///
+/// The lattice metric must be preserved to within `tolerance` relative to the squared length
+/// of the longest vector. `cell` should be reduced by `reduce_basis`, since only images with
+/// small coefficients are considered.
+pub fn lattice_rotations(cell: &[[f64; 3]; 3], tolerance: f64) -> Vec<[[i32; 3]; 3]> {
+    let mut metric = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            metric[i][j] = dot(cell[i], cell[j]);
+        }
+    }
+    let scale = (0..3).map(|i| metric[i][i]).fold(0.0, f64::max);
+    let product = |n: &[i32; 3], m: &[i32; 3]| -> f64 {
+        let mut result = 0.0;
+        for i in 0..3 {
+            for j in 0..3 {
+                result += f64::from(n[i]) * metric[i][j] * f64::from(m[j]);
+            }
+        }
+        result
+    };
+    let close = |x: f64, y: f64| (x - y).abs() < tolerance * scale;
+
+    let mut candidates = Vec::new();
+    for n0 in -2..=2 {
+        for n1 in -2..=2 {
+            for n2 in -2..=2 {
+                candidates.push([n0, n1, n2]);
+            }
+        }
+    }
+
+    // The possible images of each basis vector are the lattice vectors of the same length.
+    let images: Vec<Vec<&[i32; 3]>> = (0..3)
+        .map(|i| {
+            candidates
+                .iter()
+                .filter(|n| close(product(n, n), metric[i][i]))
+                .collect()
+        })
+        .collect();
+
+    let mut rotations = Vec::new();
+    for &n0 in &images[0] {
+        for &n1 in &images[1] {
+            if !close(product(n0, n1), metric[0][1]) {
+                continue;
+            }
+            for &n2 in &images[2] {
+                if !close(product(n0, n2), metric[0][2]) || !close(product(n1, n2), metric[1][2]) {
+                    continue;
+                }
+                let w = [*n0, *n1, *n2];
+                let det = integer_determinant(&w);
+                if det == 1 || det == -1 {
+                    rotations.push(w);
+                }
+            }
+        }
+    }
+
+    rotations
+}
+
+/// The rotation `w` of the lattice spanned by `cell`, as given by `lattice_rotations`, as a
+/// Cartesian matrix `Q` acting on row vectors: the image of `r` is `r Q`.
+pub fn cartesian_rotation(cell: &[[f64; 3]; 3], w: &[[i32; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut w_f = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            w_f[i][j] = f64::from(w[i][j]);
+        }
+    }
+
+    matrix_product(&matrix_product(&inverse(cell), &w_f), cell)
+}
+
+/// The determinant of the integer matrix `m`.
+pub fn integer_determinant(m: &[[i32; 3]; 3]) -> i32 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+/// The matrix product `a b`.
+pub(crate) fn matrix_product(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut result = [[0.0; 3]; 3];
+    for (i, row) in result.iter_mut().enumerate() {
+        *row = crystal_to_cartesian(b, a[i]);
+    }
+
+    result
+}
+
+pub(crate) fn dot(u: [f64; 3], v: [f64; 3]) -> f64 {
+    u[0] * v[0] + u[1] * v[1] + u[2] * v[2]
+}
+
+pub(crate) fn cross(u: [f64; 3], v: [f64; 3]) -> [f64; 3] {
+    [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]
+}
+
+pub(crate) fn norm(u: [f64; 3]) -> f64 {
+    dot(u, u).sqrt()
+}
+
+pub(crate) fn scale_vector(factor: f64, u: [f64; 3]) -> [f64; 3] {
+    [factor * u[0], factor * u[1], factor * u[2]]
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "`crystal_sg` positions cannot be converted to other coordinate types.")]
+    CrystalSG,
+}
@@ -1,2 +1,11 @@
 pub mod input;
 pub mod serialize;
+pub mod parse;
+pub mod output;
+pub mod xml;
+pub mod lattice;
+pub mod kpath;
+pub mod symmetry;
+pub mod supercell;
+pub mod fermi_surface;
+mod namelists;
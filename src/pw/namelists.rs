@@ -0,0 +1,570 @@
+//! The namelists of the pw.x input file, with fields as they appear in the file.
+//!
+//! `serialize` writes an `Input` by converting it to these structs, and `parse` reads an
+//! `Input` by deserializing these structs and mapping them back. Fields are written in the
+//! order they are declared here.
+use std::path::PathBuf;
+use pw::input::{
+    Calculation, ConstrainedMagnetization, Dftd3Version, Efield, Functional, Hubbard, HubbardKind,
+    HubbardParameters, Ibrav, Input, IonTemperature, LatticeDirection, LatticeUnits, Occupations,
+    SpinType, VcRelaxDynamics, VdwCorr,
+};
+use pw::serialize::Field;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Control {
+    pub calculation: Option<String>,
+    pub verbosity: Option<String>,
+    pub restart_mode: Option<String>,
+    pub disk_io: Option<String>,
+    pub wf_collect: Option<bool>,
+    pub pseudo_dir: Option<PathBuf>,
+    #[serde(rename = "outdir")]
+    pub out_dir: Option<PathBuf>,
+    pub tefield: Option<bool>,
+    pub dipfield: Option<bool>,
+    pub lelfield: Option<bool>,
+    pub nberrycyc: Option<u64>,
+    pub lberry: Option<bool>,
+    pub gdir: Option<i64>,
+    pub nppstr: Option<u64>,
+    pub prefix: Option<String>,
+    pub nstep: Option<u64>,
+    pub etot_conv_thr: Option<f64>,
+    pub forc_conv_thr: Option<f64>,
+    pub dt: Option<f64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct System {
+    pub ibrav: Option<i64>,
+    #[serde(default)]
+    pub celldm: Vec<Option<f64>>,
+    pub nat: Option<u64>,
+    pub ntyp: Option<u64>,
+    pub nbnd: Option<u64>,
+    pub ecutwfc: Option<f64>,
+    pub ecutrho: Option<f64>,
+    pub nosym: Option<bool>,
+    pub occupations: Option<String>,
+    pub smearing: Option<String>,
+    pub degauss: Option<f64>,
+    pub nspin: Option<i64>,
+    pub noncolin: Option<bool>,
+    pub lspinorb: Option<bool>,
+    #[serde(default)]
+    pub starting_magnetization: Vec<Option<f64>>,
+    #[serde(default)]
+    pub angle1: Vec<Option<f64>>,
+    #[serde(default)]
+    pub angle2: Vec<Option<f64>>,
+    pub tot_magnetization: Option<f64>,
+    pub constrained_magnetization: Option<String>,
+    #[serde(default)]
+    pub fixed_magnetization: Vec<Option<f64>>,
+    pub lambda: Option<f64>,
+    pub lda_plus_u: Option<bool>,
+    pub lda_plus_u_kind: Option<i64>,
+    #[serde(rename = "Hubbard_U", default)]
+    pub hubbard_u: Vec<Option<f64>>,
+    #[serde(rename = "Hubbard_J0", default)]
+    pub hubbard_j0: Vec<Option<f64>>,
+    #[serde(rename = "Hubbard_alpha", default)]
+    pub hubbard_alpha: Vec<Option<f64>>,
+    #[serde(rename = "Hubbard_J", default)]
+    pub hubbard_j: Vec<Vec<Option<f64>>>,
+    #[serde(rename = "U_projection_type")]
+    pub u_projection_type: Option<String>,
+    pub input_dft: Option<String>,
+    pub exx_fraction: Option<f64>,
+    pub exxdiv_treatment: Option<String>,
+    pub nqx1: Option<u64>,
+    pub nqx2: Option<u64>,
+    pub nqx3: Option<u64>,
+    pub ecutfock: Option<f64>,
+    pub vdw_corr: Option<String>,
+    pub dftd3_version: Option<i64>,
+    pub dftd3_threebody: Option<bool>,
+    pub ts_vdw_econv_thr: Option<f64>,
+    pub ts_vdw_isolated: Option<bool>,
+    pub xdm_a1: Option<f64>,
+    pub xdm_a2: Option<f64>,
+    pub edir: Option<i64>,
+    pub emaxpos: Option<f64>,
+    pub eopreg: Option<f64>,
+    pub eamp: Option<f64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Electrons {
+    pub startingwfc: Option<String>,
+    pub diagonalization: Option<String>,
+    pub conv_thr: Option<f64>,
+    pub diago_thr_init: Option<f64>,
+    #[serde(default)]
+    pub efield_cart: Vec<Option<f64>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Ions {
+    pub ion_dynamics: Option<String>,
+    pub ion_temperature: Option<String>,
+    pub tempw: Option<f64>,
+    pub tolp: Option<f64>,
+    pub delta_t: Option<f64>,
+    pub nraise: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cell {
+    pub cell_dynamics: Option<String>,
+    pub press: Option<f64>,
+    pub wmass: Option<f64>,
+    pub cell_factor: Option<f64>,
+    pub press_conv_thr: Option<f64>,
+    pub cell_dofree: Option<String>,
+}
+
+pub fn control(input: &Input) -> Control {
+    let control = &input.control;
+
+    let mut namelist = Control {
+        calculation: Some(input.calculation.value()),
+        verbosity: control.verbosity.as_ref().map(Field::value),
+        restart_mode: control.restart_mode.as_ref().map(Field::value),
+        disk_io: control.disk_io.as_ref().map(Field::value),
+        wf_collect: control.wf_collect,
+        pseudo_dir: control.pseudo_dir.clone(),
+        out_dir: control.out_dir.clone(),
+        prefix: control.prefix.clone(),
+        ..Control::default()
+    };
+
+    match input.efield {
+        Some(Efield::TeField { dipfield, .. }) => {
+            namelist.tefield = Some(true);
+            namelist.dipfield = Some(dipfield);
+        }
+        Some(Efield::LelField {
+            nberrycyc,
+            ref strings,
+            ..
+        }) => {
+            namelist.lelfield = Some(true);
+            namelist.nberrycyc = nberrycyc;
+            namelist.gdir = Some(direction_index(&strings.gdir));
+            namelist.nppstr = Some(strings.nppstr);
+        }
+        None => (),
+    }
+
+    if let Some(ref strings) = input.berry_phase {
+        namelist.lberry = Some(true);
+        namelist.gdir = Some(direction_index(&strings.gdir));
+        namelist.nppstr = Some(strings.nppstr);
+    }
+
+    match input.calculation {
+        Calculation::Relax { ref relax, .. } | Calculation::VcRelax { ref relax, .. } => {
+            namelist.nstep = relax.nstep;
+            namelist.etot_conv_thr = relax.etot_conv_thr;
+            namelist.forc_conv_thr = relax.forc_conv_thr;
+        }
+        Calculation::Md { ref md, .. } | Calculation::VcMd { ref md, .. } => {
+            namelist.nstep = md.nstep;
+            namelist.dt = Some(md.dt);
+        }
+        _ => (),
+    }
+
+    namelist
+}
+
+pub fn system(input: &Input) -> System {
+    let system = &input.system;
+
+    let (nbnd, nosym) = match input.calculation {
+        Calculation::Nscf { nbnd, nosym, .. } | Calculation::Bands { nbnd, nosym, .. } => {
+            (nbnd, nosym)
+        }
+        _ => (None, None),
+    };
+    // Berry phase calculations are done without symmetry. `nscf` and `bands` calculations
+    // give `nosym` themselves, which `input::validate` requires to be set.
+    let berry_phase =
+        input.berry_phase.is_some() || matches!(input.efield, Some(Efield::LelField { .. }));
+    let nosym = if berry_phase {
+        nosym.or(Some(true))
+    } else {
+        nosym
+    };
+
+    let (smearing, degauss) = match system.occupations {
+        Occupations::Smearing(ref smearing, degauss) => (Some(smearing.value()), Some(degauss)),
+        _ => (None, None),
+    };
+
+    let (nspin, noncolin, lspinorb, magnetization) = match system.spin_type {
+        Some(SpinType::NonPolarized) => (Some(1), None, None, None),
+        Some(SpinType::CollinearPolarized(ref magnetization)) => {
+            (Some(2), None, None, Some(magnetization))
+        }
+        Some(SpinType::Noncollinear {
+            spin_orbit,
+            ref magnetization,
+        }) => (None, Some(true), Some(spin_orbit), Some(magnetization)),
+        None => (None, None, None, None),
+    };
+
+    let mut namelist = System {
+        ibrav: Some(ibrav_index(&system.ibrav)),
+        celldm: celldm(&system.ibrav, system.alat),
+        nat: Some(input.atomic_positions.coordinates.len() as u64),
+        ntyp: Some(input.species.len() as u64),
+        nbnd,
+        ecutwfc: Some(system.ecutwfc),
+        ecutrho: Some(system.ecutrho),
+        nosym,
+        occupations: Some(system.occupations.value()),
+        smearing,
+        degauss,
+        nspin,
+        noncolin,
+        lspinorb,
+        ..System::default()
+    };
+
+    if let Some(magnetization) = magnetization {
+        namelist.starting_magnetization = magnetization.starting_magnetization.clone();
+        namelist.angle1 = magnetization
+            .angles
+            .iter()
+            .map(|angles| angles.map(|a| a[0]))
+            .collect();
+        namelist.angle2 = magnetization
+            .angles
+            .iter()
+            .map(|angles| angles.map(|a| a[1]))
+            .collect();
+        namelist.tot_magnetization = magnetization.tot_magnetization;
+
+        if let Some(ref constraint) = magnetization.constrained_magnetization {
+            namelist.constrained_magnetization = Some(constraint.value());
+            match *constraint {
+                ConstrainedMagnetization::Total {
+                    fixed_magnetization,
+                } => {
+                    namelist.fixed_magnetization =
+                        fixed_magnetization.iter().cloned().map(Some).collect()
+                }
+                ConstrainedMagnetization::TotalDirection { angle } => {
+                    namelist.fixed_magnetization = vec![None, None, Some(angle)]
+                }
+                ConstrainedMagnetization::Atomic { lambda }
+                | ConstrainedMagnetization::AtomicDirection { lambda } => {
+                    namelist.lambda = Some(lambda)
+                }
+            }
+        }
+    }
+
+    if let Some(ref hubbard) = input.hubbard {
+        namelist.hubbard_alpha = hubbard_values(input, hubbard, |p| p.alpha);
+
+        if !hubbard.card {
+            namelist.lda_plus_u = Some(true);
+            namelist.lda_plus_u_kind = Some(hubbard_kind_index(&hubbard.kind));
+            namelist.hubbard_u = hubbard_values(input, hubbard, |p| Some(p.u));
+            namelist.hubbard_j0 = hubbard_values(input, hubbard, |p| p.j0);
+            let hubbard_j = hubbard_values(input, hubbard, |p| p.j);
+            if hubbard_j.iter().any(Option::is_some) {
+                namelist.hubbard_j = vec![hubbard_j];
+            }
+            namelist.u_projection_type = Some(hubbard.projection.value());
+        }
+    }
+
+    if let Some(ref functional) = system.input_dft {
+        namelist.input_dft = Some(functional.value());
+
+        if let Functional::Hybrid(_, ref hybrid) = *functional {
+            namelist.exx_fraction = hybrid.exx_fraction;
+            namelist.exxdiv_treatment = hybrid.exxdiv_treatment.as_ref().map(Field::value);
+            if let Some(nqx) = hybrid.nqx {
+                namelist.nqx1 = Some(nqx[0]);
+                namelist.nqx2 = Some(nqx[1]);
+                namelist.nqx3 = Some(nqx[2]);
+            }
+            namelist.ecutfock = hybrid.ecutfock;
+        }
+    }
+
+    if let Some(ref vdw_corr) = system.vdw_corr {
+        namelist.vdw_corr = Some(vdw_corr.value());
+
+        match *vdw_corr {
+            VdwCorr::GrimmeD3 {
+                ref version,
+                threebody,
+            } => {
+                namelist.dftd3_version = version.as_ref().map(dftd3_version_index);
+                namelist.dftd3_threebody = threebody;
+            }
+            VdwCorr::TkatchenkoScheffler {
+                econv_thr,
+                isolated,
+            } => {
+                namelist.ts_vdw_econv_thr = econv_thr;
+                namelist.ts_vdw_isolated = isolated;
+            }
+            VdwCorr::Xdm { a1, a2 } => {
+                namelist.xdm_a1 = a1;
+                namelist.xdm_a2 = a2;
+            }
+        }
+    }
+
+    if let Some(Efield::TeField {
+        ref edir,
+        emaxpos,
+        eopreg,
+        eamp,
+        ..
+    }) = input.efield
+    {
+        namelist.edir = Some(direction_index(edir));
+        namelist.emaxpos = Some(emaxpos);
+        namelist.eopreg = Some(eopreg);
+        namelist.eamp = Some(eamp);
+    }
+
+    namelist
+}
+
+pub fn electrons(input: &Input) -> Electrons {
+    let electrons = &input.electrons;
+
+    let (conv_thr, diago_thr_init) = match input.calculation {
+        Calculation::Scf { conv_thr }
+        | Calculation::Relax { conv_thr, .. }
+        | Calculation::VcRelax { conv_thr, .. }
+        | Calculation::Md { conv_thr, .. }
+        | Calculation::VcMd { conv_thr, .. } => (Some(conv_thr), None),
+        Calculation::Nscf { diago_thr_init, .. } | Calculation::Bands { diago_thr_init, .. } => {
+            (None, diago_thr_init)
+        }
+    };
+
+    let efield_cart = match input.efield {
+        Some(Efield::LelField { efield_cart, .. }) => {
+            efield_cart.iter().cloned().map(Some).collect()
+        }
+        _ => Vec::new(),
+    };
+
+    Electrons {
+        startingwfc: electrons.startingwfc.as_ref().map(Field::value),
+        diagonalization: electrons.diagonalization.as_ref().map(Field::value),
+        conv_thr,
+        diago_thr_init,
+        efield_cart,
+    }
+}
+
+/// The `ions` namelist, or `None` if the ions do not move in this calculation.
+pub fn ions(input: &Input) -> Option<Ions> {
+    let mut namelist = Ions::default();
+
+    match input.calculation {
+        Calculation::Relax {
+            ref ion_dynamics, ..
+        } => {
+            namelist.ion_dynamics = Some(ion_dynamics.value());
+        }
+        Calculation::VcRelax { ref dynamics, .. } => {
+            namelist.ion_dynamics = Some(String::from(match *dynamics {
+                VcRelaxDynamics::Bfgs => "bfgs",
+                VcRelaxDynamics::DampPr | VcRelaxDynamics::DampW => "damp",
+            }));
+        }
+        Calculation::Md {
+            ref md,
+            ref ion_dynamics,
+            ..
+        } => {
+            namelist.ion_dynamics = Some(ion_dynamics.value());
+            set_ion_temperature(&mut namelist, md.tempw, &md.ion_temperature);
+        }
+        Calculation::VcMd { ref md, .. } => {
+            namelist.ion_dynamics = Some(String::from("beeman"));
+            set_ion_temperature(&mut namelist, md.tempw, &md.ion_temperature);
+        }
+        _ => return None,
+    }
+
+    Some(namelist)
+}
+
+fn set_ion_temperature(
+    namelist: &mut Ions,
+    tempw: Option<f64>,
+    ion_temperature: &Option<IonTemperature>,
+) {
+    namelist.tempw = tempw;
+    namelist.ion_temperature = ion_temperature.as_ref().map(Field::value);
+
+    match *ion_temperature {
+        Some(IonTemperature::Rescaling { tolp }) => namelist.tolp = tolp,
+        Some(IonTemperature::RescaleT { delta_t }) => namelist.delta_t = delta_t,
+        Some(IonTemperature::ReduceT { delta_t, nraise }) => {
+            namelist.delta_t = delta_t;
+            namelist.nraise = nraise;
+        }
+        Some(IonTemperature::RescaleV { nraise })
+        | Some(IonTemperature::Berendsen { nraise })
+        | Some(IonTemperature::Andersen { nraise }) => namelist.nraise = nraise,
+        Some(IonTemperature::Initial) | Some(IonTemperature::NotControlled) | None => (),
+    }
+}
+
+/// The `cell` namelist, or `None` if the cell is fixed in this calculation.
+pub fn cell(input: &Input) -> Option<Cell> {
+    let (cell_dynamics, cell, press_conv_thr) = match input.calculation {
+        Calculation::VcRelax {
+            ref dynamics,
+            ref cell,
+            press_conv_thr,
+            ..
+        } => {
+            let cell_dynamics = match *dynamics {
+                VcRelaxDynamics::Bfgs => "bfgs",
+                VcRelaxDynamics::DampPr => "damp-pr",
+                VcRelaxDynamics::DampW => "damp-w",
+            };
+            (String::from(cell_dynamics), cell, press_conv_thr)
+        }
+        Calculation::VcMd {
+            ref cell_dynamics,
+            ref cell,
+            ..
+        } => (cell_dynamics.value(), cell, None),
+        _ => return None,
+    };
+
+    Some(Cell {
+        cell_dynamics: Some(cell_dynamics),
+        press: cell.press,
+        wmass: cell.wmass,
+        cell_factor: cell.cell_factor,
+        press_conv_thr,
+        cell_dofree: cell.cell_dofree.as_ref().map(Field::value),
+    })
+}
+
+pub fn ibrav_index(ibrav: &Ibrav) -> i64 {
+    match *ibrav {
+        Ibrav::Free(_) => 0,
+        Ibrav::SimpleCubic => 1,
+        Ibrav::Fcc => 2,
+        Ibrav::Bcc => 3,
+        Ibrav::BccSymmetric => -3,
+        Ibrav::Hexagonal(_) => 4,
+        Ibrav::TrigonalRAxisC(_) => 5,
+        Ibrav::TrigonalRAxis111(_) => -5,
+        Ibrav::TetragonalP(_) => 6,
+        Ibrav::TetragonalI(_) => 7,
+        Ibrav::OrthorhombicP(_, _) => 8,
+        Ibrav::OrthorhombicBco(_, _) => 9,
+        Ibrav::OrthorhombicBcoAlternate(_, _) => -9,
+        Ibrav::OrthorhombicFaceCentered(_, _) => 10,
+        Ibrav::OrthorhombicBodyCentered(_, _) => 11,
+        Ibrav::MonoclinicPUniqueAxisC(_, _, _) => 12,
+        Ibrav::MonoclinicPUniqueAxisB(_, _, _) => -12,
+        Ibrav::MonoclinicBaseCentered(_, _, _) => 13,
+        Ibrav::Triclinic(_, _, _, _, _) => 14,
+    }
+}
+
+/// The `celldm` values for the given lattice, with `celldm(1) = alat`.
+/// Indices which are not used by the lattice type are `None`.
+///
+/// No values are given for `CELL_PARAMETERS` in bohr or angstrom, since pw.x then takes
+/// `alat` from the cell and rejects `celldm(1)`.
+pub fn celldm(ibrav: &Ibrav, alat: f64) -> Vec<Option<f64>> {
+    let given = match *ibrav {
+        Ibrav::Free(ref cell) if cell.units != LatticeUnits::Alat => return Vec::new(),
+        Ibrav::Free(_) | Ibrav::SimpleCubic | Ibrav::Fcc | Ibrav::Bcc | Ibrav::BccSymmetric => {
+            vec![]
+        }
+        Ibrav::Hexagonal(c_a) | Ibrav::TetragonalP(c_a) | Ibrav::TetragonalI(c_a) => {
+            vec![(3, c_a)]
+        }
+        Ibrav::TrigonalRAxisC(cos_gamma) | Ibrav::TrigonalRAxis111(cos_gamma) => {
+            vec![(4, cos_gamma)]
+        }
+        Ibrav::OrthorhombicP(b_a, c_a)
+        | Ibrav::OrthorhombicBco(b_a, c_a)
+        | Ibrav::OrthorhombicBcoAlternate(b_a, c_a)
+        | Ibrav::OrthorhombicFaceCentered(b_a, c_a)
+        | Ibrav::OrthorhombicBodyCentered(b_a, c_a) => vec![(2, b_a), (3, c_a)],
+        Ibrav::MonoclinicPUniqueAxisC(b_a, c_a, cos_ab)
+        | Ibrav::MonoclinicBaseCentered(b_a, c_a, cos_ab) => vec![(2, b_a), (3, c_a), (4, cos_ab)],
+        Ibrav::MonoclinicPUniqueAxisB(b_a, c_a, cos_ac) => vec![(2, b_a), (3, c_a), (5, cos_ac)],
+        Ibrav::Triclinic(b_a, c_a, cos_bc, cos_ac, cos_ab) => {
+            vec![(2, b_a), (3, c_a), (4, cos_bc), (5, cos_ac), (6, cos_ab)]
+        }
+    };
+
+    let mut celldm = vec![Some(alat)];
+    for (i, x) in given {
+        if celldm.len() < i {
+            celldm.resize(i, None);
+        }
+        celldm[i - 1] = Some(x);
+    }
+
+    celldm
+}
+
+pub fn hubbard_kind_index(kind: &HubbardKind) -> i64 {
+    match *kind {
+        HubbardKind::Simplified => 0,
+        HubbardKind::Full => 1,
+    }
+}
+
+/// The value of a per-species Hubbard parameter for each species of `input`, in order.
+fn hubbard_values<F>(input: &Input, hubbard: &Hubbard, value: F) -> Vec<Option<f64>>
+where
+    F: Fn(&HubbardParameters) -> Option<f64>,
+{
+    input
+        .species
+        .iter()
+        .map(|species| {
+            hubbard
+                .parameters
+                .iter()
+                .find(|p| p.species == species.label)
+                .and_then(&value)
+        })
+        .collect()
+}
+
+pub fn dftd3_version_index(version: &Dftd3Version) -> i64 {
+    match *version {
+        Dftd3Version::D2 => 2,
+        Dftd3Version::ZeroDamping => 3,
+        Dftd3Version::BeckeJohnson => 4,
+        Dftd3Version::ZeroDampingModified => 5,
+        Dftd3Version::BeckeJohnsonModified => 6,
+    }
+}
+
+pub fn direction_index(direction: &LatticeDirection) -> i64 {
+    match *direction {
+        LatticeDirection::D1 => 1,
+        LatticeDirection::D2 => 2,
+        LatticeDirection::D3 => 3,
+    }
+}
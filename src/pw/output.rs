@@ -0,0 +1,624 @@
+//! Reading of the results of a pw.x run from its standard output.
+//!
+//! Quantities are given in the units pw.x prints them in: total energies in Ry, Fermi
+//! energies and band energies in eV, forces in Ry/bohr, stress in Ry/bohr^3 and pressure
+//! in kbar. k-points are in Cartesian coordinates in units of 2pi/alat.
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use pw::input::Calculation;
+
+/// The results of a pw.x run. The form of `result` is determined by the `Calculation`
+/// that was run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+    pub n_electrons: f64,
+    pub result: CalculationOutput,
+    /// Messages from pw.x which were marked as warnings.
+    pub warnings: Vec<String>,
+    /// Total wall time of the run in seconds, if the run finished.
+    pub wall_time: Option<f64>,
+    /// `true` if pw.x reached the end of the run (`JOB DONE`).
+    pub job_done: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CalculationOutput {
+    Scf(Scf),
+    Nscf(Bands),
+    Bands(Bands),
+    Relax(Relax),
+    VcRelax(Relax),
+    Md(Md),
+    VcMd(Md),
+}
+
+/// A single self-consistent field calculation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scf {
+    pub iterations: Vec<ScfIteration>,
+    pub converged: bool,
+    /// The converged total energy (the line marked with `!`).
+    pub total_energy: Option<f64>,
+    pub fermi_energy: Option<FermiEnergy>,
+    pub band_energies: Option<BandEnergies>,
+    pub forces: Option<Forces>,
+    pub stress: Option<Stress>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScfIteration {
+    pub total_energy: f64,
+    pub estimated_scf_accuracy: Option<f64>,
+}
+
+/// Results of a non-self-consistent (`nscf` or `bands`) calculation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bands {
+    pub band_energies: BandEnergies,
+    /// Only computed by pw.x for `nscf` calculations.
+    pub fermi_energy: Option<FermiEnergy>,
+}
+
+/// Results of a `relax` or `vc-relax` calculation: one SCF calculation per ionic step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relax {
+    pub steps: Vec<Scf>,
+    pub converged: bool,
+}
+
+/// Results of an `md` or `vc-md` calculation: one SCF calculation per ionic step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Md {
+    pub steps: Vec<Scf>,
+    /// Ionic temperature in K after each step.
+    pub temperatures: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FermiEnergy {
+    Fermi(f64),
+    /// Separate Fermi energies for spin up and down, given when the magnetization is fixed.
+    SpinFermi(f64, f64),
+    /// Given for fixed occupations, possibly together with the lowest unoccupied level.
+    HighestOccupied(f64, Option<f64>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BandEnergies {
+    Unpolarized(Vec<KPointEnergies>),
+    Polarized {
+        up: Vec<KPointEnergies>,
+        down: Vec<KPointEnergies>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KPointEnergies {
+    pub k: [f64; 3],
+    pub energies: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Forces {
+    /// Force on each atom, in the order of `ATOMIC_POSITIONS`.
+    pub atoms: Vec<[f64; 3]>,
+    pub total_force: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stress {
+    pub stress: [[f64; 3]; 3],
+    pub pressure: f64,
+}
+
+pub fn read_output_file<P: AsRef<Path>>(
+    file_path: P,
+    calculation: &Calculation,
+) -> Result<Output, Error> {
+    let mut text = String::new();
+    File::open(file_path)?.read_to_string(&mut text)?;
+
+    parse_output(&text, calculation)
+}
+
+/// Parse the standard output of a pw.x run of the given `calculation`.
+///
+/// An error is returned if pw.x stopped with an error, or if the output does not contain
+/// the results expected for `calculation`. A run which was cut short without an error
+/// (e.g. by the queueing system) is returned with `job_done = false`.
+pub fn parse_output(text: &str, calculation: &Calculation) -> Result<Output, Error> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut scan = Scan::default();
+
+    let mut i = 0;
+    while i < lines.len() {
+        i = scan.line(&lines, i)?;
+    }
+
+    scan.finish_cycle();
+
+    let n_electrons = scan
+        .n_electrons
+        .ok_or(Error::Missing("number of electrons"))?;
+
+    let result = match *calculation {
+        Calculation::Scf { .. } => {
+            let mut cycles = scan.cycles;
+            match cycles.pop() {
+                Some(scf) => CalculationOutput::Scf(scf),
+                None => return Err(Error::Missing("self-consistent calculation")),
+            }
+        }
+        Calculation::Nscf { .. } | Calculation::Bands { .. } => {
+            let bands = Bands {
+                band_energies: scan.band_energies.ok_or(Error::Missing("band energies"))?,
+                fermi_energy: scan.fermi_energy,
+            };
+            match *calculation {
+                Calculation::Nscf { .. } => CalculationOutput::Nscf(bands),
+                _ => CalculationOutput::Bands(bands),
+            }
+        }
+        Calculation::Relax { .. } | Calculation::VcRelax { .. } => {
+            if scan.cycles.is_empty() {
+                return Err(Error::Missing("self-consistent calculation"));
+            }
+            let relax = Relax {
+                steps: scan.cycles,
+                converged: scan.relax_converged,
+            };
+            match *calculation {
+                Calculation::Relax { .. } => CalculationOutput::Relax(relax),
+                _ => CalculationOutput::VcRelax(relax),
+            }
+        }
+        Calculation::Md { .. } | Calculation::VcMd { .. } => {
+            if scan.cycles.is_empty() {
+                return Err(Error::Missing("self-consistent calculation"));
+            }
+            let md = Md {
+                steps: scan.cycles,
+                temperatures: scan.temperatures,
+            };
+            match *calculation {
+                Calculation::Md { .. } => CalculationOutput::Md(md),
+                _ => CalculationOutput::VcMd(md),
+            }
+        }
+    };
+
+    Ok(Output {
+        n_electrons,
+        result,
+        warnings: scan.warnings,
+        wall_time: scan.wall_time,
+        job_done: scan.job_done,
+    })
+}
+
+/// State accumulated while reading through the output.
+#[derive(Default)]
+struct Scan {
+    n_electrons: Option<f64>,
+    cycles: Vec<Scf>,
+    current: Option<Scf>,
+    band_energies: Option<BandEnergies>,
+    fermi_energy: Option<FermiEnergy>,
+    relax_converged: bool,
+    temperatures: Vec<f64>,
+    warnings: Vec<String>,
+    wall_time: Option<f64>,
+    job_done: bool,
+}
+
+impl Scan {
+    /// Read the line at `i` (and any following lines belonging to the same block),
+    /// returning the index of the next line to read.
+    fn line(&mut self, lines: &[&str], i: usize) -> Result<usize, Error> {
+        let line = lines[i].trim();
+        let line_number = i + 1;
+
+        if line.starts_with("number of electrons") {
+            self.n_electrons = Some(value_after(line, "=", line_number)?);
+        } else if line.starts_with("Self-consistent Calculation") {
+            self.finish_cycle();
+            self.current = Some(new_scf());
+        } else if line.starts_with("total energy") {
+            let total_energy = value_after(line, "=", line_number)?;
+            // The accuracy follows in the same block, after the Harris-Foulkes estimate.
+            let accuracy_line = lines[i + 1..]
+                .iter()
+                .take_while(|l| !l.trim().is_empty())
+                .position(|l| l.trim().starts_with("estimated scf accuracy"));
+            let estimated_scf_accuracy = match accuracy_line {
+                Some(offset) => {
+                    let j = i + 1 + offset;
+                    Some(value_after(lines[j], "<", j + 1)?)
+                }
+                None => None,
+            };
+            self.cycle().iterations.push(ScfIteration {
+                total_energy,
+                estimated_scf_accuracy,
+            });
+        } else if line.starts_with('!') {
+            self.cycle().total_energy = Some(value_after(line, "=", line_number)?);
+        } else if line.starts_with("convergence has been achieved") {
+            self.cycle().converged = true;
+            self.finish_cycle();
+        } else if line.starts_with("convergence NOT achieved") {
+            self.cycle().converged = false;
+            self.finish_cycle();
+        } else if line.starts_with("End of self-consistent calculation")
+            || line.starts_with("End of band structure calculation")
+        {
+            let (band_energies, next) = read_band_energies(lines, i + 1)?;
+            if line.starts_with("End of self-consistent calculation") {
+                self.cycle().band_energies = band_energies;
+            } else {
+                self.band_energies = band_energies;
+            }
+            return Ok(next);
+        } else if line.starts_with("the Fermi energy is") {
+            self.set_fermi_energy(FermiEnergy::Fermi(value_after(line, "is", line_number)?));
+        } else if line.starts_with("the spin up/dw Fermi energies are") {
+            let e = values_after(line, "are", line_number)?;
+            if e.len() < 2 {
+                return Err(value_error(line_number, "expected two Fermi energies"));
+            }
+            self.set_fermi_energy(FermiEnergy::SpinFermi(e[0], e[1]));
+        } else if line.starts_with("highest occupied") {
+            let e = values_after(line, ":", line_number)?;
+            if e.is_empty() {
+                return Err(value_error(line_number, "expected an energy"));
+            }
+            self.set_fermi_energy(FermiEnergy::HighestOccupied(e[0], e.get(1).cloned()));
+        } else if line.starts_with("Forces acting on atoms") {
+            let (forces, next) = read_forces(lines, i + 1)?;
+            self.last_cycle()?.forces = Some(forces);
+            return Ok(next);
+        } else if line.starts_with("total   stress") {
+            let stress = read_stress(lines, i)?;
+            self.last_cycle()?.stress = Some(stress);
+            return Ok(i + 4);
+        } else if line.starts_with("bfgs converged")
+            || line.starts_with("Damped Dynamics: convergence achieved")
+        {
+            self.relax_converged = true;
+        } else if line.starts_with("temperature") {
+            let temperature = value_after(line, "=", line_number)?;
+            self.temperatures.push(temperature);
+        } else if line.starts_with("Error in routine") {
+            let message = lines.get(i + 1).map_or("", |l| l.trim());
+            return Err(Error::Pw {
+                line: line_number,
+                routine: String::from(line.trim_start_matches("Error in routine ")),
+                message: String::from(message),
+            });
+        } else if line.starts_with("PWSCF") && line.ends_with("WALL") {
+            self.wall_time = Some(parse_wall_time(line, line_number)?);
+        } else if line.starts_with("JOB DONE") {
+            self.job_done = true;
+        } else if line.to_lowercase().contains("warning") {
+            self.warnings.push(String::from(line));
+        }
+
+        Ok(i + 1)
+    }
+
+    /// The SCF cycle currently being read. Some versions of pw.x do not mark the start of
+    /// the first cycle, so one is started here if needed.
+    fn cycle(&mut self) -> &mut Scf {
+        self.current.get_or_insert_with(new_scf)
+    }
+
+    /// The SCF cycle to which quantities computed after convergence (forces, stress)
+    /// belong: the most recent one.
+    fn last_cycle(&mut self) -> Result<&mut Scf, Error> {
+        if self.current.is_some() {
+            return Ok(self.cycle());
+        }
+        self.cycles
+            .last_mut()
+            .ok_or(Error::Missing("self-consistent calculation"))
+    }
+
+    fn finish_cycle(&mut self) {
+        if let Some(cycle) = self.current.take() {
+            self.cycles.push(cycle);
+        }
+    }
+
+    fn set_fermi_energy(&mut self, fermi_energy: FermiEnergy) {
+        match self.current {
+            Some(ref mut cycle) => cycle.fermi_energy = Some(fermi_energy),
+            None => self.fermi_energy = Some(fermi_energy),
+        }
+    }
+}
+
+fn new_scf() -> Scf {
+    Scf {
+        iterations: Vec::new(),
+        converged: false,
+        total_energy: None,
+        fermi_energy: None,
+        band_energies: None,
+        forces: None,
+        stress: None,
+    }
+}
+
+/// Read the band energies printed at each k-point, starting at line `start`.
+/// Returns the energies (if any were printed) and the index of the first line after them.
+fn read_band_energies(
+    lines: &[&str],
+    start: usize,
+) -> Result<(Option<BandEnergies>, usize), Error> {
+    let mut up = Vec::new();
+    let mut down = Vec::new();
+    let mut polarized = false;
+    let mut in_down = false;
+    let mut in_occupations = false;
+
+    let mut i = start;
+    while i < lines.len() {
+        let line = lines[i].trim();
+
+        if line.is_empty() || line.starts_with("Writing") || line.starts_with("Computing") {
+            // Blank lines separate the k-points; skip them.
+        } else if line.starts_with("------ SPIN UP") {
+            polarized = true;
+            in_down = false;
+        } else if line.starts_with("------ SPIN DOWN") {
+            in_down = true;
+        } else if line.starts_with("k =") {
+            let k = read_k(line, i + 1)?;
+            let list = if in_down { &mut down } else { &mut up };
+            list.push(KPointEnergies {
+                k,
+                energies: Vec::new(),
+            });
+            in_occupations = false;
+        } else if line.starts_with("occupation numbers") {
+            in_occupations = true;
+        } else if let Some(values) = split_numbers(line) {
+            if !in_occupations {
+                let list = if in_down { &mut down } else { &mut up };
+                match list.last_mut() {
+                    Some(k) => k.energies.extend(values),
+                    None => return Err(value_error(i + 1, "band energies before any k-point")),
+                }
+            }
+        } else {
+            break;
+        }
+
+        i += 1;
+    }
+
+    let band_energies = if up.is_empty() {
+        None
+    } else if polarized {
+        Some(BandEnergies::Polarized { up, down })
+    } else {
+        Some(BandEnergies::Unpolarized(up))
+    };
+
+    Ok((band_energies, i))
+}
+
+/// Read the k-point from a line such as `k = 0.0000-0.5000 0.0000 (  1139 PWs)   bands (ev):`.
+fn read_k(line: &str, line_number: usize) -> Result<[f64; 3], Error> {
+    let rest = &line["k =".len()..];
+    let end = rest.find(['(', 'b']).unwrap_or(rest.len());
+    let k =
+        split_numbers(&rest[..end]).ok_or_else(|| value_error(line_number, "invalid k-point"))?;
+
+    if k.len() != 3 {
+        return Err(value_error(
+            line_number,
+            "expected three k-point components",
+        ));
+    }
+
+    Ok([k[0], k[1], k[2]])
+}
+
+fn read_forces(lines: &[&str], start: usize) -> Result<(Forces, usize), Error> {
+    let mut atoms = Vec::new();
+
+    let mut i = start;
+    while i < lines.len() {
+        let line = lines[i].trim();
+
+        if line.starts_with("atom") {
+            let f = values_after(line, "=", i + 1)?;
+            if f.len() != 3 {
+                return Err(value_error(i + 1, "expected three force components"));
+            }
+            atoms.push([f[0], f[1], f[2]]);
+        } else if !line.is_empty() {
+            break;
+        }
+
+        i += 1;
+    }
+
+    // With `verbosity = 'high'`, the atom forces are followed by blocks giving each of their
+    // contributions, such as `The non-local contrib.  to forces`, before the total force.
+    while i < lines.len() {
+        let line = lines[i].trim();
+
+        let contribution = line.starts_with("The ") && line.ends_with("to forces");
+        if !(contribution || line.starts_with("atom") || line.is_empty()) {
+            break;
+        }
+
+        i += 1;
+    }
+
+    let total_force = match lines.get(i) {
+        Some(line) if line.trim().starts_with("Total force") => {
+            let line = line.trim();
+            let value = line["Total force".len()..]
+                .trim_start_matches([' ', '='])
+                .split_whitespace()
+                .next()
+                .unwrap_or("");
+            parse_number(value, i + 1)?
+        }
+        _ => return Err(value_error(i + 1, "expected total force")),
+    };
+
+    Ok((Forces { atoms, total_force }, i + 1))
+}
+
+/// Read the stress tensor block whose header is at line `start`:
+///
+/// ```text
+///           total   stress  (Ry/bohr**3)                   (kbar)     P=      -20.65
+///   -0.00014038   0.00000000   0.00000000          -20.65        0.00        0.00
+/// ```
+fn read_stress(lines: &[&str], start: usize) -> Result<Stress, Error> {
+    let pressure = value_after(lines[start], "P=", start + 1)?;
+    let mut stress = [[0.0; 3]; 3];
+
+    for (row, stress_row) in stress.iter_mut().enumerate() {
+        let i = start + 1 + row;
+        let line = lines
+            .get(i)
+            .ok_or_else(|| value_error(i + 1, "expected stress tensor row"))?;
+        let values = split_numbers(line).ok_or_else(|| value_error(i + 1, "invalid stress"))?;
+        if values.len() != 6 {
+            return Err(value_error(i + 1, "expected six stress components"));
+        }
+        stress_row.copy_from_slice(&values[..3]);
+    }
+
+    Ok(Stress { stress, pressure })
+}
+
+/// Parse the wall time from a line such as `PWSCF        :   1m23.45s CPU   1m30.12s WALL`.
+fn parse_wall_time(line: &str, line_number: usize) -> Result<f64, Error> {
+    let start = line.find("CPU").map_or(0, |i| i + "CPU".len());
+    let end = line.len() - "WALL".len();
+    let time: String = line[start..end].split_whitespace().collect();
+
+    let mut seconds = 0.0;
+    let mut number = String::new();
+    for c in time.chars() {
+        let unit = match c {
+            'h' => 3600.0,
+            'm' => 60.0,
+            's' => 1.0,
+            _ => {
+                number.push(c);
+                continue;
+            }
+        };
+        seconds += unit * parse_number(&number, line_number)?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return Err(value_error(line_number, "invalid wall time"));
+    }
+
+    Ok(seconds)
+}
+
+/// The first number following `marker` in `line`.
+fn value_after(line: &str, marker: &str, line_number: usize) -> Result<f64, Error> {
+    match values_after(line, marker, line_number)?.first() {
+        Some(&x) => Ok(x),
+        None => Err(value_error(
+            line_number,
+            &format!("expected a value after `{}`", marker),
+        )),
+    }
+}
+
+/// The numbers following `marker` in `line`, up to the first token which is not a number
+/// (such as a unit).
+fn values_after(line: &str, marker: &str, line_number: usize) -> Result<Vec<f64>, Error> {
+    let rest = match line.find(marker) {
+        Some(i) => &line[i + marker.len()..],
+        None => return Err(value_error(line_number, &format!("expected `{}`", marker))),
+    };
+
+    let mut values = Vec::new();
+    for token in rest.split_whitespace() {
+        match split_numbers(token) {
+            Some(xs) => values.extend(xs),
+            None => break,
+        }
+    }
+
+    Ok(values)
+}
+
+/// Split a line of numbers written in Fortran fixed format, in which a negative number
+/// may directly follow the previous one (e.g. `-1.2345-10.5432`).
+/// Returns `None` if the line is empty or contains anything other than numbers.
+pub(crate) fn split_numbers(line: &str) -> Option<Vec<f64>> {
+    let mut values = Vec::new();
+
+    for token in line.split_whitespace() {
+        let mut start = 0;
+        let bytes = token.as_bytes();
+        for j in 1..bytes.len() {
+            if bytes[j] == b'-' && (bytes[j - 1] as char).is_ascii_digit() {
+                values.push(token[start..j].parse().ok()?);
+                start = j;
+            }
+        }
+        values.push(token[start..].parse().ok()?);
+    }
+
+    if values.is_empty() {
+        None
+    } else {
+        Some(values)
+    }
+}
+
+fn parse_number(value: &str, line_number: usize) -> Result<f64, Error> {
+    value
+        .parse()
+        .map_err(|_| value_error(line_number, &format!("invalid number `{}`", value)))
+}
+
+fn value_error(line: usize, message: &str) -> Error {
+    Error::Value {
+        line,
+        message: String::from(message),
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "line {}: {}", line, message)]
+    Value { line: usize, message: String },
+    #[fail(display = "no {} found in pw.x output", _0)]
+    Missing(&'static str),
+    #[fail(
+        display = "line {}: pw.x stopped with an error in routine {}: {}",
+        line, routine, message
+    )]
+    Pw {
+        line: usize,
+        routine: String,
+        message: String,
+    },
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,1485 @@
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use namelist;
+use namelist::{Card, Namelist};
+use pw::input::{
+    AtomCoordinate, Calculation, Cell, CellDofree, ConstrainedMagnetization, Control,
+    Dftd3Version, Diagonalization, DiskIO, Efield, Electrons, ExxdivTreatment, Functional, Hubbard,
+    HubbardKind, HubbardParameters, HubbardProjection, Hybrid, HybridFunctional, Ibrav, Input,
+    IonTemperature, KPointStrings, KPoints, LatticeDirection, LatticeUnits, Magnetization,
+    MdIonDynamics, MolecularDynamics, Occupations, PositionCoordinateType, Positions, Relax,
+    RelaxIonDynamics, RestartMode, Smearing, Species, SpinType, StartingWfc, System,
+    VariableCell, VcMdCellDynamics, VcRelaxDynamics, VdwCorr, Verbosity,
+};
+use pw::namelists;
+
+const NAMELISTS: [&str; 5] = ["control", "system", "electrons", "ions", "cell"];
+
+/// All cards accepted by pw.x. Those which are not represented in `Input` are recognized
+/// here so that they are reported as unsupported instead of being read as part of the
+/// preceding card.
+const CARDS: [&str; 11] = [
+    "ATOMIC_SPECIES",
+    "ATOMIC_POSITIONS",
+    "K_POINTS",
+    "CELL_PARAMETERS",
+    "OCCUPATIONS",
+    "CONSTRAINTS",
+    "ATOMIC_VELOCITIES",
+    "ATOMIC_FORCES",
+    "ADDITIONAL_K_POINTS",
+    "SOLVENTS",
+    "HUBBARD",
+];
+
+pub fn read_input_file<P: AsRef<Path>>(file_path: P) -> Result<Input, Error> {
+    let mut text = String::new();
+    File::open(file_path)?.read_to_string(&mut text)?;
+
+    parse_input_file(&text)
+}
+
+/// Parse the text of a pw.x input file into an `Input`.
+///
+/// Every namelist field and card in the file must map onto `Input`: fields that `Input`
+/// cannot represent (including those irrelevant to the given `calculation`) are reported as
+/// errors rather than dropped, so that writing the result back out reproduces the original
+/// calculation. The resulting `Input` is not validated; use `input::validate` for that.
+pub fn parse_input_file(text: &str) -> Result<Input, Error> {
+    let file = namelist::read_file(text, &CARDS)?;
+
+    for (i, nl) in file.namelists.iter().enumerate() {
+        if !NAMELISTS.contains(&nl.name.as_str()) {
+            return Err(Error::UnknownNamelist {
+                line: nl.line,
+                name: nl.name.clone(),
+            });
+        }
+        if file.namelists[..i].iter().any(|prev| prev.name == nl.name) {
+            return Err(Error::DuplicateNamelist {
+                line: nl.line,
+                name: nl.name.clone(),
+            });
+        }
+    }
+
+    for card in &file.cards {
+        if ![
+            "ATOMIC_SPECIES",
+            "ATOMIC_POSITIONS",
+            "K_POINTS",
+            "CELL_PARAMETERS",
+            "HUBBARD",
+        ]
+        .contains(&card.name.as_str())
+        {
+            return Err(Error::UnknownCard {
+                line: card.line,
+                card: card.name.clone(),
+            });
+        }
+    }
+
+    let control_source = Source::new(&file, "control");
+    let system_source = Source::new(&file, "system");
+    let electrons_source = Source::new(&file, "electrons");
+    let ions_source = Source::new(&file, "ions");
+    let cell_source = Source::new(&file, "cell");
+
+    let control_nl: namelists::Control = control_source.read()?;
+    let system_nl: namelists::System = system_source.read()?;
+    let electrons_nl: namelists::Electrons = electrons_source.read()?;
+    let ions_nl: namelists::Ions = ions_source.read()?;
+    let cell_nl: namelists::Cell = cell_source.read()?;
+
+    let species = parse_species(required_card(&file, "ATOMIC_SPECIES")?)?;
+    let atomic_positions = parse_positions(required_card(&file, "ATOMIC_POSITIONS")?)?;
+    let k_points = parse_k_points(required_card(&file, "K_POINTS")?)?;
+
+    let input = Input {
+        calculation: parse_calculation(
+            &file,
+            &control_nl,
+            &system_nl,
+            &electrons_nl,
+            &ions_nl,
+            &cell_nl,
+        )?,
+        control: parse_control(&control_source, &control_nl)?,
+        system: parse_system(
+            &system_source,
+            &system_nl,
+            &file,
+            &species,
+            &atomic_positions,
+        )?,
+        efield: parse_efield(
+            &control_source,
+            &control_nl,
+            &system_source,
+            &system_nl,
+            &electrons_nl,
+        )?,
+        berry_phase: parse_berry_phase(&control_source, &control_nl)?,
+        hubbard: parse_hubbard(
+            &system_source,
+            &system_nl,
+            file.card("HUBBARD"),
+            &species,
+        )?,
+        electrons: parse_electrons(&electrons_source, &electrons_nl)?,
+        species,
+        atomic_positions,
+        k_points,
+    };
+
+    control_source.check_represented(&namelists::control(&input))?;
+    system_source.check_represented(&namelists::system(&input))?;
+    electrons_source.check_represented(&namelists::electrons(&input))?;
+    ions_source.check_represented(&namelists::ions(&input).unwrap_or_default())?;
+    cell_source.check_represented(&namelists::cell(&input).unwrap_or_default())?;
+
+    Ok(input)
+}
+
+fn parse_calculation(
+    file: &namelist::File,
+    control: &namelists::Control,
+    system: &namelists::System,
+    electrons: &namelists::Electrons,
+    ions: &namelists::Ions,
+    cell: &namelists::Cell,
+) -> Result<Calculation, Error> {
+    let control_source = Source::new(file, "control");
+    let ions_source = Source::new(file, "ions");
+    let cell_source = Source::new(file, "cell");
+
+    // The pw.x default.
+    let conv_thr = electrons.conv_thr.unwrap_or(1e-6);
+
+    match control.calculation.as_deref().unwrap_or("scf") {
+        "scf" => Ok(Calculation::Scf {
+            conv_thr,
+        }),
+        "nscf" => Ok(Calculation::Nscf {
+            diago_thr_init: electrons.diago_thr_init,
+            nbnd: system.nbnd,
+            nosym: system.nosym,
+        }),
+        "bands" => Ok(Calculation::Bands {
+            diago_thr_init: electrons.diago_thr_init,
+            nbnd: system.nbnd,
+            nosym: system.nosym,
+        }),
+        "relax" => Ok(Calculation::Relax {
+            conv_thr,
+            relax: parse_relax(control),
+            ion_dynamics: parse_relax_ion_dynamics(&ions_source, ions)?,
+        }),
+        "vc-relax" => Ok(Calculation::VcRelax {
+            conv_thr,
+            relax: parse_relax(control),
+            dynamics: parse_vc_relax_dynamics(&ions_source, ions, &cell_source, cell)?,
+            cell: parse_variable_cell(&cell_source, cell)?,
+            press_conv_thr: cell.press_conv_thr,
+        }),
+        "md" => Ok(Calculation::Md {
+            conv_thr,
+            md: parse_md(control, &ions_source, ions)?,
+            ion_dynamics: parse_md_ion_dynamics(&ions_source, ions)?,
+        }),
+        "vc-md" => {
+            if let Some(ion_dynamics) = ions.ion_dynamics.as_deref() {
+                if ion_dynamics != "beeman" {
+                    return Err(ions_source.value_error(
+                        "ion_dynamics",
+                        format!("`vc-md` requires `beeman`; got `{}`", ion_dynamics),
+                    ));
+                }
+            }
+
+            Ok(Calculation::VcMd {
+                conv_thr,
+                md: parse_md(control, &ions_source, ions)?,
+                cell_dynamics: parse_vc_md_cell_dynamics(&cell_source, cell)?,
+                cell: parse_variable_cell(&cell_source, cell)?,
+            })
+        }
+        other => Err(control_source.value_error(
+            "calculation",
+            format!("unsupported calculation type `{}`", other),
+        )),
+    }
+}
+
+fn parse_relax(control: &namelists::Control) -> Relax {
+    Relax {
+        nstep: control.nstep,
+        etot_conv_thr: control.etot_conv_thr,
+        forc_conv_thr: control.forc_conv_thr,
+    }
+}
+
+fn parse_relax_ion_dynamics(
+    source: &Source,
+    ions: &namelists::Ions,
+) -> Result<RelaxIonDynamics, Error> {
+    match ions.ion_dynamics.as_deref().unwrap_or("bfgs") {
+        "bfgs" => Ok(RelaxIonDynamics::Bfgs),
+        "damp" => Ok(RelaxIonDynamics::Damp),
+        other => Err(source.value_error(
+            "ion_dynamics",
+            format!("unsupported ion_dynamics `{}` for `relax`", other),
+        )),
+    }
+}
+
+fn parse_vc_relax_dynamics(
+    ions_source: &Source,
+    ions: &namelists::Ions,
+    cell_source: &Source,
+    cell: &namelists::Cell,
+) -> Result<VcRelaxDynamics, Error> {
+    let ion_dynamics = ions.ion_dynamics.as_deref().unwrap_or("bfgs");
+    let cell_dynamics = cell.cell_dynamics.as_deref().unwrap_or("bfgs");
+
+    match (ion_dynamics, cell_dynamics) {
+        ("bfgs", "bfgs") => Ok(VcRelaxDynamics::Bfgs),
+        ("damp", "damp-pr") => Ok(VcRelaxDynamics::DampPr),
+        ("damp", "damp-w") => Ok(VcRelaxDynamics::DampW),
+        ("bfgs", _) | ("damp", _) => Err(cell_source.value_error(
+            "cell_dynamics",
+            format!(
+                "cell_dynamics `{}` cannot be used with ion_dynamics `{}`",
+                cell_dynamics, ion_dynamics
+            ),
+        )),
+        (other, _) => Err(ions_source.value_error(
+            "ion_dynamics",
+            format!("unsupported ion_dynamics `{}` for `vc-relax`", other),
+        )),
+    }
+}
+
+fn parse_md(
+    control: &namelists::Control,
+    ions_source: &Source,
+    ions: &namelists::Ions,
+) -> Result<MolecularDynamics, Error> {
+    let ion_temperature = ions_source.map(
+        "ion_temperature",
+        ions.ion_temperature.as_deref(),
+        |v| match v {
+            "rescaling" => Ok(IonTemperature::Rescaling { tolp: ions.tolp }),
+            "rescale-v" => Ok(IonTemperature::RescaleV {
+                nraise: ions.nraise,
+            }),
+            "rescale-T" => Ok(IonTemperature::RescaleT {
+                delta_t: ions.delta_t,
+            }),
+            "reduce-T" => Ok(IonTemperature::ReduceT {
+                delta_t: ions.delta_t,
+                nraise: ions.nraise,
+            }),
+            "berendsen" => Ok(IonTemperature::Berendsen {
+                nraise: ions.nraise,
+            }),
+            "andersen" => Ok(IonTemperature::Andersen {
+                nraise: ions.nraise,
+            }),
+            "initial" => Ok(IonTemperature::Initial),
+            "not_controlled" => Ok(IonTemperature::NotControlled),
+            other => Err(format!("unsupported ion_temperature `{}`", other)),
+        },
+    )?;
+
+    Ok(MolecularDynamics {
+        // The pw.x default, in Rydberg atomic units.
+        dt: control.dt.unwrap_or(20.0),
+        nstep: control.nstep,
+        tempw: ions.tempw,
+        ion_temperature,
+    })
+}
+
+fn parse_md_ion_dynamics(source: &Source, ions: &namelists::Ions) -> Result<MdIonDynamics, Error> {
+    match ions.ion_dynamics.as_deref().unwrap_or("verlet") {
+        "verlet" => Ok(MdIonDynamics::Verlet),
+        "langevin" => Ok(MdIonDynamics::Langevin),
+        "langevin-smc" => Ok(MdIonDynamics::LangevinSmc),
+        other => Err(source.value_error(
+            "ion_dynamics",
+            format!("unsupported ion_dynamics `{}` for `md`", other),
+        )),
+    }
+}
+
+fn parse_vc_md_cell_dynamics(
+    source: &Source,
+    cell: &namelists::Cell,
+) -> Result<VcMdCellDynamics, Error> {
+    match source.required("cell_dynamics", cell.cell_dynamics.as_deref())? {
+        "pr" => Ok(VcMdCellDynamics::Pr),
+        "w" => Ok(VcMdCellDynamics::W),
+        other => Err(source.value_error(
+            "cell_dynamics",
+            format!("unsupported cell_dynamics `{}` for `vc-md`", other),
+        )),
+    }
+}
+
+fn parse_variable_cell(source: &Source, cell: &namelists::Cell) -> Result<VariableCell, Error> {
+    let cell_dofree = source.map("cell_dofree", cell.cell_dofree.as_deref(), |v| match v {
+        "all" => Ok(CellDofree::All),
+        "ibrav" => Ok(CellDofree::Ibrav),
+        "x" => Ok(CellDofree::X),
+        "y" => Ok(CellDofree::Y),
+        "z" => Ok(CellDofree::Z),
+        "xy" => Ok(CellDofree::Xy),
+        "xz" => Ok(CellDofree::Xz),
+        "yz" => Ok(CellDofree::Yz),
+        "xyz" => Ok(CellDofree::Xyz),
+        "shape" => Ok(CellDofree::Shape),
+        "volume" => Ok(CellDofree::Volume),
+        "2Dxy" => Ok(CellDofree::TwoDxy),
+        "2Dshape" => Ok(CellDofree::TwoDShape),
+        other => Err(format!("unsupported cell_dofree `{}`", other)),
+    })?;
+
+    Ok(VariableCell {
+        press: cell.press,
+        wmass: cell.wmass,
+        cell_factor: cell.cell_factor,
+        cell_dofree,
+    })
+}
+
+fn parse_control(source: &Source, control: &namelists::Control) -> Result<Control, Error> {
+    let verbosity = source.map("verbosity", control.verbosity.as_deref(), |v| match v {
+        "high" | "debug" | "medium" => Ok(Verbosity::High),
+        "low" | "default" | "minimal" => Ok(Verbosity::Low),
+        other => Err(format!("unknown verbosity `{}`", other)),
+    })?;
+
+    let restart_mode = source.map(
+        "restart_mode",
+        control.restart_mode.as_deref(),
+        |v| match v {
+            "from_scratch" => Ok(RestartMode::FromScratch),
+            "restart" => Ok(RestartMode::Restart),
+            other => Err(format!("unknown restart mode `{}`", other)),
+        },
+    )?;
+
+    let disk_io = source.map("disk_io", control.disk_io.as_deref(), |v| match v {
+        "low" => Ok(DiskIO::Low),
+        "medium" => Ok(DiskIO::Medium),
+        "high" => Ok(DiskIO::High),
+        "none" => Ok(DiskIO::NoDiskIO),
+        other => Err(format!("unknown disk_io setting `{}`", other)),
+    })?;
+
+    Ok(Control {
+        verbosity,
+        restart_mode,
+        disk_io,
+        wf_collect: control.wf_collect,
+        pseudo_dir: control.pseudo_dir.clone(),
+        out_dir: control.out_dir.clone(),
+        prefix: control.prefix.clone(),
+    })
+}
+
+fn parse_efield(
+    control_source: &Source,
+    control: &namelists::Control,
+    system_source: &Source,
+    system: &namelists::System,
+    electrons: &namelists::Electrons,
+) -> Result<Option<Efield>, Error> {
+    let tefield = control.tefield.unwrap_or(false);
+    let lelfield = control.lelfield.unwrap_or(false);
+
+    if tefield && lelfield {
+        return Err(control_source.value_error(
+            "lelfield",
+            String::from("cannot be used together with `tefield`"),
+        ));
+    }
+
+    if lelfield {
+        // Components beyond the third are not written, and so are reported as unrepresented.
+        let element = |i: usize| electrons.efield_cart.get(i).cloned().unwrap_or(None);
+
+        return Ok(Some(Efield::LelField {
+            efield_cart: [
+                element(0).unwrap_or(0.0),
+                element(1).unwrap_or(0.0),
+                element(2).unwrap_or(0.0),
+            ],
+            nberrycyc: control.nberrycyc,
+            strings: parse_kpoint_strings(control_source, control)?,
+        }));
+    }
+
+    if !tefield {
+        return Ok(None);
+    }
+
+    let edir = system_source.map("edir", system.edir, parse_direction)?;
+
+    Ok(Some(Efield::TeField {
+        dipfield: control.dipfield.unwrap_or(false),
+        edir: system_source.required("edir", edir)?,
+        emaxpos: system_source.required("emaxpos", system.emaxpos)?,
+        eopreg: system_source.required("eopreg", system.eopreg)?,
+        eamp: system_source.required("eamp", system.eamp)?,
+    }))
+}
+
+fn parse_berry_phase(
+    source: &Source,
+    control: &namelists::Control,
+) -> Result<Option<KPointStrings>, Error> {
+    if !control.lberry.unwrap_or(false) {
+        return Ok(None);
+    }
+
+    if control.lelfield.unwrap_or(false) {
+        return Err(source.value_error(
+            "lberry",
+            String::from("cannot be used together with `lelfield`"),
+        ));
+    }
+
+    Ok(Some(parse_kpoint_strings(source, control)?))
+}
+
+fn parse_kpoint_strings(
+    source: &Source,
+    control: &namelists::Control,
+) -> Result<KPointStrings, Error> {
+    let gdir = source.map("gdir", control.gdir, parse_direction)?;
+
+    Ok(KPointStrings {
+        gdir: source.required("gdir", gdir)?,
+        nppstr: source.required("nppstr", control.nppstr)?,
+    })
+}
+
+fn parse_direction(value: i64) -> Result<LatticeDirection, String> {
+    match value {
+        1 => Ok(LatticeDirection::D1),
+        2 => Ok(LatticeDirection::D2),
+        3 => Ok(LatticeDirection::D3),
+        other => Err(format!("must be 1, 2 or 3; got {}", other)),
+    }
+}
+
+fn parse_hubbard(
+    source: &Source,
+    system: &namelists::System,
+    card: Option<&Card>,
+    species: &[Species],
+) -> Result<Option<Hubbard>, Error> {
+    let lda_plus_u = system.lda_plus_u.unwrap_or(false);
+    let value = |values: &[Option<f64>], i: usize| values.get(i).cloned().unwrap_or(None);
+
+    if let Some(card) = card {
+        if lda_plus_u {
+            return Err(source.value_error(
+                "lda_plus_u",
+                String::from("`lda_plus_u` may not be given with the `HUBBARD` card"),
+            ));
+        }
+
+        // `Hubbard_alpha(i)` is given in the namelist in both formats.
+        let mut hubbard = parse_hubbard_card(card)?;
+        for parameters in &mut hubbard.parameters {
+            if let Some(i) = species.iter().position(|s| s.label == parameters.species) {
+                parameters.alpha = value(&system.hubbard_alpha, i);
+            }
+        }
+        return Ok(Some(hubbard));
+    }
+
+    if !lda_plus_u {
+        return Ok(None);
+    }
+
+    let kind = source.map("lda_plus_u_kind", system.lda_plus_u_kind, |v| match v {
+        0 => Ok(HubbardKind::Simplified),
+        1 => Ok(HubbardKind::Full),
+        other => Err(format!("unsupported lda_plus_u_kind {}", other)),
+    })?;
+    let projection = source.map(
+        "u_projection_type",
+        system.u_projection_type.as_deref(),
+        parse_hubbard_projection,
+    )?;
+
+    // Only `Hubbard_J(1,i)` is represented.
+    let hubbard_j = system.hubbard_j.first().map_or(&[][..], |j| &j[..]);
+    let parameters = species
+        .iter()
+        .enumerate()
+        .filter_map(|(i, s)| {
+            value(&system.hubbard_u, i).map(|u| HubbardParameters {
+                species: s.label.clone(),
+                manifold: None,
+                u,
+                j0: value(&system.hubbard_j0, i),
+                j: value(hubbard_j, i),
+                alpha: value(&system.hubbard_alpha, i),
+            })
+        })
+        .collect();
+
+    Ok(Some(Hubbard {
+        kind: kind.unwrap_or(HubbardKind::Simplified),
+        projection: projection.unwrap_or(HubbardProjection::Atomic),
+        parameters,
+        card: false,
+    }))
+}
+
+fn parse_hubbard_projection(projection: &str) -> Result<HubbardProjection, String> {
+    match projection {
+        "atomic" => Ok(HubbardProjection::Atomic),
+        "ortho-atomic" => Ok(HubbardProjection::OrthoAtomic),
+        "norm-atomic" => Ok(HubbardProjection::NormAtomic),
+        "file" => Ok(HubbardProjection::File),
+        "pseudo" => Ok(HubbardProjection::Pseudo),
+        other => Err(format!("unsupported projection type `{}`", other)),
+    }
+}
+
+/// The parameters of the `HUBBARD` card, which must give a `U` value for each species along
+/// with any `J0` or `J` values, for a single manifold of the species.
+fn parse_hubbard_card(card: &Card) -> Result<Hubbard, Error> {
+    let projection = match card.option.as_deref() {
+        Some("file") | None => {
+            return Err(card_error(
+                card,
+                card.line,
+                "expected projection type `atomic`, `ortho-atomic`, `norm-atomic` or `pseudo`",
+            ))
+        }
+        Some(option) => {
+            parse_hubbard_projection(option).map_err(|e| card_error(card, card.line, &e))?
+        }
+    };
+
+    let mut kind = HubbardKind::Simplified;
+    // The U value of each species is set once its line is read.
+    let mut parameters: Vec<(HubbardParameters, bool)> = Vec::new();
+    for line in &card.lines {
+        if line.fields.len() != 3 {
+            return Err(card_error(
+                card,
+                line.line,
+                "expected `parameter label-manifold value`",
+            ));
+        }
+        let (label, manifold) = match line.fields[1].split_once('-') {
+            Some((label, manifold)) if !label.is_empty() && !manifold.is_empty() => {
+                (label, manifold)
+            }
+            _ => return Err(card_error(card, line.line, "expected `label-manifold`")),
+        };
+        let value = parse_real(card, line.line, &line.fields[2])?;
+
+        let i = match parameters.iter().position(|p| p.0.species == label) {
+            Some(i) => i,
+            None => {
+                parameters.push((
+                    HubbardParameters {
+                        species: String::from(label),
+                        manifold: Some(String::from(manifold)),
+                        u: 0.0,
+                        j0: None,
+                        j: None,
+                        alpha: None,
+                    },
+                    false,
+                ));
+                parameters.len() - 1
+            }
+        };
+        let (ref mut p, ref mut u_given) = parameters[i];
+        if p.manifold.as_deref() != Some(manifold) {
+            return Err(card_error(
+                card,
+                line.line,
+                &format!("only one Hubbard manifold is supported for species {}", label),
+            ));
+        }
+
+        match line.fields[0].to_uppercase().as_str() {
+            "U" => {
+                p.u = value;
+                *u_given = true;
+            }
+            "J0" => p.j0 = Some(value),
+            "J" => {
+                p.j = Some(value);
+                kind = HubbardKind::Full;
+            }
+            other => {
+                return Err(card_error(
+                    card,
+                    line.line,
+                    &format!("unsupported Hubbard parameter `{}`", other),
+                ))
+            }
+        }
+    }
+
+    if let Some((p, _)) = parameters.iter().find(|&&(_, u_given)| !u_given) {
+        return Err(card_error(
+            card,
+            card.line,
+            &format!("no U value is given for species {}", p.species),
+        ));
+    }
+
+    Ok(Hubbard {
+        kind,
+        projection,
+        parameters: parameters.into_iter().map(|(p, _)| p).collect(),
+        card: true,
+    })
+}
+
+fn parse_system(
+    source: &Source,
+    system: &namelists::System,
+    file: &namelist::File,
+    species: &[Species],
+    atomic_positions: &Positions,
+) -> Result<System, Error> {
+    let celldm = system.celldm.first().cloned().unwrap_or(None);
+
+    let ibrav = parse_ibrav(source, system, file, celldm.is_some())?;
+
+    let alat = match celldm {
+        Some(celldm) => celldm,
+        // Without `celldm(1)`, QE takes `alat` to be the length of the first lattice vector.
+        None => match ibrav {
+            Ibrav::Free(ref cell) => {
+                let a1 = cell.cell[0];
+                let length = (a1[0] * a1[0] + a1[1] * a1[1] + a1[2] * a1[2]).sqrt();
+                match cell.units {
+                    LatticeUnits::Alat => return Err(source.missing("celldm(1)")),
+                    ref units => length * units.in_bohr(1.0),
+                }
+            }
+            _ => return Err(source.missing("celldm(1)")),
+        },
+    };
+
+    let nat = source.required("nat", system.nat)?;
+    if nat as usize != atomic_positions.coordinates.len() {
+        return Err(source.value_error(
+            "nat",
+            format!(
+                "`nat` = {} but ATOMIC_POSITIONS lists {} atoms",
+                nat,
+                atomic_positions.coordinates.len()
+            ),
+        ));
+    }
+
+    let ntyp = source.required("ntyp", system.ntyp)?;
+    if ntyp as usize != species.len() {
+        return Err(source.value_error(
+            "ntyp",
+            format!(
+                "`ntyp` = {} but ATOMIC_SPECIES lists {} species",
+                ntyp,
+                species.len()
+            ),
+        ));
+    }
+
+    let ecutwfc = source.required("ecutwfc", system.ecutwfc)?;
+
+    Ok(System {
+        ibrav,
+        alat,
+        ecutwfc,
+        // The pw.x default, appropriate for norm-conserving pseudopotentials.
+        ecutrho: system.ecutrho.unwrap_or(4.0 * ecutwfc),
+        occupations: parse_occupations(source, system)?,
+        spin_type: parse_spin_type(source, system)?,
+        input_dft: parse_functional(source, system)?,
+        vdw_corr: parse_vdw_corr(source, system)?,
+    })
+}
+
+/// The functional given by `input_dft`. Exact exchange parameters given for other functionals
+/// are not represented, and are reported by `check_represented`.
+fn parse_functional(
+    source: &Source,
+    system: &namelists::System,
+) -> Result<Option<Functional>, Error> {
+    let hybrid = || -> Result<Hybrid, Error> {
+        let exxdiv_treatment = source.map(
+            "exxdiv_treatment",
+            system.exxdiv_treatment.as_deref(),
+            |v| match v {
+                "gygi-baldereschi" | "gygi-bald" | "g-b" => Ok(ExxdivTreatment::GygiBaldereschi),
+                "vcut_spherical" => Ok(ExxdivTreatment::VcutSpherical),
+                "vcut_ws" => Ok(ExxdivTreatment::VcutWs),
+                "none" => Ok(ExxdivTreatment::None),
+                other => Err(format!("unsupported exxdiv_treatment `{}`", other)),
+            },
+        )?;
+
+        // QE takes values of `nqx` which are not given to be 1.
+        let nqx = if system.nqx1.is_some() || system.nqx2.is_some() || system.nqx3.is_some() {
+            Some([
+                system.nqx1.unwrap_or(1),
+                system.nqx2.unwrap_or(1),
+                system.nqx3.unwrap_or(1),
+            ])
+        } else {
+            None
+        };
+
+        Ok(Hybrid {
+            nqx,
+            exxdiv_treatment,
+            ecutfock: system.ecutfock,
+            exx_fraction: system.exx_fraction,
+        })
+    };
+
+    let input_dft = match system.input_dft {
+        Some(ref input_dft) => input_dft.to_lowercase(),
+        None => return Ok(None),
+    };
+
+    let functional = match input_dft.as_str() {
+        "pz" | "lda" => Functional::Pz,
+        "pbe" => Functional::Pbe,
+        "pbesol" => Functional::PbeSol,
+        "revpbe" => Functional::RevPbe,
+        "blyp" => Functional::Blyp,
+        "scan" => Functional::Scan,
+        "vdw-df" => Functional::VdwDf,
+        "vdw-df2" => Functional::VdwDf2,
+        "vdw-df-cx" => Functional::VdwDfCx,
+        "rvv10" => Functional::Rvv10,
+        "pbe0" => Functional::Hybrid(HybridFunctional::Pbe0, hybrid()?),
+        "hse" => Functional::Hybrid(HybridFunctional::Hse, hybrid()?),
+        "b3lyp" => Functional::Hybrid(HybridFunctional::B3lyp, hybrid()?),
+        other => {
+            return Err(source.value_error(
+                "input_dft",
+                format!("unsupported input_dft `{}`", other),
+            ))
+        }
+    };
+
+    Ok(Some(functional))
+}
+
+fn parse_vdw_corr(source: &Source, system: &namelists::System) -> Result<Option<VdwCorr>, Error> {
+    let version = source.map("dftd3_version", system.dftd3_version, |v| match v {
+        2 => Ok(Dftd3Version::D2),
+        3 => Ok(Dftd3Version::ZeroDamping),
+        4 => Ok(Dftd3Version::BeckeJohnson),
+        5 => Ok(Dftd3Version::ZeroDampingModified),
+        6 => Ok(Dftd3Version::BeckeJohnsonModified),
+        other => Err(format!("dftd3_version must be between 2 and 6; got {}", other)),
+    })?;
+
+    source.map("vdw_corr", system.vdw_corr.as_deref(), |v| {
+        match v.to_lowercase().as_str() {
+            "grimme-d3" | "dft-d3" | "d3" => Ok(VdwCorr::GrimmeD3 {
+                version,
+                threebody: system.dftd3_threebody,
+            }),
+            "ts-vdw" | "ts" | "ts-vdw-tkatchenko-scheffler" | "tkatchenko-scheffler" => {
+                Ok(VdwCorr::TkatchenkoScheffler {
+                    econv_thr: system.ts_vdw_econv_thr,
+                    isolated: system.ts_vdw_isolated,
+                })
+            }
+            "xdm" => Ok(VdwCorr::Xdm {
+                a1: system.xdm_a1,
+                a2: system.xdm_a2,
+            }),
+            other => Err(format!("unsupported vdw_corr `{}`", other)),
+        }
+    })
+}
+
+fn parse_ibrav(
+    source: &Source,
+    system: &namelists::System,
+    file: &namelist::File,
+    alat_given: bool,
+) -> Result<Ibrav, Error> {
+    let index = source.required("ibrav", system.ibrav)?;
+
+    if index != 0 {
+        if let Some(card) = file.card("CELL_PARAMETERS") {
+            return Err(card_error(
+                card,
+                card.line,
+                "`CELL_PARAMETERS` may only be given with `ibrav=0`",
+            ));
+        }
+    }
+
+    let celldm = |i: usize| {
+        let x = system.celldm.get(i - 1).cloned().unwrap_or(None);
+        source.required(&format!("celldm({})", i), x)
+    };
+
+    Ok(match index {
+        0 => {
+            let cell_card = required_card(file, "CELL_PARAMETERS")?;
+            Ibrav::Free(parse_cell(cell_card, alat_given)?)
+        }
+        1 => Ibrav::SimpleCubic,
+        2 => Ibrav::Fcc,
+        3 => Ibrav::Bcc,
+        -3 => Ibrav::BccSymmetric,
+        4 => Ibrav::Hexagonal(celldm(3)?),
+        5 => Ibrav::TrigonalRAxisC(celldm(4)?),
+        -5 => Ibrav::TrigonalRAxis111(celldm(4)?),
+        6 => Ibrav::TetragonalP(celldm(3)?),
+        7 => Ibrav::TetragonalI(celldm(3)?),
+        8 => Ibrav::OrthorhombicP(celldm(2)?, celldm(3)?),
+        9 => Ibrav::OrthorhombicBco(celldm(2)?, celldm(3)?),
+        -9 => Ibrav::OrthorhombicBcoAlternate(celldm(2)?, celldm(3)?),
+        10 => Ibrav::OrthorhombicFaceCentered(celldm(2)?, celldm(3)?),
+        11 => Ibrav::OrthorhombicBodyCentered(celldm(2)?, celldm(3)?),
+        12 => Ibrav::MonoclinicPUniqueAxisC(celldm(2)?, celldm(3)?, celldm(4)?),
+        -12 => Ibrav::MonoclinicPUniqueAxisB(celldm(2)?, celldm(3)?, celldm(5)?),
+        13 => Ibrav::MonoclinicBaseCentered(celldm(2)?, celldm(3)?, celldm(4)?),
+        14 => Ibrav::Triclinic(celldm(2)?, celldm(3)?, celldm(4)?, celldm(5)?, celldm(6)?),
+        other => {
+            return Err(source.value_error("ibrav", format!("unsupported ibrav={}", other)));
+        }
+    })
+}
+
+fn parse_occupations(source: &Source, system: &namelists::System) -> Result<Occupations, Error> {
+    match system.occupations.as_deref().unwrap_or("fixed") {
+        "smearing" => {
+            let smearing = source.map("smearing", system.smearing.as_deref(), |v| match v {
+                "gaussian" | "gauss" => Ok(Smearing::Gaussian),
+                "methfessel-paxton" | "m-p" | "mp" => Ok(Smearing::MethfesselPaxton),
+                "marzari-vanderbilt" | "cold" | "m-v" | "mv" => Ok(Smearing::MarzariVanderbilt),
+                "fermi-dirac" | "f-d" | "fd" => Ok(Smearing::FermiDirac),
+                other => Err(format!("unknown smearing `{}`", other)),
+            })?;
+
+            Ok(Occupations::Smearing(
+                smearing.unwrap_or(Smearing::Gaussian),
+                source.required("degauss", system.degauss)?,
+            ))
+        }
+        "tetrahedra" => Ok(Occupations::Tetrahedra),
+        "tetrahedra_lin" => Ok(Occupations::TetrahedraLin),
+        "tetrahedra_opt" => Ok(Occupations::TetrahedraOpt),
+        "fixed" => Ok(Occupations::Fixed),
+        other => Err(source.value_error(
+            "occupations",
+            format!("unsupported occupations `{}`", other),
+        )),
+    }
+}
+
+/// The spin type. Magnetic parameters given without spin polarization are not represented,
+/// and are reported by `check_represented`.
+fn parse_spin_type(source: &Source, system: &namelists::System) -> Result<Option<SpinType>, Error> {
+    let magnetization = parse_magnetization(source, system)?;
+
+    let nspin = source.map("nspin", system.nspin, |v| match v {
+        1 => Ok(SpinType::NonPolarized),
+        2 => Ok(SpinType::CollinearPolarized(magnetization.clone())),
+        other => Err(format!("nspin must be 1 or 2; got {}", other)),
+    })?;
+
+    if system.noncolin.unwrap_or(false) {
+        if nspin.is_some() {
+            return Err(source.value_error(
+                "nspin",
+                String::from("`nspin` may not be given with `noncolin=.true.`"),
+            ));
+        }
+
+        let spin_orbit = system.lspinorb.unwrap_or(false);
+        return Ok(Some(SpinType::Noncollinear {
+            spin_orbit,
+            magnetization,
+        }));
+    }
+
+    Ok(nspin)
+}
+
+fn parse_magnetization(source: &Source, system: &namelists::System) -> Result<Magnetization, Error> {
+    // QE takes angles which are not given to be zero.
+    let n_angles = system.angle1.len().max(system.angle2.len());
+    let element = |values: &[Option<f64>], i: usize| values.get(i).cloned().unwrap_or(None);
+    let angles = (0..n_angles)
+        .map(|i| match (element(&system.angle1, i), element(&system.angle2, i)) {
+            (None, None) => None,
+            (angle1, angle2) => Some([angle1.unwrap_or(0.0), angle2.unwrap_or(0.0)]),
+        })
+        .collect();
+
+    let fixed_magnetization = [
+        element(&system.fixed_magnetization, 0).unwrap_or(0.0),
+        element(&system.fixed_magnetization, 1).unwrap_or(0.0),
+        element(&system.fixed_magnetization, 2).unwrap_or(0.0),
+    ];
+    // The default penalty parameter of QE.
+    let lambda = system.lambda.unwrap_or(1.0);
+    let constrained_magnetization = source.map(
+        "constrained_magnetization",
+        system.constrained_magnetization.as_deref(),
+        |v| match v {
+            "total" => Ok(ConstrainedMagnetization::Total {
+                fixed_magnetization,
+            }),
+            "atomic" => Ok(ConstrainedMagnetization::Atomic { lambda }),
+            "total direction" => Ok(ConstrainedMagnetization::TotalDirection {
+                angle: fixed_magnetization[2],
+            }),
+            "atomic direction" => Ok(ConstrainedMagnetization::AtomicDirection { lambda }),
+            other => Err(format!("unsupported constrained_magnetization `{}`", other)),
+        },
+    )?;
+
+    Ok(Magnetization {
+        starting_magnetization: system.starting_magnetization.clone(),
+        angles,
+        tot_magnetization: system.tot_magnetization,
+        constrained_magnetization,
+    })
+}
+
+fn parse_electrons(source: &Source, electrons: &namelists::Electrons) -> Result<Electrons, Error> {
+    let startingwfc = source.map(
+        "startingwfc",
+        electrons.startingwfc.as_deref(),
+        |v| match v {
+            "atomic" => Ok(StartingWfc::Atomic),
+            "atomic+random" => Ok(StartingWfc::AtomicPlusRandom),
+            "random" => Ok(StartingWfc::Random),
+            "file" => Ok(StartingWfc::File),
+            other => Err(format!("unknown startingwfc `{}`", other)),
+        },
+    )?;
+
+    let diagonalization = source.map(
+        "diagonalization",
+        electrons.diagonalization.as_deref(),
+        |v| match v {
+            "david" => Ok(Diagonalization::David),
+            "cg" => Ok(Diagonalization::Cg),
+            other => Err(format!("unsupported diagonalization `{}`", other)),
+        },
+    )?;
+
+    Ok(Electrons {
+        startingwfc,
+        diagonalization,
+    })
+}
+
+fn required_card<'a>(file: &'a namelist::File, name: &str) -> Result<&'a Card, Error> {
+    file.card(name)
+        .ok_or_else(|| Error::MissingCard(String::from(name)))
+}
+
+fn parse_species(card: &Card) -> Result<Vec<Species>, Error> {
+    let mut species = Vec::new();
+
+    for line in &card.lines {
+        if line.fields.len() != 3 {
+            return Err(card_error(
+                card,
+                line.line,
+                "expected `label mass pseudopotential_filename`",
+            ));
+        }
+
+        species.push(Species {
+            label: line.fields[0].clone(),
+            mass: parse_real(card, line.line, &line.fields[1])?,
+            pseudopotential_filename: line.fields[2].clone(),
+        });
+    }
+
+    Ok(species)
+}
+
+/// If `alat_given`, the lattice constant is specified in the `system` namelist; this
+/// determines the units of a `CELL_PARAMETERS` card without units, as in QE.
+fn parse_cell(card: &Card, alat_given: bool) -> Result<Cell, Error> {
+    let units = match card.option.as_deref() {
+        Some("alat") => LatticeUnits::Alat,
+        Some("bohr") => LatticeUnits::Bohr,
+        Some("angstrom") => LatticeUnits::Angstrom,
+        None if alat_given => LatticeUnits::Alat,
+        None => LatticeUnits::Bohr,
+        Some(other) => {
+            return Err(card_error(
+                card,
+                card.line,
+                &format!("unknown units `{}`", other),
+            ))
+        }
+    };
+
+    if card.lines.len() != 3 {
+        return Err(card_error(
+            card,
+            card.line,
+            "expected exactly three lattice vectors",
+        ));
+    }
+
+    let mut cell = [[0.0; 3]; 3];
+    for (latvec, line) in cell.iter_mut().zip(card.lines.iter()) {
+        if line.fields.len() != 3 {
+            return Err(card_error(card, line.line, "expected three components"));
+        }
+        for (x, field) in latvec.iter_mut().zip(line.fields.iter()) {
+            *x = parse_real(card, line.line, field)?;
+        }
+    }
+
+    Ok(Cell { units, cell })
+}
+
+fn parse_positions(card: &Card) -> Result<Positions, Error> {
+    let coordinate_type = match card.option.as_deref() {
+        Some("alat") | None => PositionCoordinateType::AlatCartesian,
+        Some("bohr") => PositionCoordinateType::BohrCartesian,
+        Some("angstrom") => PositionCoordinateType::AngstromCartesian,
+        Some("crystal") => PositionCoordinateType::Crystal,
+        Some("crystal_sg") => PositionCoordinateType::CrystalSG,
+        Some(other) => {
+            return Err(card_error(
+                card,
+                card.line,
+                &format!("unknown coordinate type `{}`", other),
+            ))
+        }
+    };
+
+    let mut coordinates = Vec::new();
+
+    for line in &card.lines {
+        let fields = &line.fields;
+        if fields.len() != 4 && fields.len() != 7 {
+            return Err(card_error(
+                card,
+                line.line,
+                "expected `label x y z` optionally followed by three `if_pos` values",
+            ));
+        }
+
+        let mut r = [0.0; 3];
+        for (x, field) in r.iter_mut().zip(fields[1..4].iter()) {
+            *x = parse_real(card, line.line, field)?;
+        }
+
+        let if_pos = if fields.len() == 7 {
+            let mut if_pos = [false; 3];
+            for (b, field) in if_pos.iter_mut().zip(fields[4..7].iter()) {
+                *b = parse_flag(card, line.line, field)?;
+            }
+            Some(if_pos)
+        } else {
+            None
+        };
+
+        coordinates.push(AtomCoordinate {
+            species: fields[0].clone(),
+            r,
+            if_pos,
+        });
+    }
+
+    Ok(Positions {
+        coordinate_type,
+        coordinates,
+    })
+}
+
+fn parse_k_points(card: &Card) -> Result<KPoints, Error> {
+    match card.option.as_deref() {
+        Some("automatic") => {
+            let line = match card.lines.first() {
+                Some(line) if line.fields.len() == 6 && card.lines.len() == 1 => line,
+                _ => {
+                    return Err(card_error(
+                        card,
+                        card.line,
+                        "expected `nk1 nk2 nk3 sk1 sk2 sk3`",
+                    ))
+                }
+            };
+
+            let mut nk = [0; 3];
+            for (n, field) in nk.iter_mut().zip(line.fields[..3].iter()) {
+                *n = parse_count(card, line.line, field)?;
+            }
+
+            let mut sk = [false; 3];
+            for (s, field) in sk.iter_mut().zip(line.fields[3..].iter()) {
+                *s = parse_flag(card, line.line, field)?;
+            }
+
+            Ok(KPoints::Automatic {
+                nk,
+                sk: if sk == [false, false, false] {
+                    None
+                } else {
+                    Some(sk)
+                },
+            })
+        }
+        Some("tpiba") | None => Ok(KPoints::TwoPiByACartesian(parse_weighted_k_list(card)?)),
+        Some("crystal") => Ok(KPoints::Crystal(parse_weighted_k_list(card)?)),
+        Some("gamma") => {
+            if let Some(line) = card.lines.first() {
+                return Err(card_error(card, line.line, "expected no k-points"));
+            }
+
+            Ok(KPoints::Gamma)
+        }
+        Some("tpiba_b") => {
+            let (nk_per_panel, panel_bounds) = parse_band_path(card)?;
+
+            Ok(KPoints::TwoPiByACartesianBands {
+                nk_per_panel,
+                panel_bounds,
+            })
+        }
+        Some("crystal_b") => {
+            let (nk_per_panel, panel_bounds) = parse_band_path(card)?;
+
+            Ok(KPoints::CrystalBands {
+                nk_per_panel,
+                panel_bounds,
+            })
+        }
+        Some("tpiba_c") => {
+            let (corners, nk) = parse_contour(card)?;
+
+            Ok(KPoints::TwoPiByACartesianContour { corners, nk })
+        }
+        Some("crystal_c") => {
+            let (corners, nk) = parse_contour(card)?;
+
+            Ok(KPoints::CrystalContour { corners, nk })
+        }
+        Some(other) => Err(card_error(
+            card,
+            card.line,
+            &format!("unsupported k-point type `{}`", other),
+        )),
+    }
+}
+
+fn parse_weighted_k_list(card: &Card) -> Result<Vec<[f64; 4]>, Error> {
+    Ok(parse_k_list(card)?
+        .into_iter()
+        .map(|(_, k, w)| [k[0], k[1], k[2], w])
+        .collect())
+}
+
+/// Read the panel bounds of a band path, with the number of k-points in each panel given as
+/// their weights.
+fn parse_band_path(card: &Card) -> Result<(u64, Vec<[f64; 3]>), Error> {
+    let k_list = parse_k_list(card)?;
+
+    // The weight of the last point is not used by pw.x.
+    let nk_per_panel = k_list.first().map_or(0.0, |&(_, _, w)| w);
+    for &(line, _, w) in k_list[..k_list.len().saturating_sub(1)].iter() {
+        if w != nk_per_panel {
+            return Err(card_error(
+                card,
+                line,
+                "all panels must have the same number of k-points",
+            ));
+        }
+    }
+
+    if nk_per_panel < 0.0 || nk_per_panel.fract() != 0.0 {
+        return Err(card_error(
+            card,
+            card.line,
+            "number of k-points per panel must be a non-negative integer",
+        ));
+    }
+
+    Ok((
+        nk_per_panel as u64,
+        k_list.into_iter().map(|(_, k, _)| k).collect(),
+    ))
+}
+
+/// Read the corners `k0`, `k1` and `k2` of a contour, with the number of points along the
+/// sides `k1 - k0` and `k2 - k0` given as the weights of `k1` and `k2`.
+fn parse_contour(card: &Card) -> Result<([[f64; 3]; 3], [u64; 2]), Error> {
+    let k_list = parse_k_list(card)?;
+
+    if k_list.len() != 3 {
+        return Err(card_error(
+            card,
+            card.line,
+            "a contour requires exactly 3 k-points",
+        ));
+    }
+
+    let mut nk = [0; 2];
+    for (n, &(line, _, w)) in nk.iter_mut().zip(k_list[1..].iter()) {
+        if w < 0.0 || w.fract() != 0.0 {
+            return Err(card_error(
+                card,
+                line,
+                "number of k-points along a side must be a non-negative integer",
+            ));
+        }
+        *n = w as u64;
+    }
+
+    Ok(([k_list[0].1, k_list[1].1, k_list[2].1], nk))
+}
+
+/// Read a k-point list of the form `nks` followed by `nks` lines of `k1 k2 k3 w`,
+/// returning the line number, k-point and weight of each entry.
+fn parse_k_list(card: &Card) -> Result<Vec<(usize, [f64; 3], f64)>, Error> {
+    let count_line = match card.lines.first() {
+        Some(line) if line.fields.len() == 1 => line,
+        _ => {
+            return Err(card_error(
+                card,
+                card.line,
+                "expected the number of k-points",
+            ))
+        }
+    };
+    let nks = parse_count(card, count_line.line, &count_line.fields[0])? as usize;
+
+    if card.lines.len() - 1 != nks {
+        return Err(card_error(
+            card,
+            count_line.line,
+            &format!("expected {} k-points; got {}", nks, card.lines.len() - 1),
+        ));
+    }
+
+    let mut k_list = Vec::new();
+    for line in &card.lines[1..] {
+        if line.fields.len() != 4 {
+            return Err(card_error(card, line.line, "expected `k1 k2 k3 weight`"));
+        }
+
+        let mut k = [0.0; 3];
+        for (x, field) in k.iter_mut().zip(line.fields[..3].iter()) {
+            *x = parse_real(card, line.line, field)?;
+        }
+        let w = parse_real(card, line.line, &line.fields[3])?;
+
+        k_list.push((line.line, k, w));
+    }
+
+    Ok(k_list)
+}
+
+fn parse_real(card: &Card, line: usize, field: &str) -> Result<f64, Error> {
+    field
+        .to_lowercase()
+        .replace('d', "e")
+        .parse::<f64>()
+        .map_err(|_| card_error(card, line, &format!("invalid number `{}`", field)))
+}
+
+fn parse_count(card: &Card, line: usize, field: &str) -> Result<u64, Error> {
+    field
+        .parse::<u64>()
+        .map_err(|_| card_error(card, line, &format!("invalid count `{}`", field)))
+}
+
+fn parse_flag(card: &Card, line: usize, field: &str) -> Result<bool, Error> {
+    match field {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(card_error(
+            card,
+            line,
+            &format!("expected 0 or 1; got `{}`", field),
+        )),
+    }
+}
+
+fn card_error(card: &Card, line: usize, message: &str) -> Error {
+    Error::Card {
+        line,
+        card: card.name.clone(),
+        message: String::from(message),
+    }
+}
+
+/// A namelist of the input file, used to locate fields for error messages.
+/// A namelist which is not given in the file is treated as empty.
+struct Source<'a> {
+    name: &'static str,
+    nl: Option<&'a Namelist>,
+}
+
+impl<'a> Source<'a> {
+    fn new(file: &'a namelist::File, name: &'static str) -> Source<'a> {
+        Source {
+            name,
+            nl: file.namelist(name),
+        }
+    }
+
+    fn read<T: DeserializeOwned + Default>(&self) -> Result<T, Error> {
+        match self.nl {
+            Some(nl) => Ok(namelist::from_namelist(nl)?),
+            None => Ok(T::default()),
+        }
+    }
+
+    /// The line on which `field` is given, or 0 if it is not present.
+    fn line(&self, field: &str) -> usize {
+        self.nl
+            .and_then(|nl| {
+                nl.entries
+                    .iter()
+                    .find(|e| e.name == field || e.key() == field)
+            })
+            .map_or(0, |e| e.line)
+    }
+
+    fn value_error(&self, field: &str, message: String) -> Error {
+        Error::Value {
+            line: self.line(field),
+            field: String::from(field),
+            message,
+        }
+    }
+
+    fn missing(&self, field: &str) -> Error {
+        Error::Missing {
+            namelist: String::from(self.name),
+            field: String::from(field),
+        }
+    }
+
+    fn required<T>(&self, field: &str, x: Option<T>) -> Result<T, Error> {
+        x.ok_or_else(|| self.missing(field))
+    }
+
+    /// Map the value of `field`, if present, reporting failures at the field's location.
+    fn map<T, U, F>(&self, field: &str, x: Option<T>, f: F) -> Result<Option<U>, Error>
+    where
+        F: FnOnce(T) -> Result<U, String>,
+    {
+        match x {
+            Some(x) => f(x)
+                .map(Some)
+                .map_err(|message| self.value_error(field, message)),
+            None => Ok(None),
+        }
+    }
+
+    /// Check that every field given in the file is also present when the parsed `Input` is
+    /// written back out as `written`; any that are not could not be represented in `Input`.
+    fn check_represented<T: Serialize>(&self, written: &T) -> Result<(), Error> {
+        let nl = match self.nl {
+            Some(nl) => nl,
+            None => return Ok(()),
+        };
+
+        let text = namelist::to_string(self.name, written)?;
+        let written_file = namelist::read_file(&text, &[])?;
+        let written_keys: Vec<String> = written_file.namelists[0]
+            .entries
+            .iter()
+            .map(|e| e.key())
+            .collect();
+
+        match nl.entries.iter().find(|e| !written_keys.contains(&e.key())) {
+            Some(entry) => Err(Error::UnknownField {
+                line: entry.line,
+                namelist: String::from(self.name),
+                field: entry.key(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "{}", _0)]
+    Namelist(#[cause] namelist::Error),
+    #[fail(display = "line {}: unsupported namelist `&{}`", line, name)]
+    UnknownNamelist { line: usize, name: String },
+    #[fail(
+        display = "line {}: namelist `&{}` is given more than once",
+        line, name
+    )]
+    DuplicateNamelist { line: usize, name: String },
+    #[fail(
+        display = "line {}: field `{}` in `&{}` cannot be represented",
+        line, field, namelist
+    )]
+    UnknownField {
+        line: usize,
+        namelist: String,
+        field: String,
+    },
+    #[fail(display = "line {}: invalid value for `{}`: {}", line, field, message)]
+    Value {
+        line: usize,
+        field: String,
+        message: String,
+    },
+    #[fail(display = "required field `{}` is missing from `&{}`", field, namelist)]
+    Missing { namelist: String, field: String },
+    #[fail(display = "line {}: unsupported card `{}`", line, card)]
+    UnknownCard { line: usize, card: String },
+    #[fail(display = "required card `{}` is missing", _0)]
+    MissingCard(String),
+    #[fail(display = "line {}: in card `{}`: {}", line, card, message)]
+    Card {
+        line: usize,
+        card: String,
+        message: String,
+    },
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<namelist::Error> for Error {
+    fn from(e: namelist::Error) -> Error {
+        Error::Namelist(e)
+    }
+}
@@ -2,24 +2,40 @@ use std::path::Path;
 use std::io;
 use std::io::Write;
 use std::fs::File;
-use serialize_util::push_bool_field;
+use namelist;
 use pw::input;
-use pw::input::{generate_uniform_kpoints, Calculation, Diagonalization, DiskIO, Efield, Ibrav,
-                Input, KPoints, LatticeDirection, LatticeUnits, Occupations,
-                PositionCoordinateType, RestartMode, SpinType, StartingWfc};
+use pw::input::{generate_uniform_kpoints, Calculation, CellDofree, ConstrainedMagnetization,
+                Diagonalization, DiskIO, ExxdivTreatment, Functional, HubbardProjection,
+                HybridFunctional, Ibrav, Input, IonTemperature, KPoints, LatticeUnits,
+                MdIonDynamics, Occupations, PositionCoordinateType, RelaxIonDynamics,
+                RestartMode, Smearing, StartingWfc, VcMdCellDynamics, VdwCorr, Verbosity};
+use pw::namelists;
 
 pub fn make_input_file(input: &Input) -> Result<String, Error> {
-    input::validate(&input)?;
+    input::validate(input)?;
+
+    let control = make_control(input)?;
+    let system = make_system(input)?;
+    let electrons = make_electrons(input)?;
+    let ions = make_ions(input)?;
+    let cell_namelist = make_cell_namelist(input)?;
+    let species = make_species(input);
+    let cell = make_cell(input);
+    let positions = make_positions(input);
+    let k_points = make_k_points(input);
+    let hubbard = make_hubbard(input);
+
+    let mut input_sections = vec![control, system, electrons];
+
+    if let Some(ions) = ions {
+        input_sections.push(ions)
+    }
 
-    let control = make_control(&input)?;
-    let system = make_system(&input);
-    let electrons = make_electrons(&input);
-    let species = make_species(&input);
-    let cell = make_cell(&input);
-    let positions = make_positions(&input);
-    let k_points = make_k_points(&input);
+    if let Some(cell_namelist) = cell_namelist {
+        input_sections.push(cell_namelist)
+    }
 
-    let mut input_sections = vec![control, system, electrons, species];
+    input_sections.push(species);
 
     if let Some(cell) = cell {
         input_sections.push(cell)
@@ -27,133 +43,41 @@ pub fn make_input_file(input: &Input) -> Result<String, Error> {
 
     input_sections.extend(vec![positions, k_points]);
 
+    if let Some(hubbard) = hubbard {
+        input_sections.push(hubbard)
+    }
+
     let input_text = input_sections.join("\n");
 
-    return Ok(input_text);
+    Ok(input_text)
 }
 
 fn make_control(input: &Input) -> Result<String, Error> {
-    let mut lines = Vec::new();
-    lines.push(String::from(" &control"));
-
-    lines.push(format!("    calculation='{}',", input.calculation.value()));
-
-    let control = &input.control;
-
-    if let Some(ref restart_mode) = control.restart_mode {
-        lines.push(format!("    restart_mode='{}',", restart_mode.value()))
-    }
-
-    if let Some(ref disk_io) = control.disk_io {
-        lines.push(format!("    disk_io='{}',", disk_io.value()))
-    }
-
-    push_bool_field(&mut lines, "wf_collect", control.wf_collect);
-
-    if let Some(ref pseudo_dir) = control.pseudo_dir {
-        let path = pseudo_dir.to_str().ok_or(Error::PseudoDir)?;
-        lines.push(format!("    pseudo_dir='{}',", path));
-    }
-
-    if let Some(ref out_dir) = control.out_dir {
-        let path = out_dir.to_str().ok_or(Error::OutDir)?;
-        lines.push(format!("    out_dir='{}',", path));
-    }
-
-    if let Some(Efield::TeField { dipfield, .. }) = input.efield {
-        push_bool_field(&mut lines, "tefield", Some(true));
-        push_bool_field(&mut lines, "dipfield", Some(dipfield));
-    }
-
-    if let Some(ref prefix) = control.prefix {
-        lines.push(format!("    prefix='{}',", prefix));
-    }
-
-    lines.push(String::from(" /"));
-    Ok(lines.join("\n"))
+    Ok(namelist::to_string("control", &namelists::control(input))?)
 }
 
-fn make_system(input: &Input) -> String {
-    let mut lines = Vec::new();
-    lines.push(String::from(" &system"));
-
-    let system = &input.system;
-
-    lines.push(format!("    ibrav={},", system.ibrav.value()));
-    lines.push(format!("    celldm(1)={},", system.alat));
-
-    let nat = input.atomic_positions.coordinates.len();
-    lines.push(format!("    nat={},", nat));
-
-    let ntyp = input.species.len();
-    lines.push(format!("    ntyp={},", ntyp));
-
-    lines.push(format!("    ecutwfc={},", system.ecutwfc));
-    lines.push(format!("    ecutrho={},", system.ecutrho));
-
-    lines.push(format!("    occupations='{}',", system.occupations.value()));
-
-    if let Some(ref spin_type) = system.spin_type {
-        match *spin_type {
-            SpinType::NonPolarized => {
-                lines.push(format!("    nspin=1,"));
-            }
-            SpinType::CollinearPolarized => {
-                lines.push(format!("    nspin=2,"));
-            }
-            SpinType::Noncollinear { spin_orbit } => {
-                lines.push(format!("    noncolin=.true.,"));
-                push_bool_field(&mut lines, "lspinorb", Some(spin_orbit));
-            }
-        };
-    };
-
-    if let Some(Efield::TeField {
-        ref edir,
-        emaxpos,
-        eopreg,
-        eamp,
-        ..
-    }) = input.efield
-    {
-        lines.push(format!("    edir={},", edir.value()));
-        lines.push(format!("    emaxpos={},", emaxpos));
-        lines.push(format!("    eopreg={},", eopreg));
-        lines.push(format!("    eamp={:e},", eamp));
-    };
-
-    lines.push(String::from(" /"));
-    lines.join("\n")
+fn make_system(input: &Input) -> Result<String, Error> {
+    Ok(namelist::to_string("system", &namelists::system(input))?)
 }
 
-fn make_electrons(input: &Input) -> String {
-    let mut lines = Vec::new();
-    lines.push(String::from(" &electrons"));
-
-    let electrons = &input.electrons;
-
-    if let Some(ref startingwfc) = electrons.startingwfc {
-        lines.push(format!("    startingwfc='{}',", startingwfc.value()));
-    };
-
-    if let Some(ref diagonalization) = electrons.diagonalization {
-        lines.push(format!(
-            "    diagonalization='{}',",
-            diagonalization.value()
-        ));
-    };
+fn make_electrons(input: &Input) -> Result<String, Error> {
+    Ok(namelist::to_string("electrons", &namelists::electrons(input))?)
+}
 
-    match input.calculation {
-        Calculation::Scf { conv_thr } => {
-            lines.push(format!("    conv_thr={:e},", conv_thr));
-        }
-        Calculation::Nscf { diago_thr_init, .. } | Calculation::Bands { diago_thr_init, .. } => {
-            lines.push(format!("    diago_thr_init={:e},", diago_thr_init));
-        }
+/// The `ions` namelist, which is required for calculations in which the ions move.
+fn make_ions(input: &Input) -> Result<Option<String>, Error> {
+    match namelists::ions(input) {
+        Some(ions) => Ok(Some(namelist::to_string("ions", &ions)?)),
+        None => Ok(None),
     }
+}
 
-    lines.push(String::from(" /"));
-    lines.join("\n")
+/// The `cell` namelist, which is required for variable-cell calculations.
+fn make_cell_namelist(input: &Input) -> Result<Option<String>, Error> {
+    match namelists::cell(input) {
+        Some(cell) => Ok(Some(namelist::to_string("cell", &cell)?)),
+        None => Ok(None),
+    }
 }
 
 fn make_species(input: &Input) -> String {
@@ -171,8 +95,8 @@ fn make_species(input: &Input) -> String {
 }
 
 fn make_cell(input: &Input) -> Option<String> {
-    match &input.system.ibrav {
-        &Ibrav::Free(ref cell) => {
+    match input.system.ibrav {
+        Ibrav::Free(ref cell) => {
             let mut lines = Vec::new();
 
             lines.push(format!("CELL_PARAMETERS {}", cell.units.value()));
@@ -183,6 +107,8 @@ fn make_cell(input: &Input) -> Option<String> {
 
             Some(lines.join("\n"))
         }
+        // Other lattice types are fully specified by `celldm` in the `system` namelist.
+        _ => None,
     }
 }
 
@@ -220,15 +146,15 @@ fn make_k_points(input: &Input) -> String {
     let mut lines = Vec::new();
     lines.push(format!("K_POINTS {}", input.k_points.value()));
 
-    match &input.k_points {
-        &KPoints::Crystal(ref k_points) => {
+    match input.k_points {
+        KPoints::TwoPiByACartesian(ref k_points) | KPoints::Crystal(ref k_points) => {
             lines.push(format!("{}", k_points.len()));
 
             for kw in k_points {
                 lines.push(format!("{} {} {} {}", kw[0], kw[1], kw[2], kw[3]));
             }
         }
-        &KPoints::CrystalUniform(nk) => {
+        KPoints::CrystalUniform(nk) => {
             let k_points = generate_uniform_kpoints(nk);
             let weight = 1.0 / (k_points.len() as f64);
 
@@ -238,14 +164,19 @@ fn make_k_points(input: &Input) -> String {
                 lines.push(format!("{} {} {} {}", k[0], k[1], k[2], weight));
             }
         }
-        &KPoints::Automatic { nk, sk } => {
+        KPoints::Automatic { nk, sk } => {
             let sk_str = match sk {
                 Some(sk) => render_bool_list(sk),
                 None => render_bool_list([false, false, false]),
             };
             lines.push(format!("{} {} {} {}", nk[0], nk[1], nk[2], sk_str));
         }
-        &KPoints::CrystalBands {
+        KPoints::Gamma => (),
+        KPoints::TwoPiByACartesianBands {
+            nk_per_panel,
+            ref panel_bounds,
+        }
+        | KPoints::CrystalBands {
             nk_per_panel,
             ref panel_bounds,
         } => {
@@ -255,11 +186,51 @@ fn make_k_points(input: &Input) -> String {
                 lines.push(format!("{} {} {} {}", k[0], k[1], k[2], nk_per_panel));
             }
         }
+        KPoints::TwoPiByACartesianContour { corners, nk }
+        | KPoints::CrystalContour { corners, nk } => {
+            // The weight of `k0` is not used by pw.x.
+            let weights = [1, nk[0], nk[1]];
+
+            lines.push(String::from("3"));
+
+            for (k, w) in corners.iter().zip(weights.iter()) {
+                lines.push(format!("{} {} {} {}", k[0], k[1], k[2], w));
+            }
+        }
     }
 
     lines.join("\n")
 }
 
+/// The `HUBBARD` card, used when the DFT+U parameters are not given in the `system` namelist.
+fn make_hubbard(input: &Input) -> Option<String> {
+    let hubbard = match input.hubbard {
+        Some(ref hubbard) if hubbard.card => hubbard,
+        _ => return None,
+    };
+
+    let mut lines = Vec::new();
+    lines.push(format!("HUBBARD {}", hubbard.projection.value()));
+
+    for parameters in &hubbard.parameters {
+        let manifold = format!(
+            "{}-{}",
+            parameters.species,
+            parameters.manifold.as_deref().unwrap_or("")
+        );
+
+        lines.push(format!(" U {} {}", manifold, parameters.u));
+        if let Some(j0) = parameters.j0 {
+            lines.push(format!(" J0 {} {}", manifold, j0));
+        }
+        if let Some(j) = parameters.j {
+            lines.push(format!(" J {} {}", manifold, j));
+        }
+    }
+
+    Some(lines.join("\n"))
+}
+
 fn render_bool_list(xs: [bool; 3]) -> String {
     let mut result = Vec::new();
 
@@ -287,8 +258,7 @@ pub fn write_input_file<P: AsRef<Path>>(input: &Input, file_path: P) -> Result<(
 pub enum Error {
     #[fail(display = "{}", _0)] Input(input::ErrorList),
     #[fail(display = "{}", _0)] Io(#[cause] io::Error),
-    #[fail(display = "`pseudo_dir` is not valid UTF-8")] PseudoDir,
-    #[fail(display = "`out_dir` is not valid UTF-8")] OutDir,
+    #[fail(display = "{}", _0)] Namelist(#[cause] namelist::Error),
 }
 
 impl From<input::ErrorList> for Error {
@@ -303,6 +273,12 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<namelist::Error> for Error {
+    fn from(e: namelist::Error) -> Error {
+        Error::Namelist(e)
+    }
+}
+
 /// A `Field` has a method `value()` which returns its textual representation on the
 /// right-hand side of a `field_name = value` expression in the QE input file.
 pub trait Field {
@@ -315,6 +291,73 @@ impl Field for Calculation {
             Calculation::Scf { .. } => "scf",
             Calculation::Nscf { .. } => "nscf",
             Calculation::Bands { .. } => "bands",
+            Calculation::Relax { .. } => "relax",
+            Calculation::VcRelax { .. } => "vc-relax",
+            Calculation::Md { .. } => "md",
+            Calculation::VcMd { .. } => "vc-md",
+        })
+    }
+}
+
+impl Field for RelaxIonDynamics {
+    fn value(&self) -> String {
+        String::from(match *self {
+            RelaxIonDynamics::Bfgs => "bfgs",
+            RelaxIonDynamics::Damp => "damp",
+        })
+    }
+}
+
+impl Field for MdIonDynamics {
+    fn value(&self) -> String {
+        String::from(match *self {
+            MdIonDynamics::Verlet => "verlet",
+            MdIonDynamics::Langevin => "langevin",
+            MdIonDynamics::LangevinSmc => "langevin-smc",
+        })
+    }
+}
+
+impl Field for IonTemperature {
+    fn value(&self) -> String {
+        String::from(match *self {
+            IonTemperature::Rescaling { .. } => "rescaling",
+            IonTemperature::RescaleV { .. } => "rescale-v",
+            IonTemperature::RescaleT { .. } => "rescale-T",
+            IonTemperature::ReduceT { .. } => "reduce-T",
+            IonTemperature::Berendsen { .. } => "berendsen",
+            IonTemperature::Andersen { .. } => "andersen",
+            IonTemperature::Initial => "initial",
+            IonTemperature::NotControlled => "not_controlled",
+        })
+    }
+}
+
+impl Field for VcMdCellDynamics {
+    fn value(&self) -> String {
+        String::from(match *self {
+            VcMdCellDynamics::Pr => "pr",
+            VcMdCellDynamics::W => "w",
+        })
+    }
+}
+
+impl Field for CellDofree {
+    fn value(&self) -> String {
+        String::from(match *self {
+            CellDofree::All => "all",
+            CellDofree::Ibrav => "ibrav",
+            CellDofree::X => "x",
+            CellDofree::Y => "y",
+            CellDofree::Z => "z",
+            CellDofree::Xy => "xy",
+            CellDofree::Xz => "xz",
+            CellDofree::Yz => "yz",
+            CellDofree::Xyz => "xyz",
+            CellDofree::Shape => "shape",
+            CellDofree::Volume => "volume",
+            CellDofree::TwoDxy => "2Dxy",
+            CellDofree::TwoDShape => "2Dshape",
         })
     }
 }
@@ -328,6 +371,15 @@ impl Field for RestartMode {
     }
 }
 
+impl Field for Verbosity {
+    fn value(&self) -> String {
+        String::from(match *self {
+            Verbosity::Low => "low",
+            Verbosity::High => "high",
+        })
+    }
+}
+
 impl Field for DiskIO {
     fn value(&self) -> String {
         String::from(match *self {
@@ -339,32 +391,89 @@ impl Field for DiskIO {
     }
 }
 
-impl Field for LatticeDirection {
+impl Field for Occupations {
     fn value(&self) -> String {
         String::from(match *self {
-            LatticeDirection::D1 => "1",
-            LatticeDirection::D2 => "2",
-            LatticeDirection::D3 => "3",
+            Occupations::Smearing(_, _) => "smearing",
+            Occupations::Tetrahedra => "tetrahedra",
+            Occupations::TetrahedraLin => "tetrahedra_lin",
+            Occupations::TetrahedraOpt => "tetrahedra_opt",
+            Occupations::Fixed => "fixed",
         })
     }
 }
 
-impl Field for Ibrav {
+impl Field for ConstrainedMagnetization {
     fn value(&self) -> String {
         String::from(match *self {
-            Ibrav::Free(_) => "0",
+            ConstrainedMagnetization::Total { .. } => "total",
+            ConstrainedMagnetization::Atomic { .. } => "atomic",
+            ConstrainedMagnetization::TotalDirection { .. } => "total direction",
+            ConstrainedMagnetization::AtomicDirection { .. } => "atomic direction",
         })
     }
 }
 
-impl Field for Occupations {
+impl Field for Functional {
     fn value(&self) -> String {
         String::from(match *self {
-            Occupations::Smearing(_, _) => "smearing",
-            Occupations::Tetrahedra => "tetrahedra",
-            Occupations::TetrahedraLin => "tetrahedra_lin",
-            Occupations::TetrahedraOpt => "tetrahedra_opt",
-            Occupations::Fixed => "fixed",
+            Functional::Pz => "pz",
+            Functional::Pbe => "pbe",
+            Functional::PbeSol => "pbesol",
+            Functional::RevPbe => "revpbe",
+            Functional::Blyp => "blyp",
+            Functional::Scan => "scan",
+            Functional::VdwDf => "vdw-df",
+            Functional::VdwDf2 => "vdw-df2",
+            Functional::VdwDfCx => "vdw-df-cx",
+            Functional::Rvv10 => "rvv10",
+            Functional::Hybrid(HybridFunctional::Pbe0, _) => "pbe0",
+            Functional::Hybrid(HybridFunctional::Hse, _) => "hse",
+            Functional::Hybrid(HybridFunctional::B3lyp, _) => "b3lyp",
+        })
+    }
+}
+
+impl Field for ExxdivTreatment {
+    fn value(&self) -> String {
+        String::from(match *self {
+            ExxdivTreatment::GygiBaldereschi => "gygi-baldereschi",
+            ExxdivTreatment::VcutSpherical => "vcut_spherical",
+            ExxdivTreatment::VcutWs => "vcut_ws",
+            ExxdivTreatment::None => "none",
+        })
+    }
+}
+
+impl Field for VdwCorr {
+    fn value(&self) -> String {
+        String::from(match *self {
+            VdwCorr::GrimmeD3 { .. } => "grimme-d3",
+            VdwCorr::TkatchenkoScheffler { .. } => "ts-vdw",
+            VdwCorr::Xdm { .. } => "xdm",
+        })
+    }
+}
+
+impl Field for HubbardProjection {
+    fn value(&self) -> String {
+        String::from(match *self {
+            HubbardProjection::Atomic => "atomic",
+            HubbardProjection::OrthoAtomic => "ortho-atomic",
+            HubbardProjection::NormAtomic => "norm-atomic",
+            HubbardProjection::File => "file",
+            HubbardProjection::Pseudo => "pseudo",
+        })
+    }
+}
+
+impl Field for Smearing {
+    fn value(&self) -> String {
+        String::from(match *self {
+            Smearing::Gaussian => "gaussian",
+            Smearing::MethfesselPaxton => "methfessel-paxton",
+            Smearing::MarzariVanderbilt => "marzari-vanderbilt",
+            Smearing::FermiDirac => "fermi-dirac",
         })
     }
 }
@@ -414,9 +523,14 @@ impl Field for PositionCoordinateType {
 impl Field for KPoints {
     fn value(&self) -> String {
         String::from(match *self {
+            KPoints::TwoPiByACartesian(_) => "tpiba",
             KPoints::Crystal(_) | KPoints::CrystalUniform(_) => "crystal",
             KPoints::Automatic { .. } => "automatic",
+            KPoints::Gamma => "gamma",
+            KPoints::TwoPiByACartesianBands { .. } => "tpiba_b",
             KPoints::CrystalBands { .. } => "crystal_b",
+            KPoints::TwoPiByACartesianContour { .. } => "tpiba_c",
+            KPoints::CrystalContour { .. } => "crystal_c",
         })
     }
 }
@@ -0,0 +1,574 @@
+//! Transformations of the structure of an `Input`: supercells given by an integer matrix,
+//! slabs cut along Miller indices, and the substitution and removal of atoms.
+//!
+//! Each transformation gives a new `Input`, with other settings copied from the original.
+//! Supercells and slabs are given by `Ibrav::Free`, with the lattice vectors in the units of
+//! the original `Ibrav::Free` cell, or in units of `alat` for other lattices; the positions
+//! keep their coordinate type. Since `nat` and `ntyp` are derived from the positions and
+//! species, species which are no longer used by any atom are removed, along with their
+//! per-species settings. The number of bands and the total magnetization, which are given for
+//! the whole cell, are multiplied by the number of copies of the original cell.
+use std::f64::consts::PI;
+use pw::input::{
+    AtomCoordinate, Calculation, Cell, ConstrainedMagnetization, Ibrav, Input, KPoints,
+    LatticeUnits, PositionCoordinateType, Positions, Species, SpinType,
+};
+use pw::lattice::{
+    cartesian_to_crystal, cross, crystal_to_cartesian, dot, integer_determinant, inverse,
+    lattice_vectors, matrix_product, norm, reciprocal_vectors, scale_vector,
+};
+
+/// Tolerance for crystal coordinates lying on the boundary of a cell.
+const TOLERANCE: f64 = 1e-8;
+
+/// The supercell with lattice vectors `matrix A`, where the rows of `A` are the lattice vectors
+/// of `input`. The matrix must have a positive determinant, which gives the number of copies of
+/// each atom.
+///
+/// Copies of each atom follow the original atom, and keep its `if_pos` constraints.
+/// `Automatic` and `CrystalUniform` k-point meshes are scaled to keep the density of the
+/// finest direction of the original mesh; explicit k-points are given in crystal coordinates
+/// of the supercell, at the same Cartesian positions.
+pub fn supercell(input: &Input, matrix: [[i32; 3]; 3]) -> Result<Input, Error> {
+    if integer_determinant(&matrix) <= 0 {
+        return Err(Error::Matrix);
+    }
+
+    let (cell, positions) = crystal_structure(input)?;
+    let supercell = matrix_product(&to_f64(&matrix), &cell);
+    let coordinates = repeat(&positions, &matrix);
+
+    transformed_input(input, &cell, supercell, coordinates, [true, true, true])
+}
+
+/// The slab of `layers` repeats of the lattice planes with Miller indices `miller`, given with
+/// respect to the lattice vectors of `input`, separated from its periodic images by `vacuum`
+/// bohr along the surface normal.
+///
+/// The first two lattice vectors are the shortest vectors spanning the surface, and the third
+/// is normal to the surface; the lowest atom lies at the bottom of the cell. Atoms in the
+/// lowest `fixed_layers` repeats are fixed by setting `if_pos` to zero, while other atoms keep
+/// their `if_pos` constraints. The k-point mesh is scaled as for `supercell`, with a single
+/// k-point along the surface normal.
+pub fn slab(
+    input: &Input,
+    miller: [i32; 3],
+    layers: u32,
+    vacuum: f64,
+    fixed_layers: u32,
+) -> Result<Input, Error> {
+    if layers == 0 || fixed_layers > layers {
+        return Err(Error::Layers);
+    }
+    if vacuum.is_nan() || vacuum <= 0.0 {
+        return Err(Error::Vacuum);
+    }
+
+    let (cell, positions) = crystal_structure(input)?;
+    let [v1, v2, v3] = surface_vectors(&cell, miller)?;
+    let n = layers as i32;
+    let matrix = [v1, v2, [n * v3[0], n * v3[1], n * v3[2]]];
+    let oriented = matrix_product(&to_f64(&matrix), &cell);
+
+    // The normal points from the lowest layer to the highest, even for a left-handed cell.
+    let normal = cross(oriented[0], oriented[1]);
+    let normal = scale_vector(dot(normal, oriented[2]).signum() / norm(normal), normal);
+
+    let atoms: Vec<(AtomCoordinate, [f64; 3])> = repeat(&positions, &matrix)
+        .into_iter()
+        .map(|mut atom| {
+            let r = crystal_to_cartesian(&oriented, atom.r);
+            if ((atom.r[2] * f64::from(layers) + TOLERANCE).floor() as u32) < fixed_layers {
+                atom.if_pos = Some([false, false, false]);
+            }
+            (atom, r)
+        })
+        .collect();
+
+    let heights: Vec<f64> = atoms.iter().map(|&(_, r)| dot(r, normal)).collect();
+    let bottom = heights.iter().cloned().fold(f64::INFINITY, f64::min);
+    let top = heights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let slab = [
+        oriented[0],
+        oriented[1],
+        scale_vector(top - bottom + vacuum, normal),
+    ];
+
+    let coordinates = atoms
+        .into_iter()
+        .map(|(atom, r)| {
+            let r = [
+                r[0] - bottom * normal[0],
+                r[1] - bottom * normal[1],
+                r[2] - bottom * normal[2],
+            ];
+            let mut x = cartesian_to_crystal(&slab, r);
+            x[0] = wrap(x[0]);
+            x[1] = wrap(x[1]);
+            AtomCoordinate { r: x, ..atom }
+        })
+        .collect();
+
+    transformed_input(input, &cell, slab, coordinates, [true, true, false])
+}
+
+/// Replace the species of the atom with index `index` by `species`. If a species with the same
+/// label is already given by `input`, that species is used and `species` is ignored.
+pub fn substitute(input: &Input, index: usize, species: Species) -> Result<Input, Error> {
+    if index >= input.atomic_positions.coordinates.len() {
+        return Err(Error::AtomIndex(index));
+    }
+
+    let mut result = input.clone();
+    result.atomic_positions.coordinates[index].species = species.label.clone();
+    if !result.species.iter().any(|s| s.label == species.label) {
+        result.species.push(species);
+    }
+    remove_unused_species(&mut result);
+
+    Ok(result)
+}
+
+/// Remove the atoms with the given indices.
+pub fn remove_atoms(input: &Input, indices: &[usize]) -> Result<Input, Error> {
+    let nat = input.atomic_positions.coordinates.len();
+    if let Some(&index) = indices.iter().find(|&&index| index >= nat) {
+        return Err(Error::AtomIndex(index));
+    }
+
+    let mut result = input.clone();
+    result.atomic_positions.coordinates = input
+        .atomic_positions
+        .coordinates
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| !indices.contains(&i))
+        .map(|(_, atom)| atom.clone())
+        .collect();
+    remove_unused_species(&mut result);
+
+    Ok(result)
+}
+
+/// The lattice vectors of `input` in bohr, and the positions in crystal coordinates.
+fn crystal_structure(input: &Input) -> Result<([[f64; 3]; 3], Positions), Error> {
+    let alat = input.system.alat;
+    let cell = lattice_vectors(&input.system.ibrav, alat);
+    let positions = input
+        .atomic_positions
+        .to_coordinate_type(PositionCoordinateType::Crystal, &cell, alat)
+        .map_err(|_| Error::CoordinateType)?;
+
+    Ok((cell, positions))
+}
+
+/// The copies of each atom of `positions` in the supercell given by `matrix`, in crystal
+/// coordinates of the supercell.
+fn repeat(positions: &Positions, matrix: &[[i32; 3]; 3]) -> Vec<AtomCoordinate> {
+    let inverse_matrix = inverse(&to_f64(matrix));
+
+    // The lattice points within the supercell lie within the bounding box of its corners.
+    let mut low = [0; 3];
+    let mut high = [0; 3];
+    for row in matrix.iter() {
+        for d in 0..3 {
+            low[d] += row[d].min(0);
+            high[d] += row[d].max(0);
+        }
+    }
+
+    let mut result = Vec::new();
+    for atom in &positions.coordinates {
+        let x = [wrap(atom.r[0]), wrap(atom.r[1]), wrap(atom.r[2])];
+        for n0 in low[0]..=high[0] {
+            for n1 in low[1]..=high[1] {
+                for n2 in low[2]..=high[2] {
+                    let shifted = [
+                        x[0] + f64::from(n0),
+                        x[1] + f64::from(n1),
+                        x[2] + f64::from(n2),
+                    ];
+                    let mut y = [0.0; 3];
+                    for (j, y_j) in y.iter_mut().enumerate() {
+                        *y_j = (0..3).map(|i| shifted[i] * inverse_matrix[i][j]).sum();
+                    }
+                    if y.iter().all(|&y| y > -TOLERANCE && y < 1.0 - TOLERANCE) {
+                        result.push(AtomCoordinate {
+                            r: [wrap(y[0]), wrap(y[1]), wrap(y[2])],
+                            ..atom.clone()
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// The shortest integer vectors `v1` and `v2` spanning the lattice planes with Miller indices
+/// `miller`, and the shortest integer vector `v3` joining neighbouring planes, such that the
+/// vectors form a right-handed basis of the lattice.
+fn surface_vectors(cell: &[[f64; 3]; 3], miller: [i32; 3]) -> Result<[[i32; 3]; 3], Error> {
+    let divisor = gcd(gcd(miller[0], miller[1]), miller[2]);
+    if divisor == 0 {
+        return Err(Error::Miller);
+    }
+    let h = [
+        miller[0] / divisor,
+        miller[1] / divisor,
+        miller[2] / divisor,
+    ];
+
+    let bound = h.iter().map(|h| h.abs()).max().unwrap_or(0) + 1;
+    let mut candidates = Vec::new();
+    for n0 in -bound..=bound {
+        for n1 in -bound..=bound {
+            for n2 in -bound..=bound {
+                if [n0, n1, n2] != [0, 0, 0] {
+                    candidates.push([n0, n1, n2]);
+                }
+            }
+        }
+    }
+    let length = |n: &[i32; 3]| {
+        norm(crystal_to_cartesian(
+            cell,
+            [f64::from(n[0]), f64::from(n[1]), f64::from(n[2])],
+        ))
+    };
+    candidates.sort_by(|a, b| length(a).total_cmp(&length(b)));
+
+    let index = |n: &[i32; 3]| h[0] * n[0] + h[1] * n[1] + h[2] * n[2];
+    let in_plane: Vec<[i32; 3]> = candidates
+        .iter()
+        .cloned()
+        .filter(|n| index(n) == 0)
+        .collect();
+
+    // Vectors `v1` and `v2` in the planes span them if `v1 x v2 = +/- h`.
+    let mut found = None;
+    'search: for (i, v1) in in_plane.iter().enumerate() {
+        for v2 in &in_plane[i + 1..] {
+            let c = integer_cross(*v1, *v2);
+            if c == h {
+                found = Some((*v1, *v2));
+                break 'search;
+            }
+            if c == [-h[0], -h[1], -h[2]] {
+                found = Some((*v2, *v1));
+                break 'search;
+            }
+        }
+    }
+    let (v1, v2) = found.ok_or(Error::Miller)?;
+    let v3 = *candidates
+        .iter()
+        .find(|n| index(n) == 1)
+        .ok_or(Error::Miller)?;
+
+    Ok([v1, v2, v3])
+}
+
+/// The `Input` for the lattice vectors `new_cell` in bohr and the positions `coordinates` in
+/// crystal coordinates of `new_cell`. The k-point mesh is scaled from the lattice vectors
+/// `cell` of `input` along the `periodic` directions, with a single k-point along others.
+fn transformed_input(
+    input: &Input,
+    cell: &[[f64; 3]; 3],
+    new_cell: [[f64; 3]; 3],
+    coordinates: Vec<AtomCoordinate>,
+    periodic: [bool; 3],
+) -> Result<Input, Error> {
+    let units = match input.system.ibrav {
+        Ibrav::Free(ref cell) => cell.units.clone(),
+        _ => LatticeUnits::Alat,
+    };
+    // pw.x takes `alat` to be the length of the first lattice vector for cells in bohr or
+    // angstrom, since `celldm(1)` is not written for these.
+    let alat = match units {
+        LatticeUnits::Alat => input.system.alat,
+        _ => {
+            let a1 = new_cell[0];
+            (a1[0] * a1[0] + a1[1] * a1[1] + a1[2] * a1[2]).sqrt()
+        }
+    };
+    let atoms = coordinates.len();
+    let positions = Positions {
+        coordinate_type: PositionCoordinateType::Crystal,
+        coordinates,
+    };
+
+    let mut result = input.clone();
+    scale_extensive(&mut result, atoms)?;
+    result.system.alat = alat;
+    result.atomic_positions = positions
+        .to_coordinate_type(
+            input.atomic_positions.coordinate_type.clone(),
+            &new_cell,
+            alat,
+        )
+        .map_err(|_| Error::CoordinateType)?;
+    result.system.ibrav = Ibrav::Free(
+        Cell {
+            units: LatticeUnits::Bohr,
+            cell: new_cell,
+        }
+        .to_units(units, alat),
+    );
+    result.k_points = transformed_k_points(
+        &input.k_points,
+        cell,
+        &new_cell,
+        periodic,
+        alat / input.system.alat,
+    );
+    remove_unused_species(&mut result);
+
+    Ok(result)
+}
+
+/// Multiply the number of bands and the total magnetization of `input` by the number of copies
+/// of its cell given by `atoms` atoms.
+fn scale_extensive(input: &mut Input, atoms: usize) -> Result<(), Error> {
+    let original = input.atomic_positions.coordinates.len();
+    let copies = if original > 0 && atoms.is_multiple_of(original) {
+        Some(atoms / original)
+    } else {
+        None
+    };
+
+    match input.calculation {
+        Calculation::Nscf { ref mut nbnd, .. } | Calculation::Bands { ref mut nbnd, .. } => {
+            if let Some(n) = *nbnd {
+                let scaled = copies.and_then(|copies| n.checked_mul(copies as u64));
+                *nbnd = Some(scaled.ok_or(Error::Extensive)?);
+            }
+        }
+        _ => (),
+    }
+
+    match input.system.spin_type {
+        Some(SpinType::CollinearPolarized(ref mut magnetization))
+        | Some(SpinType::Noncollinear {
+            ref mut magnetization,
+            ..
+        }) => {
+            let scale = |x: f64| {
+                copies
+                    .map(|copies| x * copies as f64)
+                    .ok_or(Error::Extensive)
+            };
+            if let Some(m) = magnetization.tot_magnetization {
+                magnetization.tot_magnetization = Some(scale(m)?);
+            }
+            if let Some(ConstrainedMagnetization::Total {
+                ref mut fixed_magnetization,
+            }) = magnetization.constrained_magnetization
+            {
+                for m in fixed_magnetization.iter_mut() {
+                    *m = scale(*m)?;
+                }
+            }
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// The k-points of `k_points` for `new_cell`, where `alat_ratio` is the new `alat` divided by
+/// the original one.
+fn transformed_k_points(
+    k_points: &KPoints,
+    cell: &[[f64; 3]; 3],
+    new_cell: &[[f64; 3]; 3],
+    periodic: [bool; 3],
+    alat_ratio: f64,
+) -> KPoints {
+    let b = reciprocal_vectors(cell);
+    let new_b = reciprocal_vectors(new_cell);
+
+    // Mesh sizes keeping the density of `nk` along each direction. Each new reciprocal
+    // vector takes the finest spacing of the directions of `nk` which it has a component
+    // along, so that a diagonal supercell divides each size of `nk` by its multiple.
+    let mesh = |nk: [u64; 3]| -> [u64; 3] {
+        let mut result = [1; 3];
+        for i in 0..3 {
+            if periodic[i] {
+                let spacing = (0..3)
+                    .filter(|&j| dot(new_b[i], cell[j]).abs() / (2.0 * PI) > 1e-6)
+                    .map(|j| norm(b[j]) / nk[j].max(1) as f64)
+                    .fold(f64::INFINITY, f64::min);
+                result[i] = ((norm(new_b[i]) / spacing - 1e-6).ceil() as u64).max(1);
+            }
+        }
+        result
+    };
+    // Crystal coordinates of `k` with respect to `new_b`.
+    let convert = |k: [f64; 3]| -> [f64; 3] {
+        let k_cart = crystal_to_cartesian(&b, k);
+        [
+            dot(k_cart, new_cell[0]) / (2.0 * PI),
+            dot(k_cart, new_cell[1]) / (2.0 * PI),
+            dot(k_cart, new_cell[2]) / (2.0 * PI),
+        ]
+    };
+    // Cartesian coordinates in units of 2 pi / `alat` for the new `alat`.
+    let scale = |k: [f64; 3]| -> [f64; 3] {
+        [k[0] * alat_ratio, k[1] * alat_ratio, k[2] * alat_ratio]
+    };
+
+    match *k_points {
+        KPoints::Automatic { nk, sk } => KPoints::Automatic {
+            nk: mesh(nk),
+            sk: sk.map(|sk| {
+                [
+                    sk[0] && periodic[0],
+                    sk[1] && periodic[1],
+                    sk[2] && periodic[2],
+                ]
+            }),
+        },
+        KPoints::CrystalUniform(nk) => KPoints::CrystalUniform(mesh(nk)),
+        KPoints::Crystal(ref ks) => KPoints::Crystal(
+            ks.iter()
+                .map(|k| {
+                    let x = convert([k[0], k[1], k[2]]);
+                    [x[0], x[1], x[2], k[3]]
+                })
+                .collect(),
+        ),
+        KPoints::CrystalBands {
+            nk_per_panel,
+            ref panel_bounds,
+        } => KPoints::CrystalBands {
+            nk_per_panel,
+            panel_bounds: panel_bounds.iter().map(|&k| convert(k)).collect(),
+        },
+        KPoints::CrystalContour { corners, nk } => KPoints::CrystalContour {
+            corners: [convert(corners[0]), convert(corners[1]), convert(corners[2])],
+            nk,
+        },
+        KPoints::TwoPiByACartesian(ref ks) => KPoints::TwoPiByACartesian(
+            ks.iter()
+                .map(|k| {
+                    let x = scale([k[0], k[1], k[2]]);
+                    [x[0], x[1], x[2], k[3]]
+                })
+                .collect(),
+        ),
+        KPoints::TwoPiByACartesianBands {
+            nk_per_panel,
+            ref panel_bounds,
+        } => KPoints::TwoPiByACartesianBands {
+            nk_per_panel,
+            panel_bounds: panel_bounds.iter().map(|&k| scale(k)).collect(),
+        },
+        KPoints::TwoPiByACartesianContour { corners, nk } => KPoints::TwoPiByACartesianContour {
+            corners: [scale(corners[0]), scale(corners[1]), scale(corners[2])],
+            nk,
+        },
+        KPoints::Gamma => KPoints::Gamma,
+    }
+}
+
+/// Remove species which are not used by any atom, along with their magnetic and DFT+U
+/// parameters. Per-species magnetic parameters, which are indexed by position in the list of
+/// species, are moved along with the species which are kept.
+fn remove_unused_species(input: &mut Input) {
+    let coordinates = &input.atomic_positions.coordinates;
+    let kept: Vec<usize> = (0..input.species.len())
+        .filter(|&i| {
+            let label = &input.species[i].label;
+            coordinates.iter().any(|atom| &atom.species == label)
+        })
+        .collect();
+
+    input.species = kept.iter().map(|&i| input.species[i].clone()).collect();
+
+    match input.system.spin_type {
+        Some(SpinType::CollinearPolarized(ref mut magnetization))
+        | Some(SpinType::Noncollinear {
+            ref mut magnetization,
+            ..
+        }) => {
+            magnetization.starting_magnetization =
+                select(&magnetization.starting_magnetization, &kept);
+            magnetization.angles = select(&magnetization.angles, &kept);
+        }
+        _ => (),
+    }
+
+    if let Some(ref mut hubbard) = input.hubbard {
+        let species = &input.species;
+        hubbard
+            .parameters
+            .retain(|p| species.iter().any(|s| s.label == p.species));
+    }
+}
+
+/// The elements of `values` at `indices`, with `None` for those past its end.
+fn select<T: Copy>(values: &[Option<T>], indices: &[usize]) -> Vec<Option<T>> {
+    indices
+        .iter()
+        .map(|&i| values.get(i).cloned().unwrap_or(None))
+        .collect()
+}
+
+fn to_f64(m: &[[i32; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut result = [[0.0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            result[i][j] = f64::from(m[i][j]);
+        }
+    }
+
+    result
+}
+
+fn integer_cross(u: [i32; 3], v: [i32; 3]) -> [i32; 3] {
+    [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// `x` translated into [0, 1).
+fn wrap(x: f64) -> f64 {
+    let x = x - x.floor();
+    if x > 1.0 - TOLERANCE {
+        0.0
+    } else {
+        x
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "Transformations of `crystal_sg` positions are not supported.")]
+    CoordinateType,
+    #[fail(display = "The supercell matrix must have a positive determinant.")]
+    Matrix,
+    #[fail(display = "Miller indices must not all be zero.")]
+    Miller,
+    #[fail(display = "The number of slab layers must be nonzero, and at least `fixed_layers`.")]
+    Layers,
+    #[fail(display = "The vacuum thickness must be positive.")]
+    Vacuum,
+    #[fail(display = "There is no atom with index {}.", _0)]
+    AtomIndex(usize),
+    #[fail(
+        display = "The number of bands and the total magnetization can only be scaled by a whole number of copies of the cell."
+    )]
+    Extensive,
+}
@@ -0,0 +1,385 @@
+//! Symmetry of a crystal structure given by a `Cell` and `Positions`: the space group
+//! operations, the irreducible k-points of a Monkhorst-Pack mesh, and the primitive cell.
+//!
+//! Positions are compared to within `tolerance`, a Cartesian distance in bohr.
+use pw::input::{AtomCoordinate, Cell, KPoints, LatticeUnits, PositionCoordinateType, Positions};
+use pw::lattice::{
+    cartesian_rotation, cartesian_to_crystal, cell_vectors, crystal_to_cartesian, determinant,
+    inverse, lattice_rotations, matrix_product, norm, reduce_basis, scale_vector,
+};
+
+/// A space group operation, acting on crystal coordinates `x` (as a column vector) as
+/// `rotation x + translation`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Operation {
+    pub rotation: [[i32; 3]; 3],
+    /// Components are in [0, 1).
+    pub translation: [f64; 3],
+}
+
+/// The space group operations of the structure, including pure translations if the cell is
+/// not primitive. The identity is the first operation.
+pub fn space_group(
+    cell: &Cell,
+    alat: f64,
+    positions: &Positions,
+    tolerance: f64,
+) -> Result<Vec<Operation>, Error> {
+    let structure = Structure::new(cell, alat, positions)?;
+
+    Ok(structure.operations(tolerance))
+}
+
+/// The irreducible k-points of the mesh given by `KPoints::Automatic`, as `KPoints::Crystal`.
+///
+/// The weight of each k-point is the number of points of the mesh equivalent to it, under
+/// the rotations of the space group and time reversal, as in pw.x for calculations without
+/// noncollinear magnetism. Equivalent points which do not lie on the mesh, as for some
+/// shifted meshes, are not counted. The k-points are given in the order of the mesh, with
+/// coordinates in (-1/2, 1/2].
+pub fn irreducible_k_points(
+    cell: &Cell,
+    alat: f64,
+    positions: &Positions,
+    k_points: &KPoints,
+    tolerance: f64,
+) -> Result<KPoints, Error> {
+    let (nk, sk) = match *k_points {
+        KPoints::Automatic { nk, sk } => (nk, sk.unwrap_or([false, false, false])),
+        _ => return Err(Error::KPoints),
+    };
+    if nk.contains(&0) {
+        return Err(Error::KPoints);
+    }
+
+    let structure = Structure::new(cell, alat, positions)?;
+    let mut rotations: Vec<[[i32; 3]; 3]> = Vec::new();
+    for op in structure.operations(tolerance) {
+        if !rotations.contains(&op.rotation) {
+            rotations.push(op.rotation);
+        }
+    }
+
+    let nk = [nk[0] as usize, nk[1] as usize, nk[2] as usize];
+    let shift = [
+        if sk[0] { 0.5 } else { 0.0 },
+        if sk[1] { 0.5 } else { 0.0 },
+        if sk[2] { 0.5 } else { 0.0 },
+    ];
+    let point = |i: [usize; 3]| -> [f64; 3] {
+        let mut k = [0.0; 3];
+        for d in 0..3 {
+            k[d] = (i[d] as f64 + shift[d]) / nk[d] as f64;
+        }
+        k
+    };
+    // The index of the mesh point equivalent to `k`, if there is one.
+    let mesh_index = |k: [f64; 3]| -> Option<usize> {
+        let mut index = 0;
+        for d in 0..3 {
+            let i = k[d] * nk[d] as f64 - shift[d];
+            if (i - i.round()).abs() > 1e-5 {
+                return None;
+            }
+            let i = (i.round() as i64).rem_euclid(nk[d] as i64) as usize;
+            index = index * nk[d] + i;
+        }
+        Some(index)
+    };
+
+    let num_points = nk[0] * nk[1] * nk[2];
+    let mut weights = vec![0; num_points];
+    let mut equivalent = vec![false; num_points];
+    let mut irreducible = Vec::new();
+    for i0 in 0..nk[0] {
+        for i1 in 0..nk[1] {
+            for i2 in 0..nk[2] {
+                let index = (i0 * nk[1] + i1) * nk[2] + i2;
+                if equivalent[index] {
+                    continue;
+                }
+                let k = point([i0, i1, i2]);
+                irreducible.push((index, k));
+
+                // The rotation `W` maps k-points in crystal coordinates as `W^-T k`; over the
+                // whole group, these are the same as the `W^T k`.
+                for w in &rotations {
+                    let mut image = [0.0; 3];
+                    for d in 0..3 {
+                        image[d] = (0..3).map(|e| f64::from(w[e][d]) * k[e]).sum();
+                    }
+                    let reversed = scale_vector(-1.0, image);
+                    for &image in &[image, reversed] {
+                        if let Some(other) = mesh_index(image) {
+                            if !equivalent[other] {
+                                equivalent[other] = true;
+                                weights[index] += 1;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(KPoints::Crystal(
+        irreducible
+            .into_iter()
+            .map(|(index, k)| {
+                let k = k.iter().map(|&x| x - x.round()).map(|x| {
+                    if x <= -0.5 + 1e-10 {
+                        x + 1.0
+                    } else {
+                        x
+                    }
+                });
+                let k: Vec<f64> = k.collect();
+                [k[0], k[1], k[2], f64::from(weights[index])]
+            })
+            .collect(),
+    ))
+}
+
+/// The primitive cell of the structure, in bohr, with positions in crystal coordinates.
+///
+/// If the cell is already primitive, its lattice vectors are kept. Otherwise, the vectors of
+/// the primitive cell are reduced, as by `lattice::reduce_basis`, and form a right-handed
+/// set; each atom is kept once, with the `if_pos` of its first occurrence.
+pub fn primitive_cell(
+    cell: &Cell,
+    alat: f64,
+    positions: &Positions,
+    tolerance: f64,
+) -> Result<(Cell, Positions), Error> {
+    let structure = Structure::new(cell, alat, positions)?;
+    let translations: Vec<[f64; 3]> = structure
+        .operations(tolerance)
+        .into_iter()
+        .filter(|op| op.rotation == IDENTITY)
+        .map(|op| op.translation)
+        .collect();
+
+    let primitive = if translations.len() == 1 {
+        structure.cell
+    } else {
+        // The primitive vectors may be chosen among the pure translations and the lattice
+        // vectors, such that the cell volume is reduced by the number of translations.
+        let mut candidates: Vec<[f64; 3]> = translations[1..].to_vec();
+        candidates.extend_from_slice(&IDENTITY_F64);
+        let volume = 1.0 / translations.len() as f64;
+
+        let mut found = None;
+        'search: for i in 0..candidates.len() {
+            for j in (i + 1)..candidates.len() {
+                for k in (j + 1)..candidates.len() {
+                    let vectors = [candidates[i], candidates[j], candidates[k]];
+                    if (determinant(&vectors).abs() - volume).abs() < 1e-5 {
+                        found = Some(vectors);
+                        break 'search;
+                    }
+                }
+            }
+        }
+        let vectors = found.ok_or(Error::Primitive)?;
+
+        let primitive = reduce_basis(&matrix_product(&vectors, &structure.cell));
+        if determinant(&primitive) < 0.0 {
+            [primitive[0], primitive[1], scale_vector(-1.0, primitive[2])]
+        } else {
+            primitive
+        }
+    };
+
+    let mut coordinates: Vec<AtomCoordinate> = Vec::new();
+    let mut reduced: Vec<[f64; 3]> = Vec::new();
+    for (atom, x) in positions.coordinates.iter().zip(&structure.positions) {
+        let r = crystal_to_cartesian(&structure.cell, *x);
+        let x = wrap(cartesian_to_crystal(&primitive, r));
+        let duplicate = coordinates.iter().zip(&reduced).any(|(other, &y)| {
+            other.species == atom.species && distance(&primitive, x, y) < tolerance
+        });
+        if !duplicate {
+            coordinates.push(AtomCoordinate {
+                species: atom.species.clone(),
+                r: x,
+                if_pos: atom.if_pos,
+            });
+            reduced.push(x);
+        }
+    }
+
+    Ok((
+        Cell {
+            units: LatticeUnits::Bohr,
+            cell: primitive,
+        },
+        Positions {
+            coordinate_type: PositionCoordinateType::Crystal,
+            coordinates,
+        },
+    ))
+}
+
+const IDENTITY: [[i32; 3]; 3] = [[1, 0, 0], [0, 1, 0], [0, 0, 1]];
+const IDENTITY_F64: [[f64; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// The lattice vectors in bohr, and the positions in crystal coordinates.
+struct Structure<'a> {
+    cell: [[f64; 3]; 3],
+    species: Vec<&'a str>,
+    positions: Vec<[f64; 3]>,
+}
+
+impl<'a> Structure<'a> {
+    fn new(cell: &Cell, alat: f64, positions: &'a Positions) -> Result<Structure<'a>, Error> {
+        let cell = cell_vectors(cell, alat);
+        let scale = cell.iter().map(|a| norm(*a)).fold(0.0, f64::max);
+        let volume = determinant(&cell);
+        if volume.is_nan() || volume.abs() <= 1e-8 * scale * scale * scale {
+            return Err(Error::CellVolume);
+        }
+
+        let crystal = positions
+            .to_coordinate_type(PositionCoordinateType::Crystal, &cell, alat)
+            .map_err(|_| Error::CoordinateType)?;
+
+        Ok(Structure {
+            cell,
+            species: positions
+                .coordinates
+                .iter()
+                .map(|atom| atom.species.as_str())
+                .collect(),
+            positions: crystal.coordinates.iter().map(|atom| atom.r).collect(),
+        })
+    }
+
+    /// Find the operations by trying each rotation of the lattice, with the translations
+    /// which map an atom of the least common species onto each atom of that species.
+    fn operations(&self, tolerance: f64) -> Vec<Operation> {
+        let reduced = reduce_basis(&self.cell);
+        let scale = reduced.iter().map(|a| norm(*a)).fold(0.0, f64::max);
+        let cell_inverse = inverse(&self.cell);
+
+        let mut rotations: Vec<[[i32; 3]; 3]> = lattice_rotations(&reduced, tolerance / scale)
+            .iter()
+            .map(|w| {
+                // As a matrix acting on row vectors of crystal coordinates of `cell`, the
+                // rotation is `A Q A^-1`; its transpose acts on column vectors.
+                let q = cartesian_rotation(&reduced, w);
+                let s = matrix_product(&matrix_product(&self.cell, &q), &cell_inverse);
+                let mut rotation = [[0; 3]; 3];
+                for i in 0..3 {
+                    for j in 0..3 {
+                        rotation[i][j] = s[j][i].round() as i32;
+                    }
+                }
+                rotation
+            })
+            .collect();
+        // Put the identity first.
+        rotations.sort_by_key(|rotation| *rotation != IDENTITY);
+
+        if self.positions.is_empty() {
+            return rotations
+                .into_iter()
+                .map(|rotation| Operation {
+                    rotation,
+                    translation: [0.0; 3],
+                })
+                .collect();
+        }
+
+        let count = |species: &str| self.species.iter().filter(|&&s| s == species).count();
+        let origin = (0..self.positions.len())
+            .min_by_key(|&i| count(self.species[i]))
+            .expect("positions are not empty");
+
+        let mut operations = Vec::new();
+        for rotation in rotations {
+            let rotated: Vec<[f64; 3]> = self
+                .positions
+                .iter()
+                .map(|&x| apply(&rotation, x))
+                .collect();
+
+            for (i, &x) in self.positions.iter().enumerate() {
+                if self.species[i] != self.species[origin] {
+                    continue;
+                }
+                let mut translation = [0.0; 3];
+                for d in 0..3 {
+                    translation[d] = x[d] - rotated[origin][d];
+                }
+                let translation = wrap(translation);
+
+                let maps_atoms = rotated.iter().enumerate().all(|(j, r)| {
+                    let image = [
+                        r[0] + translation[0],
+                        r[1] + translation[1],
+                        r[2] + translation[2],
+                    ];
+                    self.positions.iter().enumerate().any(|(k, &y)| {
+                        self.species[k] == self.species[j]
+                            && distance(&self.cell, image, y) < tolerance
+                    })
+                });
+                if maps_atoms {
+                    operations.push(Operation {
+                        rotation,
+                        translation,
+                    });
+                }
+            }
+        }
+
+        operations
+    }
+}
+
+/// `m x` for the column vector `x`.
+fn apply(m: &[[i32; 3]; 3], x: [f64; 3]) -> [f64; 3] {
+    let mut result = [0.0; 3];
+    for (i, row) in m.iter().enumerate() {
+        result[i] = (0..3).map(|j| f64::from(row[j]) * x[j]).sum();
+    }
+
+    result
+}
+
+/// Crystal coordinates brought into [0, 1).
+fn wrap(x: [f64; 3]) -> [f64; 3] {
+    let mut result = x;
+    for r in result.iter_mut() {
+        *r -= r.floor();
+        if *r > 1.0 - 1e-5 {
+            *r = 0.0;
+        }
+    }
+
+    result
+}
+
+/// The Cartesian distance between the crystal coordinates `x` and `y`, up to a lattice
+/// vector.
+fn distance(cell: &[[f64; 3]; 3], x: [f64; 3], y: [f64; 3]) -> f64 {
+    let mut d = [0.0; 3];
+    for i in 0..3 {
+        d[i] = x[i] - y[i];
+        d[i] -= d[i].round();
+    }
+
+    norm(crystal_to_cartesian(cell, d))
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "Lattice vectors must span a nonzero volume.")]
+    CellVolume,
+    #[fail(display = "Symmetry analysis of `crystal_sg` positions is not supported.")]
+    CoordinateType,
+    #[fail(display = "The irreducible k-points can only be found for `Automatic` k-points.")]
+    KPoints,
+    #[fail(display = "Could not find the primitive cell.")]
+    Primitive,
+}
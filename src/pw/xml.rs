@@ -0,0 +1,364 @@
+//! Reading of the XML data file `<out_dir>/<prefix>.save/data-file-schema.xml` written by pw.x.
+//!
+//! Quantities are given in the units used in the file: lengths in bohr, energies in Hartree,
+//! and k-points in Cartesian coordinates in units of 2pi/alat.
+use std::env;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use roxmltree;
+use roxmltree::Node;
+use pw::input::Input;
+
+/// The default value of `prefix` used by pw.x.
+const DEFAULT_PREFIX: &str = "pwscf";
+
+/// The results of a pw.x run, as given in the `output` element of the data file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataFile {
+    pub species: Vec<Species>,
+    pub structure: Structure,
+    /// The symmetry operations of the crystal.
+    pub symmetries: Vec<Symmetry>,
+    pub band_structure: BandStructure,
+    /// Total energy in Hartree.
+    pub total_energy: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Species {
+    pub name: String,
+    pub mass: Option<f64>,
+    pub pseudo_file: String,
+}
+
+/// The final atomic structure.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Structure {
+    pub alat: f64,
+    /// The `ibrav` value used to construct the cell, if any.
+    pub bravais_index: Option<i64>,
+    /// Lattice vectors in bohr.
+    pub cell: [[f64; 3]; 3],
+    pub atoms: Vec<Atom>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Atom {
+    pub species: String,
+    /// Cartesian position in bohr.
+    pub r: [f64; 3],
+}
+
+/// A symmetry operation `r -> R r + f`, with `R` and `f` given in crystal coordinates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Symmetry {
+    pub rotation: [[i32; 3]; 3],
+    pub fractional_translation: [f64; 3],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BandStructure {
+    pub lsda: bool,
+    pub noncolin: bool,
+    pub spinorbit: bool,
+    /// The number of bands, for each spin in `lsda` calculations.
+    pub nbnd: usize,
+    pub nelec: f64,
+    pub fermi_energy: Option<FermiEnergy>,
+    pub k_points: Vec<KPoint>,
+}
+
+/// Energies in Hartree.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FermiEnergy {
+    Fermi(f64),
+    /// Separate Fermi energies for spin up and down, given when the magnetization is fixed.
+    SpinFermi(f64, f64),
+    /// Given for fixed occupations, possibly together with the lowest unoccupied level.
+    HighestOccupied(f64, Option<f64>),
+}
+
+/// The eigenvalues (in Hartree) and occupations at a k-point. For `lsda` calculations, the
+/// `nbnd` spin up values are followed by the `nbnd` spin down values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KPoint {
+    pub k: [f64; 3],
+    pub weight: f64,
+    pub eigenvalues: Vec<f64>,
+    pub occupations: Vec<f64>,
+}
+
+/// The path of the data file written by pw.x for `input`, relative to the directory pw.x
+/// was run in. The defaults used by pw.x are assumed for `out_dir` and `prefix` if these
+/// are not given; for `out_dir`, this is `$ESPRESSO_TMPDIR` if this is set, and the run
+/// directory otherwise.
+pub fn data_file_path(input: &Input) -> PathBuf {
+    let out_dir = match input.control.out_dir {
+        Some(ref out_dir) => out_dir.clone(),
+        None => match env::var_os("ESPRESSO_TMPDIR") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from("."),
+        },
+    };
+    let prefix = input.control.prefix.as_deref().unwrap_or(DEFAULT_PREFIX);
+
+    out_dir
+        .join(format!("{}.save", prefix))
+        .join("data-file-schema.xml")
+}
+
+/// Read the data file written by pw.x for `input`, which was run in the directory `run_dir`.
+pub fn read_data_file<P: AsRef<Path>>(input: &Input, run_dir: P) -> Result<DataFile, Error> {
+    let mut text = String::new();
+    File::open(run_dir.as_ref().join(data_file_path(input)))?.read_to_string(&mut text)?;
+
+    parse_data_file(&text)
+}
+
+pub fn parse_data_file(text: &str) -> Result<DataFile, Error> {
+    let doc = roxmltree::Document::parse(text)?;
+    let output = child(doc.root_element(), "output")?;
+
+    Ok(DataFile {
+        species: parse_species(child(output, "atomic_species")?)?,
+        structure: parse_structure(child(output, "atomic_structure")?)?,
+        symmetries: parse_symmetries(child(output, "symmetries")?)?,
+        band_structure: parse_band_structure(child(output, "band_structure")?)?,
+        total_energy: number(child(child(output, "total_energy")?, "etot")?)?,
+    })
+}
+
+fn parse_species(node: Node) -> Result<Vec<Species>, Error> {
+    children(node, "species")
+        .map(|species| {
+            Ok(Species {
+                name: String::from(attribute(species, "name")?),
+                mass: match optional_child(species, "mass") {
+                    Some(mass) => Some(number(mass)?),
+                    None => None,
+                },
+                pseudo_file: String::from(text(child(species, "pseudo_file")?).trim()),
+            })
+        })
+        .collect()
+}
+
+fn parse_structure(node: Node) -> Result<Structure, Error> {
+    let alat = attribute(node, "alat")?
+        .parse()
+        .map_err(|_| value_error(node, "invalid `alat`"))?;
+    let bravais_index = match node.attribute("bravais_index") {
+        Some(index) => Some(
+            index
+                .parse()
+                .map_err(|_| value_error(node, "invalid `bravais_index`"))?,
+        ),
+        None => None,
+    };
+
+    let cell_node = child(node, "cell")?;
+    let cell = [
+        vector(child(cell_node, "a1")?)?,
+        vector(child(cell_node, "a2")?)?,
+        vector(child(cell_node, "a3")?)?,
+    ];
+
+    let atoms = children(child(node, "atomic_positions")?, "atom")
+        .map(|atom| {
+            Ok(Atom {
+                species: String::from(attribute(atom, "name")?),
+                r: vector(atom)?,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(Structure {
+        alat,
+        bravais_index,
+        cell,
+        atoms,
+    })
+}
+
+/// The symmetries of the crystal. Those listed as `lattice_symmetry` are symmetries of the
+/// Bravais lattice only, and are skipped.
+fn parse_symmetries(node: Node) -> Result<Vec<Symmetry>, Error> {
+    let mut symmetries = Vec::new();
+
+    for symmetry in children(node, "symmetry") {
+        if text(child(symmetry, "info")?).trim() != "crystal_symmetry" {
+            continue;
+        }
+
+        let rotation_node = child(symmetry, "rotation")?;
+        let values = numbers(rotation_node)?;
+        if values.len() != 9 {
+            return Err(value_error(rotation_node, "expected 9 values"));
+        }
+
+        // The rotation is written in Fortran (column-major) order.
+        let mut rotation = [[0; 3]; 3];
+        for (i, x) in values.iter().enumerate() {
+            rotation[i % 3][i / 3] = x.round() as i32;
+        }
+
+        symmetries.push(Symmetry {
+            rotation,
+            fractional_translation: vector(child(symmetry, "fractional_translation")?)?,
+        });
+    }
+
+    Ok(symmetries)
+}
+
+fn parse_band_structure(node: Node) -> Result<BandStructure, Error> {
+    let fermi_energy = if let Some(ef) = optional_child(node, "fermi_energy") {
+        Some(FermiEnergy::Fermi(number(ef)?))
+    } else if let Some(efs) = optional_child(node, "two_fermi_energies") {
+        let efs = numbers(efs)?;
+        if efs.len() != 2 {
+            return Err(value_error(node, "expected two Fermi energies"));
+        }
+        Some(FermiEnergy::SpinFermi(efs[0], efs[1]))
+    } else if let Some(homo) = optional_child(node, "highestOccupiedLevel") {
+        let lumo = match optional_child(node, "lowestUnoccupiedLevel") {
+            Some(lumo) => Some(number(lumo)?),
+            None => None,
+        };
+        Some(FermiEnergy::HighestOccupied(number(homo)?, lumo))
+    } else {
+        None
+    };
+
+    let k_points = children(node, "ks_energies")
+        .map(|ks| {
+            let k_point = child(ks, "k_point")?;
+            Ok(KPoint {
+                k: vector(k_point)?,
+                weight: attribute(k_point, "weight")?
+                    .parse()
+                    .map_err(|_| value_error(k_point, "invalid `weight`"))?,
+                eigenvalues: numbers(child(ks, "eigenvalues")?)?,
+                occupations: numbers(child(ks, "occupations")?)?,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // For `lsda` calculations, the numbers of spin up and spin down bands are given by
+    // `nbnd_up` and `nbnd_dw` instead of `nbnd`.
+    let lsda = boolean(child(node, "lsda")?)?;
+    let nbnd = if lsda && optional_child(node, "nbnd").is_none() {
+        let up = number(child(node, "nbnd_up")?)? as usize;
+        let down = child(node, "nbnd_dw")?;
+        if number(down)? as usize != up {
+            return Err(value_error(down, "expected `nbnd_dw` equal to `nbnd_up`"));
+        }
+        up
+    } else {
+        number(child(node, "nbnd")?)? as usize
+    };
+
+    Ok(BandStructure {
+        lsda,
+        noncolin: boolean(child(node, "noncolin")?)?,
+        spinorbit: boolean(child(node, "spinorbit")?)?,
+        nbnd,
+        nelec: number(child(node, "nelec")?)?,
+        fermi_energy,
+        k_points,
+    })
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn optional_child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Result<Node<'a, 'input>, Error> {
+    optional_child(node, name).ok_or_else(|| Error::Missing {
+        parent: String::from(node.tag_name().name()),
+        element: String::from(name),
+    })
+}
+
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Result<&'a str, Error> {
+    node.attribute(name)
+        .ok_or_else(|| value_error(node, &format!("missing attribute `{}`", name)))
+}
+
+fn text<'a>(node: Node<'a, '_>) -> &'a str {
+    node.text().unwrap_or("")
+}
+
+fn numbers(node: Node) -> Result<Vec<f64>, Error> {
+    text(node)
+        .split_whitespace()
+        .map(|x| {
+            x.parse()
+                .map_err(|_| value_error(node, &format!("invalid number `{}`", x)))
+        })
+        .collect()
+}
+
+fn number(node: Node) -> Result<f64, Error> {
+    match numbers(node)?.as_slice() {
+        [x] => Ok(*x),
+        _ => Err(value_error(node, "expected a single number")),
+    }
+}
+
+fn vector(node: Node) -> Result<[f64; 3], Error> {
+    match numbers(node)?.as_slice() {
+        [x, y, z] => Ok([*x, *y, *z]),
+        _ => Err(value_error(node, "expected 3 numbers")),
+    }
+}
+
+fn boolean(node: Node) -> Result<bool, Error> {
+    match text(node).trim() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        other => Err(value_error(node, &format!("invalid boolean `{}`", other))),
+    }
+}
+
+fn value_error(node: Node, message: &str) -> Error {
+    Error::Value {
+        element: String::from(node.tag_name().name()),
+        message: String::from(message),
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "{}", _0)]
+    Xml(#[cause] roxmltree::Error),
+    #[fail(display = "element `{}` is missing from `{}`", element, parent)]
+    Missing { parent: String, element: String },
+    #[fail(display = "in element `{}`: {}", element, message)]
+    Value { element: String, message: String },
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(e: roxmltree::Error) -> Error {
+        Error::Xml(e)
+    }
+}
@@ -2,29 +2,37 @@ use std::path::Path;
 use std::io;
 use std::io::Write;
 use std::fs::File;
-use serialize_util::push_bool_field;
+use namelist;
 use pw2wannier90::input::Input;
 
-pub fn make_input_file(input: &Input) -> Result<String, Error> {
-    let mut lines = Vec::new();
-    lines.push(String::from(" &inputpp"));
-
-    lines.push(format!("   prefix='{}',", input.prefix));
-
-    if let Some(ref out_dir) = input.out_dir {
-        let path = out_dir.to_str().ok_or(Error::OutDir)?;
-        lines.push(format!("   out_dir='{}',", path));
-    }
-
-    lines.push(format!("   seedname='{}',", input.seedname));
+/// The `inputpp` namelist, with fields as they appear in the file.
+#[derive(Serialize)]
+struct Inputpp<'a> {
+    prefix: &'a str,
+    outdir: Option<&'a str>,
+    seedname: &'a str,
+    write_unk: bool,
+    write_amn: bool,
+    write_mmn: bool,
+    write_spn: bool,
+}
 
-    push_bool_field(&mut lines, "write_unk", Some(input.write_unk));
-    push_bool_field(&mut lines, "write_amn", Some(input.write_amn));
-    push_bool_field(&mut lines, "write_mmn", Some(input.write_mmn));
-    push_bool_field(&mut lines, "write_spn", Some(input.write_spn));
+pub fn make_input_file(input: &Input) -> Result<String, Error> {
+    let inputpp = Inputpp {
+        prefix: &input.prefix,
+        outdir: input
+            .out_dir
+            .as_ref()
+            .map(|path| path.to_str().ok_or(Error::OutDir))
+            .transpose()?,
+        seedname: &input.seedname,
+        write_unk: input.write_unk,
+        write_amn: input.write_amn,
+        write_mmn: input.write_mmn,
+        write_spn: input.write_spn,
+    };
 
-    lines.push(String::from(" /"));
-    Ok(lines.join("\n"))
+    Ok(namelist::to_string("inputpp", &inputpp)?)
 }
 
 pub fn write_input_file<P: AsRef<Path>>(input: &Input, file_path: P) -> Result<(), Error> {
@@ -40,6 +48,7 @@ pub fn write_input_file<P: AsRef<Path>>(input: &Input, file_path: P) -> Result<(
 pub enum Error {
     #[fail(display = "{}", _0)] Io(#[cause] io::Error),
     #[fail(display = "`out_dir` is not valid UTF-8")] OutDir,
+    #[fail(display = "{}", _0)] Namelist(#[cause] namelist::Error),
 }
 
 impl From<io::Error> for Error {
@@ -47,3 +56,9 @@ impl From<io::Error> for Error {
         Error::Io(e)
     }
 }
+
+impl From<namelist::Error> for Error {
+    fn from(e: namelist::Error) -> Error {
+        Error::Namelist(e)
+    }
+}
@@ -0,0 +1,175 @@
+//! Running the QE programs on inputs given by this crate.
+//!
+//! Each run writes the input file `<name>.in` into the run directory, launches the program
+//! there with `-in <name>.in`, and streams its standard output to `<name>.out`. The input is
+//! not given on standard input, which MPI launchers do not reliably forward to the program.
+//! The program's results are then read back from the output files.
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use bands;
+use pw;
+use pw2wannier90;
+
+/// The default value of `filband` used by bands.x.
+const DEFAULT_FILBAND: &str = "bands.out";
+
+/// How to launch one of the QE programs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Program {
+    /// Path of the executable. A bare name such as `pw.x` is searched for in `PATH`.
+    pub path: PathBuf,
+    /// Command which launches the executable, such as `["mpirun", "-np", "4"]` or `["srun"]`.
+    /// If empty, the executable is run directly.
+    pub prefix: Vec<String>,
+}
+
+/// Run pw.x on `input` in `run_dir` and read the results from its standard output.
+pub fn run_pw<P: AsRef<Path>>(
+    program: &Program,
+    input: &pw::input::Input,
+    run_dir: P,
+    name: &str,
+) -> Result<pw::output::Output, Error> {
+    let run_dir = run_dir.as_ref();
+    let (input_path, log_path) = file_paths(run_dir, name);
+
+    pw::serialize::write_input_file(input, &input_path).map_err(Error::PwInput)?;
+
+    if let Err(e) = launch(program, run_dir, &input_path, &log_path) {
+        // pw.x reports its errors on standard output, which is more informative than
+        // the exit status alone.
+        return match pw::output::read_output_file(&log_path, &input.calculation) {
+            Err(output_error @ pw::output::Error::Pw { .. }) => Err(Error::PwOutput(output_error)),
+            _ => Err(e),
+        };
+    }
+
+    pw::output::read_output_file(&log_path, &input.calculation).map_err(Error::PwOutput)
+}
+
+/// Run bands.x on `input` in `run_dir` and read the band structure it writes to `filband`.
+///
+/// `k_points` are those of the pw.x `bands` calculation which bands.x is run after, and
+/// `path` the `KPath` they were generated from, if any.
+pub fn run_bands<P: AsRef<Path>>(
+    program: &Program,
+    input: &bands::input::Input,
+    k_points: &pw::input::KPoints,
+    path: Option<&pw::kpath::KPath>,
+    run_dir: P,
+    name: &str,
+) -> Result<bands::output::BandStructure, Error> {
+    let run_dir = run_dir.as_ref();
+    let (input_path, log_path) = file_paths(run_dir, name);
+
+    bands::serialize::write_input_file(input, &input_path).map_err(Error::BandsInput)?;
+    launch(program, run_dir, &input_path, &log_path)?;
+
+    let filband = match input.filband {
+        Some(ref filband) => run_dir.join(filband),
+        None => run_dir.join(DEFAULT_FILBAND),
+    };
+
+    bands::output::read_filband(filband, k_points, path).map_err(Error::BandsOutput)
+}
+
+/// Run pw2wannier90.x on `input` in `run_dir`. The results are written to the
+/// `seedname.*` files requested in `input`.
+pub fn run_pw2wannier90<P: AsRef<Path>>(
+    program: &Program,
+    input: &pw2wannier90::input::Input,
+    run_dir: P,
+    name: &str,
+) -> Result<(), Error> {
+    let run_dir = run_dir.as_ref();
+    let (input_path, log_path) = file_paths(run_dir, name);
+
+    pw2wannier90::serialize::write_input_file(input, &input_path)
+        .map_err(Error::Pw2Wannier90Input)?;
+    launch(program, run_dir, &input_path, &log_path)
+}
+
+/// The paths of the input file and standard output log for the run `name`.
+pub fn file_paths(run_dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+    (
+        run_dir.join(format!("{}.in", name)),
+        run_dir.join(format!("{}.out", name)),
+    )
+}
+
+fn launch(
+    program: &Program,
+    run_dir: &Path,
+    input_path: &Path,
+    log_path: &Path,
+) -> Result<(), Error> {
+    let mut command = match program.prefix.split_first() {
+        Some((launcher, args)) => {
+            let mut command = process::Command::new(launcher);
+            command.args(args).arg(&program.path);
+            command
+        }
+        None => process::Command::new(&program.path),
+    };
+
+    // The input file is in the run directory, which the program is run in.
+    let input_name = input_path.file_name().unwrap_or(input_path.as_os_str());
+
+    let output = command
+        .arg("-in")
+        .arg(input_name)
+        .current_dir(run_dir)
+        .stdin(process::Stdio::null())
+        .stdout(File::create(log_path)?)
+        .output()
+        .map_err(|e| Error::Launch {
+            program: program.path.display().to_string(),
+            cause: e,
+        })?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::Exit {
+            program: program.path.display().to_string(),
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "failed to launch {}: {}", program, cause)]
+    Launch {
+        program: String,
+        #[cause]
+        cause: io::Error,
+    },
+    #[fail(display = "{} exited with status {:?}: {}", program, status, stderr)]
+    Exit {
+        program: String,
+        status: Option<i32>,
+        stderr: String,
+    },
+    #[fail(display = "{}", _0)]
+    PwInput(#[cause] pw::serialize::Error),
+    #[fail(display = "{}", _0)]
+    PwOutput(#[cause] pw::output::Error),
+    #[fail(display = "{}", _0)]
+    BandsInput(#[cause] bands::serialize::Error),
+    #[fail(display = "{}", _0)]
+    BandsOutput(#[cause] bands::output::Error),
+    #[fail(display = "{}", _0)]
+    Pw2Wannier90Input(#[cause] pw2wannier90::serialize::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,497 @@
+//! Crystallographic Information Files (CIF).
+//!
+//! Only the first data block is read. The asymmetric unit given by the `_atom_site_` loop is
+//! expanded by the symmetry operations given as `_space_group_symop_operation_xyz` or
+//! `_symmetry_equiv_pos_as_xyz`; if neither is present, the atoms are taken as given.
+//!
+//! Files are written with all atoms listed explicitly, in space group P1.
+use std::collections::HashMap;
+use std::path::Path;
+use pw::input::{AtomCoordinate, Cell, Input, LatticeUnits, PositionCoordinateType, Positions};
+use pw::lattice::{cartesian_to_crystal, crystal_to_cartesian, dot, norm};
+use structure::{
+    cell_from_parameters, element_symbol, input_structure, parse_f64, species_of, title,
+    write_text, Error, Structure,
+};
+
+/// Atoms of the same species generated by the symmetry operations are the same atom if they
+/// are closer than this distance in Angstrom.
+const DUPLICATE_TOLERANCE: f64 = 1e-3;
+
+const SYMMETRY_TAGS: [&str; 2] = [
+    "_space_group_symop_operation_xyz",
+    "_symmetry_equiv_pos_as_xyz",
+];
+
+/// Parse a CIF file into a structure with the lattice vectors in Angstrom, in the standard
+/// orientation given by `structure::cell_from_parameters`, and positions given as
+/// `Crystal`. The species of each atom is the element symbol given by
+/// `_atom_site_type_symbol`, or by `_atom_site_label` if there is no type symbol.
+pub fn parse(text: &str) -> Result<Structure, Error> {
+    let block = read_block(text)?;
+
+    let a = block.number("_cell_length_a")?;
+    let b = block.number("_cell_length_b")?;
+    let c = block.number("_cell_length_c")?;
+    let alpha = block.number("_cell_angle_alpha")?;
+    let beta = block.number("_cell_angle_beta")?;
+    let gamma = block.number("_cell_angle_gamma")?;
+    let cell = cell_from_parameters(a, b, c, alpha, beta, gamma);
+
+    let operations = match block.column_or_item(&SYMMETRY_TAGS) {
+        Some(values) => values
+            .iter()
+            .map(|&(line, ref value)| parse_operation(line, value))
+            .collect::<Result<Vec<Operation>, Error>>()?,
+        None => vec![Operation {
+            rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            translation: [0.0; 3],
+        }],
+    };
+
+    let sites = block
+        .loops
+        .iter()
+        .find(|l| {
+            l.tags
+                .iter()
+                .any(|tag| tag == "_atom_site_fract_x" || tag == "_atom_site_cartn_x")
+        })
+        .ok_or_else(|| Error::Missing(String::from("_atom_site_fract_x")))?;
+    let cartesian = !sites.has("_atom_site_fract_x");
+    let coordinate_tags = if cartesian {
+        [
+            "_atom_site_cartn_x",
+            "_atom_site_cartn_y",
+            "_atom_site_cartn_z",
+        ]
+    } else {
+        [
+            "_atom_site_fract_x",
+            "_atom_site_fract_y",
+            "_atom_site_fract_z",
+        ]
+    };
+
+    let mut coordinates: Vec<AtomCoordinate> = Vec::new();
+    for row in &sites.rows {
+        let label = sites
+            .get(row, "_atom_site_type_symbol")
+            .or_else(|| sites.get(row, "_atom_site_label"))
+            .ok_or_else(|| Error::Missing(String::from("_atom_site_label")))?;
+        let species = element_symbol(&label.1).ok_or_else(|| Error::Value {
+            line: label.0,
+            field: String::from("_atom_site_type_symbol"),
+            message: format!("no element symbol in `{}`", label.1),
+        })?;
+
+        let mut x = [0.0; 3];
+        for (x, &tag) in x.iter_mut().zip(&coordinate_tags) {
+            let &(line, ref value) = sites
+                .get(row, tag)
+                .ok_or_else(|| Error::Missing(String::from(tag)))?;
+            *x = parse_number(line, tag, value)?;
+        }
+        if cartesian {
+            x = cartesian_to_crystal(&cell, x);
+        }
+
+        for op in &operations {
+            let image = op.apply(x);
+            let duplicate = coordinates.iter().any(|atom| {
+                atom.species == species && distance(&cell, atom.r, image) < DUPLICATE_TOLERANCE
+            });
+            if !duplicate {
+                coordinates.push(AtomCoordinate {
+                    species: species.clone(),
+                    r: image,
+                    if_pos: None,
+                });
+            }
+        }
+    }
+
+    let positions = Positions {
+        coordinate_type: PositionCoordinateType::Crystal,
+        coordinates,
+    };
+
+    Ok(Structure {
+        cell: Cell {
+            units: LatticeUnits::Angstrom,
+            cell,
+        },
+        species: species_of(&positions),
+        positions,
+    })
+}
+
+/// The CIF file for the structure of `input`, with the cell lengths in Angstrom.
+///
+/// Each atom is labelled by its species and its index among the atoms of that species, and
+/// its type symbol is the element symbol of its species.
+pub fn make_file(input: &Input) -> Result<String, Error> {
+    let (cell, atoms) = input_structure(input)?;
+    let angle = |u: [f64; 3], v: [f64; 3]| (dot(u, v) / (norm(u) * norm(v))).acos().to_degrees();
+    let mut lines = Vec::new();
+
+    lines.push(format!("data_{}", title(input)));
+    lines.push(String::from("_symmetry_space_group_name_H-M 'P 1'"));
+    lines.push(String::from("_symmetry_Int_Tables_number 1"));
+    lines.push(format!("_cell_length_a {}", norm(cell[0])));
+    lines.push(format!("_cell_length_b {}", norm(cell[1])));
+    lines.push(format!("_cell_length_c {}", norm(cell[2])));
+    lines.push(format!("_cell_angle_alpha {}", angle(cell[1], cell[2])));
+    lines.push(format!("_cell_angle_beta {}", angle(cell[0], cell[2])));
+    lines.push(format!("_cell_angle_gamma {}", angle(cell[0], cell[1])));
+
+    lines.push(String::from("loop_"));
+    lines.push(String::from("_symmetry_equiv_pos_as_xyz"));
+    lines.push(String::from(" 'x, y, z'"));
+
+    lines.push(String::from("loop_"));
+    lines.push(String::from("_atom_site_label"));
+    lines.push(String::from("_atom_site_type_symbol"));
+    lines.push(String::from("_atom_site_fract_x"));
+    lines.push(String::from("_atom_site_fract_y"));
+    lines.push(String::from("_atom_site_fract_z"));
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (species, x) in atoms {
+        let count = counts.entry(species).or_insert(0);
+        *count += 1;
+        let symbol = element_symbol(species).unwrap_or_else(|| String::from(species));
+        lines.push(format!(
+            " {}{} {} {} {} {}",
+            species, count, symbol, x[0], x[1], x[2]
+        ));
+    }
+
+    Ok(lines.join("\n") + "\n")
+}
+
+pub fn write_file<P: AsRef<Path>>(input: &Input, file_path: P) -> Result<(), Error> {
+    write_text(&make_file(input)?, file_path)
+}
+
+/// A symmetry operation acting on fractional coordinates `x` as `rotation x + translation`.
+struct Operation {
+    rotation: [[f64; 3]; 3],
+    translation: [f64; 3],
+}
+
+impl Operation {
+    /// The image of `x`, brought into [0, 1).
+    fn apply(&self, x: [f64; 3]) -> [f64; 3] {
+        let mut image = [0.0; 3];
+        for (i, r) in image.iter_mut().enumerate() {
+            *r = self.translation[i] + (0..3).map(|j| self.rotation[i][j] * x[j]).sum::<f64>();
+            *r -= r.floor();
+            if *r > 1.0 - 1e-8 {
+                *r = 0.0;
+            }
+        }
+
+        image
+    }
+}
+
+/// Parse an operation such as `-x+1/2, y-x, z+0.25`.
+fn parse_operation(line: usize, text: &str) -> Result<Operation, Error> {
+    let error = || Error::Value {
+        line,
+        field: String::from("symmetry operation"),
+        message: format!("cannot parse `{}`", text),
+    };
+
+    let components: Vec<&str> = text.split(',').collect();
+    if components.len() != 3 {
+        return Err(error());
+    }
+
+    let mut op = Operation {
+        rotation: [[0.0; 3]; 3],
+        translation: [0.0; 3],
+    };
+    for (i, component) in components.iter().enumerate() {
+        let component: String = component
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_lowercase();
+
+        // Split into signed terms, such as `-x`, `+1/2` or `2y`.
+        let mut terms = Vec::new();
+        let mut start = 0;
+        for (j, c) in component.char_indices() {
+            if (c == '+' || c == '-') && j > start {
+                terms.push(&component[start..j]);
+                start = j;
+            }
+        }
+        terms.push(&component[start..]);
+
+        for term in terms {
+            let (sign, term) = match term.chars().next() {
+                Some('-') => (-1.0, &term[1..]),
+                Some('+') => (1.0, &term[1..]),
+                _ => (1.0, term),
+            };
+            let axis = match term.chars().last() {
+                Some('x') => Some(0),
+                Some('y') => Some(1),
+                Some('z') => Some(2),
+                _ => None,
+            };
+            match axis {
+                Some(axis) => {
+                    let coefficient = &term[..term.len() - 1];
+                    let coefficient = if coefficient.is_empty() {
+                        1.0
+                    } else {
+                        parse_fraction(coefficient).ok_or_else(error)?
+                    };
+                    op.rotation[i][axis] += sign * coefficient;
+                }
+                None => op.translation[i] += sign * parse_fraction(term).ok_or_else(error)?,
+            }
+        }
+    }
+
+    Ok(op)
+}
+
+fn parse_fraction(text: &str) -> Option<f64> {
+    match text.find('/') {
+        Some(i) => {
+            let numerator: f64 = text[..i].parse().ok()?;
+            let denominator: f64 = text[i + 1..].parse().ok()?;
+            Some(numerator / denominator)
+        }
+        None => text.parse().ok(),
+    }
+}
+
+/// Parse a number, ignoring a standard uncertainty given in parentheses as in `5.431(2)`.
+fn parse_number(line: usize, field: &str, value: &str) -> Result<f64, Error> {
+    let value = match value.find('(') {
+        Some(i) => &value[..i],
+        None => value,
+    };
+
+    parse_f64(line, field, value)
+}
+
+/// The Cartesian distance between the fractional coordinates `x` and `y`, up to a lattice
+/// vector.
+fn distance(cell: &[[f64; 3]; 3], x: [f64; 3], y: [f64; 3]) -> f64 {
+    let mut d = [0.0; 3];
+    for i in 0..3 {
+        d[i] = x[i] - y[i];
+        d[i] -= d[i].round();
+    }
+    let r = crystal_to_cartesian(cell, d);
+
+    (r[0] * r[0] + r[1] * r[1] + r[2] * r[2]).sqrt()
+}
+
+/// The data items of a block, with the line on which each value is given. Tags are stored in
+/// lowercase, since they are case-insensitive.
+struct Block {
+    items: HashMap<String, (usize, String)>,
+    loops: Vec<Loop>,
+}
+
+struct Loop {
+    tags: Vec<String>,
+    rows: Vec<Vec<(usize, String)>>,
+}
+
+impl Block {
+    fn number(&self, tag: &str) -> Result<f64, Error> {
+        let &(line, ref value) = self
+            .items
+            .get(tag)
+            .ok_or_else(|| Error::Missing(String::from(tag)))?;
+
+        parse_number(line, tag, value)
+    }
+
+    /// The values of the first of `tags` which is given, either as a loop or a single item.
+    fn column_or_item(&self, tags: &[&str]) -> Option<Vec<(usize, String)>> {
+        for &tag in tags {
+            if let Some(l) = self.loops.iter().find(|l| l.has(tag)) {
+                return Some(
+                    l.rows
+                        .iter()
+                        .filter_map(|row| l.get(row, tag).cloned())
+                        .collect(),
+                );
+            }
+            if let Some(item) = self.items.get(tag) {
+                return Some(vec![item.clone()]);
+            }
+        }
+
+        None
+    }
+}
+
+impl Loop {
+    fn has(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// The value of `tag` in `row`, unless it is unknown (`?`) or inapplicable (`.`).
+    fn get<'a>(&self, row: &'a [(usize, String)], tag: &str) -> Option<&'a (usize, String)> {
+        let i = self.tags.iter().position(|t| t == tag)?;
+        row.get(i)
+            .filter(|&(_, value)| value != "?" && value != ".")
+    }
+}
+
+struct Token {
+    line: usize,
+    text: String,
+    /// Quoted strings and text fields are always values, even if they look like keywords.
+    quoted: bool,
+}
+
+fn read_block(text: &str) -> Result<Block, Error> {
+    let tokens = tokenize(text)?;
+    let is_tag = |token: &Token| !token.quoted && token.text.starts_with('_');
+    let is_keyword = |token: &Token, keyword: &str| {
+        !token.quoted && token.text.to_lowercase().starts_with(keyword)
+    };
+
+    let mut block = Block {
+        items: HashMap::new(),
+        loops: Vec::new(),
+    };
+    let mut in_block = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if is_keyword(token, "data_") {
+            if in_block {
+                break;
+            }
+            in_block = true;
+            i += 1;
+        } else if is_keyword(token, "loop_") {
+            i += 1;
+            let mut tags = Vec::new();
+            while i < tokens.len() && is_tag(&tokens[i]) {
+                tags.push(tokens[i].text.to_lowercase());
+                i += 1;
+            }
+            let mut values = Vec::new();
+            while i < tokens.len()
+                && !is_tag(&tokens[i])
+                && !is_keyword(&tokens[i], "loop_")
+                && !is_keyword(&tokens[i], "data_")
+            {
+                values.push((tokens[i].line, tokens[i].text.clone()));
+                i += 1;
+            }
+            if tags.is_empty() || values.len() % tags.len() != 0 {
+                return Err(Error::Syntax {
+                    line: token.line,
+                    message: String::from("number of loop values is not a multiple of the tags"),
+                });
+            }
+            let rows = values.chunks(tags.len()).map(|row| row.to_vec()).collect();
+            block.loops.push(Loop { tags, rows });
+        } else if is_tag(token) {
+            let value = tokens.get(i + 1).ok_or_else(|| Error::Syntax {
+                line: token.line,
+                message: format!("no value for `{}`", token.text),
+            })?;
+            block
+                .items
+                .insert(token.text.to_lowercase(), (value.line, value.text.clone()));
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+
+    if !in_block {
+        return Err(Error::Missing(String::from("data_")));
+    }
+
+    Ok(block)
+}
+
+/// Split the text into whitespace-separated tokens, quoted strings and `;`-delimited text
+/// fields, dropping comments.
+fn tokenize(text: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    while let Some((line, text)) = lines.next() {
+        if let Some(first) = text.strip_prefix(';') {
+            let mut field = String::from(first);
+            loop {
+                match lines.next() {
+                    Some((_, text)) if text.starts_with(';') => break,
+                    Some((_, text)) => {
+                        field.push('\n');
+                        field.push_str(text);
+                    }
+                    None => {
+                        return Err(Error::Syntax {
+                            line,
+                            message: String::from("unterminated text field"),
+                        })
+                    }
+                }
+            }
+            tokens.push(Token {
+                line,
+                text: field,
+                quoted: true,
+            });
+            continue;
+        }
+
+        let mut rest = text.trim_start();
+        while !rest.is_empty() {
+            if rest.starts_with('#') {
+                break;
+            }
+            let quote = rest.chars().next().filter(|&c| c == '\'' || c == '"');
+            let (token, remainder) = match quote {
+                Some(quote) => {
+                    // A closing quote must be followed by whitespace or the end of the line.
+                    let end = rest[1..]
+                        .char_indices()
+                        .find(|&(j, c)| {
+                            c == quote
+                                && rest[1 + j + 1..]
+                                    .chars()
+                                    .next()
+                                    .is_none_or(char::is_whitespace)
+                        })
+                        .map(|(j, _)| 1 + j)
+                        .ok_or_else(|| Error::Syntax {
+                            line,
+                            message: String::from("unterminated quoted string"),
+                        })?;
+                    (&rest[1..end], &rest[end + 1..])
+                }
+                None => {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            tokens.push(Token {
+                line,
+                text: String::from(token),
+                quoted: quote.is_some(),
+            });
+            rest = remainder.trim_start();
+        }
+    }
+
+    Ok(tokens)
+}
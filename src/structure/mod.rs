@@ -0,0 +1,172 @@
+//! Crystal structures in the file formats used by structure databases and visualization
+//! programs, read into the `Cell` and `Positions` of a pw.x input, and written from the
+//! structure of a pw.x `Input`.
+pub mod cif;
+pub mod poscar;
+pub mod xsf;
+pub mod xyz;
+
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::num::ParseFloatError;
+use std::path::Path;
+use pw::input::{Cell, Input, LatticeUnits, PositionCoordinateType, Positions};
+use pw::lattice::lattice_vectors;
+
+/// A structure read from a file. Lengths are given in the units of the file, which for all
+/// supported formats are Angstrom.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Structure {
+    pub cell: Cell,
+    pub positions: Positions,
+    /// The species of `positions`, in order of first appearance.
+    pub species: Vec<String>,
+}
+
+/// Read a structure, with the format given by the file name: `*.cif`, `*.xyz`, or
+/// `POSCAR`, `CONTCAR` and `*.vasp` for VASP structures.
+pub fn read_file<P: AsRef<Path>>(file_path: P) -> Result<Structure, Error> {
+    let file_path = file_path.as_ref();
+    let name = file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let extension = file_path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+
+    let mut text = String::new();
+    File::open(file_path)?.read_to_string(&mut text)?;
+
+    match extension.as_deref() {
+        Some("cif") => cif::parse(&text),
+        Some("xyz") => xyz::parse(&text, None),
+        Some("vasp") | Some("poscar") => poscar::parse(&text),
+        _ if name.starts_with("poscar") || name.starts_with("contcar") => poscar::parse(&text),
+        _ => Err(Error::Format(file_path.display().to_string())),
+    }
+}
+
+/// Lattice vectors with lengths `a`, `b`, `c` and angles `alpha`, `beta`, `gamma` in degrees,
+/// in the standard orientation: `a` along x, and `b` in the xy plane.
+pub fn cell_from_parameters(
+    a: f64,
+    b: f64,
+    c: f64,
+    alpha: f64,
+    beta: f64,
+    gamma: f64,
+) -> [[f64; 3]; 3] {
+    let (cos_alpha, cos_beta) = (alpha.to_radians().cos(), beta.to_radians().cos());
+    let (cos_gamma, sin_gamma) = (gamma.to_radians().cos(), gamma.to_radians().sin());
+
+    let cx = cos_beta;
+    let cy = (cos_alpha - cos_beta * cos_gamma) / sin_gamma;
+    let cz = (1.0 - cx * cx - cy * cy).sqrt();
+
+    [
+        [a, 0.0, 0.0],
+        [b * cos_gamma, b * sin_gamma, 0.0],
+        [c * cx, c * cy, c * cz],
+    ]
+}
+
+/// The element symbol at the start of a label such as `Fe1`, `O2-` or `Si_a`: an uppercase
+/// letter optionally followed by a lowercase letter.
+pub fn element_symbol(label: &str) -> Option<String> {
+    let mut chars = label.chars();
+    let first = chars.next().filter(|c| c.is_ascii_alphabetic())?;
+
+    let mut symbol = first.to_ascii_uppercase().to_string();
+    if let Some(second) = chars.next().filter(|c| c.is_ascii_lowercase()) {
+        symbol.push(second);
+    }
+
+    Some(symbol)
+}
+
+/// The species and crystal coordinates of each atom.
+type Atoms<'a> = Vec<(&'a str, [f64; 3])>;
+
+/// The lattice vectors of `input` in Angstrom, and its atoms.
+fn input_structure(input: &Input) -> Result<([[f64; 3]; 3], Atoms<'_>), Error> {
+    let system = &input.system;
+    let cell_bohr = lattice_vectors(&system.ibrav, system.alat);
+
+    let positions = &input.atomic_positions;
+    let crystal = positions
+        .to_coordinate_type(PositionCoordinateType::Crystal, &cell_bohr, system.alat)
+        .map_err(|_| Error::CoordinateType)?;
+    let atoms = positions
+        .coordinates
+        .iter()
+        .zip(crystal.coordinates)
+        .map(|(atom, crystal)| (atom.species.as_str(), crystal.r))
+        .collect();
+
+    let cell = Cell {
+        units: LatticeUnits::Bohr,
+        cell: cell_bohr,
+    };
+    let cell = cell.to_units(LatticeUnits::Angstrom, system.alat).cell;
+
+    Ok((cell, atoms))
+}
+
+/// The name of the structure: the `prefix` of `input`, or the pw.x default.
+fn title(input: &Input) -> &str {
+    input.control.prefix.as_deref().unwrap_or("pwscf")
+}
+
+fn write_text<P: AsRef<Path>>(text: &str, file_path: P) -> Result<(), Error> {
+    let mut file = File::create(file_path)?;
+    file.write_all(text.as_bytes())?;
+
+    Ok(())
+}
+
+fn species_of(positions: &Positions) -> Vec<String> {
+    let mut species: Vec<String> = Vec::new();
+    for atom in &positions.coordinates {
+        if !species.contains(&atom.species) {
+            species.push(atom.species.clone());
+        }
+    }
+
+    species
+}
+
+fn parse_f64(line: usize, field: &str, value: &str) -> Result<f64, Error> {
+    value.parse().map_err(|e: ParseFloatError| Error::Value {
+        line,
+        field: String::from(field),
+        message: e.to_string(),
+    })
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "unrecognized structure file format: {}", _0)]
+    Format(String),
+    #[fail(display = "line {}: invalid value for `{}`: {}", line, field, message)]
+    Value {
+        line: usize,
+        field: String,
+        message: String,
+    },
+    #[fail(display = "required field `{}` is missing", _0)]
+    Missing(String),
+    #[fail(display = "line {}: {}", line, message)]
+    Syntax { line: usize, message: String },
+    #[fail(display = "Conversion of `crystal_sg` positions is not supported.")]
+    CoordinateType,
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
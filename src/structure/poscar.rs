@@ -0,0 +1,235 @@
+//! VASP `POSCAR` and `CONTCAR` files.
+//!
+//! Both the VASP 5 format, with a line of species names above the line of atom counts, and
+//! the VASP 4 format, with species names given instead by the first line, are read. Files
+//! are written in the VASP 5 format.
+use std::path::Path;
+use pw::input::{AtomCoordinate, Cell, Input, LatticeUnits, PositionCoordinateType, Positions};
+use pw::lattice::determinant;
+use structure::{
+    element_symbol, input_structure, parse_f64, species_of, title, write_text, Error, Structure,
+};
+
+/// Parse a POSCAR file. The lattice vectors and Cartesian positions are multiplied by the
+/// scaling factor, and given in Angstrom; direct positions are given as `Crystal`.
+///
+/// Selective dynamics flags are given by `if_pos`, with `T` corresponding to `true`.
+pub fn parse(text: &str) -> Result<Structure, Error> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+    let mut next_line = |field: &str| {
+        lines
+            .next()
+            .ok_or_else(|| Error::Missing(String::from(field)))
+    };
+
+    let (_, comment) = next_line("comment")?;
+
+    let (scale_line, scale) = next_line("scaling factor")?;
+    let scale = first_values(scale_line, "scaling factor", scale, 1)?[0];
+
+    let mut cell = [[0.0; 3]; 3];
+    for vector in cell.iter_mut() {
+        let (line, text) = next_line("lattice vectors")?;
+        let values = first_values(line, "lattice vectors", text, 3)?;
+        vector.copy_from_slice(&values);
+    }
+
+    // A negative scaling factor gives the cell volume.
+    let scale = if scale < 0.0 {
+        (-scale / determinant(&cell).abs()).cbrt()
+    } else {
+        scale
+    };
+    for x in cell.iter_mut().flat_map(|vector| vector.iter_mut()) {
+        *x *= scale;
+    }
+
+    let (mut line, mut text) = next_line("atom counts")?;
+    let names: Vec<String> = if starts_with_number(text) {
+        comment.split_whitespace().map(String::from).collect()
+    } else {
+        let names = text.split_whitespace().map(String::from).collect();
+        let (counts_line, counts_text) = next_line("atom counts")?;
+        line = counts_line;
+        text = counts_text;
+        names
+    };
+    let counts = text
+        .split_whitespace()
+        .map(|count| {
+            count.parse::<usize>().map_err(|e| Error::Value {
+                line,
+                field: String::from("atom counts"),
+                message: e.to_string(),
+            })
+        })
+        .collect::<Result<Vec<usize>, Error>>()?;
+    if names.len() < counts.len() {
+        return Err(Error::Missing(String::from("species names")));
+    }
+
+    let (mut line, mut text) = next_line("coordinate type")?;
+    let selective = text.trim_start().starts_with(['S', 's']);
+    if selective {
+        let (type_line, type_text) = next_line("coordinate type")?;
+        line = type_line;
+        text = type_text;
+    }
+    let direct = match text.trim_start().chars().next() {
+        Some('D') | Some('d') => true,
+        Some('C') | Some('c') | Some('K') | Some('k') => false,
+        _ => {
+            return Err(Error::Value {
+                line,
+                field: String::from("coordinate type"),
+                message: format!("expected `Direct` or `Cartesian`, found `{}`", text.trim()),
+            })
+        }
+    };
+
+    let mut coordinates = Vec::new();
+    for (name, &count) in names.iter().zip(&counts) {
+        for _ in 0..count {
+            let (line, text) = next_line("positions")?;
+            let values = first_values(line, "positions", text, 3)?;
+            let r = if direct {
+                [values[0], values[1], values[2]]
+            } else {
+                [values[0] * scale, values[1] * scale, values[2] * scale]
+            };
+
+            let if_pos = if selective {
+                let flags: Vec<&str> = text.split_whitespace().skip(3).take(3).collect();
+                if flags.len() < 3 {
+                    return Err(Error::Missing(String::from("selective dynamics flags")));
+                }
+                let mut if_pos = [true; 3];
+                for (flag, value) in if_pos.iter_mut().zip(flags) {
+                    *flag = match value {
+                        "T" | "t" => true,
+                        "F" | "f" => false,
+                        _ => {
+                            return Err(Error::Value {
+                                line,
+                                field: String::from("selective dynamics flags"),
+                                message: format!("expected `T` or `F`, found `{}`", value),
+                            })
+                        }
+                    };
+                }
+                Some(if_pos)
+            } else {
+                None
+            };
+
+            coordinates.push(AtomCoordinate {
+                species: name.clone(),
+                r,
+                if_pos,
+            });
+        }
+    }
+
+    let positions = Positions {
+        coordinate_type: if direct {
+            PositionCoordinateType::Crystal
+        } else {
+            PositionCoordinateType::AngstromCartesian
+        },
+        coordinates,
+    };
+
+    Ok(Structure {
+        cell: Cell {
+            units: LatticeUnits::Angstrom,
+            cell,
+        },
+        species: species_of(&positions),
+        positions,
+    })
+}
+
+/// The POSCAR file for the structure of `input`, with the lattice vectors in Angstrom and
+/// direct positions.
+///
+/// Atoms are grouped by species, in order of first appearance, and each group is named by
+/// the element symbol of its species. If any atom gives `if_pos`, selective dynamics flags
+/// are written for all atoms, with atoms not giving `if_pos` free to move.
+pub fn make_file(input: &Input) -> Result<String, Error> {
+    let (cell, atoms) = input_structure(input)?;
+    let species = species_of(&input.atomic_positions);
+    let coordinates = &input.atomic_positions.coordinates;
+    let selective = coordinates.iter().any(|atom| atom.if_pos.is_some());
+    let mut lines = Vec::new();
+
+    lines.push(String::from(title(input)));
+    lines.push(String::from("1.0"));
+    for a in cell.iter() {
+        lines.push(format!(" {} {} {}", a[0], a[1], a[2]));
+    }
+
+    let names: Vec<String> = species
+        .iter()
+        .map(|s| element_symbol(s).unwrap_or_else(|| s.clone()))
+        .collect();
+    let counts: Vec<String> = species
+        .iter()
+        .map(|s| {
+            atoms
+                .iter()
+                .filter(|&&(species, _)| species == s)
+                .count()
+                .to_string()
+        })
+        .collect();
+    lines.push(names.join(" "));
+    lines.push(counts.join(" "));
+
+    if selective {
+        lines.push(String::from("Selective dynamics"));
+    }
+    lines.push(String::from("Direct"));
+    for s in &species {
+        for (atom, &(_, x)) in coordinates.iter().zip(&atoms) {
+            if &atom.species != s {
+                continue;
+            }
+            let mut line = format!(" {} {} {}", x[0], x[1], x[2]);
+            if selective {
+                let if_pos = atom.if_pos.unwrap_or([true; 3]);
+                for &flag in &if_pos {
+                    line.push_str(if flag { " T" } else { " F" });
+                }
+            }
+            lines.push(line);
+        }
+    }
+
+    Ok(lines.join("\n") + "\n")
+}
+
+pub fn write_file<P: AsRef<Path>>(input: &Input, file_path: P) -> Result<(), Error> {
+    write_text(&make_file(input)?, file_path)
+}
+
+fn starts_with_number(text: &str) -> bool {
+    text.split_whitespace()
+        .next()
+        .is_some_and(|word| word.parse::<f64>().is_ok())
+}
+
+/// The first `n` values of the line, ignoring any following text.
+fn first_values(line: usize, field: &str, text: &str, n: usize) -> Result<Vec<f64>, Error> {
+    let values: Vec<&str> = text.split_whitespace().take(n).collect();
+    if values.len() < n {
+        return Err(Error::Syntax {
+            line,
+            message: format!("expected {} values for `{}`", n, field),
+        });
+    }
+
+    values
+        .into_iter()
+        .map(|value| parse_f64(line, field, value))
+        .collect()
+}
@@ -0,0 +1,32 @@
+//! XCrySDen structure files (XSF).
+use std::path::Path;
+use pw::input::Input;
+use pw::lattice::crystal_to_cartesian;
+use structure::{element_symbol, input_structure, write_text, Error};
+
+/// The XSF file for the structure of `input`, with the lattice vectors and Cartesian
+/// positions in Angstrom. Atoms are labelled by the element symbol of their species.
+pub fn make_file(input: &Input) -> Result<String, Error> {
+    let (cell, atoms) = input_structure(input)?;
+    let mut lines = Vec::new();
+
+    lines.push(String::from("CRYSTAL"));
+    lines.push(String::from("PRIMVEC"));
+    for a in cell.iter() {
+        lines.push(format!(" {} {} {}", a[0], a[1], a[2]));
+    }
+
+    lines.push(String::from("PRIMCOORD"));
+    lines.push(format!(" {} 1", atoms.len()));
+    for (species, x) in atoms {
+        let r = crystal_to_cartesian(&cell, x);
+        let symbol = element_symbol(species).unwrap_or_else(|| String::from(species));
+        lines.push(format!(" {} {} {} {}", symbol, r[0], r[1], r[2]));
+    }
+
+    Ok(lines.join("\n") + "\n")
+}
+
+pub fn write_file<P: AsRef<Path>>(input: &Input, file_path: P) -> Result<(), Error> {
+    write_text(&make_file(input)?, file_path)
+}
@@ -0,0 +1,100 @@
+//! XYZ files, with Cartesian positions in Angstrom.
+//!
+//! Plain XYZ files do not give the cell. It is read from the `Lattice="..."` property of the
+//! comment line of extended XYZ files, or otherwise must be given separately.
+use pw::input::{AtomCoordinate, Cell, LatticeUnits, PositionCoordinateType, Positions};
+use structure::{parse_f64, species_of, Error, Structure};
+
+/// Parse an XYZ file, using `cell` if it is given and the `Lattice` property of the comment
+/// line otherwise. Positions are given as `AngstromCartesian`.
+///
+/// Only the first frame of files with several frames is read.
+pub fn parse(text: &str, cell: Option<Cell>) -> Result<Structure, Error> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+    let mut next_line = |field: &str| {
+        lines
+            .next()
+            .ok_or_else(|| Error::Missing(String::from(field)))
+    };
+
+    let (line, count) = next_line("number of atoms")?;
+    let count: usize = count.trim().parse().map_err(|_| Error::Value {
+        line,
+        field: String::from("number of atoms"),
+        message: format!("expected an integer, found `{}`", count.trim()),
+    })?;
+
+    let (line, comment) = next_line("comment")?;
+    let cell = match cell {
+        Some(cell) => cell,
+        None => Cell {
+            units: LatticeUnits::Angstrom,
+            cell: lattice_property(line, comment)?
+                .ok_or_else(|| Error::Missing(String::from("Lattice")))?,
+        },
+    };
+
+    let mut coordinates = Vec::new();
+    for _ in 0..count {
+        let (line, text) = next_line("positions")?;
+        let words: Vec<&str> = text.split_whitespace().take(4).collect();
+        if words.len() < 4 {
+            return Err(Error::Syntax {
+                line,
+                message: String::from("expected a species and 3 coordinates"),
+            });
+        }
+        coordinates.push(AtomCoordinate {
+            species: String::from(words[0]),
+            r: [
+                parse_f64(line, "positions", words[1])?,
+                parse_f64(line, "positions", words[2])?,
+                parse_f64(line, "positions", words[3])?,
+            ],
+            if_pos: None,
+        });
+    }
+
+    let positions = Positions {
+        coordinate_type: PositionCoordinateType::AngstromCartesian,
+        coordinates,
+    };
+
+    Ok(Structure {
+        cell,
+        species: species_of(&positions),
+        positions,
+    })
+}
+
+/// The lattice vectors given by `Lattice="a1 a2 a3 b1 b2 b3 c1 c2 c3"`, if present.
+fn lattice_property(line: usize, comment: &str) -> Result<Option<[[f64; 3]; 3]>, Error> {
+    let start = match comment.find("Lattice=\"") {
+        Some(start) => start + "Lattice=\"".len(),
+        None => return Ok(None),
+    };
+    let end = comment[start..]
+        .find('"')
+        .map(|end| start + end)
+        .ok_or_else(|| Error::Syntax {
+            line,
+            message: String::from("unterminated `Lattice` property"),
+        })?;
+
+    let values = comment[start..end]
+        .split_whitespace()
+        .map(|value| parse_f64(line, "Lattice", value))
+        .collect::<Result<Vec<f64>, Error>>()?;
+    if values.len() != 9 {
+        return Err(Error::Syntax {
+            line,
+            message: String::from("expected 9 values for `Lattice`"),
+        });
+    }
+
+    Ok(Some([
+        [values[0], values[1], values[2]],
+        [values[3], values[4], values[5]],
+        [values[6], values[7], values[8]],
+    ]))
+}
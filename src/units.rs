@@ -0,0 +1,58 @@
+//! Units of length and energy used by QE and by the files read and written here, with the
+//! conversion factors of QE's `Modules/constants.f90`.
+//!
+//! pw.x input and XML files use bohr, and Ry or Hartree respectively; pw.x and bands.x
+//! standard output give band energies in eV; structure files use Angstrom.
+
+/// Bohr radius in Angstrom, as used by QE to convert lengths given in Angstrom.
+pub const BOHR_ANGSTROM: f64 = 0.52917720859;
+
+/// Hartree energy in eV.
+pub const HARTREE_EV: f64 = 27.21138386;
+
+/// Rydberg energy in eV.
+pub const RYDBERG_EV: f64 = HARTREE_EV / 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Length {
+    Bohr,
+    Angstrom,
+}
+
+impl Length {
+    /// The size of the unit in bohr.
+    pub fn in_bohr(self) -> f64 {
+        match self {
+            Length::Bohr => 1.0,
+            Length::Angstrom => 1.0 / BOHR_ANGSTROM,
+        }
+    }
+
+    /// Convert `value` from this unit to `unit`.
+    pub fn convert(self, value: f64, unit: Length) -> f64 {
+        value * self.in_bohr() / unit.in_bohr()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Energy {
+    Rydberg,
+    Hartree,
+    ElectronVolt,
+}
+
+impl Energy {
+    /// The size of the unit in eV.
+    pub fn in_ev(self) -> f64 {
+        match self {
+            Energy::Rydberg => RYDBERG_EV,
+            Energy::Hartree => HARTREE_EV,
+            Energy::ElectronVolt => 1.0,
+        }
+    }
+
+    /// Convert `value` from this unit to `unit`.
+    pub fn convert(self, value: f64, unit: Energy) -> f64 {
+        value * self.in_ev() / unit.in_ev()
+    }
+}
@@ -0,0 +1,406 @@
+//! Reading of the header of pseudopotential files in the UPF format (versions 1 and 2), and
+//! checks of the pw.x input against the pseudopotentials it uses.
+//!
+//! Energies are in Ry.
+use std::env;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use roxmltree;
+use pw::input::{Input, Species};
+
+/// The properties of a pseudopotential given in its `PP_HEADER`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Header {
+    pub element: String,
+    pub pseudo_type: PseudoType,
+    /// The exchange-correlation functional as given in the file, such as `PBE` or
+    /// `SLA PW PBX PBC`.
+    pub functional: String,
+    pub z_valence: f64,
+    /// Suggested `ecutwfc`. Not given if zero in the file.
+    pub wfc_cutoff: Option<f64>,
+    /// Suggested `ecutrho`. Not given if zero in the file.
+    pub rho_cutoff: Option<f64>,
+    pub spin_orbit: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PseudoType {
+    NormConserving,
+    Ultrasoft,
+    Paw,
+}
+
+/// The path of the pseudopotential file of `species`.
+///
+/// If `pseudo_dir` is not given, the default of pw.x is used: `$ESPRESSO_PSEUDO` if this is
+/// set, and `$HOME/espresso/pseudo` otherwise.
+pub fn pseudopotential_path(input: &Input, species: &Species) -> PathBuf {
+    let pseudo_dir = match input.control.pseudo_dir {
+        Some(ref pseudo_dir) => pseudo_dir.clone(),
+        None => match env::var_os("ESPRESSO_PSEUDO") {
+            Some(dir) => PathBuf::from(dir),
+            None => env::home_dir()
+                .unwrap_or_default()
+                .join("espresso")
+                .join("pseudo"),
+        },
+    };
+
+    pseudo_dir.join(&species.pseudopotential_filename)
+}
+
+/// Read the headers of the pseudopotentials of all species in `input`, in the order of
+/// `input.species`. A relative `pseudo_dir` is taken relative to `run_dir`, where pw.x is
+/// to be run.
+pub fn read_species_headers<P: AsRef<Path>>(
+    input: &Input,
+    run_dir: P,
+) -> Result<Vec<Header>, Error> {
+    input
+        .species
+        .iter()
+        .map(|species| {
+            let path = run_dir.as_ref().join(pseudopotential_path(input, species));
+            read_header(&path).map_err(|e| Error::File {
+                path: path.display().to_string(),
+                message: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn read_header<P: AsRef<Path>>(file_path: P) -> Result<Header, Error> {
+    let mut text = String::new();
+    File::open(file_path)?.read_to_string(&mut text)?;
+
+    parse_header(&text)
+}
+
+/// Parse the header of the UPF file `text`. Version 2 files are recognized by their
+/// `<UPF version="...">` root element, which may follow an XML declaration and comments;
+/// other files are read as version 1.
+pub fn parse_header(text: &str) -> Result<Header, Error> {
+    if skip_prolog(text).starts_with("<UPF") {
+        parse_header_v2(text)
+    } else {
+        parse_header_v1(text)
+    }
+}
+
+/// `text` after any leading XML declarations, processing instructions and comments.
+fn skip_prolog(text: &str) -> &str {
+    let mut rest = text.trim_start();
+    loop {
+        let end = if rest.starts_with("<?") {
+            rest.find("?>").map(|i| i + 2)
+        } else if rest.starts_with("<!--") {
+            rest.find("-->").map(|i| i + 3)
+        } else {
+            None
+        };
+
+        match end {
+            Some(end) => rest = rest[end..].trim_start(),
+            None => return rest,
+        }
+    }
+}
+
+/// In version 1, the header is a block of lines each starting with a value, followed by a
+/// description:
+///
+/// ```text
+/// <PP_HEADER>
+///    0                   Version Number
+///   Si                   Element
+///    NC                  Norm - Conserving pseudopotential
+///     F                  Nonlinear Core Correction
+///  SLA  PZ   NOGX NOGC   PZ   Exchange-Correlation functional
+///     4.00000000000      Z valence
+///    -7.47257990328      Total energy
+///     0.0000000    0.0000000 Suggested cutoff for wfc and rho
+/// ...
+/// </PP_HEADER>
+/// ```
+///
+/// Spin-orbit pseudopotentials are marked by the presence of a `PP_ADDINFO` section.
+fn parse_header_v1(text: &str) -> Result<Header, Error> {
+    let start = text
+        .find("<PP_HEADER>")
+        .ok_or(Error::Missing("PP_HEADER"))?;
+    let mut lines = text[start..].lines().skip(1);
+    let mut next_line = |field: &'static str| lines.next().ok_or(Error::Missing(field));
+
+    next_line("version")?;
+    let element = first_word(next_line("element")?, "element")?;
+    let pseudo_type = match first_word(next_line("pseudo_type")?, "pseudo_type")?.as_str() {
+        "NC" | "SL" => PseudoType::NormConserving,
+        "US" => PseudoType::Ultrasoft,
+        "PAW" => PseudoType::Paw,
+        other => return Err(value_error("pseudo_type", other)),
+    };
+    next_line("core_correction")?;
+    // The functional is given in the first 20 characters of its line.
+    let functional_line = next_line("functional")?;
+    let functional = functional_line
+        .char_indices()
+        .nth(20)
+        .map_or(functional_line, |(i, _)| &functional_line[..i])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let z_valence = number(
+        &first_word(next_line("z_valence")?, "z_valence")?,
+        "z_valence",
+    )?;
+    next_line("total_energy")?;
+    let cutoffs = next_line("cutoffs")?
+        .split_whitespace()
+        .take(2)
+        .map(|x| number(x, "cutoffs"))
+        .collect::<Result<Vec<_>, Error>>()?;
+    if cutoffs.len() != 2 {
+        return Err(Error::Missing("cutoffs"));
+    }
+
+    Ok(Header {
+        element,
+        pseudo_type,
+        functional,
+        z_valence,
+        wfc_cutoff: nonzero(cutoffs[0]),
+        rho_cutoff: nonzero(cutoffs[1]),
+        spin_orbit: text.contains("<PP_ADDINFO>"),
+    })
+}
+
+/// In version 2, the header is given by the attributes of the `PP_HEADER` element. Only
+/// this element is parsed as XML, since the rest of the file (in particular `PP_INFO`) is
+/// often not valid XML.
+fn parse_header_v2(text: &str) -> Result<Header, Error> {
+    let start = text.find("<PP_HEADER").ok_or(Error::Missing("PP_HEADER"))?;
+    let end = text[start..]
+        .find('>')
+        .map(|i| start + i + 1)
+        .ok_or(Error::Missing("PP_HEADER"))?;
+    let element_text = text[start..end].trim_end_matches('>').trim_end_matches('/');
+    let element_text = format!("{}/>", element_text);
+
+    let doc = roxmltree::Document::parse(&element_text)?;
+    let header = doc.root_element();
+    let attribute = |name: &'static str| header.attribute(name).ok_or(Error::Missing(name));
+
+    let pseudo_type = match attribute("pseudo_type")?.trim() {
+        "NC" | "SL" => PseudoType::NormConserving,
+        "US" | "USPP" => PseudoType::Ultrasoft,
+        "PAW" => PseudoType::Paw,
+        other => return Err(value_error("pseudo_type", other)),
+    };
+    let cutoff = |name: &'static str| -> Result<Option<f64>, Error> {
+        match header.attribute(name) {
+            Some(x) => Ok(nonzero(number(x, name)?)),
+            None => Ok(None),
+        }
+    };
+    let spin_orbit = match header.attribute("has_so") {
+        Some(x) => boolean(x, "has_so")?,
+        None => false,
+    };
+
+    Ok(Header {
+        element: String::from(attribute("element")?.trim()),
+        pseudo_type,
+        functional: attribute("functional")?
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        z_valence: number(attribute("z_valence")?, "z_valence")?,
+        wfc_cutoff: cutoff("wfc_cutoff")?,
+        rho_cutoff: cutoff("rho_cutoff")?,
+        spin_orbit,
+    })
+}
+
+/// Check the cutoffs and functionals of `input` against the pseudopotential `headers`,
+/// given in the order of `input.species`, one for each species. Returns a warning for each
+/// problem found.
+///
+/// `ecutwfc` and `ecutrho` should be at least the suggested values, if given. `ecutrho`
+/// should be at least `4 * ecutwfc` for norm-conserving pseudopotentials, and at least
+/// `8 * ecutwfc` for ultrasoft pseudopotentials and PAW datasets.
+pub fn check_cutoffs(input: &Input, headers: &[Header]) -> Result<Vec<Warning>, Error> {
+    if headers.len() != input.species.len() {
+        return Err(Error::HeaderCount {
+            species: input.species.len(),
+            headers: headers.len(),
+        });
+    }
+
+    let mut warnings = Vec::new();
+    let system = &input.system;
+
+    for (species, header) in input.species.iter().zip(headers) {
+        if let Some(wfc_cutoff) = header.wfc_cutoff {
+            if system.ecutwfc < wfc_cutoff {
+                warnings.push(Warning::Ecutwfc(
+                    species.label.clone(),
+                    system.ecutwfc,
+                    wfc_cutoff,
+                ));
+            }
+        }
+        if let Some(rho_cutoff) = header.rho_cutoff {
+            if system.ecutrho < rho_cutoff {
+                warnings.push(Warning::Ecutrho(
+                    species.label.clone(),
+                    system.ecutrho,
+                    rho_cutoff,
+                ));
+            }
+        }
+    }
+
+    let dual = if headers
+        .iter()
+        .any(|h| h.pseudo_type != PseudoType::NormConserving)
+    {
+        8.0
+    } else {
+        4.0
+    };
+    if system.ecutrho < dual * system.ecutwfc {
+        warnings.push(Warning::Dual(system.ecutrho, system.ecutwfc, dual));
+    }
+
+    let mut species_headers = input.species.iter().zip(headers);
+    if let Some((first_species, first_header)) = species_headers.next() {
+        let first_functional = functional_name(&first_header.functional);
+        for (species, header) in species_headers {
+            if functional_name(&header.functional) != first_functional {
+                warnings.push(Warning::MixedFunctionals(
+                    first_species.label.clone(),
+                    first_header.functional.clone(),
+                    species.label.clone(),
+                    header.functional.clone(),
+                ));
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// The short name of the functional, so that the same functional written as its short name
+/// or as its components compares equal.
+fn functional_name(functional: &str) -> String {
+    let functional = functional.to_uppercase();
+    let name = match functional.as_str() {
+        "SLA PZ NOGX NOGC" | "SLA PZ" | "LDA" => "PZ",
+        "SLA PW PBX PBC" | "SLA PW PBE PBE" => "PBE",
+        "SLA PW PSX PSC" => "PBESOL",
+        "SLA PW GGX GGC" => "PW91",
+        "SLA PW RPB PBC" => "REVPBE",
+        other => other,
+    };
+
+    String::from(name)
+}
+
+fn first_word(line: &str, field: &'static str) -> Result<String, Error> {
+    line.split_whitespace()
+        .next()
+        .map(String::from)
+        .ok_or(Error::Missing(field))
+}
+
+fn number(x: &str, field: &'static str) -> Result<f64, Error> {
+    // Fortran double precision exponents may be written with `D`.
+    x.trim()
+        .replace(['D', 'd'], "e")
+        .parse()
+        .map_err(|_| value_error(field, x))
+}
+
+fn boolean(x: &str, field: &'static str) -> Result<bool, Error> {
+    match x.trim().to_uppercase().trim_matches('.') {
+        "T" | "TRUE" => Ok(true),
+        "F" | "FALSE" => Ok(false),
+        _ => Err(value_error(field, x)),
+    }
+}
+
+fn nonzero(x: f64) -> Option<f64> {
+    if x > 0.0 {
+        Some(x)
+    } else {
+        None
+    }
+}
+
+fn value_error(field: &'static str, value: &str) -> Error {
+    Error::Value {
+        field,
+        value: String::from(value.trim()),
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum Warning {
+    #[fail(
+        display = "`ecutwfc` = {} is below the value {} suggested for species {}.",
+        _1, _2, _0
+    )]
+    Ecutwfc(String, f64, f64),
+    #[fail(
+        display = "`ecutrho` = {} is below the value {} suggested for species {}.",
+        _1, _2, _0
+    )]
+    Ecutrho(String, f64, f64),
+    #[fail(
+        display = "`ecutrho` = {} is below {} * `ecutwfc` = {} * {}.",
+        _0, _2, _2, _1
+    )]
+    Dual(f64, f64, f64),
+    #[fail(
+        display = "Species {} uses functional {}, but species {} uses functional {}.",
+        _0, _1, _2, _3
+    )]
+    MixedFunctionals(String, String, String, String),
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "{}", _0)]
+    Xml(#[cause] roxmltree::Error),
+    #[fail(display = "in pseudopotential file {}: {}", path, message)]
+    File { path: String, message: String },
+    #[fail(display = "`{}` is missing from the pseudopotential header", _0)]
+    Missing(&'static str),
+    #[fail(
+        display = "invalid value `{}` for `{}` in the pseudopotential header",
+        value, field
+    )]
+    Value { field: &'static str, value: String },
+    #[fail(
+        display = "{} pseudopotential headers given for {} species",
+        headers, species
+    )]
+    HeaderCount { species: usize, headers: usize },
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(e: roxmltree::Error) -> Error {
+        Error::Xml(e)
+    }
+}
@@ -0,0 +1,199 @@
+use error;
+use pw;
+use pw::input::{generate_uniform_kpoints, Calculation, KPoints, PositionCoordinateType, SpinType};
+use pw::lattice::lattice_vectors;
+
+/// Representation of the Wannier90 `seedname.win` input file.
+///
+/// The structure, spin and k-point fields (`mp_grid`, `kpoints`, `unit_cell_cart`,
+/// `atoms_frac`, `spinors`) must match those of the pw.x `nscf` calculation providing the Bloch states, and are
+/// usually obtained from it with `from_pw_input`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Input {
+    pub parameters: Parameters,
+    pub mp_grid: [u64; 3],
+    /// k-points in crystal coordinates.
+    pub kpoints: Vec<[f64; 3]>,
+    /// Lattice vectors in bohr.
+    pub unit_cell_cart: [[f64; 3]; 3],
+    pub atoms_frac: Vec<AtomFrac>,
+    /// Whether the Bloch states are spinors, as in a noncollinear pw.x calculation.
+    pub spinors: bool,
+}
+
+/// Wannier90 parameters which are not determined by the pw.x input.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Parameters {
+    pub num_wann: u64,
+    /// If not given, Wannier90 takes `num_bands = num_wann`.
+    pub num_bands: Option<u64>,
+    pub num_iter: Option<u64>,
+    pub projections: Projections,
+    pub disentanglement: Option<Disentanglement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Projections {
+    /// Random initial projections.
+    Random,
+    List(Vec<Projection>),
+}
+
+/// A line of the `projections` block, such as `Fe:d` or `f=0.25,0.25,0.25:sp3`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Projection {
+    pub site: ProjectionSite,
+    /// Angular momentum states in Wannier90 notation, such as `s;p` or `sp3`.
+    pub orbitals: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ProjectionSite {
+    /// Centered on each atom of the given species.
+    Species(String),
+    /// Centered at the given position in crystal coordinates.
+    Crystal([f64; 3]),
+}
+
+/// Energy windows for disentanglement, in eV. The outer window is required; the inner
+/// (frozen) window bounds are optional.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Disentanglement {
+    pub dis_win_min: f64,
+    pub dis_win_max: f64,
+    pub dis_froz_min: Option<f64>,
+    pub dis_froz_max: Option<f64>,
+    pub dis_num_iter: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtomFrac {
+    pub species: String,
+    pub r: [f64; 3],
+}
+
+/// Construct the Wannier90 input corresponding to the pw.x input `pw_input`, which must use
+/// `KPoints::CrystalUniform` so that the k-points listed here match those of pw.x exactly.
+///
+/// If `parameters.num_bands` is not given, it is taken from `nbnd` of an `nscf`
+/// calculation in `pw_input`.
+pub fn from_pw_input(pw_input: &pw::input::Input, parameters: Parameters) -> Result<Input, Error> {
+    let mp_grid = match pw_input.k_points {
+        KPoints::CrystalUniform(nk) => nk,
+        _ => return Err(Error::KPoints),
+    };
+
+    let system = &pw_input.system;
+    let cell = lattice_vectors(&system.ibrav, system.alat);
+
+    let atoms_frac = pw_input
+        .atomic_positions
+        .to_coordinate_type(PositionCoordinateType::Crystal, &cell, system.alat)
+        .map_err(|_| Error::CoordinateType)?
+        .coordinates
+        .into_iter()
+        .map(|coord| AtomFrac {
+            species: coord.species,
+            r: coord.r,
+        })
+        .collect();
+
+    let num_bands = match (parameters.num_bands, &pw_input.calculation) {
+        (Some(num_bands), _) => Some(num_bands),
+        (None, &Calculation::Nscf { nbnd, .. }) => nbnd,
+        (None, _) => None,
+    };
+
+    let spinors = matches!(system.spin_type, Some(SpinType::Noncollinear { .. }));
+
+    Ok(Input {
+        parameters: Parameters {
+            num_bands,
+            ..parameters
+        },
+        mp_grid,
+        kpoints: generate_uniform_kpoints(mp_grid),
+        unit_cell_cart: cell,
+        atoms_frac,
+        spinors,
+    })
+}
+
+/// Check the properties of the `Input` which are not encoded in the type system.
+pub fn validate(input: &Input) -> Result<(), ErrorList> {
+    let mut errs = Vec::new();
+    let parameters = &input.parameters;
+
+    let num_bands = parameters.num_bands.unwrap_or(parameters.num_wann);
+    if num_bands < parameters.num_wann {
+        errs.push(Error::NumBands(num_bands, parameters.num_wann));
+    }
+
+    // Check that the inner window is contained in the outer window.
+    if let Some(ref dis) = parameters.disentanglement {
+        if dis.dis_win_min >= dis.dis_win_max {
+            errs.push(Error::Window(dis.dis_win_min, dis.dis_win_max));
+        }
+        let froz_min = dis.dis_froz_min.unwrap_or(dis.dis_win_min);
+        let froz_max = dis.dis_froz_max.unwrap_or(dis.dis_win_max);
+        if froz_min < dis.dis_win_min || froz_max > dis.dis_win_max || froz_min >= froz_max {
+            errs.push(Error::FrozenWindow(froz_min, froz_max));
+        }
+    }
+
+    // Check that the number of k-points is consistent with the mesh.
+    let nk = input.mp_grid.iter().product::<u64>() as usize;
+    if nk != input.kpoints.len() {
+        errs.push(Error::MpGrid(nk, input.kpoints.len()));
+    }
+
+    // Projections onto species require the species to be present.
+    if let Projections::List(ref projections) = parameters.projections {
+        for projection in projections {
+            if let ProjectionSite::Species(ref species) = projection.site {
+                if !input.atoms_frac.iter().any(|atom| &atom.species == species) {
+                    errs.push(Error::Species(species.clone()));
+                }
+            }
+        }
+    }
+
+    if errs.is_empty() {
+        Ok(())
+    } else {
+        Err(ErrorList { errs })
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(
+        display = "Wannier90 input can only be generated from pw.x input with `CrystalUniform` k-points."
+    )]
+    KPoints,
+    #[fail(
+        display = "Atomic positions given as `crystal_sg` cannot be converted to Wannier90 input."
+    )]
+    CoordinateType,
+    #[fail(display = "`num_bands` = {} must be at least `num_wann` = {}.", _0, _1)]
+    NumBands(u64, u64),
+    #[fail(
+        display = "Outer window minimum {} must be less than maximum {}.",
+        _0, _1
+    )]
+    Window(f64, f64),
+    #[fail(
+        display = "Frozen window [{}, {}] must be contained in the outer window.",
+        _0, _1
+    )]
+    FrozenWindow(f64, f64),
+    #[fail(display = "`mp_grid` gives {} k-points, but {} are listed.", _0, _1)]
+    MpGrid(usize, usize),
+    #[fail(
+        display = "Species {} in projections is not present in `atoms_frac`.",
+        _0
+    )]
+    Species(String),
+}
+
+pub type ErrorList = error::ErrorList<Error>;
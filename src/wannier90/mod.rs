@@ -0,0 +1,3 @@
+pub mod input;
+pub mod serialize;
+pub mod workflow;
@@ -0,0 +1,114 @@
+use std::path::Path;
+use std::io;
+use std::io::Write;
+use std::fs::File;
+use wannier90::input;
+use wannier90::input::{Input, Projection, ProjectionSite, Projections};
+
+pub fn make_input_file(input: &Input) -> Result<String, Error> {
+    input::validate(input)?;
+
+    let parameters = &input.parameters;
+    let mut lines = Vec::new();
+
+    lines.push(format!("num_wann = {}", parameters.num_wann));
+    if let Some(num_bands) = parameters.num_bands {
+        lines.push(format!("num_bands = {}", num_bands));
+    }
+    if let Some(num_iter) = parameters.num_iter {
+        lines.push(format!("num_iter = {}", num_iter));
+    }
+    if input.spinors {
+        lines.push(String::from("spinors = .true."));
+    }
+
+    if let Some(ref dis) = parameters.disentanglement {
+        lines.push(String::new());
+        lines.push(format!("dis_win_min = {}", dis.dis_win_min));
+        lines.push(format!("dis_win_max = {}", dis.dis_win_max));
+        if let Some(dis_froz_min) = dis.dis_froz_min {
+            lines.push(format!("dis_froz_min = {}", dis_froz_min));
+        }
+        if let Some(dis_froz_max) = dis.dis_froz_max {
+            lines.push(format!("dis_froz_max = {}", dis_froz_max));
+        }
+        if let Some(dis_num_iter) = dis.dis_num_iter {
+            lines.push(format!("dis_num_iter = {}", dis_num_iter));
+        }
+    }
+
+    lines.push(String::new());
+    lines.push(String::from("begin projections"));
+    match parameters.projections {
+        Projections::Random => lines.push(String::from("random")),
+        Projections::List(ref projections) => {
+            lines.extend(projections.iter().map(make_projection));
+        }
+    }
+    lines.push(String::from("end projections"));
+
+    lines.push(String::new());
+    lines.push(String::from("begin unit_cell_cart"));
+    lines.push(String::from("bohr"));
+    for a in input.unit_cell_cart.iter() {
+        lines.push(format!(" {} {} {}", a[0], a[1], a[2]));
+    }
+    lines.push(String::from("end unit_cell_cart"));
+
+    lines.push(String::new());
+    lines.push(String::from("begin atoms_frac"));
+    for atom in &input.atoms_frac {
+        let r = atom.r;
+        lines.push(format!(" {} {} {} {}", atom.species, r[0], r[1], r[2]));
+    }
+    lines.push(String::from("end atoms_frac"));
+
+    lines.push(String::new());
+    let nk = input.mp_grid;
+    lines.push(format!("mp_grid = {} {} {}", nk[0], nk[1], nk[2]));
+
+    lines.push(String::new());
+    lines.push(String::from("begin kpoints"));
+    for k in &input.kpoints {
+        lines.push(format!(" {} {} {}", k[0], k[1], k[2]));
+    }
+    lines.push(String::from("end kpoints"));
+
+    Ok(lines.join("\n"))
+}
+
+fn make_projection(projection: &Projection) -> String {
+    match projection.site {
+        ProjectionSite::Species(ref species) => format!("{}:{}", species, projection.orbitals),
+        ProjectionSite::Crystal(r) => {
+            format!("f={},{},{}:{}", r[0], r[1], r[2], projection.orbitals)
+        }
+    }
+}
+
+pub fn write_input_file<P: AsRef<Path>>(input: &Input, file_path: P) -> Result<(), Error> {
+    let input_text = make_input_file(input)?;
+
+    let mut file = File::create(file_path)?;
+    file.write_all(input_text.as_bytes())?;
+
+    Ok(())
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "{}", _0)] Input(input::ErrorList),
+    #[fail(display = "{}", _0)] Io(#[cause] io::Error),
+}
+
+impl From<input::ErrorList> for Error {
+    fn from(errs: input::ErrorList) -> Error {
+        Error::Input(errs)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,259 @@
+//! The chain of calculations which produces Wannier functions: pw.x `scf`, pw.x `nscf` on a
+//! uniform k-point grid, pw2wannier90.x and Wannier90.
+//!
+//! The inputs for all stages are derived from a single `scf` input, so that the quantities
+//! which must agree between stages (`prefix`, `out_dir`, the structure and the k-points) do
+//! so by construction. `validate` checks this for inputs which have been modified afterwards.
+use std::fs;
+use std::io;
+use std::path::Path;
+use error;
+use pw;
+use pw::input::{Calculation, KPoints};
+use pw2wannier90;
+use run;
+use wannier90;
+
+/// Name of the pw.x `scf` run, giving the files `scf.in` and `scf.out` in the run directory.
+pub const SCF_NAME: &str = "scf";
+/// Name of the pw.x `nscf` run.
+pub const NSCF_NAME: &str = "nscf";
+/// Name of the pw2wannier90.x run.
+pub const PW2WANNIER90_NAME: &str = "pw2wan";
+
+/// The inputs for each stage of the calculation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Workflow {
+    pub scf: pw::input::Input,
+    pub nscf: pw::input::Input,
+    pub pw2wannier90: pw2wannier90::input::Input,
+    /// Written to `<seedname>.win`, with `seedname` given in `pw2wannier90`.
+    pub wannier90: wannier90::input::Input,
+}
+
+/// The parameters of the stages after `scf` which are not determined by the `scf` input.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Parameters {
+    /// The k-point grid used for the `nscf` calculation and by Wannier90.
+    pub nk: [u64; 3],
+    pub diago_thr_init: f64,
+    pub nbnd: Option<u64>,
+    pub seedname: String,
+    pub write_unk: bool,
+    pub write_spn: bool,
+    pub wannier90: wannier90::input::Parameters,
+}
+
+/// Derive the inputs for all stages from the `scf` input `base`.
+///
+/// The `nscf` input uses `KPoints::CrystalUniform` with `nosym = .true.`, so that pw.x
+/// computes the Bloch states at exactly the k-points listed for Wannier90. If `base` does not
+/// give a `prefix`, `parameters.seedname` is used as the prefix for all stages.
+pub fn from_scf_input(base: &pw::input::Input, parameters: Parameters) -> Result<Workflow, Error> {
+    if !matches!(base.calculation, Calculation::Scf { .. }) {
+        return Err(Error::ScfCalculation);
+    }
+
+    let prefix = base
+        .control
+        .prefix
+        .clone()
+        .unwrap_or_else(|| parameters.seedname.clone());
+
+    let mut scf = base.clone();
+    scf.control.prefix = Some(prefix.clone());
+
+    let nscf = pw::input::Input {
+        calculation: Calculation::Nscf {
+            diago_thr_init: Some(parameters.diago_thr_init),
+            nbnd: parameters.nbnd,
+            nosym: Some(true),
+        },
+        k_points: KPoints::CrystalUniform(parameters.nk),
+        ..scf.clone()
+    };
+
+    let pw2wannier90 = pw2wannier90::input::Input {
+        prefix,
+        out_dir: scf.control.out_dir.clone(),
+        seedname: parameters.seedname,
+        write_unk: parameters.write_unk,
+        write_amn: true,
+        write_mmn: true,
+        write_spn: parameters.write_spn,
+    };
+
+    let wannier90 = wannier90::input::from_pw_input(&nscf, parameters.wannier90)?;
+
+    Ok(Workflow {
+        scf,
+        nscf,
+        pw2wannier90,
+        wannier90,
+    })
+}
+
+/// Check that the inputs of the stages agree with each other.
+pub fn validate(workflow: &Workflow) -> Result<(), ErrorList> {
+    let mut errs = Vec::new();
+    let (scf, nscf, pw2wan) = (&workflow.scf, &workflow.nscf, &workflow.pw2wannier90);
+
+    if !matches!(scf.calculation, Calculation::Scf { .. }) {
+        errs.push(Error::ScfCalculation);
+    }
+
+    let nbnd = match nscf.calculation {
+        Calculation::Nscf {
+            nbnd,
+            nosym: Some(true),
+            ..
+        } => nbnd,
+        _ => {
+            errs.push(Error::NscfCalculation);
+            None
+        }
+    };
+
+    // pw.x and pw2wannier90.x must read and write the same data files.
+    let prefix = Some(&pw2wan.prefix);
+    if scf.control.prefix.as_ref() != prefix || nscf.control.prefix.as_ref() != prefix {
+        errs.push(Error::Prefix);
+    }
+    if scf.control.out_dir != pw2wan.out_dir || nscf.control.out_dir != pw2wan.out_dir {
+        errs.push(Error::OutDir);
+    }
+
+    if scf.system != nscf.system
+        || scf.species != nscf.species
+        || scf.atomic_positions != nscf.atomic_positions
+    {
+        errs.push(Error::Structure);
+    }
+
+    match nscf.k_points {
+        KPoints::CrystalUniform(nk) if nk == workflow.wannier90.mp_grid => (),
+        _ => errs.push(Error::KPoints),
+    }
+
+    if let (Some(nbnd), Some(num_bands)) = (nbnd, workflow.wannier90.parameters.num_bands) {
+        if nbnd != num_bands {
+            errs.push(Error::NumBands(num_bands, nbnd));
+        }
+    }
+
+    if errs.is_empty() {
+        Ok(())
+    } else {
+        Err(ErrorList { errs })
+    }
+}
+
+/// Write the input files for all stages into `run_dir`, which is created if necessary.
+///
+/// The pw.x and pw2wannier90.x inputs are written to the paths given by `run::file_paths`
+/// for `SCF_NAME`, `NSCF_NAME` and `PW2WANNIER90_NAME`; the Wannier90 input is written to
+/// `<seedname>.win`.
+pub fn write_input_files<P: AsRef<Path>>(
+    workflow: &Workflow,
+    run_dir: P,
+) -> Result<(), WriteError> {
+    validate(workflow)?;
+
+    let run_dir = run_dir.as_ref();
+    fs::create_dir_all(run_dir)?;
+
+    let (scf_path, _) = run::file_paths(run_dir, SCF_NAME);
+    pw::serialize::write_input_file(&workflow.scf, scf_path)?;
+
+    let (nscf_path, _) = run::file_paths(run_dir, NSCF_NAME);
+    pw::serialize::write_input_file(&workflow.nscf, nscf_path)?;
+
+    let (pw2wan_path, _) = run::file_paths(run_dir, PW2WANNIER90_NAME);
+    pw2wannier90::serialize::write_input_file(&workflow.pw2wannier90, pw2wan_path)?;
+
+    let win_path = run_dir.join(format!("{}.win", workflow.pw2wannier90.seedname));
+    wannier90::serialize::write_input_file(&workflow.wannier90, win_path)?;
+
+    Ok(())
+}
+
+#[derive(Fail, Debug)]
+pub enum Error {
+    #[fail(display = "The first stage of the workflow must be an `scf` calculation.")]
+    ScfCalculation,
+    #[fail(
+        display = "The second stage of the workflow must be an `nscf` calculation with `nosym = .true.`."
+    )]
+    NscfCalculation,
+    #[fail(display = "`prefix` must be the same for pw.x and pw2wannier90.x.")]
+    Prefix,
+    #[fail(display = "`outdir` must be the same for pw.x and pw2wannier90.x.")]
+    OutDir,
+    #[fail(
+        display = "The `scf` and `nscf` calculations must have the same system, species and atomic positions."
+    )]
+    Structure,
+    #[fail(
+        display = "The `nscf` calculation must use `CrystalUniform` k-points matching `mp_grid`."
+    )]
+    KPoints,
+    #[fail(
+        display = "`num_bands` = {} must be equal to `nbnd` = {} of the `nscf` calculation.",
+        _0, _1
+    )]
+    NumBands(u64, u64),
+    #[fail(display = "{}", _0)]
+    Wannier90(#[cause] wannier90::input::Error),
+}
+
+impl From<wannier90::input::Error> for Error {
+    fn from(e: wannier90::input::Error) -> Error {
+        Error::Wannier90(e)
+    }
+}
+
+pub type ErrorList = error::ErrorList<Error>;
+
+#[derive(Fail, Debug)]
+pub enum WriteError {
+    #[fail(display = "{}", _0)]
+    Workflow(ErrorList),
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "{}", _0)]
+    Pw(#[cause] pw::serialize::Error),
+    #[fail(display = "{}", _0)]
+    Pw2Wannier90(#[cause] pw2wannier90::serialize::Error),
+    #[fail(display = "{}", _0)]
+    Wannier90(#[cause] wannier90::serialize::Error),
+}
+
+impl From<ErrorList> for WriteError {
+    fn from(e: ErrorList) -> WriteError {
+        WriteError::Workflow(e)
+    }
+}
+
+impl From<io::Error> for WriteError {
+    fn from(e: io::Error) -> WriteError {
+        WriteError::Io(e)
+    }
+}
+
+impl From<pw::serialize::Error> for WriteError {
+    fn from(e: pw::serialize::Error) -> WriteError {
+        WriteError::Pw(e)
+    }
+}
+
+impl From<pw2wannier90::serialize::Error> for WriteError {
+    fn from(e: pw2wannier90::serialize::Error) -> WriteError {
+        WriteError::Pw2Wannier90(e)
+    }
+}
+
+impl From<wannier90::serialize::Error> for WriteError {
+    fn from(e: wannier90::serialize::Error) -> WriteError {
+        WriteError::Wannier90(e)
+    }
+}
@@ -0,0 +1,151 @@
+extern crate qe;
+
+use qe::bands::output;
+use qe::pw::input::KPoints;
+use qe::pw::kpath::{KPath, LatticeType};
+
+const SI_FILBAND: &str = " &plot nbnd=  12, nks=     5 /
+           0.500000  0.500000  0.500000
+  -3.420  -0.837   4.978   4.978   7.732   9.523   9.523  13.784  14.125  14.125
+  16.802  17.311
+           0.250000  0.250000  0.250000
+  -5.092   0.472   5.402   5.402   8.314   9.172   9.172  13.160  14.821  14.821
+  15.950  18.201
+           0.000000  0.000000  0.000000
+  -5.810   6.255   6.255   6.255   8.822   8.822   8.822   9.723  13.640  13.640
+  13.640  17.952
+           0.000000  0.500000  0.000000
+  -4.373   2.625   5.044   5.044   8.996   9.871   9.871  11.301  14.106  14.106
+  16.215  16.215
+           0.000000  1.000000  0.000000
+  -1.617  -1.617   3.381   3.381   6.805   6.805  16.044  16.044  16.870  16.870
+  17.200  17.200
+";
+
+const SI_FILBAND_GNU: &str = "      0.0000  -3.4200
+      0.4330  -5.0920
+      0.8660  -5.8100
+      1.3660  -4.3730
+      1.8660  -1.6170
+
+      0.0000  -0.8370
+      0.4330   0.4720
+      0.8660   6.2550
+      1.3660   2.6250
+      1.8660  -1.6170
+
+";
+
+fn si_path() -> KPoints {
+    KPoints::CrystalBands {
+        nk_per_panel: 2,
+        panel_bounds: vec![[0.5, 0.5, 0.5], [0.0, 0.0, 0.0], [0.0, 0.5, 0.5]],
+    }
+}
+
+#[test]
+fn parse_filband() {
+    let bands = output::parse_filband(SI_FILBAND, &si_path(), None).unwrap();
+
+    let ks = bands.k_points.unwrap();
+    assert_eq!(ks.len(), 5);
+    assert_eq!(ks[3], [0.0, 0.5, 0.0]);
+
+    assert_eq!(bands.bands.len(), 12);
+    assert_eq!(bands.bands[0], vec![-3.42, -5.092, -5.81, -4.373, -1.617]);
+    assert_eq!(bands.bands[11][4], 17.2);
+
+    let expected = [
+        0.0,
+        0.75f64.sqrt() / 2.0,
+        0.75f64.sqrt(),
+        0.75f64.sqrt() + 0.5,
+    ];
+    for (d, e) in bands.distances.iter().zip(expected.iter()) {
+        assert!((d - e).abs() < 1e-12);
+    }
+
+    assert_eq!(bands.ticks.len(), 3);
+    assert!((bands.ticks[1] - 0.75f64.sqrt()).abs() < 1e-12);
+    assert!((bands.ticks[2] - (0.75f64.sqrt() + 1.0)).abs() < 1e-12);
+
+    // The k-points of a different path do not match the band structure.
+    let other_path = KPoints::CrystalBands {
+        nk_per_panel: 10,
+        panel_bounds: vec![[0.5, 0.5, 0.5], [0.0, 0.0, 0.0]],
+    };
+    match output::parse_filband(SI_FILBAND, &other_path, None) {
+        Err(output::Error::KPoints { expected, found }) => {
+            assert_eq!(expected, 11);
+            assert_eq!(found, 5);
+        }
+        other => panic!("expected k-points error; got {:?}", other),
+    }
+
+    // Counts in the header which do not match the file are rejected before being used.
+    for header in &["nbnd=  12, nks=     6", "nbnd=  12, nks=4000000000000000000"] {
+        let text = SI_FILBAND.replace("nbnd=  12, nks=     5", header);
+        match output::parse_filband(&text, &si_path(), None) {
+            Err(output::Error::Value { line, .. }) => assert_eq!(line, 1),
+            other => panic!("expected value error; got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn parse_filband_gnu() {
+    let bands = output::parse_filband_gnu(SI_FILBAND_GNU, &si_path(), None).unwrap();
+
+    assert_eq!(bands.k_points, None);
+    assert_eq!(bands.distances, vec![0.0, 0.433, 0.866, 1.366, 1.866]);
+    assert_eq!(bands.bands.len(), 2);
+    assert_eq!(bands.bands[1][2], 6.255);
+    assert_eq!(bands.ticks, vec![0.0, 0.866, 1.866]);
+}
+
+#[test]
+fn parse_filband_jumps() {
+    // The second panel jumps from Γ to X, the start of the next segment of the path.
+    let path = KPath {
+        lattice_type: LatticeType::Fcc,
+        segments: vec![
+            vec![String::from("L"), String::from("Γ")],
+            vec![String::from("X")],
+        ],
+        k_points: si_path(),
+        labels: vec![String::from("L"), String::from("Γ"), String::from("X")],
+        jumps: vec![1],
+    };
+    let bands = output::parse_filband(SI_FILBAND, &path.k_points, Some(&path)).unwrap();
+
+    // The k-point inside the jump is dropped, and Γ and X share one tick.
+    let l = 0.75f64.sqrt();
+    let ks = bands.k_points.unwrap();
+    assert_eq!(ks.len(), 4);
+    assert_eq!(ks[2], [0.0, 0.0, 0.0]);
+    assert_eq!(ks[3], [0.0, 1.0, 0.0]);
+    assert_eq!(bands.distances.len(), 4);
+    for band in bands.bands.iter() {
+        assert_eq!(band.len(), 4);
+    }
+    assert_eq!(bands.bands[0], vec![-3.42, -5.092, -5.81, -1.617]);
+    let expected = [0.0, l / 2.0, l, l];
+    for (d, e) in bands.distances.iter().zip(expected.iter()) {
+        assert!((d - e).abs() < 1e-12);
+    }
+    assert_eq!(bands.ticks.len(), 2);
+    assert!((bands.ticks[1] - l).abs() < 1e-12);
+
+    let bands = output::parse_filband_gnu(SI_FILBAND_GNU, &path.k_points, Some(&path)).unwrap();
+    for band in bands.bands.iter() {
+        assert_eq!(band.len(), 4);
+    }
+    assert_eq!(bands.bands[1], vec![-0.837, 0.472, 6.255, -1.617]);
+    let expected = [0.0, 0.433, 0.866, 0.866];
+    assert_eq!(bands.distances.len(), 4);
+    for (d, e) in bands.distances.iter().zip(expected.iter()) {
+        assert!((d - e).abs() < 1e-12);
+    }
+    assert_eq!(bands.ticks.len(), 2);
+    assert!((bands.ticks[1] - 0.866).abs() < 1e-12);
+}
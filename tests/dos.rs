@@ -0,0 +1,70 @@
+extern crate qe;
+
+use qe::dos::input::{BzSum, Input};
+use qe::dos::{output, serialize};
+
+const SI_DOS: &str = "#  E (eV)   dos(E)     Int dos(E) EFermi =    6.255 eV
+  -6.000  0.1000E+00  0.1000E-01
+  -5.990  0.2500E+00  0.3500E-01
+  -5.980  0.4000E+00  0.7500E-01
+";
+
+const FE_DOS: &str = "#  E (eV)  dosup(E)   dosdw(E)  Int dos(E) EFermi =   17.822 eV
+  10.000  0.1000E+01  0.5000E+00  0.1500E-01
+  10.010  0.2000E+01  0.1000E+01  0.4500E-01
+";
+
+#[test]
+fn make_dos_input() {
+    let input = Input {
+        prefix: Some(String::from("si")),
+        out_dir: Some("./data".into()),
+        fildos: Some("si.dos".into()),
+        bz_sum: Some(BzSum::TetrahedraOpt),
+        ngauss: None,
+        degauss: None,
+        emin: Some(-6.0),
+        emax: Some(16.0),
+        delta_e: Some(0.01),
+    };
+
+    let text = serialize::make_input_file(&input).unwrap();
+
+    assert!(text.starts_with(" &dos\n"), "{}", text);
+    assert!(text.contains("outdir='./data',"), "{}", text);
+    assert!(text.contains("bz_sum='tetrahedra_opt',"), "{}", text);
+    assert!(text.contains("Emin=-6"), "{}", text);
+    assert!(text.contains("DeltaE=0.01"), "{}", text);
+    assert!(!text.contains("degauss"), "{}", text);
+}
+
+#[test]
+fn parse_dos_file() {
+    let dos = output::parse_dos_file(SI_DOS).unwrap();
+
+    assert_eq!(dos.fermi_energy, Some(6.255));
+    assert_eq!(dos.energies, vec![-6.0, -5.99, -5.98]);
+    assert_eq!(dos.dos, output::Dos::Unpolarized(vec![0.1, 0.25, 0.4]));
+    assert_eq!(dos.integrated_dos, vec![0.01, 0.035, 0.075]);
+
+    let dos = output::parse_dos_file(FE_DOS).unwrap();
+
+    assert_eq!(dos.fermi_energy, Some(17.822));
+    assert_eq!(
+        dos.dos,
+        output::Dos::Polarized {
+            up: vec![1.0, 2.0],
+            down: vec![0.5, 1.0],
+        }
+    );
+    assert_eq!(dos.dos.total(), vec![1.5, 3.0]);
+
+    assert!(matches!(
+        output::parse_dos_file("#  E (eV)   dos(E)     Int dos(E)\n"),
+        Err(output::Error::Empty)
+    ));
+    assert!(matches!(
+        output::parse_dos_file(&SI_DOS.replace("0.4000E+00", "")),
+        Err(output::Error::Value { line: 4, .. })
+    ));
+}
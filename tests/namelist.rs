@@ -0,0 +1,136 @@
+extern crate qe;
+#[macro_use]
+extern crate serde_derive;
+
+use qe::namelist;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Occupations {
+    Smearing,
+    Fixed,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct System {
+    ibrav: i64,
+    #[serde(default)]
+    celldm: Vec<Option<f64>>,
+    ecutwfc: f64,
+    title: Option<String>,
+    occupations: Occupations,
+    lspinorb: bool,
+    #[serde(rename = "Hubbard_U", default)]
+    hubbard_u: Vec<Option<f64>>,
+}
+
+#[test]
+fn write_namelist() {
+    let system = System {
+        ibrav: 4,
+        celldm: vec![Some(5.0), None, Some(1.6)],
+        ecutwfc: 45.0,
+        title: Some(String::from("Fe's cell")),
+        occupations: Occupations::Smearing,
+        lspinorb: false,
+        hubbard_u: vec![None, Some(4.5e-8)],
+    };
+
+    let expected = " &system
+    ibrav=4,
+    celldm(1)=5,
+    celldm(3)=1.6,
+    ecutwfc=45,
+    title='Fe''s cell',
+    occupations='smearing',
+    lspinorb=.false.,
+    Hubbard_U(2)=4.5e-8,
+ /";
+
+    let text = namelist::to_string("system", &system).unwrap();
+    assert_eq!(text, expected);
+    assert_eq!(namelist::from_str::<System>(&text).unwrap(), system);
+}
+
+#[test]
+fn read_namelist() {
+    let text = "
+ &SYSTEM  ! comment after the namelist name
+    IBRAV = 4, celldm = 5.0d0, 1.0 ! two values assigned to an array
+    celldm(3)=1.6D0
+    ecutwfc = 45, title = \"a \"\"quoted\"\" title\"
+    occupations = 'fixed', lspinorb = .TRUE., hubbard_u(2) = 3.0
+ /
+";
+
+    let system: System = namelist::from_str(text).unwrap();
+
+    assert_eq!(
+        system,
+        System {
+            ibrav: 4,
+            celldm: vec![Some(5.0), Some(1.0), Some(1.6)],
+            ecutwfc: 45.0,
+            title: Some(String::from("a \"quoted\" title")),
+            occupations: Occupations::Fixed,
+            lspinorb: true,
+            hubbard_u: vec![None, Some(3.0)],
+        }
+    );
+}
+
+#[test]
+fn read_namelist_invalid_value() {
+    let text = " &system
+    ibrav = 4, ecutwfc = 45.0,
+    occupations = 'fixed', lspinorb = 1
+ /";
+
+    match namelist::from_str::<System>(text) {
+        Err(namelist::Error::Value { line, field, .. }) => {
+            assert_eq!(line, 3);
+            assert_eq!(field, "lspinorb");
+        }
+        other => panic!("expected invalid value error; got {:?}", other),
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Electrons {
+    efield_cart: Option<[f64; 3]>,
+    #[serde(default)]
+    starting_ns: Vec<Option<f64>>,
+}
+
+#[test]
+fn read_namelist_index_bounds() {
+    let electrons: Electrons = namelist::from_str(
+        " &electrons
+    efield_cart(1) = 0.0, efield_cart(2) = 0.0, efield_cart(3) = 0.01
+ /",
+    )
+    .unwrap();
+    assert_eq!(electrons.efield_cart, Some([0.0, 0.0, 0.01]));
+
+    // Indices beyond the array are rejected before the array is allocated.
+    let text = " &electrons
+    starting_ns(100000000) = 1.0
+ /";
+
+    match namelist::from_str::<Electrons>(text) {
+        Err(namelist::Error::Value { line, field, .. }) => {
+            assert_eq!(line, 2);
+            assert_eq!(field, "starting_ns(100000000)");
+        }
+        other => panic!("expected invalid value error; got {:?}", other),
+    }
+
+    let text = " &electrons
+    efield_cart(4) = 0.01
+ /";
+
+    match namelist::from_str::<Electrons>(text) {
+        Err(namelist::Error::Value { field, .. }) => assert_eq!(field, "efield_cart(4)"),
+        other => panic!("expected invalid value error; got {:?}", other),
+    }
+}
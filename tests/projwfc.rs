@@ -0,0 +1,166 @@
+extern crate qe;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use qe::dos::output::Dos;
+use qe::projwfc::input::Input;
+use qe::projwfc::{output, serialize};
+
+const PDOS_TOT: &str = "# E (eV)  dos(E)    pdos(E)
+  -6.000  0.400E+00  0.380E+00
+  -5.990  0.800E+00  0.760E+00
+";
+
+const PDOS_S: &str = "# E (eV)  ldos(E)   pdos(E)
+  -6.000  0.100E+00  0.100E+00
+  -5.990  0.200E+00  0.200E+00
+";
+
+const PDOS_P: &str = "# E (eV)  ldos(E)   pdos(E)   pdos(E)   pdos(E)
+  -6.000  0.090E+00  0.030E+00  0.030E+00  0.030E+00
+  -5.990  0.180E+00  0.060E+00  0.060E+00  0.060E+00
+";
+
+const PDOS_POLARIZED: &str = "# E (eV)  ldosup(E)  ldosdw(E) pdosup(E)  pdosdw(E)
+  10.000  0.100E+01  0.500E+00  0.100E+01  0.500E+00
+";
+
+fn si_input() -> Input {
+    Input {
+        prefix: Some(String::from("si")),
+        out_dir: Some("./data".into()),
+        filpdos: Some(String::from("si.pdos")),
+        ngauss: Some(0),
+        degauss: Some(0.01),
+        emin: Some(-6.0),
+        emax: Some(16.0),
+        delta_e: Some(0.01),
+        lsym: None,
+    }
+}
+
+/// An empty directory to write the projwfc.x output files in.
+fn pdos_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("qe-projwfc-{}-{}", name, process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+#[test]
+fn make_projwfc_input() {
+    let text = serialize::make_input_file(&si_input()).unwrap();
+
+    assert!(text.starts_with(" &projwfc\n"), "{}", text);
+    assert!(text.contains("outdir='./data',"), "{}", text);
+    assert!(text.contains("filpdos='si.pdos',"), "{}", text);
+    assert!(text.contains("Emax=16"), "{}", text);
+    assert!(!text.contains("lsym"), "{}", text);
+}
+
+#[test]
+fn parse_pdos_files() {
+    let total = output::parse_pdos(PDOS_TOT).unwrap();
+    assert_eq!(total.energies, vec![-6.0, -5.99]);
+    assert_eq!(total.ldos, Dos::Unpolarized(vec![0.4, 0.8]));
+    assert_eq!(total.pdos, vec![Dos::Unpolarized(vec![0.38, 0.76])]);
+
+    let p = output::parse_pdos(PDOS_P).unwrap();
+    assert_eq!(p.pdos.len(), 3);
+
+    let polarized = output::parse_pdos(PDOS_POLARIZED).unwrap();
+    assert_eq!(
+        polarized.ldos,
+        Dos::Polarized {
+            up: vec![1.0],
+            down: vec![0.5],
+        }
+    );
+    assert_eq!(polarized.pdos.len(), 1);
+
+    assert!(output::parse_pdos("# E (eV)  ldos(E)\n  -6.000  0.1  0.1\n").is_err());
+}
+
+#[test]
+fn parse_wfc_names() {
+    let wfc = output::parse_wfc_name("atm#12(Fe)_wfc#3(d)").unwrap();
+    assert_eq!(wfc.atom, 12);
+    assert_eq!(wfc.species, "Fe");
+    assert_eq!(wfc.wfc, 3);
+    assert_eq!(wfc.l, 2);
+    assert_eq!(wfc.j, None);
+
+    let wfc = output::parse_wfc_name("atm#1(Pt)_wfc#4(p_j1.5)").unwrap();
+    assert_eq!(wfc.l, 1);
+    assert_eq!(wfc.j, Some(1.5));
+
+    assert!(output::parse_wfc_name("atm#1(Si)_wfc#2(x)").is_err());
+    assert!(output::parse_wfc_name("atm#1(Si)").is_err());
+}
+
+#[test]
+fn read_and_group_projections() {
+    let dir = pdos_dir("si");
+    let files = [
+        ("si.pdos.pdos_tot", PDOS_TOT),
+        ("si.pdos.pdos_atm#2(Si)_wfc#2(p)", PDOS_P),
+        ("si.pdos.pdos_atm#2(Si)_wfc#1(s)", PDOS_S),
+        ("si.pdos.pdos_atm#1(Si)_wfc#2(p)", PDOS_P),
+        ("si.pdos.pdos_atm#1(Si)_wfc#1(s)", PDOS_S),
+        ("si.dos", PDOS_TOT),
+    ];
+    for &(name, text) in files.iter() {
+        fs::write(dir.join(name), text).unwrap();
+    }
+
+    let projections = output::read_projections(&si_input(), &dir).unwrap();
+
+    assert_eq!(projections.total.ldos, Dos::Unpolarized(vec![0.4, 0.8]));
+    let order: Vec<(usize, usize)> = projections
+        .wfcs
+        .iter()
+        .map(|p| (p.wfc.atom, p.wfc.wfc))
+        .collect();
+    assert_eq!(order, vec![(1, 1), (1, 2), (2, 1), (2, 2)]);
+
+    let close = |dos: &Dos, expected: &[f64]| {
+        let total = dos.total();
+        total.len() == expected.len()
+            && total
+                .iter()
+                .zip(expected)
+                .all(|(x, y)| (x - y).abs() < 1e-12)
+    };
+
+    let by_atom = output::dos_by_atom(&projections).unwrap();
+    assert_eq!(by_atom.len(), 2);
+    assert_eq!(by_atom[1].0, 2);
+    assert!(close(&by_atom[1].1, &[0.19, 0.38]));
+
+    let by_species = output::dos_by_species(&projections).unwrap();
+    assert_eq!(by_species.len(), 1);
+    assert_eq!(by_species[0].0, "Si");
+    assert!(close(&by_species[0].1, &[0.38, 0.76]));
+
+    let by_l = output::dos_by_l(&projections).unwrap();
+    assert_eq!(by_l.len(), 2);
+    assert_eq!(by_l[1].0, 1);
+    assert!(close(&by_l[1].1, &[0.18, 0.36]));
+
+    let by_species_l = output::group_by(&projections, |wfc| (wfc.species.clone(), wfc.l)).unwrap();
+    assert_eq!(by_species_l[0].0, (String::from("Si"), 0));
+
+    // All projections must be on the energy grid of `pdos_tot`.
+    fs::write(dir.join("si.pdos.pdos_atm#3(Si)_wfc#1(s)"), PDOS_POLARIZED).unwrap();
+    assert!(matches!(
+        output::read_projections(&si_input(), &dir),
+        Err(output::Error::Mismatch(_))
+    ));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
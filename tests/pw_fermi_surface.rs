@@ -0,0 +1,216 @@
+extern crate qe;
+
+use std::f64::consts::PI;
+use std::path::Path;
+use qe::pw::input::{generate_uniform_kpoints, Ibrav, KPoints, Verbosity};
+use qe::pw::{fermi_surface, lattice, output, parse, serialize, xml};
+use qe::units::{BOHR_ANGSTROM, HARTREE_EV};
+
+const SI_SCF: &str = "
+ &control
+    calculation = 'scf', prefix = 'si', outdir = './data'
+ /
+ &system
+    ibrav = 2, celldm(1) = 10.2, nat = 2, ntyp = 1,
+    ecutwfc = 30, ecutrho = 120
+ /
+ &electrons
+    conv_thr = 1e-8
+ /
+ATOMIC_SPECIES
+ Si 28.0855 Si.pz-vbc.UPF
+ATOMIC_POSITIONS alat
+ Si 0.00 0.00 0.00
+ Si 0.25 0.25 0.25
+K_POINTS automatic
+ 2 2 2 0 0 0
+";
+
+/// The points of the `nscf` grid in Cartesian coordinates in units of 2pi/alat, in reverse
+/// order, with a band energy identifying each point.
+fn grid_points(nk: [u64; 3], alat: f64) -> Vec<([f64; 3], f64)> {
+    let b = lattice::reciprocal_vectors(&lattice::lattice_vectors(&Ibrav::Fcc, alat));
+    let mut points: Vec<([f64; 3], f64)> = generate_uniform_kpoints(nk)
+        .into_iter()
+        .map(|x| {
+            let k = lattice::crystal_to_cartesian(&b, x);
+            let k = [
+                k[0] * alat / (2.0 * PI),
+                k[1] * alat / (2.0 * PI),
+                k[2] * alat / (2.0 * PI),
+            ];
+            (k, x[0] + 2.0 * x[1] + 4.0 * x[2])
+        })
+        .collect();
+    points.reverse();
+    points
+}
+
+#[test]
+fn fermi_surface_from_data_file() {
+    let input = parse::parse_input_file(SI_SCF).unwrap();
+    let parameters = fermi_surface::Parameters {
+        grid: [3, 3, 3],
+        diago_thr_init: 1e-6,
+        nbnd: Some(8),
+    };
+    let nscf = fermi_surface::from_scf_input(&input, &parameters).unwrap();
+
+    // The periodic endpoints are not computed by pw.x.
+    assert_eq!(nscf.k_points, KPoints::CrystalUniform([2, 2, 2]));
+    let text = serialize::make_input_file(&nscf).unwrap();
+    assert!(text.contains("nosym=.true."), "{}", text);
+    assert!(text.contains("K_POINTS crystal\n8\n"), "{}", text);
+
+    let run_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let mut data = xml::read_data_file(&input, run_dir).unwrap();
+    let template = data.band_structure.k_points[0].clone();
+    data.band_structure.k_points = grid_points([2, 2, 2], input.system.alat)
+        .into_iter()
+        .map(|(k, e)| xml::KPoint {
+            k,
+            eigenvalues: vec![e, 0.5],
+            ..template.clone()
+        })
+        .collect();
+
+    let surface = fermi_surface::from_data_file(&nscf, &data).unwrap();
+
+    assert_eq!(surface.grid, [3, 3, 3]);
+    assert!((surface.fermi_energy - 0.2310931571 * HARTREE_EV).abs() < 1e-10);
+    assert_eq!(surface.bands.len(), 2);
+    assert_eq!(surface.bands[0].len(), 27);
+    // The point (0, 1/2, 1) is the periodic image of (0, 1/2, 0).
+    assert!((surface.bands[0][5] - HARTREE_EV).abs() < 1e-10);
+    assert_eq!(surface.bands[0][26], surface.bands[0][0]);
+    assert!(surface.bands[1].iter().all(|&e| e == 0.5 * HARTREE_EV));
+
+    // The fcc reciprocal vectors are of length 2pi sqrt(3) / alat.
+    let b = surface.reciprocal_vectors[0];
+    let length = (b[0] * b[0] + b[1] * b[1] + b[2] * b[2]).sqrt();
+    let expected = 2.0 * PI * 3f64.sqrt() / (input.system.alat * BOHR_ANGSTROM);
+    assert!((length - expected).abs() < 1e-10);
+
+    let bxsf = fermi_surface::make_bxsf(&surface);
+    let lines: Vec<&str> = bxsf.lines().collect();
+    assert_eq!(lines[0], "BEGIN_INFO");
+    assert_eq!(lines[6], " 2");
+    assert_eq!(lines[7], " 3 3 3");
+    assert!(bxsf.contains("\nBAND: 2\n"), "{}", bxsf);
+    assert!(bxsf.ends_with("END_BANDGRID_3D\nEND_BLOCK_BANDGRID_3D\n"));
+
+    assert!(matches!(
+        fermi_surface::from_scf_input(
+            &input,
+            &fermi_surface::Parameters {
+                grid: [1, 3, 3],
+                ..parameters
+            }
+        ),
+        Err(fermi_surface::Error::Grid([1, 3, 3]))
+    ));
+}
+
+#[test]
+fn fermi_surface_from_output() {
+    let input = parse::parse_input_file(SI_SCF).unwrap();
+    let parameters = fermi_surface::Parameters {
+        grid: [4, 3, 3],
+        diago_thr_init: 1e-6,
+        nbnd: None,
+    };
+    let nscf = fermi_surface::from_scf_input(&input, &parameters).unwrap();
+
+    let energies = |spin: f64| -> Vec<output::KPointEnergies> {
+        grid_points([3, 2, 2], input.system.alat)
+            .into_iter()
+            .map(|(k, e)| output::KPointEnergies {
+                k,
+                energies: vec![e + spin],
+            })
+            .collect()
+    };
+    let mut run = output::Output {
+        n_electrons: 8.0,
+        result: output::CalculationOutput::Nscf(output::Bands {
+            band_energies: output::BandEnergies::Polarized {
+                up: energies(0.0),
+                down: energies(1.0),
+            },
+            fermi_energy: Some(output::FermiEnergy::Fermi(6.0)),
+        }),
+        warnings: Vec::new(),
+        wall_time: None,
+        job_done: true,
+    };
+
+    let surface = fermi_surface::from_output(&nscf, &run).unwrap();
+
+    assert_eq!(surface.fermi_energy, 6.0);
+    // The spin up band is followed by the spin down band.
+    assert_eq!(surface.bands.len(), 2);
+    assert_eq!(surface.bands[0].len(), 36);
+    assert!((surface.bands[1][9] - surface.bands[0][9] - 1.0).abs() < 1e-10);
+    // The point (1/3, 0, 0) is at index 9.
+    assert!((surface.bands[0][9] - 1.0 / 3.0).abs() < 1e-10);
+
+    // Every point of the grid must be given.
+    run.result = output::CalculationOutput::Nscf(output::Bands {
+        band_energies: output::BandEnergies::Unpolarized(energies(0.0)[1..].to_vec()),
+        fermi_energy: Some(output::FermiEnergy::Fermi(6.0)),
+    });
+    assert!(matches!(
+        fermi_surface::from_output(&nscf, &run),
+        Err(fermi_surface::Error::KPoints)
+    ));
+
+    // Grids with no points or too many points to count are rejected.
+    run.result = output::CalculationOutput::Nscf(output::Bands {
+        band_energies: output::BandEnergies::Unpolarized(Vec::new()),
+        fermi_energy: Some(output::FermiEnergy::Fermi(6.0)),
+    });
+    for &nk in &[[0, 2, 2], [u64::MAX, u64::MAX, 2]] {
+        let mut nscf = nscf.clone();
+        nscf.k_points = KPoints::CrystalUniform(nk);
+        assert!(matches!(
+            fermi_surface::from_output(&nscf, &run),
+            Err(fermi_surface::Error::KPoints)
+        ));
+    }
+}
+
+#[test]
+fn fermi_surface_from_output_text() {
+    let input = parse::parse_input_file(SI_SCF).unwrap();
+    let parameters = fermi_surface::Parameters {
+        grid: [4, 4, 4],
+        diago_thr_init: 1e-6,
+        nbnd: None,
+    };
+    let nscf = fermi_surface::from_scf_input(&input, &parameters).unwrap();
+
+    // pw.x only writes the band energies of 100 or more k-points with a high verbosity.
+    assert_eq!(nscf.control.verbosity, Some(Verbosity::High));
+    let text = serialize::make_input_file(&nscf).unwrap();
+    assert!(text.contains("verbosity='high'"), "{}", text);
+
+    // The k-points of a grid with thirds, written with 4 decimals as by pw.x.
+    let mut text = String::from("     number of electrons       =         8.00\n\n");
+    text.push_str("     End of band structure calculation\n\n");
+    for (k, e) in grid_points([3, 3, 3], input.system.alat) {
+        text.push_str(&format!(
+            "          k ={:7.4}{:7.4}{:7.4} (   811 PWs)   bands (ev):\n\n    {:8.4}\n\n",
+            k[0], k[1], k[2], e
+        ));
+    }
+    text.push_str("     the Fermi energy is     6.0000 ev\n\n     JOB DONE.\n");
+
+    let run = output::parse_output(&text, &nscf.calculation).unwrap();
+    let surface = fermi_surface::from_output(&nscf, &run).unwrap();
+
+    assert_eq!(surface.grid, [4, 4, 4]);
+    assert_eq!(surface.bands[0].len(), 64);
+    // The point (0, 1/3, 2/3) is at index 6.
+    assert!((surface.bands[0][6] - 10.0 / 3.0).abs() < 1e-3);
+    assert_eq!(surface.bands[0][63], surface.bands[0][0]);
+}
@@ -3,11 +3,11 @@ extern crate qe;
 use qe::pw::input;
 use qe::pw::serialize;
 
-#[test]
-fn generate_pw_input() {
+fn fe_input() -> input::Input {
     let calculation = input::Calculation::Scf { conv_thr: 1e-8 };
 
     let control = input::Control {
+        verbosity: None,
         restart_mode: None,
         disk_io: Some(input::DiskIO::Low),
         wf_collect: None,
@@ -28,10 +28,16 @@ fn generate_pw_input() {
         ecutrho: 240.0,
         occupations: input::Occupations::Tetrahedra,
         spin_type: None,
+        input_dft: None,
+        vdw_corr: None,
     };
 
     let efield = None;
 
+    let berry_phase = None;
+
+    let hubbard = None;
+
     let electrons = input::Electrons {
         startingwfc: None,
         diagonalization: None,
@@ -61,18 +67,401 @@ fn generate_pw_input() {
         sk: None,
     };
 
-    let test_input = input::Input {
+    input::Input {
         calculation,
         control,
         system,
         efield,
+        berry_phase,
+        hubbard,
         electrons,
         species,
         atomic_positions,
         k_points,
-    };
+    }
+}
+
+#[test]
+fn generate_pw_input() {
+    let test_input = fe_input();
 
     let input_text = serialize::make_input_file(&test_input).unwrap();
 
     println!("{}", input_text);
 }
+
+#[test]
+fn validate_pw_input_cell_and_species() {
+    let mut test_input = fe_input();
+    assert!(input::validate(&test_input).is_ok());
+
+    assert!(input::warnings(&test_input).is_empty());
+
+    // Swapping two lattice vectors gives a left-handed cell, which pw.x accepts with a warning.
+    test_input.system.ibrav = input::Ibrav::Free(input::Cell {
+        units: input::LatticeUnits::Alat,
+        cell: [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+    });
+    let warnings = input::warnings(&test_input);
+    assert_eq!(warnings.len(), 1);
+    assert!(matches!(warnings[0], input::Warning::LeftHandedCell(v) if v == -27.0));
+    test_input.atomic_positions.coordinates[0].species = String::from("Co");
+    test_input.species.push(test_input.species[0].clone());
+
+    let errs = input::validate(&test_input).unwrap_err().errs;
+
+    assert_eq!(errs.len(), 2);
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::Species(ref s) if s == "Co")));
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::DuplicateSpecies(ref s) if s == "Fe")));
+
+    // Linearly dependent lattice vectors do not span a volume.
+    test_input = fe_input();
+    test_input.system.ibrav = input::Ibrav::Free(input::Cell {
+        units: input::LatticeUnits::Alat,
+        cell: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0]],
+    });
+    match input::validate(&test_input) {
+        Err(ref list) if list.errs.len() == 1 => {
+            assert!(matches!(list.errs[0], input::Error::CellVolume(_)))
+        }
+        other => panic!("expected cell volume error; got {:?}", other),
+    }
+
+    // `alat` is the length of the first lattice vector of a cell in bohr or angstrom.
+    test_input = fe_input();
+    test_input.system.ibrav = input::Ibrav::Free(input::Cell {
+        units: input::LatticeUnits::Bohr,
+        cell: [[10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]],
+    });
+    test_input.system.alat = 20.0;
+    match input::validate(&test_input) {
+        Err(ref list) if list.errs.len() == 1 => assert!(matches!(
+            list.errs[0],
+            input::Error::CellAlat(length, alat) if length == 10.0 && alat == 20.0
+        )),
+        other => panic!("expected cell alat error; got {:?}", other),
+    }
+    test_input.system.alat = 10.0;
+    assert!(input::validate(&test_input).is_ok());
+
+    // Lattices given by `ibrav` with valid parameters span a nonzero volume.
+    for ibrav in &[
+        input::Ibrav::Fcc,
+        input::Ibrav::BccSymmetric,
+        input::Ibrav::TrigonalRAxis111(0.3),
+        input::Ibrav::OrthorhombicBcoAlternate(1.2, 1.5),
+        input::Ibrav::MonoclinicBaseCentered(1.2, 1.5, -0.2),
+        input::Ibrav::Triclinic(1.2, 1.5, 0.1, 0.2, 0.3),
+    ] {
+        test_input.system.ibrav = ibrav.clone();
+        assert!(input::validate(&test_input).is_ok(), "{:?}", ibrav);
+    }
+}
+
+#[test]
+fn validate_pw_input_efield_and_occupations() {
+    let mut test_input = fe_input();
+    test_input.efield = Some(input::Efield::TeField {
+        dipfield: false,
+        edir: input::LatticeDirection::D3,
+        emaxpos: 1.2,
+        eopreg: 0.1,
+        eamp: 0.001,
+    });
+    test_input.k_points = input::KPoints::CrystalUniform([8, 8, 8]);
+
+    let errs = input::validate(&test_input).unwrap_err().errs;
+
+    assert_eq!(errs.len(), 2);
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::Emaxpos(x) if x == 1.2)));
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::TetrahedraKPoints)));
+
+    // The input file is not generated from an invalid input.
+    assert!(serialize::make_input_file(&test_input).is_err());
+}
+
+#[test]
+fn validate_pw_input_berry_phase() {
+    let mut test_input = fe_input();
+    test_input.system.occupations = input::Occupations::Fixed;
+    test_input.calculation = input::Calculation::Nscf {
+        diago_thr_init: Some(1e-6),
+        nbnd: None,
+        nosym: None,
+    };
+    let strings = input::KPointStrings {
+        gdir: input::LatticeDirection::D2,
+        nppstr: 4,
+    };
+    test_input.berry_phase = Some(strings.clone());
+    let ks = input::generate_kpoint_strings([2, 4, 2], &strings.gdir, true).unwrap();
+    test_input.k_points =
+        input::KPoints::Crystal(ks.iter().map(|k| [k[0], k[1], k[2], 1.0]).collect());
+
+    // Strings run along `gdir`, from one point to its periodic image.
+    assert_eq!(ks.len(), 16);
+    assert_eq!(ks[3], [0.0, 1.0, 0.0]);
+    assert_eq!(ks[4], [0.0, 0.0, 0.5]);
+    // Such a string needs at least two points.
+    assert_eq!(
+        input::generate_kpoint_strings([2, 1, 2], &strings.gdir, true),
+        None
+    );
+
+    let errs = input::validate(&test_input).unwrap_err().errs;
+
+    assert_eq!(errs.len(), 1);
+    assert!(matches!(errs[0], input::Error::BerryPhaseSymmetry));
+
+    test_input.calculation = input::Calculation::Nscf {
+        diago_thr_init: Some(1e-6),
+        nbnd: None,
+        nosym: Some(true),
+    };
+    input::validate(&test_input).unwrap();
+
+    // A finite field uses strings without the endpoint, and cannot be combined with `lberry`.
+    test_input.efield = Some(input::Efield::LelField {
+        efield_cart: [0.0, 0.001, 0.0],
+        nberrycyc: None,
+        strings,
+    });
+    let errs = input::validate(&test_input).unwrap_err().errs;
+
+    assert_eq!(errs.len(), 1);
+    assert!(matches!(errs[0], input::Error::BerryPhaseLelfield));
+
+    test_input.berry_phase = None;
+    let errs = input::validate(&test_input).unwrap_err().errs;
+
+    assert_eq!(errs.len(), 1);
+    assert!(matches!(errs[0], input::Error::BerryPhaseKPoints(4)));
+
+    let ks = input::generate_kpoint_strings([2, 4, 2], &input::LatticeDirection::D2, false)
+        .unwrap();
+    test_input.k_points =
+        input::KPoints::Crystal(ks.iter().map(|k| [k[0], k[1], k[2], 1.0]).collect());
+    input::validate(&test_input).unwrap();
+
+    test_input.k_points = input::KPoints::Automatic {
+        nk: [2, 4, 2],
+        sk: None,
+    };
+    let errs = input::validate(&test_input).unwrap_err().errs;
+
+    assert!(matches!(errs[0], input::Error::BerryPhaseKPoints(4)));
+}
+
+#[test]
+fn pw_input_magnetization() {
+    let mut test_input = fe_input();
+    test_input.system.spin_type = Some(input::SpinType::CollinearPolarized(
+        input::Magnetization {
+            starting_magnetization: vec![Some(0.4)],
+            tot_magnetization: Some(2.2),
+            constrained_magnetization: Some(input::ConstrainedMagnetization::Atomic {
+                lambda: 0.5,
+            }),
+            ..input::Magnetization::default()
+        },
+    ));
+
+    let input_text = serialize::make_input_file(&test_input).unwrap();
+    for field in &[
+        "nspin=2",
+        "starting_magnetization(1)=0.4",
+        "tot_magnetization=2.2",
+        "constrained_magnetization='atomic'",
+        "lambda=0.5",
+    ] {
+        assert!(input_text.contains(field), "{}\n{}", field, input_text);
+    }
+
+    // Angles are only used for noncollinear calculations, and each value must correspond
+    // to a species.
+    if let Some(input::SpinType::CollinearPolarized(ref mut magnetization)) =
+        test_input.system.spin_type
+    {
+        magnetization.starting_magnetization = vec![Some(1.5), Some(0.2)];
+        magnetization.angles = vec![Some([90.0, 0.0])];
+    }
+    let errs = input::validate(&test_input).unwrap_err().errs;
+
+    assert_eq!(errs.len(), 3);
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::MagnetizationSpecies(2, 1))));
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::StartingMagnetization(x) if x == 1.5)));
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::MagnetizationSpinType(_))));
+
+    test_input.system.spin_type = Some(input::SpinType::Noncollinear {
+        spin_orbit: false,
+        magnetization: input::Magnetization {
+            starting_magnetization: vec![Some(0.5)],
+            angles: vec![Some([90.0, 45.0])],
+            tot_magnetization: Some(2.0),
+            constrained_magnetization: Some(input::ConstrainedMagnetization::TotalDirection {
+                angle: 30.0,
+            }),
+        },
+    });
+    match input::validate(&test_input) {
+        Err(ref list) if list.errs.len() == 1 => assert!(matches!(
+            list.errs[0],
+            input::Error::MagnetizationSpinType(ref field) if field == "tot_magnetization"
+        )),
+        other => panic!("expected spin type error; got {:?}", other),
+    }
+
+    if let Some(input::SpinType::Noncollinear {
+        ref mut magnetization,
+        ..
+    }) = test_input.system.spin_type
+    {
+        magnetization.tot_magnetization = None;
+    }
+    let input_text = serialize::make_input_file(&test_input).unwrap();
+    for field in &[
+        "noncolin=.true.",
+        "angle1(1)=90",
+        "angle2(1)=45",
+        "constrained_magnetization='total direction'",
+        "fixed_magnetization(3)=30",
+    ] {
+        assert!(input_text.contains(field), "{}\n{}", field, input_text);
+    }
+}
+
+#[test]
+fn validate_pw_input_hubbard() {
+    let mut test_input = fe_input();
+    let parameters = input::HubbardParameters {
+        species: String::from("Fe"),
+        manifold: None,
+        u: 4.0,
+        j0: None,
+        j: Some(0.9),
+        alpha: None,
+    };
+    test_input.hubbard = Some(input::Hubbard {
+        kind: input::HubbardKind::Full,
+        projection: input::HubbardProjection::Atomic,
+        parameters: vec![
+            parameters.clone(),
+            input::HubbardParameters {
+                species: String::from("Ni"),
+                ..parameters.clone()
+            },
+        ],
+        card: false,
+    });
+
+    let errs = input::validate(&test_input).unwrap_err().errs;
+    assert_eq!(errs.len(), 1);
+    assert!(matches!(errs[0], input::Error::HubbardSpecies(ref s) if s == "Ni"));
+
+    if let Some(ref mut hubbard) = test_input.hubbard {
+        hubbard.parameters.pop();
+    }
+    let input_text = serialize::make_input_file(&test_input).unwrap();
+    for field in &[
+        "lda_plus_u=.true.",
+        "lda_plus_u_kind=1",
+        "Hubbard_U(1)=4",
+        "Hubbard_J(1,1)=0.9",
+        "U_projection_type='atomic'",
+    ] {
+        assert!(input_text.contains(field), "{}\n{}", field, input_text);
+    }
+
+    // The `HUBBARD` card requires the manifold of each species.
+    if let Some(ref mut hubbard) = test_input.hubbard {
+        hubbard.card = true;
+        hubbard.projection = input::HubbardProjection::File;
+    }
+    let errs = input::validate(&test_input).unwrap_err().errs;
+    assert_eq!(errs.len(), 2);
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::HubbardManifold(ref s) if s == "Fe")));
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::HubbardProjection)));
+}
+
+#[test]
+fn validate_pw_input_functional() {
+    let mut test_input = fe_input();
+    test_input.system.input_dft = Some(input::Functional::Hybrid(
+        input::HybridFunctional::Pbe0,
+        input::Hybrid {
+            nqx: Some([4, 4, 3]),
+            ecutfock: Some(480.0),
+            ..input::Hybrid::default()
+        },
+    ));
+    test_input.system.vdw_corr = Some(input::VdwCorr::TkatchenkoScheffler {
+        econv_thr: Some(-1e-6),
+        isolated: None,
+    });
+
+    let errs = input::validate(&test_input).unwrap_err().errs;
+
+    assert_eq!(errs.len(), 3);
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::NqxKPoints([4, 4, 3], [8, 8, 8]))));
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::Ecutfock(x) if x == 480.0)));
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::TsVdwEconvThr(_))));
+
+    test_input.system.input_dft = Some(input::Functional::Hybrid(
+        input::HybridFunctional::Pbe0,
+        input::Hybrid {
+            nqx: Some([4, 4, 4]),
+            exx_fraction: Some(0.3),
+            ..input::Hybrid::default()
+        },
+    ));
+    test_input.system.vdw_corr = Some(input::VdwCorr::Xdm {
+        a1: Some(0.65),
+        a2: Some(1.7),
+    });
+    let input_text = serialize::make_input_file(&test_input).unwrap();
+    for field in &[
+        "input_dft='pbe0'",
+        "exx_fraction=0.3",
+        "nqx1=4",
+        "nqx3=4",
+        "vdw_corr='xdm'",
+        "xdm_a1=0.65",
+    ] {
+        assert!(input_text.contains(field), "{}\n{}", field, input_text);
+    }
+
+    // Gamma-only runs use only the Gamma point for exact exchange also.
+    test_input.system.occupations = input::Occupations::Fixed;
+    test_input.k_points = input::KPoints::Gamma;
+    let errs = input::validate(&test_input).unwrap_err().errs;
+
+    assert_eq!(errs.len(), 1);
+    assert!(matches!(
+        errs[0],
+        input::Error::NqxKPoints([4, 4, 4], [1, 1, 1])
+    ));
+}
@@ -0,0 +1,226 @@
+extern crate qe;
+
+use std::f64::consts::PI;
+use qe::pw::input::{Cell, Ibrav, KPoints, LatticeUnits};
+use qe::pw::kpath::{self, KPath, LatticeType};
+use qe::pw::lattice;
+
+const ALAT: f64 = 6.0;
+
+fn panel_bounds(path: &KPath) -> &[[f64; 3]] {
+    match path.k_points {
+        KPoints::CrystalBands {
+            ref panel_bounds, ..
+        } => panel_bounds,
+        ref other => panic!("expected CrystalBands; got {:?}", other),
+    }
+}
+
+fn cartesian(cell: &[[f64; 3]; 3], k: [f64; 3]) -> [f64; 3] {
+    lattice::crystal_to_cartesian(&lattice::reciprocal_vectors(cell), k)
+}
+
+fn length(v: [f64; 3]) -> f64 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+/// The length of each labelled k-point of the path, in units of 2 pi / alat.
+fn lengths(ibrav: &Ibrav, path: &KPath) -> Vec<(String, f64)> {
+    let cell = lattice::lattice_vectors(ibrav, ALAT);
+    path.labels
+        .iter()
+        .zip(panel_bounds(path))
+        .map(|(label, &k)| {
+            (
+                label.clone(),
+                length(cartesian(&cell, k)) * ALAT / (2.0 * PI),
+            )
+        })
+        .collect()
+}
+
+/// High-symmetry points lie in the first Brillouin zone, at least as close to Gamma as to any
+/// other reciprocal lattice point.
+fn assert_in_first_zone(ibrav: &Ibrav, path: &KPath) {
+    let cell = lattice::lattice_vectors(ibrav, ALAT);
+    for (label, &k) in path.labels.iter().zip(panel_bounds(path)) {
+        let k_length = length(cartesian(&cell, k));
+        for n0 in -2..3 {
+            for n1 in -2..3 {
+                for n2 in -2..3 {
+                    let n = [f64::from(n0), f64::from(n1), f64::from(n2)];
+                    let shifted = [k[0] - n[0], k[1] - n[1], k[2] - n[2]];
+                    assert!(
+                        k_length <= length(cartesian(&cell, shifted)) + 1e-8,
+                        "{:?} {} {:?} is outside the first zone",
+                        path.lattice_type,
+                        label,
+                        k
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn fcc_path() {
+    let path = kpath::setyawan_curtarolo(&Ibrav::Fcc, ALAT, 20).unwrap();
+
+    assert_eq!(path.lattice_type, LatticeType::Fcc);
+    assert_eq!(path.segments.len(), 2);
+    assert_eq!(
+        path.labels,
+        vec!["Γ", "X", "W", "K", "Γ", "L", "U", "W", "L", "K", "U", "X"]
+    );
+    assert_eq!(panel_bounds(&path).len(), path.labels.len());
+    // The path jumps from K to U.
+    assert_eq!(path.jumps, vec![9]);
+
+    for (label, k) in lengths(&Ibrav::Fcc, &path) {
+        let expected = match label.as_str() {
+            "Γ" => 0.0,
+            "X" => 1.0,
+            "L" => 3.0f64.sqrt() / 2.0,
+            "K" | "U" => 3.0 * 2.0f64.sqrt() / 4.0,
+            "W" => 1.25f64.sqrt(),
+            _ => panic!("unexpected label {}", label),
+        };
+        assert!((k - expected).abs() < 1e-10, "{} {} {}", label, k, expected);
+    }
+}
+
+#[test]
+fn lattice_types_of_ibrav() {
+    let cases = vec![
+        (Ibrav::SimpleCubic, LatticeType::Cub),
+        (Ibrav::Bcc, LatticeType::Bcc),
+        (Ibrav::BccSymmetric, LatticeType::Bcc),
+        (Ibrav::Hexagonal(1.6), LatticeType::Hex),
+        (Ibrav::TrigonalRAxisC(0.3), LatticeType::Rhl1),
+        (Ibrav::TrigonalRAxis111(-0.3), LatticeType::Rhl2),
+        (Ibrav::TetragonalP(1.4), LatticeType::Tet),
+        (Ibrav::TetragonalI(0.8), LatticeType::Bct1),
+        (Ibrav::TetragonalI(1.5), LatticeType::Bct2),
+        (Ibrav::OrthorhombicP(1.2, 1.5), LatticeType::Orc),
+        (Ibrav::OrthorhombicBco(1.3, 1.7), LatticeType::Orcc),
+        (
+            Ibrav::OrthorhombicFaceCentered(1.5, 2.0),
+            LatticeType::Orcf1,
+        ),
+        (
+            Ibrav::OrthorhombicFaceCentered(1.2, 1.3),
+            LatticeType::Orcf2,
+        ),
+        (
+            Ibrav::OrthorhombicFaceCentered(1.25, 5.0 / 3.0),
+            LatticeType::Orcf3,
+        ),
+        (Ibrav::OrthorhombicBodyCentered(1.2, 1.5), LatticeType::Orci),
+        (
+            Ibrav::MonoclinicPUniqueAxisC(1.2, 1.5, 0.2),
+            LatticeType::Mcl,
+        ),
+        (
+            Ibrav::MonoclinicPUniqueAxisB(1.2, 1.5, -0.3),
+            LatticeType::Mcl,
+        ),
+        (
+            Ibrav::MonoclinicBaseCentered(0.6, 0.6, -0.4),
+            LatticeType::Mclc1,
+        ),
+        (
+            Ibrav::MonoclinicBaseCentered(0.8, 0.8, -0.6),
+            LatticeType::Mclc2,
+        ),
+        (
+            Ibrav::MonoclinicBaseCentered(1.2, 1.5, 0.2),
+            LatticeType::Mclc3,
+        ),
+        (
+            Ibrav::MonoclinicBaseCentered(2.5, 1.1, 0.1),
+            LatticeType::Mclc3,
+        ),
+        (
+            Ibrav::MonoclinicBaseCentered(0.6, 1.2, -0.2),
+            LatticeType::Mclc4,
+        ),
+        (
+            Ibrav::MonoclinicBaseCentered(0.6, 1.1, -0.4),
+            LatticeType::Mclc5,
+        ),
+        (
+            Ibrav::Triclinic(1.2, 1.5, 0.1, 0.2, 0.3),
+            LatticeType::Tri1a,
+        ),
+        (
+            Ibrav::Triclinic(1.1, 1.3, -0.2, -0.1, -0.3),
+            LatticeType::Tri1b,
+        ),
+        (
+            Ibrav::Triclinic(1.1, 1.5, -0.6, -0.6, -0.1),
+            LatticeType::Tri2a,
+        ),
+        (
+            Ibrav::Triclinic(1.1, 1.5, -0.4, 0.4, 0.4),
+            LatticeType::Tri2b,
+        ),
+    ];
+
+    for (ibrav, lattice_type) in cases {
+        let path = kpath::setyawan_curtarolo(&ibrav, ALAT, 10).unwrap();
+        assert_eq!(path.lattice_type, lattice_type, "{:?}", ibrav);
+        assert_eq!(panel_bounds(&path).len(), path.labels.len());
+        assert_in_first_zone(&ibrav, &path);
+    }
+}
+
+#[test]
+fn path_independent_of_cell_basis() {
+    for ibrav in &[
+        Ibrav::Bcc,
+        Ibrav::Hexagonal(1.6),
+        Ibrav::TetragonalI(1.5),
+        Ibrav::OrthorhombicFaceCentered(1.5, 2.0),
+        Ibrav::MonoclinicBaseCentered(1.2, 1.5, 0.2),
+        Ibrav::Triclinic(1.2, 1.5, 0.1, 0.2, 0.3),
+    ] {
+        let cell = lattice::lattice_vectors(ibrav, 1.0);
+        // A different choice of primitive vectors for the same lattice.
+        let mut other = [[0.0; 3]; 3];
+        for j in 0..3 {
+            other[0][j] = cell[0][j] + cell[1][j];
+            other[1][j] = cell[1][j];
+            other[2][j] = cell[2][j] - 2.0 * cell[1][j] + cell[0][j];
+        }
+        let free = Ibrav::Free(Cell {
+            units: LatticeUnits::Alat,
+            cell: other,
+        });
+
+        let path = kpath::setyawan_curtarolo(ibrav, ALAT, 10).unwrap();
+        let free_path = kpath::setyawan_curtarolo(&free, ALAT, 10).unwrap();
+
+        assert_eq!(path.lattice_type, free_path.lattice_type, "{:?}", ibrav);
+        assert_eq!(path.labels, free_path.labels);
+        for ((label, k), (_, free_k)) in lengths(ibrav, &path)
+            .into_iter()
+            .zip(lengths(&free, &free_path))
+        {
+            assert!((k - free_k).abs() < 1e-8, "{:?} {}", ibrav, label);
+        }
+    }
+}
+
+#[test]
+fn degenerate_cell() {
+    let free = Ibrav::Free(Cell {
+        units: LatticeUnits::Alat,
+        cell: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0]],
+    });
+
+    assert!(matches!(
+        kpath::lattice_type(&free, ALAT),
+        Err(kpath::Error::CellVolume)
+    ));
+}
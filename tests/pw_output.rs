@@ -0,0 +1,174 @@
+extern crate qe;
+
+use qe::pw::input;
+use qe::pw::output;
+use qe::pw::output::{BandEnergies, CalculationOutput, FermiEnergy};
+
+const FE_SCF_OUT: &str = include_str!("data/fe_scf.out");
+const SI_BANDS_OUT: &str = include_str!("data/si_bands.out");
+const SI_RELAX_OUT: &str = include_str!("data/si_relax.out");
+const SI_RELAX_HIGH_OUT: &str = include_str!("data/si_relax_high.out");
+
+#[test]
+fn parse_scf_output() {
+    let calculation = input::Calculation::Scf { conv_thr: 1e-10 };
+    let out = output::parse_output(FE_SCF_OUT, &calculation).unwrap();
+
+    assert_eq!(out.n_electrons, 16.0);
+    assert!(out.job_done);
+    assert_eq!(out.wall_time, Some(1.37));
+    assert!(out.warnings.is_empty());
+
+    let scf = match out.result {
+        CalculationOutput::Scf(scf) => scf,
+        other => panic!("expected scf output; got {:?}", other),
+    };
+
+    assert!(scf.converged);
+    assert_eq!(scf.iterations.len(), 2);
+    assert_eq!(scf.iterations[1].total_energy, -329.57381226);
+    assert_eq!(scf.iterations[1].estimated_scf_accuracy, Some(0.01913426));
+    assert_eq!(scf.total_energy, Some(-329.57532046));
+    assert_eq!(scf.fermi_energy, Some(FermiEnergy::Fermi(17.6932)));
+
+    match scf.band_energies {
+        Some(BandEnergies::Polarized { ref up, ref down }) => {
+            assert_eq!(up.len(), 3);
+            assert_eq!(down.len(), 3);
+            assert_eq!(up[1].k, [-0.5, 0.5, -0.5]);
+            assert_eq!(up[1].energies.len(), 12);
+            assert_eq!(down[2].energies[11], 32.2218);
+        }
+        ref other => panic!("expected polarized band energies; got {:?}", other),
+    }
+
+    let forces = scf.forces.unwrap();
+    assert_eq!(forces.atoms, vec![[0.0, 0.0, 0.0]]);
+    assert_eq!(forces.total_force, 0.0);
+
+    let stress = scf.stress.unwrap();
+    assert_eq!(stress.pressure, -20.65);
+    assert_eq!(stress.stress[1][1], -0.00014038);
+}
+
+#[test]
+fn parse_bands_output() {
+    let calculation = input::Calculation::Bands {
+        diago_thr_init: Some(1e-10),
+        nbnd: Some(8),
+        nosym: None,
+    };
+    let out = output::parse_output(SI_BANDS_OUT, &calculation).unwrap();
+
+    let bands = match out.result {
+        CalculationOutput::Bands(bands) => bands,
+        other => panic!("expected bands output; got {:?}", other),
+    };
+
+    assert_eq!(bands.fermi_energy, None);
+    match bands.band_energies {
+        BandEnergies::Unpolarized(ref ks) => {
+            assert_eq!(ks.len(), 4);
+            assert_eq!(ks[3].k, [0.5, 0.0, 0.5]);
+            assert_eq!(ks[3].energies[0], -1.6175);
+            assert_eq!(ks[3].energies.len(), 8);
+        }
+        ref other => panic!("expected unpolarized band energies; got {:?}", other),
+    }
+
+    // The same output does not contain the results of a self-consistent calculation.
+    let scf = input::Calculation::Scf { conv_thr: 1e-10 };
+    assert!(output::parse_output(SI_BANDS_OUT, &scf).is_err());
+}
+
+#[test]
+fn parse_relax_output() {
+    let calculation = input::Calculation::Relax {
+        conv_thr: 1e-8,
+        relax: input::Relax {
+            nstep: None,
+            etot_conv_thr: None,
+            forc_conv_thr: None,
+        },
+        ion_dynamics: input::RelaxIonDynamics::Bfgs,
+    };
+    let out = output::parse_output(SI_RELAX_OUT, &calculation).unwrap();
+
+    assert_eq!(
+        out.warnings,
+        vec!["WARNING:      1 eigenvalues not converged"]
+    );
+
+    let relax = match out.result {
+        CalculationOutput::Relax(relax) => relax,
+        other => panic!("expected relax output; got {:?}", other),
+    };
+
+    assert!(relax.converged);
+    assert_eq!(relax.steps.len(), 2);
+    assert_eq!(
+        relax.steps[0].fermi_energy,
+        Some(FermiEnergy::HighestOccupied(6.2891, None))
+    );
+    assert_eq!(relax.steps[1].total_energy, Some(-15.79859045));
+    assert_eq!(
+        relax.steps[1].forces.as_ref().unwrap().atoms[1],
+        [-0.00002551, -0.00002551, -0.00002551]
+    );
+}
+
+#[test]
+fn parse_high_verbosity_forces() {
+    let calculation = input::Calculation::Relax {
+        conv_thr: 1e-8,
+        relax: input::Relax {
+            nstep: None,
+            etot_conv_thr: None,
+            forc_conv_thr: None,
+        },
+        ion_dynamics: input::RelaxIonDynamics::Bfgs,
+    };
+    let out = output::parse_output(SI_RELAX_HIGH_OUT, &calculation).unwrap();
+
+    let relax = match out.result {
+        CalculationOutput::Relax(relax) => relax,
+        other => panic!("expected relax output; got {:?}", other),
+    };
+
+    // The contributions to the forces are skipped.
+    let forces = relax.steps[0].forces.as_ref().unwrap();
+    assert_eq!(
+        forces.atoms,
+        vec![
+            [-0.01226474, -0.01226474, -0.01226474],
+            [0.01226474, 0.01226474, 0.01226474],
+        ]
+    );
+    assert_eq!(forces.total_force, 0.030042);
+    assert_eq!(relax.steps[1].forces.as_ref().unwrap().total_force, 0.000062);
+}
+
+#[test]
+fn parse_output_error() {
+    let text = SI_BANDS_OUT.replace(
+        "     End of band structure calculation",
+        " %%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%%
+     Error in routine c_bands (1):
+     too many bands are not converged",
+    );
+    let calculation = input::Calculation::Nscf {
+        diago_thr_init: Some(1e-10),
+        nbnd: None,
+        nosym: None,
+    };
+
+    match output::parse_output(&text, &calculation) {
+        Err(output::Error::Pw {
+            routine, message, ..
+        }) => {
+            assert_eq!(routine, "c_bands (1):");
+            assert_eq!(message, "too many bands are not converged");
+        }
+        other => panic!("expected pw.x error; got {:?}", other),
+    }
+}
@@ -0,0 +1,604 @@
+extern crate qe;
+
+use qe::pw::input;
+use qe::pw::parse;
+use qe::pw::serialize;
+
+const FE_SCF: &str = "
+ &CONTROL
+    calculation = 'scf',  ! self-consistent run
+    prefix = 'fe', pseudo_dir = './pseudo', outdir = './tmp'
+    disk_io = 'low'
+ /
+ &system
+    ibrav = 0, celldm(1) = 5.42,
+    nat = 1, ntyp = 1,
+    ecutwfc = 4.5D1, ecutrho = 3.6d2
+    occupations = 'smearing', smearing = 'mv', degauss = 0.02
+    nspin = 2, starting_magnetization(1) = 0.6
+ /
+ &electrons
+    conv_thr = 1.0d-10
+ /
+ATOMIC_SPECIES
+ Fe 55.845 Fe.pbe-spn-kjpaw_psl.0.2.1.UPF
+CELL_PARAMETERS {alat}
+ -0.5 0.5 0.5
+ 0.5 -0.5 0.5
+ 0.5 0.5 -0.5
+ATOMIC_POSITIONS {crystal}
+ Fe 0.0 0.0 0.0
+K_POINTS automatic
+ 12 12 12 1 1 1
+";
+
+#[test]
+fn parse_pw_input() {
+    let input = parse::parse_input_file(FE_SCF).unwrap();
+
+    assert_eq!(
+        input.calculation,
+        input::Calculation::Scf { conv_thr: 1e-10 }
+    );
+    assert_eq!(input.control.prefix, Some(String::from("fe")));
+    assert_eq!(input.control.out_dir, Some("./tmp".into()));
+    assert_eq!(input.control.disk_io, Some(input::DiskIO::Low));
+    assert_eq!(input.system.alat, 5.42);
+    assert_eq!(input.system.ecutrho, 360.0);
+    assert_eq!(
+        input.system.occupations,
+        input::Occupations::Smearing(input::Smearing::MarzariVanderbilt, 0.02)
+    );
+    assert_eq!(
+        input.system.spin_type,
+        Some(input::SpinType::CollinearPolarized(input::Magnetization {
+            starting_magnetization: vec![Some(0.6)],
+            ..input::Magnetization::default()
+        }))
+    );
+    assert_eq!(input.species[0].mass, 55.845);
+    assert_eq!(
+        input.k_points,
+        input::KPoints::Automatic {
+            nk: [12, 12, 12],
+            sk: Some([true, true, true]),
+        }
+    );
+
+    input::validate(&input).unwrap();
+}
+
+#[test]
+fn pw_input_round_trip() {
+    let input = parse::parse_input_file(FE_SCF).unwrap();
+    let text = serialize::make_input_file(&input).unwrap();
+
+    assert!(text.contains("outdir='./tmp',"), "{}", text);
+    assert_eq!(parse::parse_input_file(&text).unwrap(), input);
+}
+
+#[test]
+fn pw_input_round_trip_angstrom_cell() {
+    let text = FE_SCF
+        .replace("ibrav = 0, celldm(1) = 5.42,", "ibrav = 0,")
+        .replace("CELL_PARAMETERS {alat}", "CELL_PARAMETERS angstrom");
+    let input = parse::parse_input_file(&text).unwrap();
+
+    // `alat` is taken from the cell, so `celldm(1)` is not written.
+    assert!((input.system.alat - 0.75f64.sqrt() / qe::units::BOHR_ANGSTROM).abs() < 1e-10);
+    let written = serialize::make_input_file(&input).unwrap();
+    assert!(!written.contains("celldm"), "{}", written);
+    assert!(written.contains("CELL_PARAMETERS angstrom"), "{}", written);
+    assert_eq!(parse::parse_input_file(&written).unwrap(), input);
+}
+
+#[test]
+fn parse_pw_input_defaults() {
+    // The pw.x defaults are used for `ecutrho`, `conv_thr` and `dt`.
+    let text = FE_SCF
+        .replace(", ecutrho = 3.6d2", "")
+        .replace("conv_thr = 1.0d-10", "")
+        .replace("'scf'", "'md', nstep = 50");
+
+    let input = parse::parse_input_file(&text).unwrap();
+
+    assert_eq!(input.system.ecutrho, 180.0);
+    match input.calculation {
+        input::Calculation::Md { conv_thr, ref md, .. } => {
+            assert_eq!(conv_thr, 1e-6);
+            assert_eq!(md.dt, 20.0);
+        }
+        ref other => panic!("expected md calculation; got {:?}", other),
+    }
+
+    // `diago_thr_init` is left to pw.x.
+    let text = FE_SCF.replace("'scf'", "'nscf'").replace("conv_thr = 1.0d-10", "");
+    let input = parse::parse_input_file(&text).unwrap();
+
+    assert_eq!(
+        input.calculation,
+        input::Calculation::Nscf {
+            diago_thr_init: None,
+            nbnd: None,
+            nosym: None,
+        }
+    );
+    let written = serialize::make_input_file(&input).unwrap();
+    assert!(!written.contains("diago_thr_init"), "{}", written);
+    assert_eq!(parse::parse_input_file(&written).unwrap(), input);
+}
+
+#[test]
+fn parse_pw_input_unknown_field() {
+    let text = FE_SCF.replace("nspin = 2", "nspin = 2, ecfixed = 16.0");
+
+    match parse::parse_input_file(&text) {
+        Err(parse::Error::UnknownField { line, field, .. }) => {
+            assert_eq!(line, 12);
+            assert_eq!(field, "ecfixed");
+        }
+        other => panic!("expected unknown field error; got {:?}", other),
+    }
+
+    // Magnetic parameters cannot be given without spin polarization.
+    let text = FE_SCF.replace("nspin = 2", "nspin = 1");
+
+    match parse::parse_input_file(&text) {
+        Err(parse::Error::UnknownField { field, .. }) => {
+            assert_eq!(field, "starting_magnetization(1)")
+        }
+        other => panic!("expected unknown field error; got {:?}", other),
+    }
+}
+
+const GRAPHITE_SCF: &str = "
+ &control
+    calculation = 'scf'
+ /
+ &system
+    ibrav = 4, celldm(1) = 4.65, celldm(3) = 2.72,
+    nat = 2, ntyp = 1,
+    ecutwfc = 40, ecutrho = 320,
+    occupations = 'smearing', smearing = 'gaussian', degauss = 0.01
+ /
+ &electrons
+    conv_thr = 1e-8
+ /
+ATOMIC_SPECIES
+ C 12.011 C.pbe-n-kjpaw_psl.1.0.0.UPF
+ATOMIC_POSITIONS crystal
+ C 0.0 0.0 0.25
+ C 0.0 0.0 0.75
+K_POINTS automatic
+ 12 12 4 0 0 0
+";
+
+#[test]
+fn parse_pw_input_bravais_lattice() {
+    let input = parse::parse_input_file(GRAPHITE_SCF).unwrap();
+
+    assert_eq!(input.system.ibrav, input::Ibrav::Hexagonal(2.72));
+    assert_eq!(input.system.alat, 4.65);
+
+    let text = serialize::make_input_file(&input).unwrap();
+    assert_eq!(parse::parse_input_file(&text).unwrap(), input);
+
+    let missing = GRAPHITE_SCF.replace("celldm(3) = 2.72,", "");
+    match parse::parse_input_file(&missing) {
+        Err(parse::Error::Missing { field, .. }) => assert_eq!(field, "celldm(3)"),
+        other => panic!("expected missing field error; got {:?}", other),
+    }
+
+    let invalid = GRAPHITE_SCF.replace("celldm(3) = 2.72", "celldm(3) = -2.72");
+    let input = parse::parse_input_file(&invalid).unwrap();
+    let errs = input::validate(&input).unwrap_err();
+    assert!(errs
+        .errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::LatticeRatio(_))));
+}
+
+#[test]
+fn parse_pw_input_vc_relax() {
+    let text = FE_SCF
+        .replace("'scf'", "'vc-relax', forc_conv_thr = 1d-4, nstep = 100")
+        .replace(
+            "ATOMIC_SPECIES",
+            " &ions
+    ion_dynamics = 'bfgs'
+ /
+ &cell
+    cell_dynamics = 'bfgs', press = 10.0, cell_dofree = 'shape'
+ /
+ATOMIC_SPECIES",
+        );
+
+    let input = parse::parse_input_file(&text).unwrap();
+
+    assert_eq!(
+        input.calculation,
+        input::Calculation::VcRelax {
+            conv_thr: 1e-10,
+            relax: input::Relax {
+                nstep: Some(100),
+                etot_conv_thr: None,
+                forc_conv_thr: Some(1e-4),
+            },
+            dynamics: input::VcRelaxDynamics::Bfgs,
+            cell: input::VariableCell {
+                press: Some(10.0),
+                wmass: None,
+                cell_factor: None,
+                cell_dofree: Some(input::CellDofree::Shape),
+            },
+            press_conv_thr: None,
+        }
+    );
+
+    let written = serialize::make_input_file(&input).unwrap();
+    assert!(written.contains(" &cell\n    cell_dynamics='bfgs',"));
+    assert_eq!(parse::parse_input_file(&written).unwrap(), input);
+
+    // `bfgs` is the default for both ions and cell.
+    let default = text
+        .replace("    ion_dynamics = 'bfgs'\n", "")
+        .replace("cell_dynamics = 'bfgs', ", "");
+    assert_eq!(parse::parse_input_file(&default).unwrap(), input);
+
+    let mismatched = text.replace("ion_dynamics = 'bfgs'", "ion_dynamics = 'damp'");
+    match parse::parse_input_file(&mismatched) {
+        Err(parse::Error::Value { field, .. }) => assert_eq!(field, "cell_dynamics"),
+        other => panic!("expected invalid value error; got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_pw_input_md() {
+    let text = FE_SCF
+        .replace("'scf'", "'md', dt = 40, nstep = 50")
+        .replace(
+            "ATOMIC_SPECIES",
+            " &ions
+    ion_temperature = 'rescale-v', tempw = 600, nraise = 10
+ /
+ATOMIC_SPECIES",
+        );
+
+    let input = parse::parse_input_file(&text).unwrap();
+
+    assert_eq!(
+        input.calculation,
+        input::Calculation::Md {
+            conv_thr: 1e-10,
+            md: input::MolecularDynamics {
+                dt: 40.0,
+                nstep: Some(50),
+                tempw: Some(600.0),
+                ion_temperature: Some(input::IonTemperature::RescaleV { nraise: Some(10) }),
+            },
+            ion_dynamics: input::MdIonDynamics::Verlet,
+        }
+    );
+
+    let written = serialize::make_input_file(&input).unwrap();
+    assert_eq!(parse::parse_input_file(&written).unwrap(), input);
+
+    // `tolp` is only used with `ion_temperature = 'rescaling'`.
+    let irrelevant = text.replace("nraise = 10", "nraise = 10, tolp = 50");
+    match parse::parse_input_file(&irrelevant) {
+        Err(parse::Error::UnknownField { field, .. }) => assert_eq!(field, "tolp"),
+        other => panic!("expected unknown field error; got {:?}", other),
+    }
+    // Starting velocities are not represented in `Input`.
+    let velocities = text.replace(
+        "K_POINTS",
+        "ATOMIC_VELOCITIES a.u.
+ Fe 0.0 0.0 0.001
+K_POINTS",
+    );
+    match parse::parse_input_file(&velocities) {
+        Err(parse::Error::UnknownCard { card, .. }) => assert_eq!(card, "ATOMIC_VELOCITIES"),
+        other => panic!("expected unknown card error; got {:?}", other),
+    }
+}
+
+const FEO_SCF: &str = "
+ &control
+    calculation = 'scf', prefix = 'feo'
+ /
+ &system
+    ibrav = 2, celldm(1) = 8.19, nat = 2, ntyp = 2,
+    ecutwfc = 45, ecutrho = 360,
+    occupations = 'smearing', smearing = 'mv', degauss = 0.02
+    nspin = 2, starting_magnetization(1) = 0.5
+    lda_plus_u = .true., U_projection_type = 'ortho-atomic',
+    Hubbard_U(1) = 4.3, Hubbard_J0(1) = 0.9, Hubbard_alpha(1) = 0.05
+ /
+ &electrons
+    conv_thr = 1e-8
+ /
+ATOMIC_SPECIES
+ Fe 55.845 Fe.pbe-spn-kjpaw_psl.0.2.1.UPF
+ O 15.999 O.pbe-n-kjpaw_psl.0.1.UPF
+ATOMIC_POSITIONS crystal
+ Fe 0.0 0.0 0.0
+ O 0.5 0.5 0.5
+K_POINTS automatic
+ 8 8 8 0 0 0
+";
+
+#[test]
+fn parse_pw_input_hubbard() {
+    let input = parse::parse_input_file(FEO_SCF).unwrap();
+    let expected = input::Hubbard {
+        kind: input::HubbardKind::Simplified,
+        projection: input::HubbardProjection::OrthoAtomic,
+        parameters: vec![input::HubbardParameters {
+            species: String::from("Fe"),
+            manifold: None,
+            u: 4.3,
+            j0: Some(0.9),
+            j: None,
+            alpha: Some(0.05),
+        }],
+        card: false,
+    };
+    assert_eq!(input.hubbard, Some(expected.clone()));
+    input::validate(&input).unwrap();
+
+    let written = serialize::make_input_file(&input).unwrap();
+    assert!(written.contains("Hubbard_U(1)=4.3,"), "{}", written);
+    assert_eq!(parse::parse_input_file(&written).unwrap(), input);
+
+    // The same parameters given by the QE 7 `HUBBARD` card.
+    let text = FEO_SCF
+        .replace(
+            "lda_plus_u = .true., U_projection_type = 'ortho-atomic',",
+            "",
+        )
+        .replace("Hubbard_U(1) = 4.3, Hubbard_J0(1) = 0.9, ", "")
+        + "HUBBARD {ortho-atomic}\n U Fe-3d 4.3\n J0 Fe-3d 0.9\n";
+    let input = parse::parse_input_file(&text).unwrap();
+    let mut card = expected.clone();
+    card.parameters[0].manifold = Some(String::from("3d"));
+    card.card = true;
+    assert_eq!(input.hubbard, Some(card));
+
+    let written = serialize::make_input_file(&input).unwrap();
+    assert!(!written.contains("lda_plus_u"), "{}", written);
+    assert!(written.contains("Hubbard_alpha(1)=0.05,"), "{}", written);
+    assert!(written.ends_with("HUBBARD ortho-atomic\n U Fe-3d 4.3\n J0 Fe-3d 0.9"));
+    assert_eq!(parse::parse_input_file(&written).unwrap(), input);
+
+    // Each species in the card needs a U value.
+    let missing = text.replace(" U Fe-3d 4.3\n", "");
+    match parse::parse_input_file(&missing) {
+        Err(parse::Error::Card { card, .. }) => assert_eq!(card, "HUBBARD"),
+        other => panic!("expected card error; got {:?}", other),
+    }
+
+    // Values for a species without U cannot be represented.
+    let unused = FEO_SCF.replace("Hubbard_alpha(1) = 0.05", "Hubbard_J0(2) = 0.1");
+    match parse::parse_input_file(&unused) {
+        Err(parse::Error::UnknownField { field, .. }) => assert_eq!(field, "hubbard_j0(2)"),
+        other => panic!("expected unknown field error; got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_pw_input_functional() {
+    let text = GRAPHITE_SCF.replace(
+        "degauss = 0.01",
+        "degauss = 0.01
+    input_dft = 'HSE', nqx1 = 4, nqx2 = 4, exxdiv_treatment = 'gygi-baldereschi'
+    ecutfock = 160, vdw_corr = 'dft-d3', dftd3_version = 4",
+    );
+    let input = parse::parse_input_file(&text).unwrap();
+
+    assert_eq!(
+        input.system.input_dft,
+        Some(input::Functional::Hybrid(
+            input::HybridFunctional::Hse,
+            input::Hybrid {
+                nqx: Some([4, 4, 1]),
+                exxdiv_treatment: Some(input::ExxdivTreatment::GygiBaldereschi),
+                ecutfock: Some(160.0),
+                exx_fraction: None,
+            }
+        ))
+    );
+    assert_eq!(
+        input.system.vdw_corr,
+        Some(input::VdwCorr::GrimmeD3 {
+            version: Some(input::Dftd3Version::BeckeJohnson),
+            threebody: None,
+        })
+    );
+    input::validate(&input).unwrap();
+
+    let written = serialize::make_input_file(&input).unwrap();
+    assert!(written.contains("input_dft='hse',"), "{}", written);
+    assert!(written.contains("vdw_corr='grimme-d3',"), "{}", written);
+    assert_eq!(parse::parse_input_file(&written).unwrap(), input);
+
+    // Exact exchange parameters are only used for hybrid functionals.
+    let semilocal = text.replace("'HSE'", "'pbesol'");
+    match parse::parse_input_file(&semilocal) {
+        Err(parse::Error::UnknownField { field, .. }) => assert_eq!(field, "nqx1"),
+        other => panic!("expected unknown field error; got {:?}", other),
+    }
+
+    let unknown = text.replace("'dft-d3'", "'mbd'");
+    match parse::parse_input_file(&unknown) {
+        Err(parse::Error::Value { field, .. }) => assert_eq!(field, "vdw_corr"),
+        other => panic!("expected invalid value error; got {:?}", other),
+    }
+}
+
+const ALAS_BERRY: &str = "
+ &control
+    calculation = 'nscf', prefix = 'alas'
+    lberry = .true., gdir = 3, nppstr = 3
+ /
+ &system
+    ibrav = 2, celldm(1) = 10.5, nat = 2, ntyp = 2,
+    ecutwfc = 30, ecutrho = 240, nosym = .true.
+ /
+ &electrons
+    diago_thr_init = 1e-6
+ /
+ATOMIC_SPECIES
+ Al 26.98 Al.UPF
+ As 74.92 As.UPF
+ATOMIC_POSITIONS crystal
+ Al 0.0 0.0 0.0
+ As 0.25 0.25 0.25
+K_POINTS crystal
+ 6
+ 0.0 0.0 0.0 1
+ 0.0 0.0 0.5 1
+ 0.0 0.0 1.0 1
+ 0.0 0.5 0.0 1
+ 0.0 0.5 0.5 1
+ 0.0 0.5 1.0 1
+";
+
+#[test]
+fn parse_pw_input_berry_phase() {
+    let input = parse::parse_input_file(ALAS_BERRY).unwrap();
+    let strings = input::KPointStrings {
+        gdir: input::LatticeDirection::D3,
+        nppstr: 3,
+    };
+
+    assert_eq!(input.berry_phase, Some(strings.clone()));
+    assert_eq!(input.efield, None);
+    let ks = input::generate_kpoint_strings([1, 2, 3], &input::LatticeDirection::D3, true)
+        .unwrap();
+    let expected: Vec<[f64; 4]> = ks.iter().map(|k| [k[0], k[1], k[2], 1.0]).collect();
+    assert_eq!(input.k_points, input::KPoints::Crystal(expected));
+    input::validate(&input).unwrap();
+
+    let written = serialize::make_input_file(&input).unwrap();
+    assert!(written.contains("lberry=.true.,"), "{}", written);
+    assert_eq!(parse::parse_input_file(&written).unwrap(), input);
+
+    // A finite field uses strings without the periodic endpoint, in an scf calculation for
+    // which `nosym` is set implicitly.
+    let text = ALAS_BERRY
+        .replace("'nscf'", "'scf'")
+        .replace("lberry = .true.", "lelfield = .true., nberrycyc = 2")
+        .replace(", nosym = .true.", "")
+        .replace("diago_thr_init = 1e-6", "conv_thr = 1e-8, efield_cart(3) = 0.001")
+        .replace("nppstr = 3", "nppstr = 2")
+        .replace(" 0.0 0.0 1.0 1\n", "")
+        .replace(" 0.0 0.5 1.0 1\n", "")
+        .replace(" 6\n", " 4\n");
+    let input = parse::parse_input_file(&text).unwrap();
+
+    assert_eq!(
+        input.efield,
+        Some(input::Efield::LelField {
+            efield_cart: [0.0, 0.0, 0.001],
+            nberrycyc: Some(2),
+            strings: input::KPointStrings { nppstr: 2, ..strings },
+        })
+    );
+    assert_eq!(input.berry_phase, None);
+    input::validate(&input).unwrap();
+
+    let written = serialize::make_input_file(&input).unwrap();
+    assert!(written.contains("nosym=.true.,"), "{}", written);
+    assert_eq!(parse::parse_input_file(&written).unwrap(), input);
+
+    let both = text.replace("lelfield = .true.", "lelfield = .true., lberry = .true.");
+    match parse::parse_input_file(&both) {
+        Err(parse::Error::Value { field, .. }) => assert_eq!(field, "lberry"),
+        other => panic!("expected invalid value error; got {:?}", other),
+    }
+}
+
+#[test]
+fn parse_pw_input_k_point_types() {
+    let gamma = GRAPHITE_SCF.replace("K_POINTS automatic\n 12 12 4 0 0 0\n", "K_POINTS gamma\n");
+    let input = parse::parse_input_file(&gamma).unwrap();
+
+    assert_eq!(input.k_points, input::KPoints::Gamma);
+    let written = serialize::make_input_file(&input).unwrap();
+    assert!(written.ends_with("K_POINTS gamma"), "{}", written);
+    assert_eq!(parse::parse_input_file(&written).unwrap(), input);
+
+    // A list without an option is in units of 2 pi / alat.
+    let tpiba = GRAPHITE_SCF.replace(
+        "K_POINTS automatic\n 12 12 4 0 0 0\n",
+        "K_POINTS\n 2\n 0.0 0.0 0.0 1\n 0.0 0.0 0.5 3\n",
+    );
+    let input = parse::parse_input_file(&tpiba).unwrap();
+
+    assert_eq!(
+        input.k_points,
+        input::KPoints::TwoPiByACartesian(vec![[0.0, 0.0, 0.0, 1.0], [0.0, 0.0, 0.5, 3.0]])
+    );
+    let written = serialize::make_input_file(&input).unwrap();
+    assert!(written.contains("K_POINTS tpiba"), "{}", written);
+    assert_eq!(parse::parse_input_file(&written).unwrap(), input);
+
+    let bands = GRAPHITE_SCF
+        .replace("'scf'", "'bands'")
+        .replace("conv_thr = 1e-8", "diago_thr_init = 1e-6")
+        .replace(
+            "K_POINTS automatic\n 12 12 4 0 0 0\n",
+            "K_POINTS {tpiba_b}\n 3\n 0.0 0.0 0.0 20\n 0.5 0.0 0.0 20\n 0.5 0.2887 0.0 1\n",
+        );
+    let input = parse::parse_input_file(&bands).unwrap();
+
+    assert_eq!(
+        input.k_points,
+        input::KPoints::TwoPiByACartesianBands {
+            nk_per_panel: 20,
+            panel_bounds: vec![[0.0, 0.0, 0.0], [0.5, 0.0, 0.0], [0.5, 0.2887, 0.0]],
+        }
+    );
+    assert_eq!(
+        parse::parse_input_file(&serialize::make_input_file(&input).unwrap()).unwrap(),
+        input
+    );
+
+    // Gamma-only wavefunctions cannot give bands at other k-points.
+    let gamma_bands = bands.replace(
+        "K_POINTS {tpiba_b}\n 3\n 0.0 0.0 0.0 20\n 0.5 0.0 0.0 20\n 0.5 0.2887 0.0 1\n",
+        "K_POINTS gamma\n",
+    );
+    let input = parse::parse_input_file(&gamma_bands).unwrap();
+    let errs = input::validate(&input).unwrap_err().errs;
+    assert!(matches!(errs[0], input::Error::GammaBands));
+
+    let contour = bands.replace(
+        "K_POINTS {tpiba_b}\n 3\n 0.0 0.0 0.0 20\n 0.5 0.0 0.0 20\n 0.5 0.2887 0.0 1\n",
+        "K_POINTS crystal_c\n 3\n -0.5 -0.5 0.0 1\n 0.5 -0.5 0.0 30\n -0.5 0.5 0.0 40\n",
+    );
+    let input = parse::parse_input_file(&contour).unwrap();
+
+    assert_eq!(
+        input.k_points,
+        input::KPoints::CrystalContour {
+            corners: [[-0.5, -0.5, 0.0], [0.5, -0.5, 0.0], [-0.5, 0.5, 0.0]],
+            nk: [30, 40],
+        }
+    );
+    input::validate(&input).unwrap();
+    let written = serialize::make_input_file(&input).unwrap();
+    assert!(written.contains("-0.5 0.5 0 40"), "{}", written);
+    assert_eq!(parse::parse_input_file(&written).unwrap(), input);
+
+    let degenerate = contour.replace("-0.5 0.5 0.0 40", "-0.5 -0.5 0.0 40");
+    let input = parse::parse_input_file(&degenerate).unwrap();
+    let errs = input::validate(&input).unwrap_err().errs;
+    assert!(matches!(errs[0], input::Error::Contour));
+
+    let missing = contour.replace(" 3\n", " 2\n").replace(" -0.5 0.5 0.0 40\n", "");
+    match parse::parse_input_file(&missing) {
+        Err(parse::Error::Card { card, .. }) => assert_eq!(card, "K_POINTS"),
+        other => panic!("expected invalid card error; got {:?}", other),
+    }
+}
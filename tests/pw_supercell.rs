@@ -0,0 +1,308 @@
+extern crate qe;
+
+use qe::pw::input::{
+    Calculation, Hubbard, HubbardKind, HubbardParameters, HubbardProjection, Ibrav, Input, KPoints,
+    LatticeUnits, Magnetization, Species, SpinType,
+};
+use qe::pw::{lattice, parse, serialize, supercell};
+
+const SI_SCF: &str = "
+ &control
+    calculation = 'scf', prefix = 'si'
+ /
+ &system
+    ibrav = 2, celldm(1) = 10.26, nat = 2, ntyp = 1,
+    ecutwfc = 30, ecutrho = 240
+ /
+ &electrons
+    conv_thr = 1e-8
+ /
+ATOMIC_SPECIES
+ Si 28.086 Si.pbe-n-rrkjus_psl.1.0.0.UPF
+ATOMIC_POSITIONS crystal
+ Si 0.0 0.0 0.0 0 0 0
+ Si 0.25 0.25 0.25
+K_POINTS automatic
+ 4 4 4 1 1 1
+";
+
+const PO_SCF: &str = "
+ &control
+    calculation = 'scf', prefix = 'po'
+ /
+ &system
+    ibrav = 1, celldm(1) = 6.34, nat = 1, ntyp = 1,
+    ecutwfc = 40, ecutrho = 320
+ /
+ &electrons
+    conv_thr = 1e-8
+ /
+ATOMIC_SPECIES
+ Po 209.0 Po.UPF
+ATOMIC_POSITIONS crystal
+ Po 0.0 0.0 0.0
+K_POINTS automatic
+ 4 4 4 0 0 0
+";
+
+fn free_cell(input: &Input) -> [[f64; 3]; 3] {
+    match input.system.ibrav {
+        Ibrav::Free(ref cell) => {
+            assert_eq!(cell.units, LatticeUnits::Alat);
+            lattice::cell_vectors(cell, input.system.alat)
+        }
+        ref other => panic!("expected a free cell; got {:?}", other),
+    }
+}
+
+#[test]
+fn conventional_supercell() {
+    let input = parse::parse_input_file(SI_SCF).unwrap();
+    let alat = input.system.alat;
+
+    // The conventional cubic cell of the fcc lattice.
+    let result = supercell::supercell(&input, [[-1, 1, -1], [-1, 1, 1], [1, 1, -1]]).unwrap();
+    let cell = free_cell(&result);
+    for i in 0..3 {
+        for j in 0..3 {
+            let expected = if i == j { alat } else { 0.0 };
+            assert!((cell[i][j] - expected).abs() < 1e-10, "{:?}", cell);
+        }
+    }
+
+    let coordinates = &result.atomic_positions.coordinates;
+    assert_eq!(coordinates.len(), 8);
+    for (i, atom) in coordinates.iter().enumerate() {
+        assert!(atom.r.iter().all(|x| (0.0..1.0).contains(x)), "{:?}", atom);
+        for other in &coordinates[..i] {
+            assert!((0..3).any(|d| (atom.r[d] - other.r[d]).abs() > 1e-8));
+        }
+    }
+    // Copies of the first atom come first, and keep its constraints.
+    assert!(coordinates[..4]
+        .iter()
+        .all(|atom| atom.if_pos == Some([false, false, false])));
+    assert!(coordinates[4..].iter().all(|atom| atom.if_pos.is_none()));
+    // The second atom lies at (-1/4, 1/4, 1/4) alat.
+    let r = [0.75, 0.25, 0.25];
+    assert!(coordinates
+        .iter()
+        .any(|atom| (0..3).all(|d| (atom.r[d] - r[d]).abs() < 1e-10)));
+
+    // The reciprocal vectors are shortened by a factor of sqrt(3).
+    assert_eq!(
+        result.k_points,
+        KPoints::Automatic {
+            nk: [3, 3, 3],
+            sk: Some([true, true, true]),
+        }
+    );
+
+    let text = serialize::make_input_file(&result).unwrap();
+    assert!(text.contains("nat=8"), "{}", text);
+
+    assert!(matches!(
+        supercell::supercell(&input, [[0, 1, 0], [1, 0, 0], [0, 0, 1]]),
+        Err(supercell::Error::Matrix)
+    ));
+}
+
+#[test]
+fn supercell_k_points() {
+    let text = PO_SCF.replace("4 4 4 0 0 0", "12 12 1 0 0 0");
+    let input = parse::parse_input_file(&text).unwrap();
+
+    // The identity leaves the mesh unchanged.
+    let result = supercell::supercell(&input, [[1, 0, 0], [0, 1, 0], [0, 0, 1]]).unwrap();
+    assert_eq!(result.k_points, input.k_points);
+    assert_eq!(result.atomic_positions, input.atomic_positions);
+
+    // Each direction keeps its own density, with a single k-point along the third.
+    let result = supercell::supercell(&input, [[2, 0, 0], [0, 2, 0], [0, 0, 1]]).unwrap();
+    assert_eq!(
+        result.k_points,
+        KPoints::Automatic {
+            nk: [6, 6, 1],
+            sk: None,
+        }
+    );
+
+    // Without `celldm(1)`, `alat` is the length of the first lattice vector, so Cartesian
+    // k-points are rescaled to the new `alat`.
+    let text = "
+ &control
+    calculation = 'scf'
+ /
+ &system
+    ibrav = 0, nat = 1, ntyp = 1, ecutwfc = 40
+ /
+ &electrons
+ /
+ATOMIC_SPECIES
+ Po 209.0 Po.UPF
+ATOMIC_POSITIONS crystal
+ Po 0.0 0.0 0.0
+CELL_PARAMETERS bohr
+ 6.34 0.0 0.0
+ 0.0 6.34 0.0
+ 0.0 0.0 6.34
+K_POINTS tpiba
+ 1
+ 0.5 0.25 0.0 1.0
+";
+    let input = parse::parse_input_file(text).unwrap();
+    let result = supercell::supercell(&input, [[2, 0, 0], [0, 1, 0], [0, 0, 1]]).unwrap();
+    assert!((result.system.alat - 2.0 * input.system.alat).abs() < 1e-10);
+    match result.k_points {
+        KPoints::TwoPiByACartesian(ref ks) => {
+            assert_eq!(ks.len(), 1);
+            let expected = [1.0, 0.5, 0.0, 1.0];
+            for (x, e) in ks[0].iter().zip(expected.iter()) {
+                assert!((x - e).abs() < 1e-10);
+            }
+        }
+        ref other => panic!("expected Cartesian k-points; got {:?}", other),
+    }
+}
+
+#[test]
+fn supercell_extensive_settings() {
+    let mut input = parse::parse_input_file(PO_SCF).unwrap();
+    input.calculation = Calculation::Nscf {
+        diago_thr_init: None,
+        nbnd: Some(10),
+        nosym: None,
+    };
+    input.system.spin_type = Some(SpinType::CollinearPolarized(Magnetization {
+        starting_magnetization: vec![Some(0.5)],
+        tot_magnetization: Some(2.0),
+        ..Magnetization::default()
+    }));
+
+    let result = supercell::supercell(&input, [[2, 0, 0], [0, 2, 0], [0, 0, 2]]).unwrap();
+    match result.calculation {
+        Calculation::Nscf { nbnd, .. } => assert_eq!(nbnd, Some(80)),
+        ref other => panic!("expected an nscf calculation; got {:?}", other),
+    }
+    match result.system.spin_type {
+        Some(SpinType::CollinearPolarized(ref magnetization)) => {
+            assert_eq!(magnetization.tot_magnetization, Some(16.0));
+            assert_eq!(magnetization.starting_magnetization, vec![Some(0.5)]);
+        }
+        ref other => panic!("expected a collinear calculation; got {:?}", other),
+    }
+    let text = serialize::make_input_file(&result).unwrap();
+    assert!(text.contains("nat=8"), "{}", text);
+    assert!(text.contains("tot_magnetization=16"), "{}", text);
+
+    // The slab has 3 copies of the cell.
+    let result = supercell::slab(&input, [1, 1, 0], 3, 20.0, 0).unwrap();
+    match result.calculation {
+        Calculation::Nscf { nbnd, .. } => assert_eq!(nbnd, Some(30)),
+        ref other => panic!("expected an nscf calculation; got {:?}", other),
+    }
+
+    // Without atoms, there is no number of copies to scale by.
+    input.atomic_positions.coordinates.clear();
+    assert!(matches!(
+        supercell::supercell(&input, [[2, 0, 0], [0, 1, 0], [0, 0, 1]]),
+        Err(supercell::Error::Extensive)
+    ));
+}
+
+#[test]
+fn slab_with_fixed_layers() {
+    let input = parse::parse_input_file(PO_SCF).unwrap();
+    let alat = input.system.alat;
+    let vacuum = 20.0;
+
+    let result = supercell::slab(&input, [1, 1, 0], 3, vacuum, 1).unwrap();
+    let cell = free_cell(&result);
+    let spacing = alat / 2f64.sqrt();
+
+    // The surface is spanned by a [0 0 1] and a [1 -1 0], with the third vector normal to it.
+    let length = |v: [f64; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    assert!((length(cell[0]) - alat).abs() < 1e-10);
+    assert!((length(cell[1]) - 2f64.sqrt() * alat).abs() < 1e-10);
+    assert!((length(cell[2]) - (2.0 * spacing + vacuum)).abs() < 1e-10);
+    for i in 0..2 {
+        let dot: f64 = (0..3).map(|d| cell[i][d] * cell[2][d]).sum();
+        assert!(dot.abs() < 1e-10);
+    }
+
+    let coordinates = &result.atomic_positions.coordinates;
+    assert_eq!(coordinates.len(), 3);
+    let mut heights: Vec<(f64, Option<[bool; 3]>)> = coordinates
+        .iter()
+        .map(|atom| (atom.r[2] * length(cell[2]), atom.if_pos))
+        .collect();
+    heights.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for (i, &(height, _)) in heights.iter().enumerate() {
+        assert!((height - i as f64 * spacing).abs() < 1e-10, "{:?}", heights);
+    }
+    assert_eq!(heights[0].1, Some([false, false, false]));
+    assert_eq!(heights[1].1, None);
+    assert_eq!(heights[2].1, None);
+
+    assert_eq!(
+        result.k_points,
+        KPoints::Automatic {
+            nk: [4, 3, 1],
+            sk: None,
+        }
+    );
+
+    assert!(matches!(
+        supercell::slab(&input, [0, 0, 0], 3, vacuum, 0),
+        Err(supercell::Error::Miller)
+    ));
+    assert!(matches!(
+        supercell::slab(&input, [1, 1, 0], 2, vacuum, 3),
+        Err(supercell::Error::Layers)
+    ));
+}
+
+#[test]
+fn substitute_and_remove_atoms() {
+    let input = parse::parse_input_file(SI_SCF).unwrap();
+    let si16 = supercell::supercell(&input, [[2, 0, 0], [0, 2, 0], [0, 0, 2]]).unwrap();
+    assert_eq!(si16.atomic_positions.coordinates.len(), 16);
+
+    let ge = Species {
+        label: String::from("Ge"),
+        mass: 72.63,
+        pseudopotential_filename: String::from("Ge.UPF"),
+    };
+    let alloy = supercell::substitute(&si16, 3, ge).unwrap();
+    assert_eq!(alloy.atomic_positions.coordinates[3].species, "Ge");
+    let labels: Vec<&str> = alloy.species.iter().map(|s| s.label.as_str()).collect();
+    assert_eq!(labels, vec!["Si", "Ge"]);
+
+    // Per-species settings of removed species are removed also.
+    let mut alloy = alloy;
+    alloy.hubbard = Some(Hubbard {
+        kind: HubbardKind::Simplified,
+        projection: HubbardProjection::Atomic,
+        parameters: vec![HubbardParameters {
+            species: String::from("Ge"),
+            manifold: None,
+            u: 1.0,
+            j0: None,
+            j: None,
+            alpha: None,
+        }],
+        card: false,
+    });
+
+    let vacancy = supercell::remove_atoms(&alloy, &[3, 0]).unwrap();
+    assert_eq!(vacancy.atomic_positions.coordinates.len(), 14);
+    assert_eq!(vacancy.species.len(), 1);
+    assert!(vacancy.hubbard.as_ref().unwrap().parameters.is_empty());
+    let text = serialize::make_input_file(&vacancy).unwrap();
+    assert!(text.contains("ntyp=1"), "{}", text);
+
+    assert!(matches!(
+        supercell::remove_atoms(&si16, &[16]),
+        Err(supercell::Error::AtomIndex(16))
+    ));
+}
@@ -0,0 +1,122 @@
+extern crate qe;
+
+use qe::pw::input::{AtomCoordinate, Cell, KPoints, LatticeUnits, PositionCoordinateType, Positions};
+use qe::pw::lattice;
+use qe::pw::symmetry;
+
+const TOLERANCE: f64 = 1e-5;
+
+fn atom(species: &str, r: [f64; 3]) -> AtomCoordinate {
+    AtomCoordinate {
+        species: String::from(species),
+        r,
+        if_pos: None,
+    }
+}
+
+fn si_diamond() -> (Cell, Positions) {
+    let cell = Cell {
+        units: LatticeUnits::Alat,
+        cell: [[-0.5, 0.0, 0.5], [0.0, 0.5, 0.5], [-0.5, 0.5, 0.0]],
+    };
+    let positions = Positions {
+        coordinate_type: PositionCoordinateType::Crystal,
+        coordinates: vec![atom("Si", [0.0, 0.0, 0.0]), atom("Si", [0.25, 0.25, 0.25])],
+    };
+
+    (cell, positions)
+}
+
+fn irreducible(cell: &Cell, positions: &Positions, nk: [u64; 3], sk: bool) -> Vec<[f64; 4]> {
+    let k_points = KPoints::Automatic {
+        nk,
+        sk: Some([sk, sk, sk]),
+    };
+    match symmetry::irreducible_k_points(cell, 10.26, positions, &k_points, TOLERANCE).unwrap() {
+        KPoints::Crystal(k_points) => k_points,
+        other => panic!("expected Crystal k-points; got {:?}", other),
+    }
+}
+
+#[test]
+fn diamond_space_group_and_k_points() {
+    let (cell, positions) = si_diamond();
+
+    let operations = symmetry::space_group(&cell, 10.26, &positions, TOLERANCE).unwrap();
+    assert_eq!(operations.len(), 48);
+    assert_eq!(operations[0].rotation, [[1, 0, 0], [0, 1, 0], [0, 0, 1]]);
+    assert_eq!(operations[0].translation, [0.0, 0.0, 0.0]);
+
+    // Both meshes are standard pw.x examples for silicon.
+    let k_points = irreducible(&cell, &positions, [4, 4, 4], false);
+    assert_eq!(k_points.len(), 8);
+    assert_eq!(k_points[0], [0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(k_points.iter().map(|k| k[3]).sum::<f64>(), 64.0);
+
+    let k_points = irreducible(&cell, &positions, [4, 4, 4], true);
+    assert_eq!(k_points.len(), 10);
+    assert_eq!(k_points.iter().map(|k| k[3]).sum::<f64>(), 64.0);
+
+    // Displacing an atom in Cartesian coordinates lowers the symmetry.
+    let mut distorted = positions.clone();
+    distorted.coordinate_type = PositionCoordinateType::AlatCartesian;
+    distorted.coordinates[1].r = [0.25, 0.25, 0.3];
+    let operations = symmetry::space_group(&cell, 10.26, &distorted, TOLERANCE).unwrap();
+    assert_eq!(operations.len(), 8);
+    assert!(irreducible(&cell, &distorted, [4, 4, 4], false).len() > 8);
+}
+
+#[test]
+fn primitive_cell_of_conventional_bcc() {
+    let cell = Cell {
+        units: LatticeUnits::Angstrom,
+        cell: [[2.87, 0.0, 0.0], [0.0, 2.87, 0.0], [0.0, 0.0, 2.87]],
+    };
+    let mut positions = Positions {
+        coordinate_type: PositionCoordinateType::AngstromCartesian,
+        coordinates: vec![
+            atom("Fe", [0.0, 0.0, 0.0]),
+            atom("Fe", [1.435, 1.435, 1.435]),
+        ],
+    };
+    positions.coordinates[0].if_pos = Some([false, true, true]);
+
+    let operations = symmetry::space_group(&cell, 1.0, &positions, TOLERANCE).unwrap();
+    assert_eq!(operations.len(), 96);
+
+    let (primitive, primitive_positions) =
+        symmetry::primitive_cell(&cell, 1.0, &positions, TOLERANCE).unwrap();
+    let volume = lattice::determinant(&lattice::cell_vectors(&cell, 1.0));
+    let primitive_volume = lattice::determinant(&primitive.cell);
+
+    assert_eq!(primitive.units, LatticeUnits::Bohr);
+    assert!((primitive_volume - volume / 2.0).abs() < 1e-8 * volume);
+    assert_eq!(primitive_positions.coordinates.len(), 1);
+    assert_eq!(primitive_positions.coordinates[0].r, [0.0, 0.0, 0.0]);
+    assert_eq!(
+        primitive_positions.coordinates[0].if_pos,
+        Some([false, true, true])
+    );
+
+    // The primitive cell is already primitive, and has the full point group.
+    let operations =
+        symmetry::space_group(&primitive, 1.0, &primitive_positions, TOLERANCE).unwrap();
+    assert_eq!(operations.len(), 48);
+}
+
+#[test]
+fn symmetry_errors() {
+    let (cell, mut positions) = si_diamond();
+
+    let k_points = KPoints::CrystalUniform([4, 4, 4]);
+    assert!(matches!(
+        symmetry::irreducible_k_points(&cell, 10.26, &positions, &k_points, TOLERANCE),
+        Err(symmetry::Error::KPoints)
+    ));
+
+    positions.coordinate_type = PositionCoordinateType::CrystalSG;
+    assert!(matches!(
+        symmetry::space_group(&cell, 10.26, &positions, TOLERANCE),
+        Err(symmetry::Error::CoordinateType)
+    ));
+}
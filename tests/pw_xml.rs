@@ -0,0 +1,108 @@
+extern crate qe;
+
+use std::env;
+use std::path::{Path, PathBuf};
+use qe::pw::parse;
+use qe::pw::xml;
+
+const SI_SCF: &str = "
+ &control
+    calculation = 'scf', prefix = 'si', outdir = './data'
+ /
+ &system
+    ibrav = 2, celldm(1) = 10.2, nat = 2, ntyp = 1,
+    ecutwfc = 30, ecutrho = 120
+ /
+ &electrons
+    conv_thr = 1e-8
+ /
+ATOMIC_SPECIES
+ Si 28.0855 Si.pz-vbc.UPF
+ATOMIC_POSITIONS alat
+ Si 0.00 0.00 0.00
+ Si 0.25 0.25 0.25
+K_POINTS automatic
+ 2 2 2 0 0 0
+";
+
+#[test]
+fn read_xml_data_file() {
+    let input = parse::parse_input_file(SI_SCF).unwrap();
+
+    assert_eq!(
+        xml::data_file_path(&input),
+        PathBuf::from("./data/si.save/data-file-schema.xml")
+    );
+
+    let run_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let data = xml::read_data_file(&input, run_dir).unwrap();
+
+    assert_eq!(data.species[0].name, "Si");
+    assert_eq!(data.species[0].pseudo_file, "Si.pz-vbc.UPF");
+    assert_eq!(data.total_energy, -7.899162065);
+
+    assert_eq!(data.structure.alat, 10.2);
+    assert_eq!(data.structure.bravais_index, Some(2));
+    assert_eq!(data.structure.cell[0], [-5.1, 0.0, 5.1]);
+    assert_eq!(data.structure.atoms[1].r, [2.55, 2.55, 2.55]);
+
+    // Symmetries of the Bravais lattice alone are not included.
+    assert_eq!(data.symmetries.len(), 2);
+    assert_eq!(
+        data.symmetries[1].rotation,
+        [[0, 1, -1], [1, 0, -1], [0, 0, -1]]
+    );
+
+    let bands = &data.band_structure;
+    assert!(!bands.lsda);
+    assert_eq!(bands.nbnd, 4);
+    assert_eq!(
+        bands.fermi_energy,
+        Some(xml::FermiEnergy::HighestOccupied(0.2310931571, None))
+    );
+    assert_eq!(bands.k_points.len(), 2);
+    assert_eq!(bands.k_points[1].k, [-0.5, 0.5, -0.5]);
+    assert_eq!(bands.k_points[1].weight, 1.75);
+    assert_eq!(bands.k_points[1].eigenvalues[1], -0.0307813744);
+    assert_eq!(bands.k_points[1].occupations, vec![1.0; 4]);
+}
+
+#[test]
+fn read_spin_polarized_xml_data_file() {
+    let text = include_str!("data/fe.save/data-file-schema.xml");
+    let data = xml::parse_data_file(text).unwrap();
+
+    // The spin up eigenvalues at each k-point are followed by the spin down ones.
+    let bands = &data.band_structure;
+    assert!(bands.lsda);
+    assert_eq!(bands.nbnd, 2);
+    assert_eq!(bands.fermi_energy, Some(xml::FermiEnergy::Fermi(0.650213481)));
+    assert_eq!(bands.k_points.len(), 2);
+    assert_eq!(bands.k_points[1].eigenvalues.len(), 4);
+    assert_eq!(bands.k_points[1].eigenvalues[2], 0.4881930742);
+
+    let text = text.replace("<nbnd_dw>2</nbnd_dw>", "<nbnd_dw>3</nbnd_dw>");
+    assert!(matches!(
+        xml::parse_data_file(&text),
+        Err(xml::Error::Value { ref element, .. }) if element == "nbnd_dw"
+    ));
+}
+
+#[test]
+fn data_file_path_defaults() {
+    let text = SI_SCF.replace(", prefix = 'si', outdir = './data'", "");
+    let input = parse::parse_input_file(&text).unwrap();
+
+    // Without `outdir`, pw.x writes to `$ESPRESSO_TMPDIR` if this is set.
+    env::set_var("ESPRESSO_TMPDIR", "/scratch/tmp");
+    assert_eq!(
+        xml::data_file_path(&input),
+        PathBuf::from("/scratch/tmp/pwscf.save/data-file-schema.xml")
+    );
+
+    env::remove_var("ESPRESSO_TMPDIR");
+    assert_eq!(
+        xml::data_file_path(&input),
+        PathBuf::from("./pwscf.save/data-file-schema.xml")
+    );
+}
@@ -0,0 +1,199 @@
+#![cfg(unix)]
+
+extern crate qe;
+
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use qe::bands;
+use qe::pw;
+use qe::pw::input::KPoints;
+use qe::pw::output::CalculationOutput;
+use qe::pw2wannier90;
+use qe::run;
+
+const SI_SCF: &str = "
+ &control
+    calculation = 'scf', prefix = 'si'
+ /
+ &system
+    ibrav = 2, celldm(1) = 10.2, nat = 2, ntyp = 1,
+    ecutwfc = 30, ecutrho = 120
+ /
+ &electrons
+    conv_thr = 1e-8
+ /
+ATOMIC_SPECIES
+ Si 28.0855 Si.pz-vbc.UPF
+ATOMIC_POSITIONS alat
+ Si 0.00 0.00 0.00
+ Si 0.25 0.25 0.25
+K_POINTS automatic
+ 2 2 2 0 0 0
+";
+
+/// An empty directory to run the fake programs in.
+fn run_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("qe-run-{}-{}", name, process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+/// Write an executable shell script standing in for one of the QE programs.
+fn fake_program(dir: &Path, name: &str, script: &str) -> PathBuf {
+    let path = dir.join(name);
+    let mut file = File::create(&path).unwrap();
+    write!(file, "#!/bin/sh\n{}\n", script).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    path
+}
+
+fn data_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("data")
+        .join(name)
+}
+
+#[test]
+fn run_pw_scf() {
+    let dir = run_dir("pw-scf");
+    let script = format!(
+        "test \"$1\" = -in && cp \"$2\" received.in\ncat '{}'",
+        data_path("fe_scf.out").display()
+    );
+    let program = run::Program {
+        path: fake_program(&dir, "pw.x", &script),
+        prefix: vec![String::from("env"), String::from("OMP_NUM_THREADS=1")],
+    };
+    let input = pw::parse::parse_input_file(SI_SCF).unwrap();
+
+    let out = run::run_pw(&program, &input, &dir, "scf").unwrap();
+
+    assert!(out.job_done);
+    match out.result {
+        CalculationOutput::Scf(ref scf) => assert!(scf.converged),
+        ref other => panic!("expected scf output; got {:?}", other),
+    }
+
+    // The program is given the input file by `-in`, and its stdout is kept as a log.
+    let (input_path, log_path) = run::file_paths(&dir, "scf");
+    assert_eq!(
+        fs::read_to_string(dir.join("received.in")).unwrap(),
+        fs::read_to_string(input_path).unwrap()
+    );
+    assert_eq!(
+        fs::read_to_string(log_path).unwrap(),
+        fs::read_to_string(data_path("fe_scf.out")).unwrap()
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn run_pw_error() {
+    let dir = run_dir("pw-error");
+    let script = "echo '     Error in routine cdiaghg (2):'
+echo '     problems computing cholesky'
+exit 1";
+    let program = run::Program {
+        path: fake_program(&dir, "pw.x", script),
+        prefix: Vec::new(),
+    };
+    let input = pw::parse::parse_input_file(SI_SCF).unwrap();
+
+    match run::run_pw(&program, &input, &dir, "scf") {
+        Err(run::Error::PwOutput(pw::output::Error::Pw {
+            ref routine,
+            ref message,
+            ..
+        })) => {
+            assert_eq!(routine, "cdiaghg (2):");
+            assert_eq!(message, "problems computing cholesky");
+        }
+        other => panic!("expected pw.x error; got {:?}", other),
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn run_bands_filband() {
+    let dir = run_dir("bands");
+    let script = "cat > si_bands.dat << EOF
+ &plot nbnd=   2, nks=     3 /
+           0.000000  0.000000  0.000000
+  -5.810   6.255
+           0.000000  0.500000  0.000000
+  -4.373   2.625
+           0.000000  1.000000  0.000000
+  -1.617  -1.617
+EOF";
+    let program = run::Program {
+        path: fake_program(&dir, "bands.x", script),
+        prefix: Vec::new(),
+    };
+    let input = bands::input::Input {
+        prefix: Some(String::from("si")),
+        out_dir: None,
+        filband: Some(PathBuf::from("si_bands.dat")),
+        lsym: false,
+    };
+    let k_points = KPoints::CrystalBands {
+        nk_per_panel: 2,
+        panel_bounds: vec![[0.0, 0.0, 0.0], [0.0, 0.5, 0.5]],
+    };
+
+    let bands = run::run_bands(&program, &input, &k_points, None, &dir, "bands").unwrap();
+
+    assert_eq!(
+        bands.bands,
+        vec![vec![-5.81, -4.373, -1.617], vec![6.255, 2.625, -1.617]]
+    );
+    assert_eq!(bands.ticks, vec![0.0, 1.0]);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn run_pw2wannier90_exit_status() {
+    let dir = run_dir("pw2wannier90");
+    let program = run::Program {
+        path: fake_program(
+            &dir,
+            "pw2wannier90.x",
+            "echo 'failed' >&2\nexit 3",
+        ),
+        prefix: Vec::new(),
+    };
+    let input = pw2wannier90::input::Input {
+        prefix: String::from("si"),
+        out_dir: None,
+        seedname: String::from("si"),
+        write_unk: false,
+        write_amn: true,
+        write_mmn: true,
+        write_spn: false,
+    };
+
+    match run::run_pw2wannier90(&program, &input, &dir, "pw2wan") {
+        Err(run::Error::Exit {
+            status, ref stderr, ..
+        }) => {
+            assert_eq!(status, Some(3));
+            assert_eq!(stderr, "failed\n");
+        }
+        other => panic!("expected non-zero exit status; got {:?}", other),
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
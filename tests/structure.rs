@@ -0,0 +1,272 @@
+extern crate qe;
+
+use std::path::Path;
+use qe::pw::input::{Cell, LatticeUnits, PositionCoordinateType};
+use qe::pw::{lattice, parse};
+use qe::structure;
+use qe::structure::{cif, poscar, xsf, xyz};
+
+fn contains(positions: &[[f64; 3]], r: [f64; 3]) -> bool {
+    positions
+        .iter()
+        .any(|x| (0..3).all(|i| (x[i] - r[i]).abs() < 1e-8))
+}
+
+#[test]
+fn read_cif_with_symmetry_expansion() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/structure/TiO2_rutile.cif");
+    let structure = structure::read_file(path).unwrap();
+
+    assert_eq!(structure.cell.units, LatticeUnits::Angstrom);
+    assert_eq!(structure.cell.cell[0], [4.5937, 0.0, 0.0]);
+    assert!((structure.cell.cell[2][2] - 2.9587).abs() < 1e-12);
+    assert!(structure.cell.cell[2][0].abs() < 1e-12);
+    assert_eq!(structure.species, vec!["Ti", "O"]);
+
+    let positions = &structure.positions;
+    assert_eq!(positions.coordinate_type, PositionCoordinateType::Crystal);
+    let ti: Vec<[f64; 3]> = positions
+        .coordinates
+        .iter()
+        .filter(|atom| atom.species == "Ti")
+        .map(|atom| atom.r)
+        .collect();
+    let o: Vec<[f64; 3]> = positions
+        .coordinates
+        .iter()
+        .filter(|atom| atom.species == "O")
+        .map(|atom| atom.r)
+        .collect();
+
+    assert_eq!(ti.len(), 2);
+    assert!(contains(&ti, [0.5, 0.5, 0.5]));
+    assert_eq!(o.len(), 4);
+    for &r in &[
+        [0.3049, 0.3049, 0.0],
+        [0.6951, 0.6951, 0.0],
+        [0.1951, 0.8049, 0.5],
+        [0.8049, 0.1951, 0.5],
+    ] {
+        assert!(contains(&o, r), "{:?}", r);
+    }
+}
+
+#[test]
+fn read_cif_errors() {
+    let text = "data_x
+_cell_length_a 4.0
+_cell_length_b 4.0
+_cell_angle_alpha 90
+_cell_angle_beta 90
+_cell_angle_gamma 90
+";
+    match cif::parse(text) {
+        Err(structure::Error::Missing(ref tag)) => assert_eq!(tag, "_cell_length_c"),
+        other => panic!("expected missing tag; got {:?}", other),
+    }
+
+    let text = "data_x
+_cell_length_a 4.0
+_cell_length_b 4.0
+_cell_length_c 4.0
+_cell_angle_alpha 90
+_cell_angle_beta 90
+_cell_angle_gamma 90
+_symmetry_equiv_pos_as_xyz 'x,y,w'
+loop_
+_atom_site_label
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+Fe1 0 0 0
+";
+    assert!(matches!(
+        cif::parse(text),
+        Err(structure::Error::Value { line: 8, .. })
+    ));
+}
+
+#[test]
+fn read_poscar() {
+    let text = "bcc Fe with a vacancy
+2.0
+  1.435 0.0 0.0
+  0.0 1.435 0.0
+  0.0 0.0 1.435
+Fe Co
+1 1
+Selective dynamics
+Cartesian
+  0.0 0.0 0.0 F F F
+  0.7175 0.7175 0.7175 T T F
+";
+    let structure = poscar::parse(text).unwrap();
+
+    assert_eq!(structure.cell.units, LatticeUnits::Angstrom);
+    assert_eq!(structure.cell.cell[0], [2.87, 0.0, 0.0]);
+    assert_eq!(structure.species, vec!["Fe", "Co"]);
+    let positions = &structure.positions;
+    assert_eq!(
+        positions.coordinate_type,
+        PositionCoordinateType::AngstromCartesian
+    );
+    assert_eq!(positions.coordinates[1].species, "Co");
+    assert_eq!(positions.coordinates[1].r, [1.435, 1.435, 1.435]);
+    assert_eq!(positions.coordinates[0].if_pos, Some([false, false, false]));
+    assert_eq!(positions.coordinates[1].if_pos, Some([true, true, false]));
+
+    // VASP 4 files give the species names in the first line. A negative scaling factor gives
+    // the cell volume.
+    let text = "Si
+-27.0
+  1.0 0.0 0.0
+  0.0 1.0 0.0
+  0.0 0.0 1.0
+2
+Direct
+  0.0 0.0 0.0
+  0.25 0.25 0.25
+";
+    let structure = poscar::parse(text).unwrap();
+
+    assert_eq!(structure.cell.cell[2], [0.0, 0.0, 3.0]);
+    assert_eq!(
+        structure.positions.coordinate_type,
+        PositionCoordinateType::Crystal
+    );
+    assert_eq!(structure.positions.coordinates[1].r, [0.25, 0.25, 0.25]);
+    assert_eq!(structure.positions.coordinates[1].if_pos, None);
+}
+
+#[test]
+fn read_xyz() {
+    let text = "2
+Lattice=\"5.0 0.0 0.0 0.0 5.0 0.0 0.0 0.0 6.0\" Properties=species:S:1:pos:R:3
+O 0.0 0.0 0.0
+H 0.0 0.757 0.586
+";
+    let structure = xyz::parse(text, None).unwrap();
+
+    assert_eq!(structure.cell.cell[2], [0.0, 0.0, 6.0]);
+    assert_eq!(structure.species, vec!["O", "H"]);
+    assert_eq!(
+        structure.positions.coordinate_type,
+        PositionCoordinateType::AngstromCartesian
+    );
+    assert_eq!(structure.positions.coordinates[1].r, [0.0, 0.757, 0.586]);
+
+    // Plain XYZ files need a cell to be given.
+    let text = "1
+water fragment
+O 0.0 0.0 0.0
+";
+    assert!(matches!(
+        xyz::parse(text, None),
+        Err(structure::Error::Missing(_))
+    ));
+    let cell = Cell {
+        units: LatticeUnits::Bohr,
+        cell: [[20.0, 0.0, 0.0], [0.0, 20.0, 0.0], [0.0, 0.0, 20.0]],
+    };
+    let structure = xyz::parse(text, Some(cell.clone())).unwrap();
+    assert_eq!(structure.cell, cell);
+
+    // A file with fewer positions than its atom count is truncated.
+    let text = text.replacen('1', "4000000000000000000", 1);
+    assert!(matches!(
+        xyz::parse(&text, Some(cell)),
+        Err(structure::Error::Missing(ref field)) if field == "positions"
+    ));
+}
+
+const FE_CO_SCF: &str = "
+ &control
+    calculation = 'scf', prefix = 'feco'
+ /
+ &system
+    ibrav = 3, celldm(1) = 5.42, nat = 3, ntyp = 2,
+    ecutwfc = 40, ecutrho = 320
+ /
+ &electrons
+    conv_thr = 1e-8
+ /
+ATOMIC_SPECIES
+ Fe1 55.845 Fe.UPF
+ Co 58.933 Co.UPF
+ATOMIC_POSITIONS alat
+ Fe1 0.0 0.0 0.0 0 0 0
+ Co 0.25 0.25 0.25
+ Fe1 0.0 0.0 0.5
+K_POINTS automatic
+ 4 4 4 0 0 0
+";
+
+#[test]
+fn export_input_structure() {
+    let input = parse::parse_input_file(FE_CO_SCF).unwrap();
+    let cell_angstrom = 5.42 * lattice::BOHR_ANGSTROM / 2.0;
+
+    // The `alat` positions are (0, 0, 0), (1/2, 0, 0) and (1/2, 0, 1/2) in crystal
+    // coordinates of the bcc primitive vectors.
+    let expected = [[0.0, 0.0, 0.0], [0.5, 0.0, 0.0], [0.5, 0.0, 0.5]];
+
+    let poscar_text = poscar::make_file(&input).unwrap();
+    let structure = poscar::parse(&poscar_text).unwrap();
+    assert!(poscar_text.starts_with("feco\n"));
+    assert_eq!(structure.species, vec!["Fe", "Co"]);
+    let coordinates = &structure.positions.coordinates;
+    // Atoms are grouped by species.
+    assert_eq!(coordinates[1].species, "Fe");
+    assert_eq!(coordinates[2].species, "Co");
+    for (atom, &r) in coordinates
+        .iter()
+        .zip(&[expected[0], expected[2], expected[1]])
+    {
+        assert!(
+            (0..3).all(|i| (atom.r[i] - r[i]).abs() < 1e-12),
+            "{:?}",
+            atom
+        );
+    }
+    assert_eq!(coordinates[0].if_pos, Some([false, false, false]));
+    assert_eq!(coordinates[2].if_pos, Some([true, true, true]));
+    assert!((structure.cell.cell[0][0] - cell_angstrom).abs() < 1e-12);
+
+    let cif_text = cif::make_file(&input).unwrap();
+    let structure = cif::parse(&cif_text).unwrap();
+    assert!(cif_text.contains(" Fe12 Fe "));
+    let positions: Vec<[f64; 3]> = structure
+        .positions
+        .coordinates
+        .iter()
+        .map(|atom| atom.r)
+        .collect();
+    assert_eq!(positions.len(), 3);
+    for &r in &expected {
+        assert!(contains(&positions, r), "{:?}", r);
+    }
+    // The bcc primitive vectors are of length sqrt(3) a / 2.
+    let a = structure.cell.cell[0][0];
+    assert!((a - 3.0f64.sqrt() * cell_angstrom).abs() < 1e-8);
+
+    let xsf_text = xsf::make_file(&input).unwrap();
+    let lines: Vec<&str> = xsf_text.lines().collect();
+    assert_eq!(lines[0], "CRYSTAL");
+    assert_eq!(lines[6], " 3 1");
+    let co: Vec<f64> = lines[8]
+        .split_whitespace()
+        .skip(1)
+        .map(|x| x.parse().unwrap())
+        .collect();
+    assert!(lines[8].starts_with(" Co "));
+    for &x in &co {
+        assert!((x - 0.25 * 5.42 * lattice::BOHR_ANGSTROM).abs() < 1e-12);
+    }
+
+    let mut crystal_sg = input.clone();
+    crystal_sg.atomic_positions.coordinate_type = PositionCoordinateType::CrystalSG;
+    assert!(matches!(
+        xsf::make_file(&crystal_sg),
+        Err(structure::Error::CoordinateType)
+    ));
+}
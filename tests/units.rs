@@ -0,0 +1,98 @@
+extern crate qe;
+
+use qe::pw::input::{AtomCoordinate, Cell, Ibrav, LatticeUnits, PositionCoordinateType, Positions};
+use qe::pw::lattice;
+use qe::units::{Energy, Length, BOHR_ANGSTROM, HARTREE_EV};
+
+fn assert_close(x: [f64; 3], y: [f64; 3]) {
+    assert!(
+        (0..3).all(|i| (x[i] - y[i]).abs() < 1e-12),
+        "{:?} {:?}",
+        x,
+        y
+    );
+}
+
+#[test]
+fn convert_lengths_and_energies() {
+    assert_eq!(Length::Bohr.convert(1.0, Length::Angstrom), BOHR_ANGSTROM);
+    assert!((Length::Angstrom.convert(BOHR_ANGSTROM, Length::Bohr) - 1.0).abs() < 1e-15);
+
+    assert_eq!(
+        Energy::Hartree.convert(1.0, Energy::ElectronVolt),
+        HARTREE_EV
+    );
+    assert_eq!(Energy::Hartree.convert(1.0, Energy::Rydberg), 2.0);
+    assert!((Energy::ElectronVolt.convert(HARTREE_EV, Energy::Rydberg) - 2.0).abs() < 1e-15);
+}
+
+#[test]
+fn convert_cell_units() {
+    let alat = 5.0;
+    let cell = Cell {
+        units: LatticeUnits::Alat,
+        cell: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.5, 2.0]],
+    };
+
+    let bohr = cell.to_units(LatticeUnits::Bohr, alat);
+    assert_eq!(bohr.units, LatticeUnits::Bohr);
+    assert_eq!(bohr.cell[2], [0.0, 2.5, 10.0]);
+    assert_eq!(bohr.cell, lattice::cell_vectors(&cell, alat));
+
+    let angstrom = bohr.to_units(LatticeUnits::Angstrom, alat);
+    assert_close(angstrom.cell[0], [5.0 * BOHR_ANGSTROM, 0.0, 0.0]);
+
+    let alat_cell = angstrom.to_units(LatticeUnits::Alat, alat);
+    for i in 0..3 {
+        assert_close(alat_cell.cell[i], cell.cell[i]);
+    }
+}
+
+#[test]
+fn convert_positions() {
+    let alat = 6.0;
+    let cell = lattice::lattice_vectors(&Ibrav::Fcc, alat);
+    let positions = Positions {
+        coordinate_type: PositionCoordinateType::Crystal,
+        coordinates: vec![AtomCoordinate {
+            species: String::from("Si"),
+            r: [0.25, 0.25, 0.25],
+            if_pos: Some([true, false, true]),
+        }],
+    };
+
+    // The fcc primitive vectors are (a/2)(-1, 0, 1), (a/2)(0, 1, 1) and (a/2)(-1, 1, 0).
+    let alat_positions = positions
+        .to_coordinate_type(PositionCoordinateType::AlatCartesian, &cell, alat)
+        .unwrap();
+    assert_eq!(
+        alat_positions.coordinate_type,
+        PositionCoordinateType::AlatCartesian
+    );
+    assert_close(alat_positions.coordinates[0].r, [-0.25, 0.25, 0.25]);
+    assert_eq!(
+        alat_positions.coordinates[0].if_pos,
+        Some([true, false, true])
+    );
+
+    let angstrom = alat_positions
+        .to_coordinate_type(PositionCoordinateType::AngstromCartesian, &cell, alat)
+        .unwrap();
+    let x = 0.25 * alat * BOHR_ANGSTROM;
+    assert_close(angstrom.coordinates[0].r, [-x, x, x]);
+
+    let bohr = angstrom
+        .to_coordinate_type(PositionCoordinateType::BohrCartesian, &cell, alat)
+        .unwrap();
+    assert_close(bohr.coordinates[0].r, [-1.5, 1.5, 1.5]);
+
+    let crystal = bohr
+        .to_coordinate_type(PositionCoordinateType::Crystal, &cell, alat)
+        .unwrap();
+    assert_close(crystal.coordinates[0].r, positions.coordinates[0].r);
+
+    assert!(matches!(
+        positions.to_coordinate_type(PositionCoordinateType::CrystalSG, &cell, alat),
+        Err(lattice::Error::CrystalSG)
+    ));
+}
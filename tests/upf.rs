@@ -0,0 +1,143 @@
+extern crate qe;
+
+use std::path::Path;
+use qe::pw::parse;
+use qe::upf;
+use qe::upf::{PseudoType, Warning};
+
+const FE_SI_SCF: &str = "
+ &control
+    calculation = 'scf', pseudo_dir = './data/pseudo'
+ /
+ &system
+    ibrav = 1, celldm(1) = 5.42, nat = 2, ntyp = 2,
+    ecutwfc = 40, ecutrho = 320
+ /
+ &electrons
+    conv_thr = 1e-8
+ /
+ATOMIC_SPECIES
+ Fe 55.845 Fe.pbe-spn-kjpaw_psl.0.2.1.UPF
+ Si 28.0855 Si.pz-vbc.UPF
+ATOMIC_POSITIONS crystal
+ Fe 0.0 0.0 0.0
+ Si 0.5 0.5 0.5
+K_POINTS automatic
+ 4 4 4 0 0 0
+";
+
+#[test]
+fn read_upf_headers() {
+    let mut input = parse::parse_input_file(FE_SI_SCF).unwrap();
+    let run_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+
+    let headers = upf::read_species_headers(&input, &run_dir).unwrap();
+
+    let fe = &headers[0];
+    assert_eq!(fe.element, "Fe");
+    assert_eq!(fe.pseudo_type, PseudoType::Paw);
+    assert_eq!(fe.functional, "SLA PW PBX PBC");
+    assert_eq!(fe.z_valence, 16.0);
+    assert_eq!(fe.wfc_cutoff, Some(48.0));
+    assert_eq!(fe.rho_cutoff, Some(576.0));
+    assert!(!fe.spin_orbit);
+
+    let si = &headers[1];
+    assert_eq!(si.element, "Si");
+    assert_eq!(si.pseudo_type, PseudoType::NormConserving);
+    assert_eq!(si.functional, "SLA PZ NOGX NOGC");
+    assert_eq!(si.z_valence, 4.0);
+    assert_eq!(si.wfc_cutoff, None);
+    assert_eq!(si.rho_cutoff, None);
+
+    input.species[1].pseudopotential_filename = String::from("missing.UPF");
+    match upf::read_species_headers(&input, &run_dir) {
+        Err(upf::Error::File { ref path, .. }) => assert!(path.ends_with("missing.UPF")),
+        other => panic!("expected file error; got {:?}", other),
+    }
+}
+
+#[test]
+fn check_cutoffs_against_upf_headers() {
+    let mut input = parse::parse_input_file(FE_SI_SCF).unwrap();
+    let run_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let headers = upf::read_species_headers(&input, &run_dir).unwrap();
+
+    let warnings = upf::check_cutoffs(&input, &headers).unwrap();
+
+    assert_eq!(warnings.len(), 3);
+    assert!(warnings.iter().any(|w| match *w {
+        Warning::Ecutwfc(ref species, ecutwfc, suggested) => {
+            species == "Fe" && ecutwfc == 40.0 && suggested == 48.0
+        }
+        _ => false,
+    }));
+    assert!(warnings
+        .iter()
+        .any(|w| matches!(*w, Warning::Ecutrho(ref species, _, _) if species == "Fe")));
+    assert!(warnings.iter().any(|w| match *w {
+        Warning::MixedFunctionals(ref a, _, ref b, _) => a == "Fe" && b == "Si",
+        _ => false,
+    }));
+
+    // A header must be given for each species.
+    assert!(matches!(
+        upf::check_cutoffs(&input, &headers[..1]),
+        Err(upf::Error::HeaderCount {
+            species: 2,
+            headers: 1
+        })
+    ));
+
+    // The PAW dataset requires ecutrho of at least 8 * ecutwfc.
+    input.system.ecutwfc = 50.0;
+    input.system.ecutrho = 300.0;
+    let mut fe_input = input.clone();
+    fe_input.species.truncate(1);
+    let warnings = upf::check_cutoffs(&fe_input, &headers[..1]).unwrap();
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings
+        .iter()
+        .any(|w| matches!(*w, Warning::Dual(ecutrho, _, dual) if ecutrho == 300.0 && dual == 8.0)));
+
+    // Functionals written as their components or as their short names are the same.
+    let mut pbe = headers[0].clone();
+    pbe.functional = String::from("PBE");
+    input.system.ecutrho = 600.0;
+    let warnings = upf::check_cutoffs(&input, &[headers[0].clone(), pbe]).unwrap();
+    assert!(warnings.is_empty(), "{:?}", warnings);
+}
+
+#[test]
+fn parse_upf_v2_spin_orbit() {
+    let text = "<UPF version=\"2.0.1\">
+  <PP_HEADER element=\"Pt\" pseudo_type=\"US\" functional=\"PBE\"
+     z_valence=\"1.0D1\" wfc_cutoff=\"0.0\" rho_cutoff=\"0.0\" has_so=\".true.\">
+  </PP_HEADER>
+</UPF>";
+    let header = upf::parse_header(text).unwrap();
+
+    assert_eq!(header.pseudo_type, PseudoType::Ultrasoft);
+    assert_eq!(header.z_valence, 10.0);
+    assert_eq!(header.wfc_cutoff, None);
+    assert!(header.spin_orbit);
+}
+
+#[test]
+fn parse_upf_v2_xml_declaration() {
+    let text = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!-- Generated by a pseudopotential converter -->
+<UPF version=\"2.0.1\">
+  <PP_INFO>
+  </PP_INFO>
+  <PP_HEADER element=\"Si\" pseudo_type=\"NC\" functional=\"PZ\"
+     z_valence=\"4.0\" wfc_cutoff=\"2.0E1\" rho_cutoff=\"8.0E1\" has_so=\"F\"/>
+</UPF>";
+    let header = upf::parse_header(text).unwrap();
+
+    assert_eq!(header.element, "Si");
+    assert_eq!(header.pseudo_type, PseudoType::NormConserving);
+    assert_eq!(header.z_valence, 4.0);
+    assert_eq!(header.wfc_cutoff, Some(20.0));
+    assert!(!header.spin_orbit);
+}
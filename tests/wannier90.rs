@@ -0,0 +1,263 @@
+extern crate qe;
+
+use std::env;
+use std::fs;
+use std::process;
+use qe::pw;
+use qe::pw::input::{KPoints, Magnetization, PositionCoordinateType, SpinType};
+use qe::pw::lattice;
+use qe::wannier90::input;
+use qe::wannier90::serialize;
+use qe::wannier90::workflow;
+
+const SI_NSCF: &str = "
+ &control
+    calculation = 'nscf', prefix = 'si'
+ /
+ &system
+    ibrav = 2, celldm(1) = 10.2, nat = 2, ntyp = 1,
+    ecutwfc = 30, ecutrho = 120, nbnd = 12, nosym = .true.
+ /
+ &electrons
+    diago_thr_init = 1e-8
+ /
+ATOMIC_SPECIES
+ Si 28.0855 Si.pz-vbc.UPF
+ATOMIC_POSITIONS alat
+ Si 0.00 0.00 0.00
+ Si 0.25 0.25 0.25
+K_POINTS automatic
+ 4 4 4 0 0 0
+";
+
+const SI_SCF: &str = "
+ &control
+    calculation = 'scf', outdir = './work'
+ /
+ &system
+    ibrav = 2, celldm(1) = 10.2, nat = 2, ntyp = 1,
+    ecutwfc = 30, ecutrho = 120
+ /
+ &electrons
+    conv_thr = 1e-8
+ /
+ATOMIC_SPECIES
+ Si 28.0855 Si.pz-vbc.UPF
+ATOMIC_POSITIONS alat
+ Si 0.00 0.00 0.00
+ Si 0.25 0.25 0.25
+K_POINTS automatic
+ 4 4 4 0 0 0
+";
+
+fn parameters() -> input::Parameters {
+    input::Parameters {
+        num_wann: 8,
+        num_bands: None,
+        num_iter: Some(100),
+        projections: input::Projections::List(vec![input::Projection {
+            site: input::ProjectionSite::Species(String::from("Si")),
+            orbitals: String::from("sp3"),
+        }]),
+        disentanglement: Some(input::Disentanglement {
+            dis_win_min: -6.0,
+            dis_win_max: 17.0,
+            dis_froz_min: None,
+            dis_froz_max: Some(6.5),
+            dis_num_iter: None,
+        }),
+    }
+}
+
+#[test]
+fn wannier90_input_from_pw() {
+    let mut pw_input = pw::parse::parse_input_file(SI_NSCF).unwrap();
+
+    // Wannier90 needs the full k-point list, which `automatic` does not give.
+    match input::from_pw_input(&pw_input, parameters()) {
+        Err(input::Error::KPoints) => (),
+        other => panic!("expected k-points error; got {:?}", other),
+    }
+
+    pw_input.k_points = KPoints::CrystalUniform([4, 4, 4]);
+    let win = input::from_pw_input(&pw_input, parameters()).unwrap();
+
+    assert_eq!(win.parameters.num_bands, Some(12));
+    assert_eq!(win.mp_grid, [4, 4, 4]);
+    assert_eq!(win.kpoints.len(), 64);
+    assert_eq!(win.kpoints[1], [0.0, 0.0, 0.25]);
+    assert_eq!(win.unit_cell_cart[0], [-5.1, 0.0, 5.1]);
+
+    let expected = [-0.25, 0.75, -0.25];
+    for (x, e) in win.atoms_frac[1].r.iter().zip(expected.iter()) {
+        assert!((x - e).abs() < 1e-12);
+    }
+
+    let text = serialize::make_input_file(&win).unwrap();
+    assert!(!win.spinors);
+    assert!(text.starts_with("num_wann = 8\nnum_bands = 12\nnum_iter = 100\n"));
+    assert!(!text.contains("spinors"));
+    assert!(text.contains("begin projections\nSi:sp3\nend projections"));
+    assert!(text.contains("dis_froz_max = 6.5\n"));
+    assert!(text.contains("mp_grid = 4 4 4\n"));
+    assert!(text.ends_with(" 0.75 0.75 0.75\nend kpoints"));
+
+    // The k-points written for Wannier90 are those written for pw.x.
+    let pw_text = pw::serialize::make_input_file(&pw_input).unwrap();
+    for k in &win.kpoints {
+        let line = format!("{} {} {} {}", k[0], k[1], k[2], 1.0 / 64.0);
+        assert!(pw_text.contains(&line));
+    }
+
+    // The Bloch states of a noncollinear calculation are spinors.
+    pw_input.system.spin_type = Some(SpinType::Noncollinear {
+        spin_orbit: true,
+        magnetization: Magnetization::default(),
+    });
+    let win = input::from_pw_input(&pw_input, parameters()).unwrap();
+    assert!(win.spinors);
+    let text = serialize::make_input_file(&win).unwrap();
+    assert!(text.starts_with("num_wann = 8\nnum_bands = 12\nnum_iter = 100\nspinors = .true.\n"));
+}
+
+#[test]
+fn wannier90_input_validation() {
+    let mut pw_input = pw::parse::parse_input_file(SI_NSCF).unwrap();
+    pw_input.k_points = KPoints::CrystalUniform([2, 2, 2]);
+
+    let mut parameters = parameters();
+    parameters.num_bands = Some(6);
+    parameters.projections = input::Projections::List(vec![input::Projection {
+        site: input::ProjectionSite::Species(String::from("Ge")),
+        orbitals: String::from("s"),
+    }]);
+
+    let win = input::from_pw_input(&pw_input, parameters).unwrap();
+    let errs = input::validate(&win).unwrap_err().errs;
+
+    assert_eq!(errs.len(), 2);
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::NumBands(6, 8))));
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, input::Error::Species(ref s) if s == "Ge")));
+}
+
+#[test]
+fn wannier90_input_coordinate_types() {
+    let mut pw_input = pw::parse::parse_input_file(SI_NSCF).unwrap();
+    pw_input.k_points = KPoints::CrystalUniform([2, 2, 2]);
+    let expected = input::from_pw_input(&pw_input, parameters()).unwrap();
+
+    // Positions in any Cartesian units give the same fractional coordinates.
+    let cell = lattice::lattice_vectors(&pw_input.system.ibrav, pw_input.system.alat);
+    pw_input.atomic_positions = pw_input
+        .atomic_positions
+        .to_coordinate_type(
+            PositionCoordinateType::AngstromCartesian,
+            &cell,
+            pw_input.system.alat,
+        )
+        .unwrap();
+    let win = input::from_pw_input(&pw_input, parameters()).unwrap();
+    for (atom, expected) in win.atoms_frac.iter().zip(&expected.atoms_frac) {
+        for (x, e) in atom.r.iter().zip(expected.r.iter()) {
+            assert!((x - e).abs() < 1e-12);
+        }
+    }
+
+    // Positions given as `crystal_sg` depend on the space group and are not converted.
+    pw_input.atomic_positions.coordinate_type = PositionCoordinateType::CrystalSG;
+    match input::from_pw_input(&pw_input, parameters()) {
+        Err(input::Error::CoordinateType) => (),
+        other => panic!("expected coordinate type error; got {:?}", other),
+    }
+}
+
+fn workflow_parameters() -> workflow::Parameters {
+    workflow::Parameters {
+        nk: [2, 2, 2],
+        diago_thr_init: 1e-8,
+        nbnd: Some(12),
+        seedname: String::from("si"),
+        write_unk: false,
+        write_spn: false,
+        wannier90: parameters(),
+    }
+}
+
+#[test]
+fn wannier90_workflow_from_scf() {
+    let scf = pw::parse::parse_input_file(SI_SCF).unwrap();
+    let wf = workflow::from_scf_input(&scf, workflow_parameters()).unwrap();
+
+    // The seedname is used as the prefix, since none is given.
+    assert_eq!(wf.scf.control.prefix, Some(String::from("si")));
+    assert_eq!(wf.nscf.control.prefix, Some(String::from("si")));
+    assert_eq!(wf.pw2wannier90.prefix, "si");
+    assert_eq!(wf.pw2wannier90.out_dir, scf.control.out_dir);
+    let pw2wan_text = qe::pw2wannier90::serialize::make_input_file(&wf.pw2wannier90).unwrap();
+    assert!(pw2wan_text.contains("outdir='./work',"), "{}", pw2wan_text);
+    assert!(wf.pw2wannier90.write_amn && wf.pw2wannier90.write_mmn);
+
+    assert_eq!(wf.scf.k_points, scf.k_points);
+    assert_eq!(wf.nscf.k_points, KPoints::CrystalUniform([2, 2, 2]));
+    assert_eq!(
+        wf.nscf.calculation,
+        pw::input::Calculation::Nscf {
+            diago_thr_init: Some(1e-8),
+            nbnd: Some(12),
+            nosym: Some(true),
+        }
+    );
+    assert_eq!(wf.wannier90.parameters.num_bands, Some(12));
+    assert_eq!(wf.wannier90.mp_grid, [2, 2, 2]);
+
+    let run_dir = env::temp_dir().join(format!("qe-wannier90-{}", process::id()));
+    workflow::write_input_files(&wf, &run_dir).unwrap();
+
+    for name in &["scf.in", "nscf.in", "pw2wan.in", "si.win"] {
+        assert!(run_dir.join(name).exists(), "{} not written", name);
+    }
+    let nscf_text = fs::read_to_string(run_dir.join("nscf.in")).unwrap();
+    assert!(nscf_text.contains("K_POINTS crystal\n8\n"));
+
+    fs::remove_dir_all(run_dir).unwrap();
+
+    // Only `scf` inputs can be the base of the workflow.
+    let nscf = pw::parse::parse_input_file(SI_NSCF).unwrap();
+    match workflow::from_scf_input(&nscf, workflow_parameters()) {
+        Err(workflow::Error::ScfCalculation) => (),
+        other => panic!("expected calculation error; got {:?}", other),
+    }
+}
+
+#[test]
+fn wannier90_workflow_validation() {
+    let scf = pw::parse::parse_input_file(SI_SCF).unwrap();
+    let mut wf = workflow::from_scf_input(&scf, workflow_parameters()).unwrap();
+    assert!(workflow::validate(&wf).is_ok());
+
+    wf.nscf.control.prefix = Some(String::from("other"));
+    wf.nscf.k_points = KPoints::CrystalUniform([4, 4, 4]);
+    wf.nscf.system.alat = 10.3;
+    wf.wannier90.parameters.num_bands = Some(10);
+
+    let errs = workflow::validate(&wf).unwrap_err().errs;
+
+    assert_eq!(errs.len(), 4);
+    assert!(errs.iter().any(|e| matches!(*e, workflow::Error::Prefix)));
+    assert!(errs.iter().any(|e| matches!(*e, workflow::Error::KPoints)));
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, workflow::Error::Structure)));
+    assert!(errs
+        .iter()
+        .any(|e| matches!(*e, workflow::Error::NumBands(10, 12))));
+
+    match workflow::write_input_files(&wf, env::temp_dir()) {
+        Err(workflow::WriteError::Workflow(_)) => (),
+        other => panic!("expected workflow error; got {:?}", other),
+    }
+}